@@ -6,6 +6,7 @@ import {
   Keypair,
   Account,
   rpc,
+  scValToNative,
 } from "@stellar/stellar-sdk";
 
 const TESTNET_CONFIG = {
@@ -43,7 +44,8 @@ export async function GET() {
 
     const returnValue = simResult.result?.retval;
     if (returnValue) {
-      return NextResponse.json({ value: returnValue.u32() ?? 0 });
+      // `get` returns a u64, which decodes to a bigint; JSON has no bigint
+      return NextResponse.json({ value: Number(scValToNative(returnValue)) });
     }
 
     return NextResponse.json({ value: 0 });
@@ -1,26 +1,1232 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env};
+use soroban_sdk::{
+    contract, contracterror, contractevent, contractimpl, contracttype, symbol_short,
+    token::TokenClient, Address, Env, IntoVal, String, Symbol, Vec,
+};
+
+/// The name of the counter `increment` and `get` use, the only one that
+/// keeps per-caller counts.
+const DEFAULT_NAME: Symbol = symbol_short!("default");
+/// The most counters `increment_named` creates besides the default one.
+const MAX_NAMES: u32 = 100;
+/// Ledgers of life left below which a write extends an entry, about a week.
+const TTL_THRESHOLD: u32 = 7 * 17_280;
+/// Ledgers of life a write extends an entry to, about 30 days.
+const TTL_EXTEND_TO: u32 = 30 * 17_280;
+/// The most increments `set_history_size` lets the history hold.
+const MAX_HISTORY: u32 = 64;
+/// The most bytes an `increment_with_memo` memo may have.
+const MAX_MEMO_LEN: u32 = 64;
+/// The most names one `increment_many` takes.
+const MAX_BATCH: u32 = 10;
+/// The most callers `top_callers` keeps track of.
+const MAX_LEADERS: u32 = 20;
+/// Where the counter kept its total, as a `u32`, before `DataKey`.
+const LEGACY_TOTAL: Symbol = symbol_short!("count");
 
 #[contract]
 pub struct Counter;
 
+#[contracttype]
+#[derive(Clone)]
+enum DataKey {
+    /// The sum of every caller's count, as a `u64`. Until written, the
+    /// total is read from `LEGACY_TOTAL`.
+    TotalV2,
+    /// One caller's count since the start over numbered by the epoch.
+    Caller(u32, Address),
+    /// A counter created by `increment_named`.
+    Named(Symbol),
+    /// The names `increment_named` has created counters for.
+    Names,
+    /// How many times the counts have started over, by `reset` or at the
+    /// end of a scheduled epoch.
+    Epoch,
+    /// The address admin calls need auth from, if any.
+    Admin,
+    /// The admin `transfer_admin` proposed, until they call `accept_admin`.
+    PendingAdmin,
+    /// The most the total may reach, if capped.
+    Cap,
+    /// Set while the counting calls are paused.
+    Paused,
+    /// The most increments of the total one ledger may hold, if limited.
+    RateLimit,
+    /// How many increments of the total the ledger with this sequence
+    /// number has held, in temporary storage.
+    LedgerIncrements(u32),
+    /// How many increments new history keeps; absent when not recording.
+    HistorySize,
+    /// The ring the history is kept in, as its size and the number of
+    /// entries ever written to it.
+    HistoryRing,
+    /// One slot of the history ring.
+    History(u32),
+    /// Every how many increments of the total a milestone is reached, and
+    /// the contract told of it, if any.
+    Milestone,
+    /// The token and least amount `increment_paid` charges.
+    Fee,
+    /// Whether only allowlisted callers may increment.
+    AllowlistEnabled,
+    /// A caller on the allowlist.
+    Allowed(Address),
+    /// How many ledgers a scheduled epoch lasts, if the total is reset on
+    /// a schedule.
+    EpochLength,
+    /// The scheduled epoch the total and per-caller counts belong to.
+    CurrentEpoch,
+    /// The total a scheduled epoch ended with.
+    EpochValue(u32),
+    /// The callers with the highest counts, highest first.
+    Leaders,
+    /// The sequence number of the ledger a count was last written in.
+    LastModified,
+    /// What `decrement` does for a caller whose count is 0; `Error` until
+    /// set.
+    UnderflowMode,
+    /// The most increments one caller may make per window, and the
+    /// window's length in ledgers, if callers have a quota.
+    Quota,
+    /// How many increments a caller has made in the quota window with this
+    /// number, in temporary storage.
+    QuotaUsage(Address, u32),
+}
+
+/// What `decrement` does for a caller whose count is 0.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnderflowMode {
+    /// Fail with `Underflow`.
+    Error,
+    /// Leave the counts at 0 and publish a `clamped` event.
+    Clamp,
+}
+
+/// One increment of the total, as `get_history` returns it.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HistoryEntry {
+    pub caller: Address,
+    /// The total after the increment.
+    pub new_value: u64,
+    /// The sequence number of the ledger the increment was made in.
+    pub ledger_seq: u32,
+    /// The note `increment_with_memo` attached; empty for other calls.
+    pub memo: String,
+}
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum CounterError {
+    /// `decrement` was called by a caller whose count is 0.
+    Underflow = 1,
+    /// An increment would take a counter past `u64::MAX`.
+    Overflow = 2,
+    /// `increment_by` was called with an amount of 0.
+    ZeroAmount = 3,
+    /// `increment_named` would create more than `MAX_NAMES` counters.
+    TooManyNames = 4,
+    /// An admin-only call on a counter that has no admin yet.
+    NoAdmin = 5,
+    /// `claim_admin` on a counter that already has an admin.
+    AdminAlreadySet = 6,
+    /// `accept_admin` with no transfer pending.
+    NoPendingAdmin = 7,
+    /// An increment would take the total past the cap `set_cap` set.
+    CapExceeded = 8,
+    /// A counting call, or `pause`, while the counter is paused.
+    Paused = 9,
+    /// `unpause` while the counter is not paused.
+    NotPaused = 10,
+    /// An increment past the per-ledger limit `set_rate_limit` set.
+    RateLimited = 11,
+    /// `set_history_size` with more than `MAX_HISTORY`.
+    HistoryTooLarge = 12,
+    /// `increment_paid` on a counter whose admin has not set a fee.
+    FeeNotSet = 13,
+    /// `increment_paid` paying in another token than the fee's.
+    WrongFeeToken = 14,
+    /// `increment_paid` paying less than the fee, or `set_fee` with an
+    /// amount below 0.
+    FeeTooLow = 15,
+    /// An increment by a caller not on the allowlist while it is enabled.
+    NotAllowed = 16,
+    /// `set_epoch_length` with 0 ledgers.
+    BadEpochLength = 17,
+    /// `increment_many` with more than `MAX_BATCH` names.
+    BatchTooLong = 18,
+    /// `increment_with_memo` with a memo over `MAX_MEMO_LEN` bytes.
+    MemoTooLong = 19,
+    /// An increment past the caller's quota for the window.
+    QuotaExceeded = 20,
+    /// `set_caller_quota` with a window of 0 ledgers.
+    BadQuotaWindow = 21,
+}
+
+/// Emitted when the default counter goes up, by `increment` or any of the
+/// other calls that add to the total.
+#[contractevent(topics = ["counter", "inc"], data_format = "vec")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Incremented {
+    pub caller: Address,
+    pub old_value: u64,
+    pub new_value: u64,
+    /// The note `increment_with_memo` attached; empty for other calls.
+    pub memo: String,
+}
+
+/// Emitted when the default counter goes down, by `decrement`.
+#[contractevent(topics = ["counter", "dec"], data_format = "vec")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decremented {
+    pub caller: Address,
+    pub old_value: u64,
+    pub new_value: u64,
+}
+
+/// Emitted when a named counter goes up, by `increment_named`.
+#[contractevent(topics = ["counter", "inc_named"], data_format = "vec")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NamedIncremented {
+    #[topic]
+    pub name: Symbol,
+    pub caller: Address,
+    pub old_value: u64,
+    pub new_value: u64,
+}
+
+/// Emitted when `decrement` leaves a count of 0 as it is, in
+/// `UnderflowMode::Clamp`.
+#[contractevent(topics = ["counter", "clamped"], data_format = "vec")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Clamped {
+    pub caller: Address,
+    /// The total, which the call left unchanged.
+    pub value: u64,
+}
+
+/// Emitted when `reset` zeroes the counters.
+#[contractevent(topics = ["counter", "reset"], data_format = "vec")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reset {
+    pub caller: Address,
+    /// The default counter's total before the reset.
+    pub old_value: u64,
+    pub new_value: u64,
+}
+
+/// Emitted when the total reaches a multiple of the `set_milestone` step.
+#[contractevent(topics = ["counter", "milestone"], data_format = "vec")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Milestone {
+    /// The multiple reached.
+    pub count: u64,
+}
+
+/// Emitted when `pause` stops the counting calls.
+#[contractevent(topics = ["counter", "paused"], data_format = "vec")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Paused {
+    pub admin: Address,
+}
+
+/// Emitted when `unpause` lets the counting calls run again.
+#[contractevent(topics = ["counter", "unpaused"], data_format = "vec")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Unpaused {
+    pub admin: Address,
+}
+
 #[contractimpl]
 impl Counter {
-    /// Increment the counter. Requires auth from `caller`.
-    pub fn increment(e: Env, caller: Address) -> u32 {
+    /// Sets the admin allowed to `reset` the counters. With `None`, the
+    /// first `claim_admin` caller becomes admin.
+    pub fn __constructor(e: Env, admin: Option<Address>) {
+        if let Some(admin) = admin {
+            e.storage().instance().set(&DataKey::Admin, &admin);
+        }
+    }
+
+    /// Makes `caller` the admin of a counter deployed without one. Requires
+    /// auth from `caller`; fails with `AdminAlreadySet` once there is an
+    /// admin.
+    pub fn claim_admin(e: Env, caller: Address) -> Result<(), CounterError> {
+        caller.require_auth();
+        if e.storage().instance().has(&DataKey::Admin) {
+            return Err(CounterError::AdminAlreadySet);
+        }
+        e.storage().instance().set(&DataKey::Admin, &caller);
+        Ok(())
+    }
+
+    /// Zeroes every counter: the total, each caller's count and the named
+    /// counters, whose names are forgotten. Admin only.
+    pub fn reset(e: Env) -> Result<(), CounterError> {
+        let admin = require_admin(&e)?;
+
+        let old_total = total(&e);
+        store_count(&e, &DataKey::TotalV2, 0);
+        // Per-caller counts cannot be listed, so they are left behind in
+        // the old epoch rather than removed
+        e.storage()
+            .instance()
+            .set(&DataKey::Epoch, &(epoch(&e) + 1));
+        for name in list_names(&e) {
+            e.storage().persistent().remove(&DataKey::Named(name));
+        }
+        e.storage().persistent().remove(&DataKey::Names);
+        e.storage().persistent().remove(&DataKey::Leaders);
+
+        Reset {
+            caller: admin,
+            old_value: old_total,
+            new_value: 0,
+        }
+        .publish(&e);
+        Ok(())
+    }
+
+    pub fn admin(e: Env) -> Option<Address> {
+        e.storage().instance().get(&DataKey::Admin)
+    }
+
+    /// Proposes `new_admin`, who takes over once they call `accept_admin`,
+    /// so a mistyped address cannot take the role out of reach. Admin only;
+    /// a later proposal replaces this one.
+    pub fn transfer_admin(e: Env, new_admin: Address) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        e.storage()
+            .instance()
+            .set(&DataKey::PendingAdmin, &new_admin);
+        Ok(())
+    }
+
+    /// Makes the proposed admin the admin. Authorized by the proposed admin.
+    pub fn accept_admin(e: Env) -> Result<(), CounterError> {
+        let pending: Address = e
+            .storage()
+            .instance()
+            .get(&DataKey::PendingAdmin)
+            .ok_or(CounterError::NoPendingAdmin)?;
+        pending.require_auth();
+        e.storage().instance().set(&DataKey::Admin, &pending);
+        e.storage().instance().remove(&DataKey::PendingAdmin);
+        Ok(())
+    }
+
+    /// The admin proposed by `transfer_admin` and not yet accepted.
+    pub fn pending_admin(e: Env) -> Option<Address> {
+        e.storage().instance().get(&DataKey::PendingAdmin)
+    }
+
+    /// Caps the total at `cap`, so that increments past it fail with
+    /// `CapExceeded` whatever the caller's account policies allow. `None`
+    /// removes the cap. A cap below the current total only blocks further
+    /// increments. Named counters are not capped. Admin only.
+    pub fn set_cap(e: Env, cap: Option<u64>) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        match cap {
+            Some(cap) => e.storage().instance().set(&DataKey::Cap, &cap),
+            None => e.storage().instance().remove(&DataKey::Cap),
+        }
+        Ok(())
+    }
+
+    /// The cap on the total, if any.
+    pub fn get_cap(e: Env) -> Option<u64> {
+        e.storage().instance().get(&DataKey::Cap)
+    }
+
+    /// Limits the increments of the total to `max_per_ledger` per ledger,
+    /// counting calls rather than amounts, so that more fail with
+    /// `RateLimited` until the next ledger. Unlike an account's rate-limit
+    /// policy this holds for every caller. `None` removes the limit. Named
+    /// counters are not limited. Admin only.
+    pub fn set_rate_limit(e: Env, max_per_ledger: Option<u32>) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        match max_per_ledger {
+            Some(max) => e.storage().instance().set(&DataKey::RateLimit, &max),
+            None => e.storage().instance().remove(&DataKey::RateLimit),
+        }
+        Ok(())
+    }
+
+    /// The per-ledger limit on increments, if any.
+    pub fn get_rate_limit(e: Env) -> Option<u32> {
+        e.storage().instance().get(&DataKey::RateLimit)
+    }
+
+    /// Keeps the last `size` increments of the total for `get_history`, up
+    /// to `MAX_HISTORY`. 0 stops recording but keeps what was recorded
+    /// readable, and a later call with the same size resumes it; any other
+    /// size starts an empty history. Named counters are not recorded.
+    /// Admin only.
+    pub fn set_history_size(e: Env, size: u32) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        if size > MAX_HISTORY {
+            return Err(CounterError::HistoryTooLarge);
+        }
+        if size == 0 {
+            e.storage().instance().remove(&DataKey::HistorySize);
+            return Ok(());
+        }
+        let (ring_size, _) = history_ring(&e);
+        if size != ring_size {
+            for slot in 0..ring_size {
+                e.storage().persistent().remove(&DataKey::History(slot));
+            }
+            e.storage()
+                .instance()
+                .set(&DataKey::HistoryRing, &(size, 0u32));
+        }
+        e.storage().instance().set(&DataKey::HistorySize, &size);
+        Ok(())
+    }
+
+    /// How many increments the history is recording, 0 when it is not.
+    pub fn get_history_size(e: Env) -> u32 {
+        e.storage()
+            .instance()
+            .get(&DataKey::HistorySize)
+            .unwrap_or(0)
+    }
+
+    /// The recorded increments, oldest first.
+    pub fn get_history(e: Env) -> Vec<HistoryEntry> {
+        let (size, written) = history_ring(&e);
+        let mut history = Vec::new(&e);
+        if written == 0 {
+            return history;
+        }
+        let len = written.min(size);
+        let oldest = written - len;
+        for i in oldest..written {
+            if let Some(entry) = e.storage().persistent().get(&DataKey::History(i % size)) {
+                history.push_back(entry);
+            }
+        }
+        history
+    }
+
+    /// Marks every `every`th increment of the total with a `milestone`
+    /// event and, if `hook` is set, a call to `hook.on_milestone(count)`.
+    /// An increment that jumps over a multiple reaches it, and one that
+    /// jumps over several reaches only the highest. A hook that traps or
+    /// refuses does not fail the increment. 0 turns milestones off. Admin
+    /// only.
+    pub fn set_milestone(e: Env, every: u64, hook: Option<Address>) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        if every == 0 {
+            e.storage().instance().remove(&DataKey::Milestone);
+        } else {
+            e.storage()
+                .instance()
+                .set(&DataKey::Milestone, &(every, hook));
+        }
+        Ok(())
+    }
+
+    /// The milestone step and hook, if milestones are on.
+    pub fn get_milestone(e: Env) -> Option<(u64, Option<Address>)> {
+        e.storage().instance().get(&DataKey::Milestone)
+    }
+
+    /// Makes `increment_paid` charge at least `amount` of `token`. Admin
+    /// only.
+    pub fn set_fee(e: Env, token: Address, amount: i128) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        if amount < 0 {
+            return Err(CounterError::FeeTooLow);
+        }
+        e.storage().instance().set(&DataKey::Fee, &(token, amount));
+        Ok(())
+    }
+
+    /// The token and least amount `increment_paid` charges, if set.
+    pub fn get_fee(e: Env) -> Option<(Address, i128)> {
+        e.storage().instance().get(&DataKey::Fee)
+    }
+
+    /// Sends the counter's whole balance of the fee token to `to`, returning
+    /// the amount. Fees paid in a token the fee has since moved away from
+    /// stay behind. Admin only.
+    pub fn withdraw_fees(e: Env, to: Address) -> Result<i128, CounterError> {
+        require_admin(&e)?;
+        let (token, _): (Address, i128) = e
+            .storage()
+            .instance()
+            .get(&DataKey::Fee)
+            .ok_or(CounterError::FeeNotSet)?;
+        let token = TokenClient::new(&e, &token);
+        let balance = token.balance(&e.current_contract_address());
+        if balance > 0 {
+            token.transfer(&e.current_contract_address(), &to, &balance);
+        }
+        Ok(balance)
+    }
+
+    /// Makes increments by callers not on the allowlist fail with
+    /// `NotAllowed`, after their auth has passed, so that a caller's
+    /// account can authorize a call the counter still refuses. Disabling
+    /// keeps the list. Admin only.
+    pub fn set_allowlist_enabled(e: Env, enabled: bool) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        if enabled {
+            e.storage()
+                .instance()
+                .set(&DataKey::AllowlistEnabled, &true);
+        } else {
+            e.storage().instance().remove(&DataKey::AllowlistEnabled);
+        }
+        Ok(())
+    }
+
+    pub fn allowlist_enabled(e: Env) -> bool {
+        allowlist_enabled(&e)
+    }
+
+    /// Adds `caller` to the allowlist. Admin only.
+    pub fn allow(e: Env, caller: Address) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        let key = DataKey::Allowed(caller);
+        e.storage().persistent().set(&key, &true);
+        extend(&e, &key, TTL_THRESHOLD, TTL_EXTEND_TO);
+        Ok(())
+    }
+
+    /// Removes `caller` from the allowlist. Admin only.
+    pub fn disallow(e: Env, caller: Address) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        e.storage().persistent().remove(&DataKey::Allowed(caller));
+        Ok(())
+    }
+
+    /// Whether `caller` is on the allowlist, enabled or not.
+    pub fn is_allowed(e: Env, caller: Address) -> bool {
+        e.storage().persistent().has(&DataKey::Allowed(caller))
+    }
+
+    /// Restarts the total and the per-caller counts from 0 every `ledgers`
+    /// ledgers, with no admin call. Scheduled epoch `n` covers sequence
+    /// numbers `n * ledgers` up to the next; when the first write of a new
+    /// epoch comes, the total the last one ended with is kept for
+    /// `get_epoch_value`. Named counters carry on. `None` stops the
+    /// schedule and leaves the counts as they are. Fails with
+    /// `BadEpochLength` for 0. Admin only.
+    pub fn set_epoch_length(e: Env, ledgers: Option<u32>) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        roll_epoch(&e);
+        match ledgers {
+            Some(0) => return Err(CounterError::BadEpochLength),
+            Some(ledgers) => {
+                e.storage().instance().set(&DataKey::EpochLength, &ledgers);
+                e.storage()
+                    .instance()
+                    .set(&DataKey::CurrentEpoch, &(e.ledger().sequence() / ledgers));
+            }
+            None => e.storage().instance().remove(&DataKey::EpochLength),
+        }
+        Ok(())
+    }
+
+    /// The scheduled epoch the ledger is in, or the last one before the
+    /// schedule stopped; 0 if there never was one.
+    pub fn current_epoch(e: Env) -> u32 {
+        ledger_epoch(&e).unwrap_or_else(|| counted_epoch(&e))
+    }
+
+    /// The total scheduled epoch `epoch` ended with, if it has ended and
+    /// anything was written after it.
+    pub fn get_epoch_value(e: Env, epoch: u32) -> Option<u64> {
+        e.storage().persistent().get(&DataKey::EpochValue(epoch))
+    }
+
+    /// Limits each caller to `max_per_window` increments of the total per
+    /// window of `window_ledgers` ledgers, counting calls like
+    /// `set_rate_limit` but per caller and on top of it, so that more fail
+    /// with `QuotaExceeded` until the next window. Window `n` covers
+    /// sequence numbers `n * window_ledgers` up to the next. `None` removes
+    /// the quota. Fails with `BadQuotaWindow` for a window of 0. Admin
+    /// only.
+    pub fn set_caller_quota(
+        e: Env,
+        max_per_window: Option<u32>,
+        window_ledgers: u32,
+    ) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        match max_per_window {
+            Some(_) if window_ledgers == 0 => return Err(CounterError::BadQuotaWindow),
+            Some(max) => e
+                .storage()
+                .instance()
+                .set(&DataKey::Quota, &(max, window_ledgers)),
+            None => e.storage().instance().remove(&DataKey::Quota),
+        }
+        Ok(())
+    }
+
+    /// How many increments `caller` has made in the current quota window;
+    /// 0 without a quota.
+    pub fn get_quota_usage(e: Env, caller: Address) -> u32 {
+        let Some((_, window_ledgers)) = quota(&e) else {
+            return 0;
+        };
+        e.storage()
+            .temporary()
+            .get(&quota_key(&e, &caller, window_ledgers))
+            .unwrap_or(0)
+    }
+
+    /// Sets what `decrement` does for a caller whose count is 0: fail, the
+    /// default, or leave the counts as they are. Admin only.
+    pub fn set_underflow_mode(e: Env, mode: UnderflowMode) -> Result<(), CounterError> {
+        require_admin(&e)?;
+        e.storage().instance().set(&DataKey::UnderflowMode, &mode);
+        Ok(())
+    }
+
+    pub fn get_underflow_mode(e: Env) -> UnderflowMode {
+        e.storage()
+            .instance()
+            .get(&DataKey::UnderflowMode)
+            .unwrap_or(UnderflowMode::Error)
+    }
+
+    /// Makes the counting calls (`increment`, `increment_by`,
+    /// `increment_named`, `increment_many`, `increment_with_memo`,
+    /// `get_and_increment`, `increment_paid`, `decrement`) fail with
+    /// `Paused`, after their auth has passed, until `unpause`. Reads and
+    /// admin calls keep working. Pausing a paused counter fails with
+    /// `Paused`. Admin only.
+    pub fn pause(e: Env) -> Result<(), CounterError> {
+        let admin = require_admin(&e)?;
+        if is_paused(&e) {
+            return Err(CounterError::Paused);
+        }
+        e.storage().instance().set(&DataKey::Paused, &true);
+        Paused { admin }.publish(&e);
+        Ok(())
+    }
+
+    /// Lets the counting calls run again. Fails with `NotPaused` unless
+    /// paused. Admin only.
+    pub fn unpause(e: Env) -> Result<(), CounterError> {
+        let admin = require_admin(&e)?;
+        if !is_paused(&e) {
+            return Err(CounterError::NotPaused);
+        }
+        e.storage().instance().remove(&DataKey::Paused);
+        Unpaused { admin }.publish(&e);
+        Ok(())
+    }
+
+    pub fn is_paused(e: Env) -> bool {
+        is_paused(&e)
+    }
+
+    /// Increment `caller`'s count and the total. Requires auth from
+    /// `caller`. Returns the new total.
+    pub fn increment(e: Env, caller: Address) -> Result<u64, CounterError> {
+        Self::increment_named(e, caller, DEFAULT_NAME)
+    }
+
+    /// Increment the counter `name`, creating it at first use. Requires auth
+    /// from `caller`. Returns the counter's new value. `default` is the
+    /// counter `increment` uses.
+    pub fn increment_named(e: Env, caller: Address, name: Symbol) -> Result<u64, CounterError> {
+        caller.require_auth();
+        check_not_paused(&e)?;
+        if name == DEFAULT_NAME {
+            add(&e, &caller, 1)
+        } else {
+            add_named(&e, &caller, name)
+        }
+    }
+
+    /// Increment `caller`'s count and the total like `increment`, noting
+    /// `memo` in the history and in the `inc` event. Fails with
+    /// `MemoTooLong` past `MAX_MEMO_LEN` bytes, before anything is counted.
+    /// Returns the counter's new value.
+    pub fn increment_with_memo(e: Env, caller: Address, memo: String) -> Result<u64, CounterError> {
+        caller.require_auth();
+        if memo.len() > MAX_MEMO_LEN {
+            return Err(CounterError::MemoTooLong);
+        }
+        check_not_paused(&e)?;
+        add_with_memo(&e, &caller, 1, Some(&memo))
+    }
+
+    /// Increment each counter in `names`, in order, under one auth from
+    /// `caller`. Returns each counter's value after its increment. A name
+    /// listed twice is incremented twice, the second value one past the
+    /// first. If any increment fails, none is made. Fails with
+    /// `BatchTooLong` past `MAX_BATCH` names.
+    pub fn increment_many(
+        e: Env,
+        caller: Address,
+        names: Vec<Symbol>,
+    ) -> Result<Vec<u64>, CounterError> {
         caller.require_auth();
-        let key = symbol_short!("count");
-        let count: u32 = e.storage().persistent().get(&key).unwrap_or(0);
-        let new_count = count + 1;
-        e.storage().persistent().set(&key, &new_count);
-        new_count
+        check_not_paused(&e)?;
+        if names.len() > MAX_BATCH {
+            return Err(CounterError::BatchTooLong);
+        }
+        let mut values = Vec::new(&e);
+        for name in names {
+            let value = if name == DEFAULT_NAME {
+                add(&e, &caller, 1)?
+            } else {
+                add_named(&e, &caller, name)?
+            };
+            values.push_back(value);
+        }
+        Ok(values)
     }
 
-    /// Get current counter value.
-    pub fn get(e: Env) -> u32 {
-        let key = symbol_short!("count");
-        e.storage().persistent().get(&key).unwrap_or(0)
+    /// Increment `caller`'s count and the total like `increment`, but
+    /// return the total from before, so a caller using the counter as a
+    /// sequence gets its number in the same call that takes it. Fails as
+    /// `increment` does on overflow, past the cap or while paused.
+    pub fn get_and_increment(e: Env, caller: Address) -> Result<u64, CounterError> {
+        caller.require_auth();
+        check_not_paused(&e)?;
+        Ok(add(&e, &caller, 1)? - 1)
+    }
+
+    /// Increment `caller`'s count and the total like `increment`, after
+    /// moving `fee` of `token` from `caller` to the counter. Requires auth
+    /// from `caller`, which also covers the transfer, so a spending-limit
+    /// policy on `caller`'s account sees the fee. Fails with `FeeNotSet`,
+    /// `WrongFeeToken` or `FeeTooLow` unless the payment meets the fee
+    /// `set_fee` set.
+    pub fn increment_paid(
+        e: Env,
+        caller: Address,
+        token: Address,
+        fee: i128,
+    ) -> Result<u64, CounterError> {
+        caller.require_auth();
+        check_not_paused(&e)?;
+        let (fee_token, amount): (Address, i128) = e
+            .storage()
+            .instance()
+            .get(&DataKey::Fee)
+            .ok_or(CounterError::FeeNotSet)?;
+        if token != fee_token {
+            return Err(CounterError::WrongFeeToken);
+        }
+        if fee < amount {
+            return Err(CounterError::FeeTooLow);
+        }
+        if fee > 0 {
+            TokenClient::new(&e, &token).transfer(&caller, &e.current_contract_address(), &fee);
+        }
+        add(&e, &caller, 1)
+    }
+
+    /// Add `amount` to `caller`'s count and the total. Requires auth from
+    /// `caller`, whose signed arguments carry `amount` for policies that cap
+    /// it. Fails with `ZeroAmount` for 0 and with `Overflow` past
+    /// `u64::MAX`, leaving the counts as they were.
+    pub fn increment_by(e: Env, caller: Address, amount: u32) -> Result<u64, CounterError> {
+        caller.require_auth();
+        check_not_paused(&e)?;
+        if amount == 0 {
+            return Err(CounterError::ZeroAmount);
+        }
+        add(&e, &caller, amount)
+    }
+
+    /// Decrement `caller`'s count and the total. Requires auth from
+    /// `caller`. When `caller`'s own count is 0, so that a caller cannot
+    /// undo another's increments, fails with `Underflow`, or with
+    /// `UnderflowMode::Clamp` returns the total unchanged and publishes a
+    /// `clamped` event.
+    pub fn decrement(e: Env, caller: Address) -> Result<u64, CounterError> {
+        caller.require_auth();
+        check_not_paused(&e)?;
+        roll_epoch(&e);
+        let caller_key = caller_key(&e, &caller);
+        let Some(count) = get_count(&e, &caller_key).checked_sub(1) else {
+            if Self::get_underflow_mode(e.clone()) == UnderflowMode::Error {
+                return Err(CounterError::Underflow);
+            }
+            let value = total(&e);
+            Clamped { caller, value }.publish(&e);
+            return Ok(value);
+        };
+        let old_total = total(&e);
+        let total = old_total.checked_sub(1).ok_or(CounterError::Underflow)?;
+        store_count(&e, &caller_key, count);
+        store_count(&e, &DataKey::TotalV2, total);
+        rank(&e, &caller, count);
+
+        Decremented {
+            caller,
+            old_value: old_total,
+            new_value: total,
+        }
+        .publish(&e);
+        Ok(total)
+    }
+
+    /// Moves a total kept by the version before `DataKey`, a `u32` under
+    /// `"count"`, to `DataKey::TotalV2` as a `u64`, and removes it. Reads
+    /// and increments understand the old entry, so this only tidies it
+    /// away: once any write has made `TotalV2`, which already counts the
+    /// old total, the old entry is just removed. A no-op once migrated.
+    /// Anyone may call it.
+    pub fn migrate(e: Env) {
+        let Some(legacy) = e.storage().persistent().get::<_, u32>(&LEGACY_TOTAL) else {
+            return;
+        };
+        if !e.storage().persistent().has(&DataKey::TotalV2) {
+            store_count(&e, &DataKey::TotalV2, u64::from(legacy));
+        }
+        e.storage().persistent().remove(&LEGACY_TOTAL);
+    }
+
+    /// Extends the TTL of the contract instance, the total, the named
+    /// counters and their registry, the leaders and the history to
+    /// `extend_to` ledgers wherever it is below `threshold`, so a demo left
+    /// idle is not archived. Anyone may call it. Per-caller counts are
+    /// extended when their caller counts.
+    pub fn extend_ttl(e: Env, threshold: u32, extend_to: u32) {
+        e.storage().instance().extend_ttl(threshold, extend_to);
+        for key in [DataKey::TotalV2, DataKey::Names, DataKey::Leaders] {
+            if e.storage().persistent().has(&key) {
+                extend(&e, &key, threshold, extend_to);
+            }
+        }
+        for name in list_names(&e) {
+            extend(&e, &DataKey::Named(name), threshold, extend_to);
+        }
+        let (size, written) = history_ring(&e);
+        for slot in 0..written.min(size) {
+            extend(&e, &DataKey::History(slot), threshold, extend_to);
+        }
+    }
+
+    /// Get the total over all callers.
+    pub fn get(e: Env) -> u64 {
+        Self::get_named(e, DEFAULT_NAME)
+    }
+
+    /// The total, with the sequence number of the ledger it was read in.
+    pub fn get_with_ledger(e: Env) -> (u64, u32) {
+        (Self::get(e.clone()), e.ledger().sequence())
+    }
+
+    /// The total, if no count has been written since the ledger
+    /// `expected_ledger`, as returned by `get_with_ledger`; `None` if one
+    /// has, so a client can tell its earlier read is stale. A scheduled
+    /// epoch starting counts as a write at its first ledger, even before
+    /// anything is written in it.
+    pub fn get_if_unchanged(e: Env, expected_ledger: u32) -> Option<u64> {
+        if last_modified(&e) > expected_ledger {
+            return None;
+        }
+        Some(Self::get(e))
+    }
+
+    /// Get the counter `name`; 0 for a name never incremented.
+    pub fn get_named(e: Env, name: Symbol) -> u64 {
+        if name == DEFAULT_NAME {
+            return if epoch_ended(&e) { 0 } else { total(&e) };
+        }
+        get_count(&e, &DataKey::Named(name))
+    }
+
+    /// The names counters have been created for, oldest first. The default
+    /// counter always exists and is not listed.
+    pub fn list_names(e: Env) -> Vec<Symbol> {
+        list_names(&e)
+    }
+
+    /// Up to `limit` callers with the highest counts and their counts,
+    /// highest first; of two with the same count, the one that reached it
+    /// first ranks first.
+    ///
+    /// Per-caller counts cannot be listed, so the counter keeps the top
+    /// `MAX_LEADERS` as they count rather than ranking at read time. Once
+    /// that many are tracked, a caller enters only by counting past the
+    /// lowest, who drops out until their own count beats the lowest again.
+    /// A tracked caller who decrements below an untracked one therefore
+    /// still ranks above them until the untracked one counts again.
+    pub fn top_callers(e: Env, limit: u32) -> Vec<(Address, u64)> {
+        let leaders = if epoch_ended(&e) {
+            Vec::new(&e)
+        } else {
+            leaders(&e)
+        };
+        leaders.slice(0..limit.min(leaders.len()))
+    }
+
+    /// Get `caller`'s count since the last `reset`, or the start of the
+    /// scheduled epoch; 0 for an address that never incremented.
+    pub fn get_for(e: Env, caller: Address) -> u64 {
+        if epoch_ended(&e) {
+            return 0;
+        }
+        get_count(&e, &caller_key(&e, &caller))
+    }
+}
+
+/// Adds `amount` to `caller`'s count and the total, returning the total.
+fn add(e: &Env, caller: &Address, amount: u32) -> Result<u64, CounterError> {
+    add_with_memo(e, caller, amount, None)
+}
+
+/// `add`, noting `memo` in the history and the event.
+fn add_with_memo(
+    e: &Env,
+    caller: &Address,
+    amount: u32,
+    memo: Option<&String>,
+) -> Result<u64, CounterError> {
+    check_allowed(e, caller)?;
+    roll_epoch(e);
+    let amount = u64::from(amount);
+    let caller_key = caller_key(e, caller);
+    let count = get_count(e, &caller_key)
+        .checked_add(amount)
+        .ok_or(CounterError::Overflow)?;
+    let old_total = total(e);
+    let total = old_total
+        .checked_add(amount)
+        .ok_or(CounterError::Overflow)?;
+    let cap: Option<u64> = e.storage().instance().get(&DataKey::Cap);
+    if cap.is_some_and(|cap| total > cap) {
+        return Err(CounterError::CapExceeded);
+    }
+    count_in_ledger(e)?;
+    count_in_quota(e, caller)?;
+    store_count(e, &caller_key, count);
+    store_count(e, &DataKey::TotalV2, total);
+    rank(e, caller, count);
+    let memo = memo.cloned().unwrap_or_else(|| String::from_str(e, ""));
+    record(e, caller, total, &memo);
+
+    Incremented {
+        caller: caller.clone(),
+        old_value: old_total,
+        new_value: total,
+        memo,
+    }
+    .publish(e);
+    reach_milestone(e, old_total, total);
+    Ok(total)
+}
+
+/// Publishes the milestone, and calls the hook, when the total goes from
+/// `old_total` past a multiple of the step.
+fn reach_milestone(e: &Env, old_total: u64, total: u64) {
+    let Some((every, hook)) = e
+        .storage()
+        .instance()
+        .get::<_, (u64, Option<Address>)>(&DataKey::Milestone)
+    else {
+        return;
+    };
+    if total / every == old_total / every {
+        return;
+    }
+    let count = total - total % every;
+    Milestone { count }.publish(e);
+    if let Some(hook) = hook {
+        // The increment stands whatever the hook does
+        let _ = e.try_invoke_contract::<(), soroban_sdk::Error>(
+            &hook,
+            &Symbol::new(e, "on_milestone"),
+            (count,).into_val(e),
+        );
+    }
+}
+
+/// Increments the counter `name`, registering the name if it is new.
+fn add_named(e: &Env, caller: &Address, name: Symbol) -> Result<u64, CounterError> {
+    check_allowed(e, caller)?;
+    let key = DataKey::Named(name.clone());
+    if !e.storage().persistent().has(&key) {
+        let mut names = list_names(e);
+        if names.len() >= MAX_NAMES {
+            return Err(CounterError::TooManyNames);
+        }
+        names.push_back(name.clone());
+        e.storage().persistent().set(&DataKey::Names, &names);
+        extend(e, &DataKey::Names, TTL_THRESHOLD, TTL_EXTEND_TO);
+    }
+    let old_value = get_count(e, &key);
+    let value = old_value.checked_add(1).ok_or(CounterError::Overflow)?;
+    store_count(e, &key, value);
+
+    NamedIncremented {
+        name,
+        caller: caller.clone(),
+        old_value,
+        new_value: value,
+    }
+    .publish(e);
+    Ok(value)
+}
+
+/// Counts an increment against the ledger's limit, if there is one. Each
+/// ledger's count is a temporary entry left to expire.
+fn count_in_ledger(e: &Env) -> Result<(), CounterError> {
+    let Some(max) = e.storage().instance().get::<_, u32>(&DataKey::RateLimit) else {
+        return Ok(());
+    };
+    let key = DataKey::LedgerIncrements(e.ledger().sequence());
+    let count: u32 = e.storage().temporary().get(&key).unwrap_or(0);
+    if count >= max {
+        return Err(CounterError::RateLimited);
+    }
+    e.storage().temporary().set(&key, &(count + 1));
+    Ok(())
+}
+
+/// Writes the increment to the next slot of the history ring, over the
+/// oldest entry once the ring is full. Nothing is written, or even read
+/// beyond the size, while the history is off.
+fn record(e: &Env, caller: &Address, new_value: u64, memo: &String) {
+    if !e.storage().instance().has(&DataKey::HistorySize) {
+        return;
+    }
+    let (size, written) = history_ring(e);
+    let key = DataKey::History(written % size);
+    let entry = HistoryEntry {
+        caller: caller.clone(),
+        new_value,
+        ledger_seq: e.ledger().sequence(),
+        memo: memo.clone(),
+    };
+    e.storage().persistent().set(&key, &entry);
+    extend(e, &key, TTL_THRESHOLD, TTL_EXTEND_TO);
+    e.storage()
+        .instance()
+        .set(&DataKey::HistoryRing, &(size, written + 1));
+}
+
+fn history_ring(e: &Env) -> (u32, u32) {
+    e.storage()
+        .instance()
+        .get(&DataKey::HistoryRing)
+        .unwrap_or((0, 0))
+}
+
+/// Moves `caller`, now at `count`, to their place among the leaders, if
+/// they have one: below every caller with as much, and in place of the
+/// lowest if they beat them when the leaders are full.
+fn rank(e: &Env, caller: &Address, count: u64) {
+    let mut leaders = leaders(e);
+    if let Some(index) = leaders.iter().position(|(leader, _)| leader == *caller) {
+        leaders.remove(index as u32);
+    } else if leaders.len() >= MAX_LEADERS {
+        match leaders.last() {
+            Some((_, lowest)) if count > lowest => {
+                leaders.pop_back();
+            }
+            _ => return,
+        }
+    }
+    let index = leaders
+        .iter()
+        .position(|(_, leader_count)| leader_count < count)
+        .map_or(leaders.len(), |index| index as u32);
+    leaders.insert(index, (caller.clone(), count));
+    e.storage().persistent().set(&DataKey::Leaders, &leaders);
+    extend(e, &DataKey::Leaders, TTL_THRESHOLD, TTL_EXTEND_TO);
+}
+
+fn leaders(e: &Env) -> Vec<(Address, u64)> {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Leaders)
+        .unwrap_or_else(|| Vec::new(e))
+}
+
+fn quota(e: &Env) -> Option<(u32, u32)> {
+    e.storage().instance().get(&DataKey::Quota)
+}
+
+fn quota_key(e: &Env, caller: &Address, window_ledgers: u32) -> DataKey {
+    DataKey::QuotaUsage(caller.clone(), e.ledger().sequence() / window_ledgers)
+}
+
+/// Counts an increment against `caller`'s quota, if there is one. Each
+/// window's usage is a temporary entry that lives out the window, or as
+/// long as the storage's max TTL allows, and is then left to expire.
+fn count_in_quota(e: &Env, caller: &Address) -> Result<(), CounterError> {
+    let Some((max, window_ledgers)) = quota(e) else {
+        return Ok(());
+    };
+    let key = quota_key(e, caller, window_ledgers);
+    let used: u32 = e.storage().temporary().get(&key).unwrap_or(0);
+    if used >= max {
+        return Err(CounterError::QuotaExceeded);
+    }
+    e.storage().temporary().set(&key, &(used + 1));
+    let ttl = window_ledgers.min(e.storage().max_ttl());
+    e.storage().temporary().extend_ttl(&key, ttl, ttl);
+    Ok(())
+}
+
+fn list_names(e: &Env) -> Vec<Symbol> {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Names)
+        .unwrap_or_else(|| Vec::new(e))
+}
+
+/// The scheduled epoch the ledger is in, if the total is reset on a
+/// schedule.
+fn ledger_epoch(e: &Env) -> Option<u32> {
+    e.storage()
+        .instance()
+        .get::<_, u32>(&DataKey::EpochLength)
+        .map(|ledgers| e.ledger().sequence() / ledgers)
+}
+
+/// The scheduled epoch the counts belong to.
+fn counted_epoch(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&DataKey::CurrentEpoch)
+        .unwrap_or(0)
+}
+
+/// Whether the ledger has moved past the scheduled epoch the counts belong
+/// to.
+fn epoch_ended(e: &Env) -> bool {
+    ledger_epoch(e).is_some_and(|current| current != counted_epoch(e))
+}
+
+/// The sequence number of the ledger the counts last changed in: the last
+/// write, or the start of the epoch after the counted one if that has begun
+/// and the reset is not written yet.
+fn last_modified(e: &Env) -> u32 {
+    let written = e
+        .storage()
+        .instance()
+        .get(&DataKey::LastModified)
+        .unwrap_or(0);
+    if !epoch_ended(e) {
+        return written;
+    }
+    let ledgers: u32 = e
+        .storage()
+        .instance()
+        .get(&DataKey::EpochLength)
+        .unwrap_or(0);
+    let reset = (counted_epoch(e) + 1).saturating_mul(ledgers);
+    written.max(reset)
+}
+
+/// Keeps the total of a scheduled epoch that has ended and starts the
+/// counts over for the ledger's epoch. The writes note the ledger in
+/// `LastModified`, as every count write does.
+fn roll_epoch(e: &Env) {
+    let Some(current) = ledger_epoch(e) else {
+        return;
+    };
+    let ended = counted_epoch(e);
+    if current == ended {
+        return;
+    }
+    store_count(e, &DataKey::EpochValue(ended), total(e));
+    store_count(e, &DataKey::TotalV2, 0);
+    e.storage().instance().set(&DataKey::Epoch, &(epoch(e) + 1));
+    e.storage().persistent().remove(&DataKey::Leaders);
+    e.storage().instance().set(&DataKey::CurrentEpoch, &current);
+}
+
+fn caller_key(e: &Env, caller: &Address) -> DataKey {
+    DataKey::Caller(epoch(e), caller.clone())
+}
+
+fn epoch(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::Epoch).unwrap_or(0)
+}
+
+fn is_paused(e: &Env) -> bool {
+    e.storage()
+        .instance()
+        .get(&DataKey::Paused)
+        .unwrap_or(false)
+}
+
+fn check_not_paused(e: &Env) -> Result<(), CounterError> {
+    if is_paused(e) {
+        return Err(CounterError::Paused);
+    }
+    Ok(())
+}
+
+fn allowlist_enabled(e: &Env) -> bool {
+    e.storage()
+        .instance()
+        .get(&DataKey::AllowlistEnabled)
+        .unwrap_or(false)
+}
+
+fn check_allowed(e: &Env, caller: &Address) -> Result<(), CounterError> {
+    if allowlist_enabled(e)
+        && !e
+            .storage()
+            .persistent()
+            .has(&DataKey::Allowed(caller.clone()))
+    {
+        return Err(CounterError::NotAllowed);
+    }
+    Ok(())
+}
+
+fn require_admin(e: &Env) -> Result<Address, CounterError> {
+    let admin: Address = e
+        .storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(CounterError::NoAdmin)?;
+    admin.require_auth();
+    Ok(admin)
+}
+
+/// The total, read from `LEGACY_TOTAL` while `migrate` has not moved it and
+/// no write has replaced it.
+fn total(e: &Env) -> u64 {
+    if let Some(total) = e.storage().persistent().get(&DataKey::TotalV2) {
+        return total;
     }
+    e.storage()
+        .persistent()
+        .get::<_, u32>(&LEGACY_TOTAL)
+        .map_or(0, u64::from)
+}
+
+fn get_count(e: &Env, key: &DataKey) -> u64 {
+    e.storage().persistent().get(key).unwrap_or(0)
+}
+
+/// Writes a count, notes the ledger it was written in, and keeps it and the
+/// contract instance from being archived while the counter is in use.
+fn store_count(e: &Env, key: &DataKey, value: u64) {
+    e.storage().persistent().set(key, &value);
+    e.storage()
+        .instance()
+        .set(&DataKey::LastModified, &e.ledger().sequence());
+    extend(e, key, TTL_THRESHOLD, TTL_EXTEND_TO);
+    e.storage()
+        .instance()
+        .extend_ttl(TTL_THRESHOLD, TTL_EXTEND_TO);
+}
+
+/// Extends a persistent entry's TTL to `extend_to` if below `threshold`.
+fn extend(e: &Env, key: &DataKey, threshold: u32, extend_to: u32) {
+    e.storage()
+        .persistent()
+        .extend_ttl(key, threshold, extend_to);
 }
 
 #[cfg(test)]
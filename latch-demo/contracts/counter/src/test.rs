@@ -1,13 +1,58 @@
 #![cfg(test)]
-use crate::{Counter, CounterClient};
-use soroban_sdk::{testutils::Address as _, Address, Env};
+use crate::{
+    Counter, CounterClient, CounterError, DataKey, HistoryEntry, UnderflowMode, DEFAULT_NAME,
+    LEGACY_TOTAL, MAX_BATCH, MAX_HISTORY, MAX_LEADERS, MAX_MEMO_LEN, MAX_NAMES, TTL_EXTEND_TO,
+    TTL_THRESHOLD,
+};
+use soroban_sdk::{
+    contract, contractimpl, symbol_short,
+    testutils::{
+        storage::{Instance as _, Persistent as _},
+        Address as _, Events as _, Ledger as _, MockAuth, MockAuthInvoke,
+    },
+    token::{StellarAssetClient, TokenClient},
+    vec, Address, Env, IntoVal, String, Symbol, Val, Vec,
+};
+
+extern crate std;
+
+/// Records the counts it is told of.
+#[contract]
+pub(crate) struct RecordingHook;
+
+#[contractimpl]
+impl RecordingHook {
+    pub fn on_milestone(e: Env, count: u64) {
+        let mut calls = Self::calls(e.clone());
+        calls.push_back(count);
+        e.storage().instance().set(&symbol_short!("calls"), &calls);
+    }
+
+    pub fn calls(e: Env) -> Vec<u64> {
+        e.storage()
+            .instance()
+            .get(&symbol_short!("calls"))
+            .unwrap_or_else(|| Vec::new(&e))
+    }
+}
+
+/// Traps on every milestone.
+#[contract]
+pub(crate) struct PanickingHook;
+
+#[contractimpl]
+impl PanickingHook {
+    pub fn on_milestone(_e: Env, _count: u64) {
+        panic!("hook failed");
+    }
+}
 
 #[test]
 fn test_increment() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register(Counter, ());
+    let contract_id = env.register(Counter, (None::<Address>,));
     let client = CounterClient::new(&env, &contract_id);
 
     let caller = Address::generate(&env);
@@ -31,7 +76,7 @@ fn test_multiple_callers() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register(Counter, ());
+    let contract_id = env.register(Counter, (None::<Address>,));
     let client = CounterClient::new(&env, &contract_id);
 
     let caller1 = Address::generate(&env);
@@ -44,3 +89,1907 @@ fn test_multiple_callers() {
 
     assert_eq!(client.get(), 3);
 }
+
+#[test]
+fn test_decrement() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+
+    client.increment(&caller);
+    client.increment(&caller);
+
+    assert_eq!(client.decrement(&caller), 1);
+    assert_eq!(client.get(), 1);
+    assert_eq!(client.decrement(&caller), 0);
+    assert_eq!(client.get(), 0);
+}
+
+#[test]
+fn test_decrement_at_zero_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+
+    assert_eq!(
+        client.try_decrement(&caller),
+        Err(Ok(CounterError::Underflow))
+    );
+    assert_eq!(client.get(), 0);
+
+    // Nor below zero after coming back down to it
+    client.increment(&caller);
+    client.decrement(&caller);
+    assert_eq!(
+        client.try_decrement(&caller),
+        Err(Ok(CounterError::Underflow))
+    );
+    assert_eq!(client.get(), 0);
+}
+
+fn clamped_event(
+    env: &Env,
+    contract_id: &Address,
+    caller: &Address,
+    value: u64,
+) -> (Address, Vec<Val>, Val) {
+    (
+        contract_id.clone(),
+        (symbol_short!("counter"), symbol_short!("clamped")).into_val(env),
+        (caller.clone(), value).into_val(env),
+    )
+}
+
+#[test]
+fn test_clamp_mode_leaves_zero() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    assert_eq!(client.get_underflow_mode(), UnderflowMode::Error);
+
+    client.set_underflow_mode(&UnderflowMode::Clamp);
+    assert_eq!(client.get_underflow_mode(), UnderflowMode::Clamp);
+    assert_eq!(client.decrement(&caller), 0);
+    assert_eq!(
+        env.events().all(),
+        vec![&env, clamped_event(&env, &client.address, &caller, 0)]
+    );
+    assert_eq!(client.get(), 0);
+
+    // The total another caller built is left alone too
+    let other = Address::generate(&env);
+    client.increment_by(&other, &3);
+    assert_eq!(client.decrement(&caller), 3);
+    assert_eq!(
+        env.events().all(),
+        vec![&env, clamped_event(&env, &client.address, &caller, 3)]
+    );
+    assert_eq!(client.get_for(&other), 3);
+}
+
+#[test]
+fn test_clamp_mode_is_silent_above_zero() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    client.set_underflow_mode(&UnderflowMode::Clamp);
+    client.increment(&caller);
+    assert_eq!(client.decrement(&caller), 0);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            counter_event(
+                &env,
+                &client.address,
+                (symbol_short!("counter"), symbol_short!("dec")).into_val(&env),
+                &caller,
+                1,
+                0
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_underflow_mode_switches_mid_sequence() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    client.increment(&caller);
+    client.decrement(&caller);
+    assert_eq!(
+        client.try_decrement(&caller),
+        Err(Ok(CounterError::Underflow))
+    );
+
+    client.set_underflow_mode(&UnderflowMode::Clamp);
+    assert_eq!(client.decrement(&caller), 0);
+    client.increment(&caller);
+    assert_eq!(client.decrement(&caller), 0);
+
+    client.set_underflow_mode(&UnderflowMode::Error);
+    assert_eq!(
+        client.try_decrement(&caller),
+        Err(Ok(CounterError::Underflow))
+    );
+}
+
+#[test]
+fn test_interleaved_increment_and_decrement() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+
+    client.increment(&caller);
+    client.increment(&caller);
+    client.decrement(&caller);
+    client.increment(&caller);
+    client.increment(&caller);
+    client.decrement(&caller);
+
+    assert_eq!(client.increment(&caller), 3);
+    assert_eq!(client.get(), 3);
+}
+
+/// Stores `count` as the counter's total, as if other callers had reached
+/// it.
+fn set_count(env: &Env, contract_id: &Address, count: u64) {
+    env.as_contract(contract_id, || {
+        env.storage().persistent().set(&DataKey::TotalV2, &count);
+    });
+}
+
+/// Stores `count` where the counter kept its total before `DataKey`.
+fn set_legacy_count(env: &Env, contract_id: &Address, count: u32) {
+    env.as_contract(contract_id, || {
+        env.storage().persistent().set(&LEGACY_TOTAL, &count);
+    });
+}
+
+/// Whether the legacy total and the current one are stored, in that order.
+fn stored_totals(env: &Env, contract_id: &Address) -> (bool, bool) {
+    env.as_contract(contract_id, || {
+        (
+            env.storage().persistent().has(&LEGACY_TOTAL),
+            env.storage().persistent().has(&DataKey::TotalV2),
+        )
+    })
+}
+
+#[test]
+fn test_legacy_total_is_read_before_migration() {
+    let env = Env::default();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    set_legacy_count(&env, &contract_id, 41);
+    assert_eq!(client.get(), 41);
+    assert_eq!(client.get_named(&DEFAULT_NAME), 41);
+}
+
+#[test]
+fn test_migrate_moves_legacy_total() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let caller = Address::generate(&env);
+
+    set_legacy_count(&env, &contract_id, u32::MAX);
+    client.migrate();
+    assert_eq!(stored_totals(&env, &contract_id), (false, true));
+    assert_eq!(client.get(), u64::from(u32::MAX));
+
+    // Again, nothing is left to move
+    client.migrate();
+    assert_eq!(client.get(), u64::from(u32::MAX));
+
+    // Past what the legacy entry could hold, in the new key alone
+    assert_eq!(client.increment(&caller), u64::from(u32::MAX) + 1);
+    assert_eq!(stored_totals(&env, &contract_id), (false, true));
+}
+
+#[test]
+fn test_increment_before_migration_carries_on_from_legacy_total() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let caller = Address::generate(&env);
+
+    set_legacy_count(&env, &contract_id, 7);
+    assert_eq!(client.increment(&caller), 8);
+    assert_eq!(stored_totals(&env, &contract_id), (true, true));
+
+    // The new total already counts the old one, which is only removed
+    client.migrate();
+    assert_eq!(stored_totals(&env, &contract_id), (false, true));
+    assert_eq!(client.get(), 8);
+}
+
+#[test]
+fn test_migrate_without_legacy_total_is_noop() {
+    let env = Env::default();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    client.migrate();
+    assert_eq!(stored_totals(&env, &contract_id), (false, false));
+    assert_eq!(client.get(), 0);
+}
+
+#[test]
+fn test_increment_by() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+
+    assert_eq!(client.increment_by(&caller, &5), 5);
+    client.increment(&caller);
+    assert_eq!(client.increment_by(&caller, &10), 16);
+    assert_eq!(client.get(), 16);
+}
+
+#[test]
+fn test_increment_by_zero_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+    client.increment(&caller);
+
+    assert_eq!(
+        client.try_increment_by(&caller, &0),
+        Err(Ok(CounterError::ZeroAmount))
+    );
+    assert_eq!(client.get(), 1);
+}
+
+#[test]
+fn test_increment_by_overflow_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+    set_count(&env, &contract_id, u64::MAX - 2);
+
+    assert_eq!(
+        client.try_increment_by(&caller, &3),
+        Err(Ok(CounterError::Overflow))
+    );
+    assert_eq!(client.get(), u64::MAX - 2);
+
+    // Up to the max itself is fine
+    assert_eq!(client.increment_by(&caller, &2), u64::MAX);
+    assert_eq!(
+        client.try_increment_by(&caller, &1),
+        Err(Ok(CounterError::Overflow))
+    );
+}
+
+#[test]
+fn test_get_and_increment_returns_value_before() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+
+    assert_eq!(client.get_and_increment(&caller), 0);
+    assert_eq!(client.get(), 1);
+    assert_eq!(client.get_for(&caller), 1);
+
+    assert_eq!(client.get_and_increment(&caller), 1);
+    assert_eq!(client.get_and_increment(&caller), 2);
+    assert_eq!(client.get(), 3);
+
+    // It counts like the other increments
+    assert_eq!(client.increment(&caller), 4);
+    assert_eq!(client.get_and_increment(&caller), 4);
+}
+
+#[test]
+fn test_get_and_increment_fails_like_increment_by() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    client.set_cap(&Some(1));
+    assert_eq!(client.get_and_increment(&caller), 0);
+    assert_eq!(
+        client.try_get_and_increment(&caller),
+        Err(Ok(CounterError::CapExceeded))
+    );
+    assert_eq!(client.get(), 1);
+
+    client.set_cap(&None);
+    set_count(&env, &client.address, u64::MAX - 1);
+    assert_eq!(client.get_and_increment(&caller), u64::MAX - 1);
+    assert_eq!(
+        client.try_get_and_increment(&caller),
+        Err(Ok(CounterError::Overflow))
+    );
+    assert_eq!(client.get(), u64::MAX);
+}
+
+#[test]
+fn test_per_caller_counts() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller1 = Address::generate(&env);
+    let caller2 = Address::generate(&env);
+
+    client.increment(&caller1);
+    client.increment(&caller2);
+    client.increment(&caller1);
+    client.increment(&caller2);
+
+    assert_eq!(client.get_for(&caller1), 2);
+    assert_eq!(client.get_for(&caller2), 2);
+    assert_eq!(client.get(), 4);
+
+    // An address that never called has no count
+    assert_eq!(client.get_for(&Address::generate(&env)), 0);
+}
+
+#[test]
+fn test_decrement_only_undoes_own_count() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller1 = Address::generate(&env);
+    let caller2 = Address::generate(&env);
+
+    client.increment_by(&caller1, &3);
+    assert_eq!(
+        client.try_decrement(&caller2),
+        Err(Ok(CounterError::Underflow))
+    );
+
+    assert_eq!(client.decrement(&caller1), 2);
+    assert_eq!(client.get_for(&caller1), 2);
+    assert_eq!(client.get_for(&caller2), 0);
+}
+
+#[test]
+fn test_named_counters_are_independent() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+    let swap = symbol_short!("swap");
+    let mint = symbol_short!("mint");
+
+    assert_eq!(client.increment_named(&caller, &swap), 1);
+    assert_eq!(client.increment_named(&caller, &swap), 2);
+    assert_eq!(client.increment_named(&caller, &mint), 1);
+
+    assert_eq!(client.get_named(&swap), 2);
+    assert_eq!(client.get_named(&mint), 1);
+    assert_eq!(client.get_named(&symbol_short!("stake")), 0);
+    // The default counter is untouched
+    assert_eq!(client.get(), 0);
+
+    assert_eq!(client.list_names(), Vec::from_array(&env, [swap, mint]));
+}
+
+#[test]
+fn test_named_counter_cap() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.cost_estimate().budget().reset_unlimited();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+    for i in 0..MAX_NAMES {
+        client.increment_named(&caller, &Symbol::new(&env, &std::format!("n{i}")));
+    }
+    assert_eq!(client.list_names().len(), MAX_NAMES);
+
+    assert_eq!(
+        client.try_increment_named(&caller, &symbol_short!("onemore")),
+        Err(Ok(CounterError::TooManyNames))
+    );
+    // Existing names, and the default counter, still increment
+    assert_eq!(client.increment_named(&caller, &symbol_short!("n0")), 2);
+    assert_eq!(client.increment(&caller), 1);
+}
+
+#[test]
+fn test_increment_many() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let caller = Address::generate(&env);
+    let (swap, send) = (symbol_short!("swap"), symbol_short!("send"));
+
+    client.increment_named(&caller, &send);
+    assert_eq!(
+        client.increment_many(
+            &caller,
+            &vec![&env, swap.clone(), DEFAULT_NAME, send.clone()]
+        ),
+        vec![&env, 1, 1, 2]
+    );
+    assert_eq!(client.get_named(&swap), 1);
+    assert_eq!(client.get(), 1);
+    assert_eq!(client.get_for(&caller), 1);
+    assert_eq!(client.get_named(&send), 2);
+}
+
+#[test]
+fn test_increment_many_applies_duplicates_in_turn() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let caller = Address::generate(&env);
+    let swap = symbol_short!("swap");
+
+    assert_eq!(
+        client.increment_many(
+            &caller,
+            &vec![&env, swap.clone(), DEFAULT_NAME, swap.clone(), swap.clone()]
+        ),
+        vec![&env, 1, 1, 2, 3]
+    );
+    assert_eq!(client.get_named(&swap), 3);
+}
+
+#[test]
+fn test_increment_many_rolls_back_on_failure() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    let swap = symbol_short!("swap");
+
+    // The default counter is the second name, and at its cap
+    client.set_cap(&Some(1));
+    client.increment(&caller);
+    assert_eq!(
+        client.try_increment_many(&caller, &vec![&env, swap.clone(), DEFAULT_NAME]),
+        Err(Ok(CounterError::CapExceeded))
+    );
+    assert_eq!(client.get_named(&swap), 0);
+    assert!(client.list_names().is_empty());
+    assert_eq!(client.get(), 1);
+}
+
+#[test]
+fn test_increment_many_is_bounded() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let caller = Address::generate(&env);
+
+    let mut names = Vec::new(&env);
+    for _ in 0..MAX_BATCH {
+        names.push_back(symbol_short!("swap"));
+    }
+    assert_eq!(client.increment_many(&caller, &names).len(), MAX_BATCH);
+
+    names.push_back(symbol_short!("swap"));
+    assert_eq!(
+        client.try_increment_many(&caller, &names),
+        Err(Ok(CounterError::BatchTooLong))
+    );
+    assert_eq!(
+        client.get_named(&symbol_short!("swap")),
+        u64::from(MAX_BATCH)
+    );
+}
+
+#[test]
+fn test_legacy_entrypoints_use_default_counter() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+
+    client.increment(&caller);
+    assert_eq!(client.get_named(&DEFAULT_NAME), 1);
+    assert_eq!(client.increment_named(&caller, &DEFAULT_NAME), 2);
+    assert_eq!(client.get(), 2);
+    assert_eq!(client.get_for(&caller), 2);
+
+    // The default counter is not a created name
+    assert!(client.list_names().is_empty());
+}
+
+/// The `(contract, topics, data)` event a mutation publishes: data is
+/// `(caller, old_value, new_value)`.
+fn counter_event(
+    env: &Env,
+    contract_id: &Address,
+    topics: Vec<Val>,
+    caller: &Address,
+    old_value: u64,
+    new_value: u64,
+) -> (Address, Vec<Val>, Val) {
+    (
+        contract_id.clone(),
+        topics,
+        (caller.clone(), old_value, new_value).into_val(env),
+    )
+}
+
+/// The `inc` event of an increment from `old_value` to `new_value`, with
+/// the memo `increment_with_memo` gave it, empty for other calls.
+fn inc_event(
+    env: &Env,
+    contract_id: &Address,
+    caller: &Address,
+    old_value: u64,
+    new_value: u64,
+    memo: &str,
+) -> (Address, Vec<Val>, Val) {
+    (
+        contract_id.clone(),
+        (symbol_short!("counter"), symbol_short!("inc")).into_val(env),
+        (
+            caller.clone(),
+            old_value,
+            new_value,
+            String::from_str(env, memo),
+        )
+            .into_val(env),
+    )
+}
+
+#[test]
+fn test_increment_emits_one_event_per_call() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+
+    // The events of the last call only, so one each
+    for old_value in 0..3u64 {
+        client.increment(&caller);
+        assert_eq!(
+            env.events().all(),
+            vec![
+                &env,
+                inc_event(&env, &contract_id, &caller, old_value, old_value + 1, "")
+            ]
+        );
+    }
+
+    client.increment_by(&caller, &5);
+    assert_eq!(
+        env.events().all(),
+        vec![&env, inc_event(&env, &contract_id, &caller, 3, 8, "")]
+    );
+}
+
+#[test]
+fn test_decrement_emits_event() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+    client.increment_by(&caller, &2);
+
+    client.decrement(&caller);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            counter_event(
+                &env,
+                &contract_id,
+                (symbol_short!("counter"), symbol_short!("dec")).into_val(&env),
+                &caller,
+                2,
+                1
+            )
+        ]
+    );
+
+    // A failed decrement publishes nothing
+    client.decrement(&caller);
+    assert!(client.try_decrement(&caller).is_err());
+    assert!(env.events().all().is_empty());
+}
+
+#[test]
+fn test_increment_named_emits_event() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+    let swap = symbol_short!("swap");
+
+    client.increment_named(&caller, &swap);
+    client.increment_named(&caller, &swap);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            counter_event(
+                &env,
+                &contract_id,
+                (
+                    symbol_short!("counter"),
+                    Symbol::new(&env, "inc_named"),
+                    swap
+                )
+                    .into_val(&env),
+                &caller,
+                1,
+                2
+            )
+        ]
+    );
+}
+
+/// A counter administered by a fresh address.
+fn setup_with_admin(env: &Env) -> (CounterClient<'_>, Address) {
+    let admin = Address::generate(env);
+    let contract_id = env.register(Counter, (Some(admin.clone()),));
+    (CounterClient::new(env, &contract_id), admin)
+}
+
+#[test]
+fn test_reset_requires_admin() {
+    let env = Env::default();
+    let (client, admin) = setup_with_admin(&env);
+    assert_eq!(client.admin(), Some(admin));
+
+    let stranger = Address::generate(&env);
+    env.mock_auths(&[MockAuth {
+        address: &stranger,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "reset",
+            args: ().into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_reset().is_err());
+
+    // Nor can anyone reset a counter with no admin
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    assert_eq!(
+        CounterClient::new(&env, &contract_id).try_reset(),
+        Err(Ok(CounterError::NoAdmin))
+    );
+}
+
+#[test]
+fn test_reset_clears_state() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup_with_admin(&env);
+
+    let caller1 = Address::generate(&env);
+    let caller2 = Address::generate(&env);
+    let swap = symbol_short!("swap");
+    client.increment(&caller1);
+    client.increment_by(&caller2, &4);
+    client.increment_named(&caller1, &swap);
+
+    client.reset();
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            counter_event(
+                &env,
+                &client.address,
+                (symbol_short!("counter"), symbol_short!("reset")).into_val(&env),
+                &admin,
+                5,
+                0
+            )
+        ]
+    );
+    assert_eq!(client.get(), 0);
+    assert_eq!(client.get_for(&caller1), 0);
+    assert_eq!(client.get_for(&caller2), 0);
+    assert_eq!(client.get_named(&swap), 0);
+    assert!(client.list_names().is_empty());
+
+    // Counting starts over, and a caller cannot decrement into old counts
+    assert_eq!(
+        client.try_decrement(&caller2),
+        Err(Ok(CounterError::Underflow))
+    );
+    assert_eq!(client.increment(&caller1), 1);
+    assert_eq!(client.get_for(&caller1), 1);
+}
+
+#[test]
+fn test_admin_transfer_takes_two_steps() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup_with_admin(&env);
+
+    // A typo'd address is only proposed, and the admin can still act
+    let typo = Address::generate(&env);
+    client.transfer_admin(&typo);
+    assert_eq!(client.admin(), Some(admin.clone()));
+    assert_eq!(client.pending_admin(), Some(typo));
+    client.reset();
+
+    // A later proposal replaces it, and only the proposed admin accepts
+    let new_admin = Address::generate(&env);
+    client.transfer_admin(&new_admin);
+    env.mock_auths(&[MockAuth {
+        address: &admin,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "accept_admin",
+            args: ().into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_accept_admin().is_err());
+
+    env.mock_all_auths();
+    client.accept_admin();
+    assert_eq!(client.admin(), Some(new_admin));
+    assert_eq!(client.pending_admin(), None);
+    assert_eq!(
+        client.try_accept_admin(),
+        Err(Ok(CounterError::NoPendingAdmin))
+    );
+}
+
+#[test]
+fn test_first_claim_admin_wins() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    assert_eq!(client.admin(), None);
+
+    let first = Address::generate(&env);
+    client.claim_admin(&first);
+    assert_eq!(client.admin(), Some(first));
+
+    assert_eq!(
+        client.try_claim_admin(&Address::generate(&env)),
+        Err(Ok(CounterError::AdminAlreadySet))
+    );
+    client.reset();
+}
+
+#[test]
+fn test_cap_blocks_increments_past_it() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    assert_eq!(client.get_cap(), None);
+
+    client.set_cap(&Some(3));
+    assert_eq!(client.get_cap(), Some(3));
+    client.increment(&caller);
+    client.increment(&caller);
+    assert_eq!(client.increment(&caller), 3);
+    assert_eq!(
+        client.try_increment(&caller),
+        Err(Ok(CounterError::CapExceeded))
+    );
+    assert_eq!(client.get(), 3);
+
+    // Raising the cap unblocks, up to the new cap
+    client.set_cap(&Some(5));
+    assert_eq!(client.increment(&caller), 4);
+
+    // An increment_by that would jump over the cap leaves the counts as
+    // they were
+    assert_eq!(
+        client.try_increment_by(&caller, &2),
+        Err(Ok(CounterError::CapExceeded))
+    );
+    assert_eq!(client.get(), 4);
+    assert_eq!(client.get_for(&caller), 4);
+    assert_eq!(client.increment_by(&caller, &1), 5);
+
+    // Without a cap the count grows again
+    client.set_cap(&None);
+    assert_eq!(client.get_cap(), None);
+    assert_eq!(client.increment_by(&caller, &10), 15);
+}
+
+#[test]
+fn test_set_cap_requires_admin() {
+    let env = Env::default();
+    let (client, _) = setup_with_admin(&env);
+
+    let stranger = Address::generate(&env);
+    let cap = Some(1u64);
+    env.mock_auths(&[MockAuth {
+        address: &stranger,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "set_cap",
+            args: (cap,).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_set_cap(&cap).is_err());
+    assert_eq!(client.get_cap(), None);
+}
+
+#[test]
+fn test_rate_limit_blocks_increments_within_a_ledger() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    assert_eq!(client.get_rate_limit(), None);
+
+    client.set_rate_limit(&Some(2));
+    assert_eq!(client.get_rate_limit(), Some(2));
+    client.increment(&caller);
+    client.increment(&caller);
+    assert_eq!(
+        client.try_increment(&caller),
+        Err(Ok(CounterError::RateLimited))
+    );
+    assert_eq!(client.get(), 2);
+
+    // The next ledger starts a new window
+    env.ledger()
+        .set_sequence_number(env.ledger().sequence() + 1);
+    assert_eq!(client.increment(&caller), 3);
+    assert_eq!(client.increment_by(&caller, &5), 8);
+    assert_eq!(
+        client.try_increment_by(&caller, &1),
+        Err(Ok(CounterError::RateLimited))
+    );
+
+    // Without a limit every increment goes through
+    client.set_rate_limit(&None);
+    assert_eq!(client.get_rate_limit(), None);
+    for _ in 0..5 {
+        client.increment(&caller);
+    }
+    assert_eq!(client.get(), 13);
+}
+
+#[test]
+fn test_set_rate_limit_requires_admin() {
+    let env = Env::default();
+    let (client, _) = setup_with_admin(&env);
+
+    let stranger = Address::generate(&env);
+    let max = Some(1u32);
+    env.mock_auths(&[MockAuth {
+        address: &stranger,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "set_rate_limit",
+            args: (max,).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_set_rate_limit(&max).is_err());
+    assert_eq!(client.get_rate_limit(), None);
+}
+
+#[test]
+fn test_caller_quota_is_per_caller() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    env.ledger().set_sequence_number(1_000);
+
+    client.set_caller_quota(&Some(2), &100);
+    client.increment(&alice);
+    client.increment_by(&alice, &5);
+    assert_eq!(
+        client.try_increment(&alice),
+        Err(Ok(CounterError::QuotaExceeded))
+    );
+    assert_eq!(
+        client.try_increment_by(&alice, &1),
+        Err(Ok(CounterError::QuotaExceeded))
+    );
+    assert_eq!(client.get_quota_usage(&alice), 2);
+    assert_eq!(client.get_for(&alice), 6);
+
+    // Bob's usage is counted apart
+    assert_eq!(client.get_quota_usage(&bob), 0);
+    client.increment(&bob);
+    assert_eq!(client.get_quota_usage(&bob), 1);
+    assert_eq!(client.get(), 7);
+
+    // Usage lasts the whole window, past a temporary entry's minimum TTL
+    advance_ledgers(&env, 99);
+    assert_eq!(client.get_quota_usage(&alice), 2);
+    assert!(client.try_increment(&alice).is_err());
+}
+
+#[test]
+fn test_caller_quota_window_past_max_ttl() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    client.set_caller_quota(&Some(2), &u32::MAX);
+    client.increment(&caller);
+    client.increment(&caller);
+    assert_eq!(
+        client.try_increment(&caller),
+        Err(Ok(CounterError::QuotaExceeded))
+    );
+    assert_eq!(client.get_quota_usage(&caller), 2);
+}
+
+#[test]
+fn test_caller_quota_resets_each_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    env.ledger().set_sequence_number(1_050);
+
+    client.set_caller_quota(&Some(1), &100);
+    client.increment(&caller);
+    assert!(client.try_increment(&caller).is_err());
+
+    // 1_100 starts the next window
+    env.ledger().set_sequence_number(1_100);
+    assert_eq!(client.get_quota_usage(&caller), 0);
+    assert_eq!(client.increment(&caller), 2);
+    assert_eq!(client.get_quota_usage(&caller), 1);
+}
+
+#[test]
+fn test_caller_quota_and_rate_limit_both_apply() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+
+    client.set_caller_quota(&Some(5), &100);
+    client.set_rate_limit(&Some(1));
+    client.increment(&alice);
+    assert_eq!(
+        client.try_increment(&bob),
+        Err(Ok(CounterError::RateLimited))
+    );
+    // A rejected increment uses none of the quota
+    assert_eq!(client.get_quota_usage(&bob), 0);
+}
+
+#[test]
+fn test_disabling_caller_quota() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    client.set_caller_quota(&Some(1), &100);
+    client.increment(&caller);
+    assert!(client.try_increment(&caller).is_err());
+
+    client.set_caller_quota(&None, &0);
+    assert_eq!(client.get_quota_usage(&caller), 0);
+    for _ in 0..3 {
+        client.increment(&caller);
+    }
+    assert_eq!(client.get(), 4);
+}
+
+#[test]
+fn test_caller_quota_needs_a_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+
+    assert_eq!(
+        client.try_set_caller_quota(&Some(1), &0),
+        Err(Ok(CounterError::BadQuotaWindow))
+    );
+}
+
+#[test]
+fn test_set_caller_quota_requires_admin() {
+    let env = Env::default();
+    let (client, _) = setup_with_admin(&env);
+
+    let stranger = Address::generate(&env);
+    let caller = Address::generate(&env);
+    let (max, window) = (Some(1u32), 100u32);
+    env.mock_auths(&[MockAuth {
+        address: &stranger,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "set_caller_quota",
+            args: (max, window).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_set_caller_quota(&max, &window).is_err());
+
+    env.mock_all_auths();
+    client.increment(&caller);
+    client.increment(&caller);
+    assert_eq!(client.get_quota_usage(&caller), 0);
+}
+
+/// The totals in `client`'s history, oldest first.
+fn history_values(client: &CounterClient) -> std::vec::Vec<u64> {
+    client.get_history().iter().map(|h| h.new_value).collect()
+}
+
+#[test]
+fn test_history_wraps_around() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    assert_eq!(client.get_history(), Vec::new(&env));
+
+    client.set_history_size(&3);
+    assert_eq!(client.get_history_size(), 3);
+    client.increment(&caller);
+    client.increment_by(&caller, &4);
+    assert_eq!(history_values(&client), [1, 5]);
+
+    // Two past full, the oldest two are overwritten
+    for _ in 0..3 {
+        client.increment(&caller);
+    }
+    assert_eq!(history_values(&client), [6, 7, 8]);
+    assert_eq!(
+        client.get_history().get(2),
+        Some(HistoryEntry {
+            caller,
+            new_value: 8,
+            ledger_seq: env.ledger().sequence(),
+            memo: String::from_str(&env, ""),
+        })
+    );
+}
+
+#[test]
+fn test_increment_with_memo() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    let memo = String::from_str(&env, "gm from the demo");
+
+    client.set_history_size(&4);
+    assert_eq!(client.increment_with_memo(&caller, &memo), 1);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            inc_event(&env, &client.address, &caller, 0, 1, "gm from the demo")
+        ]
+    );
+
+    // Other calls leave the memo empty
+    client.increment(&caller);
+    let memos: std::vec::Vec<String> = client.get_history().iter().map(|h| h.memo).collect();
+    assert_eq!(memos, [memo, String::from_str(&env, "")]);
+}
+
+#[test]
+fn test_memo_length_is_bounded() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    client.set_history_size(&4);
+
+    let longest = "m".repeat(MAX_MEMO_LEN as usize);
+    client.increment_with_memo(&caller, &String::from_str(&env, &longest));
+    assert_eq!(
+        client.try_increment_with_memo(&caller, &String::from_str(&env, &(longest + "m"))),
+        Err(Ok(CounterError::MemoTooLong))
+    );
+    assert_eq!(client.get(), 1);
+    assert_eq!(client.get_history().len(), 1);
+}
+
+#[test]
+fn test_increment_with_memo_counts_past_u32() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    let memo = String::from_str(&env, "gm");
+
+    set_count(&env, &client.address, u64::from(u32::MAX));
+    assert_eq!(
+        client.increment_with_memo(&caller, &memo),
+        u64::from(u32::MAX) + 1
+    );
+    assert_eq!(client.get(), u64::from(u32::MAX) + 1);
+}
+
+#[test]
+fn test_history_records_each_caller() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller1 = Address::generate(&env);
+    let caller2 = Address::generate(&env);
+
+    client.set_history_size(&4);
+    client.increment(&caller1);
+    client.increment(&caller2);
+    client.get_and_increment(&caller1);
+    // Named counters are not the total, and are not recorded
+    client.increment_named(&caller2, &symbol_short!("swap"));
+
+    let callers: std::vec::Vec<Address> = client.get_history().iter().map(|h| h.caller).collect();
+    assert_eq!(callers, [caller1.clone(), caller2, caller1]);
+}
+
+#[test]
+fn test_disabled_history_keeps_its_entries() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    // Off by default, with nothing written
+    client.increment(&caller);
+    env.as_contract(&client.address, || {
+        assert!(!env.storage().persistent().has(&DataKey::History(0)));
+    });
+
+    client.set_history_size(&2);
+    client.increment(&caller);
+    client.set_history_size(&0);
+    assert_eq!(client.get_history_size(), 0);
+    client.increment(&caller);
+    assert_eq!(history_values(&client), [2]);
+
+    // The same size resumes the history, another starts it over
+    client.set_history_size(&2);
+    client.increment(&caller);
+    assert_eq!(history_values(&client), [2, 4]);
+    client.set_history_size(&5);
+    assert!(client.get_history().is_empty());
+}
+
+#[test]
+fn test_history_size_is_bounded() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+
+    client.set_history_size(&MAX_HISTORY);
+    assert_eq!(
+        client.try_set_history_size(&(MAX_HISTORY + 1)),
+        Err(Ok(CounterError::HistoryTooLarge))
+    );
+    assert_eq!(client.get_history_size(), MAX_HISTORY);
+}
+
+fn milestone_event(env: &Env, contract_id: &Address, count: u64) -> (Address, Vec<Val>, Val) {
+    (
+        contract_id.clone(),
+        (symbol_short!("counter"), symbol_short!("milestone")).into_val(env),
+        (count,).into_val(env),
+    )
+}
+
+#[test]
+fn test_milestone_every_ten_increments() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    assert_eq!(client.get_milestone(), None);
+
+    client.set_milestone(&10, &None);
+    assert_eq!(client.get_milestone(), Some((10, None)));
+    for old_value in 0..20u64 {
+        let total = client.increment(&caller);
+        let mut expected = vec![
+            &env,
+            inc_event(&env, &client.address, &caller, old_value, total, ""),
+        ];
+        if total == 10 || total == 20 {
+            expected.push_back(milestone_event(&env, &client.address, total));
+        }
+        assert_eq!(env.events().all(), expected);
+    }
+
+    // Jumping over a multiple still reaches it
+    client.increment_by(&caller, &15);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            inc_event(&env, &client.address, &caller, 20, 35, ""),
+            milestone_event(&env, &client.address, 30),
+        ]
+    );
+
+    client.set_milestone(&0, &None);
+    assert_eq!(client.get_milestone(), None);
+    client.increment_by(&caller, &5);
+    assert_eq!(
+        env.events().all(),
+        vec![&env, inc_event(&env, &client.address, &caller, 35, 40, "")]
+    );
+}
+
+#[test]
+fn test_milestone_calls_hook() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    let hook = RecordingHookClient::new(&env, &env.register(RecordingHook, ()));
+
+    client.set_milestone(&3, &Some(hook.address.clone()));
+    for _ in 0..7 {
+        client.increment(&caller);
+    }
+    assert_eq!(hook.calls(), vec![&env, 3, 6]);
+}
+
+#[test]
+fn test_trapping_hook_does_not_fail_increment() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    let hook = env.register(PanickingHook, ());
+
+    client.set_milestone(&2, &Some(hook));
+    client.increment(&caller);
+    assert_eq!(client.increment(&caller), 2);
+    assert_eq!(client.get(), 2);
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            inc_event(&env, &client.address, &caller, 1, 2, ""),
+            milestone_event(&env, &client.address, 2),
+        ]
+    );
+}
+
+/// A Stellar Asset Contract with `amount` minted to `holder`.
+fn fee_token(env: &Env, holder: &Address, amount: i128) -> Address {
+    let issuer = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(issuer).address();
+    StellarAssetClient::new(env, &token).mint(holder, &amount);
+    token
+}
+
+#[test]
+fn test_increment_paid_moves_fee() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    let token = fee_token(&env, &caller, 100);
+    let balances = TokenClient::new(&env, &token);
+
+    client.set_fee(&token, &10);
+    assert_eq!(client.get_fee(), Some((token.clone(), 10)));
+    assert_eq!(client.increment_paid(&caller, &token, &10), 1);
+    // Paying over the fee is allowed, and kept
+    assert_eq!(client.increment_paid(&caller, &token, &15), 2);
+    assert_eq!(balances.balance(&caller), 75);
+    assert_eq!(balances.balance(&client.address), 25);
+    assert_eq!(client.get_for(&caller), 2);
+}
+
+#[test]
+fn test_increment_paid_rejects_underpayment() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    let token = fee_token(&env, &caller, 100);
+    let other = fee_token(&env, &caller, 100);
+
+    assert_eq!(
+        client.try_increment_paid(&caller, &token, &10),
+        Err(Ok(CounterError::FeeNotSet))
+    );
+
+    client.set_fee(&token, &10);
+    assert_eq!(
+        client.try_increment_paid(&caller, &token, &9),
+        Err(Ok(CounterError::FeeTooLow))
+    );
+    assert_eq!(
+        client.try_increment_paid(&caller, &other, &10),
+        Err(Ok(CounterError::WrongFeeToken))
+    );
+    assert_eq!(client.get(), 0);
+    assert_eq!(TokenClient::new(&env, &token).balance(&caller), 100);
+
+    assert_eq!(
+        client.try_set_fee(&token, &-1),
+        Err(Ok(CounterError::FeeTooLow))
+    );
+}
+
+#[test]
+fn test_withdraw_fees_sweeps_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let token = fee_token(&env, &caller, 100);
+    let balances = TokenClient::new(&env, &token);
+
+    client.set_fee(&token, &10);
+    for _ in 0..3 {
+        client.increment_paid(&caller, &token, &10);
+    }
+    assert_eq!(client.withdraw_fees(&treasury), 30);
+    assert_eq!(balances.balance(&treasury), 30);
+    assert_eq!(balances.balance(&client.address), 0);
+
+    // Nothing left to sweep
+    assert_eq!(client.withdraw_fees(&treasury), 0);
+}
+
+#[test]
+fn test_allowlist_rejects_others() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let allowed = Address::generate(&env);
+    let stranger = Address::generate(&env);
+    assert!(!client.allowlist_enabled());
+
+    client.allow(&allowed);
+    client.set_allowlist_enabled(&true);
+    assert!(client.allowlist_enabled());
+    assert!(client.is_allowed(&allowed));
+    assert!(!client.is_allowed(&stranger));
+
+    assert_eq!(client.increment(&allowed), 1);
+    assert_eq!(
+        client.try_increment(&stranger),
+        Err(Ok(CounterError::NotAllowed))
+    );
+    assert_eq!(
+        client.try_increment_by(&stranger, &2),
+        Err(Ok(CounterError::NotAllowed))
+    );
+    assert_eq!(
+        client.try_increment_named(&stranger, &symbol_short!("swap")),
+        Err(Ok(CounterError::NotAllowed))
+    );
+    assert_eq!(client.get(), 1);
+    assert_eq!(client.get_for(&stranger), 0);
+}
+
+#[test]
+fn test_allowlist_off_and_removal() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    client.set_allowlist_enabled(&true);
+    client.allow(&caller);
+    client.increment(&caller);
+
+    client.disallow(&caller);
+    assert!(!client.is_allowed(&caller));
+    assert_eq!(
+        client.try_increment(&caller),
+        Err(Ok(CounterError::NotAllowed))
+    );
+
+    // Off, the list is not consulted
+    client.set_allowlist_enabled(&false);
+    assert_eq!(client.increment(&caller), 2);
+}
+
+#[test]
+fn test_allowlist_requires_admin() {
+    let env = Env::default();
+    let (client, _) = setup_with_admin(&env);
+
+    let stranger = Address::generate(&env);
+    env.mock_auths(&[MockAuth {
+        address: &stranger,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "allow",
+            args: (stranger.clone(),).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_allow(&stranger).is_err());
+    assert!(!client.is_allowed(&stranger));
+}
+
+#[test]
+fn test_scheduled_epoch_restarts_counts() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    env.ledger().set_sequence_number(10);
+
+    client.set_epoch_length(&Some(100));
+    assert_eq!(client.current_epoch(), 0);
+    for _ in 0..3 {
+        client.increment(&caller);
+    }
+
+    // Past the boundary the counts read 0 before anything is written
+    env.ledger().set_sequence_number(150);
+    assert_eq!(client.current_epoch(), 1);
+    assert_eq!(client.get(), 0);
+    assert_eq!(client.get_for(&caller), 0);
+    assert_eq!(client.get_epoch_value(&0), None);
+
+    assert_eq!(client.increment(&caller), 1);
+    assert_eq!(client.get_for(&caller), 1);
+    assert_eq!(client.get_epoch_value(&0), Some(3));
+    assert_eq!(client.get_epoch_value(&1), None);
+}
+
+#[test]
+fn test_epochs_without_writes_keep_no_value() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    client.set_epoch_length(&Some(100));
+    client.increment(&caller);
+    client.increment_named(&caller, &symbol_short!("swap"));
+    env.ledger().set_sequence_number(120);
+    client.increment_by(&caller, &4);
+
+    // Epochs 2 and 3 pass with no writes
+    env.ledger().set_sequence_number(450);
+    assert_eq!(client.increment(&caller), 1);
+    assert_eq!(client.current_epoch(), 4);
+    assert_eq!(client.get_epoch_value(&0), Some(1));
+    assert_eq!(client.get_epoch_value(&1), Some(4));
+    assert_eq!(client.get_epoch_value(&2), None);
+    assert_eq!(client.get_epoch_value(&3), None);
+
+    // Named counters are not on the schedule
+    assert_eq!(client.get_named(&symbol_short!("swap")), 1);
+}
+
+#[test]
+fn test_disabling_epochs_freezes_counts() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+
+    client.set_epoch_length(&Some(100));
+    env.ledger().set_sequence_number(250);
+    client.increment(&caller);
+    client.set_epoch_length(&None);
+
+    env.ledger().set_sequence_number(1_000);
+    assert_eq!(client.current_epoch(), 2);
+    assert_eq!(client.get(), 1);
+    assert_eq!(client.increment(&caller), 2);
+    assert_eq!(client.get_epoch_value(&2), None);
+
+    assert_eq!(
+        client.try_set_epoch_length(&Some(0)),
+        Err(Ok(CounterError::BadEpochLength))
+    );
+}
+
+#[test]
+fn test_top_callers_in_descending_order() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let [a, b, c] = [0; 3].map(|_| Address::generate(&env));
+    assert!(client.top_callers(&5).is_empty());
+
+    client.increment(&a);
+    client.increment_by(&b, &3);
+    client.increment(&c);
+    client.increment(&a);
+    client.increment(&a);
+    client.increment(&c);
+    client.decrement(&b);
+    // b is back down to 2 after c got there
+    assert_eq!(
+        client.top_callers(&3),
+        vec![&env, (a.clone(), 3), (c.clone(), 2), (b.clone(), 2)]
+    );
+
+    // A limit past the tracked callers returns them all, and a lower one
+    // the top
+    assert_eq!(client.top_callers(&10).len(), 3);
+    assert_eq!(client.top_callers(&1), vec![&env, (a, 3)]);
+}
+
+#[test]
+fn test_top_callers_ties_rank_first_to_reach() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let [a, b] = [0; 2].map(|_| Address::generate(&env));
+
+    client.increment(&b);
+    client.increment(&a);
+    assert_eq!(
+        client.top_callers(&2),
+        vec![&env, (b.clone(), 1), (a.clone(), 1)]
+    );
+
+    // a reaches 2 first, then b catches up
+    client.increment(&a);
+    client.increment(&b);
+    assert_eq!(client.top_callers(&2), vec![&env, (a, 2), (b, 2)]);
+}
+
+#[test]
+fn test_top_callers_evicts_the_lowest() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    // Tracked callers with counts 2 and up, the last with the lowest
+    let tracked: std::vec::Vec<Address> = (0..MAX_LEADERS)
+        .map(|i| {
+            let caller = Address::generate(&env);
+            client.increment_by(&caller, &(MAX_LEADERS + 1 - i));
+            caller
+        })
+        .collect();
+    let lowest = tracked.last().unwrap().clone();
+    assert_eq!(
+        client.top_callers(&MAX_LEADERS).last(),
+        Some((lowest.clone(), 2))
+    );
+
+    // A newcomer only enters past the lowest
+    let climber = Address::generate(&env);
+    client.increment(&climber);
+    client.increment(&climber);
+    assert_eq!(client.top_callers(&MAX_LEADERS).len(), MAX_LEADERS);
+    assert_eq!(
+        client.top_callers(&MAX_LEADERS).last(),
+        Some((lowest.clone(), 2))
+    );
+    client.increment(&climber);
+    assert_eq!(
+        client.top_callers(&MAX_LEADERS).last(),
+        Some((climber.clone(), 3))
+    );
+    assert!(!client
+        .top_callers(&MAX_LEADERS)
+        .iter()
+        .any(|(caller, _)| caller == lowest));
+
+    // The evicted caller comes back once they beat the new lowest
+    client.increment(&lowest);
+    assert_eq!(
+        client.top_callers(&MAX_LEADERS).last(),
+        Some((climber.clone(), 3))
+    );
+    client.increment(&lowest);
+    let leaders = client.top_callers(&MAX_LEADERS);
+    assert_eq!(leaders.len(), MAX_LEADERS);
+    assert_eq!(
+        leaders.last(),
+        Some((tracked[MAX_LEADERS as usize - 2].clone(), 3))
+    );
+    assert!(leaders.contains((lowest, 4)));
+    assert!(!leaders.iter().any(|(caller, _)| caller == climber));
+}
+
+fn last_modified(env: &Env, contract_id: &Address) -> Option<u32> {
+    env.as_contract(contract_id, || {
+        env.storage().instance().get(&DataKey::LastModified)
+    })
+}
+
+#[test]
+fn test_last_modified_follows_writes_only() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let caller = Address::generate(&env);
+    assert_eq!(last_modified(&env, &contract_id), None);
+
+    env.ledger().set_sequence_number(5);
+    client.increment(&caller);
+    assert_eq!(last_modified(&env, &contract_id), Some(5));
+
+    env.ledger().set_sequence_number(9);
+    client.get();
+    client.get_with_ledger();
+    client.get_if_unchanged(&9);
+    assert_eq!(last_modified(&env, &contract_id), Some(5));
+
+    client.decrement(&caller);
+    assert_eq!(last_modified(&env, &contract_id), Some(9));
+}
+
+#[test]
+fn test_get_if_unchanged() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+    let caller = Address::generate(&env);
+
+    env.ledger().set_sequence_number(10);
+    client.increment(&caller);
+    env.ledger().set_sequence_number(12);
+    let (value, ledger) = client.get_with_ledger();
+    assert_eq!((value, ledger), (client.get(), 12));
+    assert_eq!(value, 1);
+    assert_eq!(client.get_if_unchanged(&ledger), Some(1));
+
+    // Written in a later ledger
+    env.ledger().set_sequence_number(13);
+    client.increment(&caller);
+    assert_eq!(client.get_if_unchanged(&ledger), None);
+    assert_eq!(client.get_if_unchanged(&13), Some(2));
+}
+
+#[test]
+fn test_get_if_unchanged_across_epoch_rollover() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    env.ledger().set_sequence_number(10);
+    client.set_epoch_length(&Some(100));
+    client.increment(&caller);
+
+    env.ledger().set_sequence_number(50);
+    let (value, ledger) = client.get_with_ledger();
+    assert_eq!(value, 1);
+    assert_eq!(client.get_if_unchanged(&ledger), Some(1));
+
+    // Epoch 1 starts at ledger 100 and reads 0 before anything is written
+    env.ledger().set_sequence_number(150);
+    assert_eq!(client.get_if_unchanged(&ledger), None);
+    assert_eq!(client.get_if_unchanged(&100), Some(0));
+
+    // The first write of the epoch keeps the earlier read stale
+    env.ledger().set_sequence_number(160);
+    client.increment(&caller);
+    assert_eq!(client.get_if_unchanged(&ledger), None);
+    assert_eq!(client.get_if_unchanged(&160), Some(1));
+}
+
+#[test]
+fn test_pause_blocks_counting_calls() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup_with_admin(&env);
+    let caller = Address::generate(&env);
+    client.increment(&caller);
+
+    client.pause();
+    assert!(client.is_paused());
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (symbol_short!("counter"), symbol_short!("paused")).into_val(&env),
+                (admin.clone(),).into_val(&env),
+            )
+        ]
+    );
+
+    assert_eq!(client.try_increment(&caller), Err(Ok(CounterError::Paused)));
+    assert_eq!(
+        client.try_increment_by(&caller, &2),
+        Err(Ok(CounterError::Paused))
+    );
+    assert_eq!(
+        client.try_increment_named(&caller, &symbol_short!("swap")),
+        Err(Ok(CounterError::Paused))
+    );
+    assert_eq!(
+        client.try_get_and_increment(&caller),
+        Err(Ok(CounterError::Paused))
+    );
+    assert_eq!(client.try_decrement(&caller), Err(Ok(CounterError::Paused)));
+
+    // Reads are unaffected
+    assert_eq!(client.get(), 1);
+    assert_eq!(client.get_for(&caller), 1);
+
+    client.unpause();
+    assert!(!client.is_paused());
+    assert_eq!(
+        env.events().all(),
+        vec![
+            &env,
+            (
+                client.address.clone(),
+                (symbol_short!("counter"), Symbol::new(&env, "unpaused")).into_val(&env),
+                (admin,).into_val(&env),
+            )
+        ]
+    );
+    assert_eq!(client.increment(&caller), 2);
+}
+
+#[test]
+fn test_pause_twice_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+
+    assert_eq!(client.try_unpause(), Err(Ok(CounterError::NotPaused)));
+    client.pause();
+    assert_eq!(client.try_pause(), Err(Ok(CounterError::Paused)));
+    assert!(client.is_paused());
+}
+
+#[test]
+fn test_pause_requires_admin() {
+    let env = Env::default();
+    let (client, _) = setup_with_admin(&env);
+
+    let stranger = Address::generate(&env);
+    env.mock_auths(&[MockAuth {
+        address: &stranger,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "pause",
+            args: ().into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(client.try_pause().is_err());
+    assert!(!client.is_paused());
+}
+
+/// Ledgers of life `key` has left.
+fn ttl(env: &Env, contract_id: &Address, key: &DataKey) -> u32 {
+    env.as_contract(contract_id, || env.storage().persistent().get_ttl(key))
+}
+
+fn instance_ttl(env: &Env, contract_id: &Address) -> u32 {
+    env.as_contract(contract_id, || env.storage().instance().get_ttl())
+}
+
+fn advance_ledgers(env: &Env, ledgers: u32) {
+    env.ledger().with_mut(|li| li.sequence_number += ledgers);
+}
+
+#[test]
+fn test_increment_extends_ttl() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+    client.increment(&caller);
+    assert_eq!(ttl(&env, &contract_id, &DataKey::TotalV2), TTL_EXTEND_TO);
+
+    // Above the threshold, an increment leaves the TTL alone
+    advance_ledgers(&env, 1_000);
+    client.increment(&caller);
+    assert_eq!(
+        ttl(&env, &contract_id, &DataKey::TotalV2),
+        TTL_EXTEND_TO - 1_000
+    );
+
+    // Below it, the next increment brings it back up, and the count carries on
+    advance_ledgers(&env, TTL_EXTEND_TO - TTL_THRESHOLD);
+    assert!(ttl(&env, &contract_id, &DataKey::TotalV2) < TTL_THRESHOLD);
+    assert_eq!(client.increment(&caller), 3);
+    assert_eq!(ttl(&env, &contract_id, &DataKey::TotalV2), TTL_EXTEND_TO);
+    assert_eq!(instance_ttl(&env, &contract_id), TTL_EXTEND_TO);
+    assert_eq!(client.get_for(&caller), 3);
+}
+
+#[test]
+fn test_extend_ttl_is_permissionless() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(Counter, (None::<Address>,));
+    let client = CounterClient::new(&env, &contract_id);
+
+    let caller = Address::generate(&env);
+    let swap = symbol_short!("swap");
+    client.increment(&caller);
+    client.increment_named(&caller, &swap);
+    advance_ledgers(&env, TTL_EXTEND_TO - 100);
+
+    // No auth is mocked for the maintenance call
+    env.set_auths(&[]);
+    client.extend_ttl(&TTL_THRESHOLD, &TTL_EXTEND_TO);
+
+    for key in [
+        DataKey::TotalV2,
+        DataKey::Names,
+        DataKey::Named(swap.clone()),
+    ] {
+        assert_eq!(ttl(&env, &contract_id, &key), TTL_EXTEND_TO);
+    }
+    assert_eq!(instance_ttl(&env, &contract_id), TTL_EXTEND_TO);
+    assert_eq!(client.get(), 1);
+    assert_eq!(client.get_named(&swap), 1);
+}
@@ -0,0 +1,39 @@
+//! Builds the upgrade-target contract to wasm for the upgrade tests, which
+//! upload it as the verifier's next code. Only host builds, the ones tests
+//! run in, need it; the verifier's own wasm build skips it.
+use std::{env, path::PathBuf, process::Command};
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let upgrade_target = manifest_dir.join("../upgrade-target");
+    println!(
+        "cargo:rerun-if-changed={}",
+        upgrade_target.join("src").display()
+    );
+    println!(
+        "cargo:rerun-if-changed={}",
+        upgrade_target.join("Cargo.toml").display()
+    );
+    if env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("wasm32") {
+        return;
+    }
+
+    // A target dir of its own, so the outer build's lock is not waited on
+    let target_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("upgrade-target");
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let status = Command::new(cargo)
+        .args(["build", "--release", "--target", "wasm32-unknown-unknown"])
+        .arg("--manifest-path")
+        .arg(upgrade_target.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        // Flags meant for the host build would reach the wasm one
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env_remove("RUSTFLAGS")
+        .status()
+        .expect("cargo runs");
+    assert!(
+        status.success(),
+        "building upgrade-target failed; is the wasm32-unknown-unknown target installed?"
+    );
+}
@@ -1,22 +1,329 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, contracttype, xdr::FromXdr, Bytes, BytesN, Env};
+use latch_utils::{hex_encode, hex_len};
+use soroban_sdk::{
+    contract, contracterror, contractevent, contractimpl, contracttype, panic_with_error,
+    xdr::FromXdr, Address, Bytes, BytesN, Env, Symbol, TryFromVal, Val, Vec,
+};
 use stellar_accounts::verifiers::Verifier;
 
-/// The prefix that Phantom wallet prepends to the auth payload hash.
+/// The prefix that Phantom wallet prepends to the auth payload hash, used
+/// unless the deployer chose another.
 const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
-const PREFIX_LEN: usize = 28; // "Stellar Smart Account Auth:\n" = 28 bytes
+const MAX_PREFIX_LEN: usize = 64;
+const MAX_PREFIXES: u32 = 4;
 const PAYLOAD_LEN: usize = 32;
-const HEX_LEN: usize = 64;
-const TOTAL_LEN: usize = PREFIX_LEN + HEX_LEN; // 92 bytes
+const HEX_LEN: usize = hex_len(PAYLOAD_LEN);
+/// Standard base64 of the payload, with padding.
+const BASE64_LEN: usize = 44;
+const KEY_LEN: u32 = 32;
+const STRKEY_LEN: usize = 56;
+const FOR_LABEL: &[u8] = b"\nFor: ";
+const ACCOUNT_LABEL: &[u8] = b"\nAccount: ";
+const NETWORK_LABEL: &[u8] = b"\nNetwork: ";
+/// "\nAccount: " + strkey + "\nNetwork: " + hex(network id).
+const DOMAIN_SUFFIX_LEN: usize = ACCOUNT_LABEL.len() + STRKEY_LEN + NETWORK_LABEL.len() + HEX_LEN;
+const EXPIRES_LABEL: &[u8] = b"\nExpires: ";
+/// u64::MAX is 20 digits.
+const MAX_U64_DIGITS: usize = 20;
+/// Longest prefixed message any layout allows: the longest prefix and the
+/// hex, then the account and network, binding and expiry suffixes and a
+/// trailing newline. Longer messages are refused before they are read.
+const MAX_MESSAGE_LEN: usize = MAX_PREFIX_LEN
+    + HEX_LEN
+    + DOMAIN_SUFFIX_LEN
+    + FOR_LABEL.len()
+    + HEX_LEN
+    + EXPIRES_LABEL.len()
+    + MAX_U64_DIGITS
+    + 1;
+const SEP53_PREFIX: &[u8] = b"Stellar Signed Message:\n";
+const SOLANA_SIGNING_DOMAIN: &[u8] = b"\xffsolana offchain";
+/// Signing domain (16) + version (1) + format (1) + length (2).
+const SOLANA_HEADER_LEN: usize = 20;
+/// Ends the first line of a Sign-In-With-Solana message, after the domain.
+const SIWS_HEADER_SUFFIX: &[u8] = b" wants you to sign in with your Solana account:";
+const NONCE_LABEL: &[u8] = b"Nonce: ";
+const ISSUED_AT_LABEL: &[u8] = b"Issued At: ";
+/// Longest SIWS message accepted, statement and resources included.
+const MAX_SIWS_LEN: usize = 1024;
+/// The canonical encodings of the eight points of small order (the identity
+/// among them), for which a signature says nothing about who made it.
+const SMALL_ORDER_KEYS: [[u8; 32]; 8] = [
+    // Identity (order 1)
+    [
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ],
+    // Order 2
+    [
+        0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0x7f,
+    ],
+    // Order 4; also the all-zero key
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ],
+    // Order 4
+    [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x80,
+    ],
+    // Order 8
+    [
+        0xc7, 0x17, 0x6a, 0x70, 0x3d, 0x4d, 0xd8, 0x4f, 0xba, 0x3c, 0x0b, 0x76, 0x0d, 0x10, 0x67,
+        0x0f, 0x2a, 0x20, 0x53, 0xfa, 0x2c, 0x39, 0xcc, 0xc6, 0x4e, 0xc7, 0xfd, 0x77, 0x92, 0xac,
+        0x03, 0x7a,
+    ],
+    // Order 8
+    [
+        0xc7, 0x17, 0x6a, 0x70, 0x3d, 0x4d, 0xd8, 0x4f, 0xba, 0x3c, 0x0b, 0x76, 0x0d, 0x10, 0x67,
+        0x0f, 0x2a, 0x20, 0x53, 0xfa, 0x2c, 0x39, 0xcc, 0xc6, 0x4e, 0xc7, 0xfd, 0x77, 0x92, 0xac,
+        0x03, 0xfa,
+    ],
+    // Order 8
+    [
+        0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0, 0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98,
+        0xf0, 0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39, 0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53,
+        0xfc, 0x05,
+    ],
+    // Order 8
+    [
+        0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0, 0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98,
+        0xf0, 0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39, 0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53,
+        0xfc, 0x85,
+    ],
+];
+/// The order L of the Ed25519 base point, little-endian as signatures encode
+/// `s`. A signature with `s` at or above it has a twin, `s - L`, that
+/// verifies the same.
+const GROUP_ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// The most signatures one `batch_verify` call takes.
+const MAX_BATCH: u32 = 16;
+/// The most keys one any-of key_data lists.
+const MAX_ANY_OF_KEYS: u32 = 4;
+/// Ledgers a `verify_and_log` record is kept for, about a day.
+const LOG_TTL_LEDGERS: u32 = 17_280;
 
 #[contract]
 pub struct Ed25519Verifier;
 
+#[contracttype]
+#[derive(Clone)]
+enum DataKey {
+    Admin,
+    PendingAdmin,
+    Prefixes,
+    ShortFormat,
+    SiwsDomain,
+    ExpectedKeyLen,
+    /// The last `verify_and_log` success for the sha256 of a key_data.
+    LastVerification(BytesN<32>),
+}
+
+/// The settings a deployment was made with, for tooling to check before
+/// registering a signer.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifierConfig {
+    /// The only key_data length `verify` accepts, if pinned.
+    pub expected_key_len: Option<u32>,
+    pub accept_short_format: bool,
+    pub siws_domain: Option<Bytes>,
+}
+
+/// One signature in a `batch_verify` call, as `verify` takes it.
+#[contracttype]
+pub struct BatchItem {
+    pub key_data: Bytes,
+    pub sig_data: Bytes,
+}
+
 /// Signature data containing both the prefixed message and signature.
 #[contracttype]
 pub struct Ed25519SigData {
     pub prefixed_message: Bytes,
     pub signature: BytesN<64>,
+    /// Unix time after which the signature is refused. When set, the message
+    /// ends in "\nExpires: <expires_at>" so the signer sees it.
+    pub expires_at: Option<u64>,
+}
+
+/// key_data, as XDR, for a key tied to an account. A bare 32-byte public
+/// key is tied to none.
+#[contracttype]
+pub struct Ed25519KeyData {
+    pub pubkey: BytesN<32>,
+    /// The account messages name after "\nAccount: ", if they carry the
+    /// account and network.
+    pub account: Option<Address>,
+    /// The account-id hash messages must end in after "\nFor: ", so a
+    /// signature for one account does not verify for another.
+    pub binding: Option<BytesN<32>>,
+}
+
+/// Emitted by `verify_and_log` for each signature it verifies.
+#[contractevent(topics = ["verified"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Verified {
+    /// sha256 of the key_data.
+    #[topic]
+    pub key_hash: BytesN<32>,
+    pub payload_hash: BytesN<32>,
+    pub ledger: u32,
+}
+
+/// sig_data for key_data that lists several keys, any one of which may
+/// sign.
+#[contracttype]
+pub struct Ed25519AnyOfSigData {
+    /// Which of the listed keys signed. Ed25519 verification traps on a
+    /// mismatch, so the key cannot be found by trying each in turn.
+    pub key_index: u32,
+    /// sig_data for that key alone, in any of the forms `verify` takes.
+    pub sig_data: Bytes,
+}
+
+/// `Ed25519SigData` before `expires_at`, still accepted as bare sig_data.
+#[contracttype(export = false)]
+struct LegacySigData {
+    prefixed_message: Bytes,
+    signature: BytesN<64>,
+}
+
+/// How the signature was produced. sig_data that is a bare `Ed25519SigData`
+/// rather than this enum is read as `Prefixed`, so existing signers keep
+/// working.
+#[contracttype]
+pub enum Ed25519Signature {
+    /// Over the Phantom-style prefixed message.
+    Prefixed(Ed25519SigData),
+    /// Over the 32-byte signature payload itself, for hardware wallets and
+    /// HSMs that sign it directly.
+    Raw(BytesN<64>),
+    /// Over a Solana off-chain message envelope wrapping the prefixed
+    /// message, as Phantom's Solana signing produces.
+    SolanaOffchain(Ed25519EnvelopeSigData),
+    /// SEP-53: `prefixed_message` is "Stellar Signed Message:\n" + hex, and
+    /// the signature is over its sha256, as Freighter, xBull and the Stellar
+    /// SDKs' message signing produce.
+    Sep53(Ed25519SigData),
+    /// Over a Sign-In-With-Solana message whose nonce is the hex of the
+    /// payload, for dapps that sign users in and authorize in one step.
+    Siws(Ed25519SiwsSigData),
+    /// Over the sha256 of the Phantom-style prefixed message, as Ledger's
+    /// Stellar app signs long messages. The message is checked as for
+    /// `Prefixed`.
+    Hashed(Ed25519SigData),
+}
+
+/// Versioned sig_data, so the format can change without breaking the
+/// signers of an older one. sig_data that is not an envelope is read as
+/// before: a bare `Ed25519SigData` is V1, an `Ed25519Signature` is V2.
+#[contracttype]
+pub enum SigEnvelope {
+    /// The original format, a prefixed message and its signature.
+    V1(Ed25519SigData),
+    /// Any signing mode. New formats are added as modes here.
+    V2(Ed25519Signature),
+}
+
+/// A signed Solana off-chain message envelope.
+#[contracttype]
+pub struct Ed25519EnvelopeSigData {
+    pub envelope: Bytes,
+    pub signature: BytesN<64>,
+}
+
+/// A signed Sign-In-With-Solana message, as the wallet displayed it.
+#[contracttype]
+pub struct Ed25519SiwsSigData {
+    pub message: Bytes,
+    pub signature: BytesN<64>,
+}
+
+/// The check `verify_checked` failed at.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum VerifierError {
+    /// sig_data is XDR of something other than `SigEnvelope`,
+    /// `Ed25519Signature` or `Ed25519SigData`.
+    MalformedSigData = 1,
+    /// key_data is neither a 32-byte public key nor XDR of
+    /// `Ed25519KeyData` or a list of keys, is not the length the deployment
+    /// pinned, or its account's strkey is not 56 characters.
+    BadKeyLength = 2,
+    /// The prefixed message is neither prefix + 64 hex characters nor
+    /// prefix + 44 base64 characters, the signed message is longer than its
+    /// mode allows, or the payload is not 32 bytes.
+    WrongMessageLength = 3,
+    MissingPrefix = 4,
+    /// The message's hex (in any letter case) or base64 is not the
+    /// payload's.
+    PayloadMismatch = 5,
+    /// Never returned by this verifier: `ed25519_verify` traps on a bad
+    /// signature. Kept so the codes line up across verifiers.
+    BadSignature = 6,
+    /// A prefix is over `MAX_PREFIX_LEN` bytes. 7-9 are taken by the other
+    /// verifiers.
+    PrefixTooLong = 10,
+    /// Adding the prefix would exceed `MAX_PREFIXES`.
+    TooManyPrefixes = 11,
+    DuplicatePrefix = 12,
+    PrefixNotFound = 13,
+    /// Removing the prefix would leave none.
+    LastPrefix = 14,
+    /// The verifier was deployed without an admin, so its prefixes are
+    /// fixed.
+    NoAdmin = 15,
+    /// A Solana off-chain envelope without the signing domain, of a version
+    /// other than 0, or whose length field is not its body's.
+    BadEnvelope = 16,
+    /// The message's account and network suffix is not the key's bound
+    /// account and this network, or is missing where the short format is
+    /// not accepted.
+    DomainMismatch = 17,
+    /// The message's "\nExpires: " suffix is missing or not `expires_at`.
+    ExpiryMismatch = 18,
+    /// The ledger is past `expires_at`.
+    SignatureExpired = 19,
+    /// The key has a binding and the message's "\nFor: " suffix is missing
+    /// or another account's.
+    BindingMismatch = 20,
+    /// A batch has more than `MAX_BATCH` items.
+    BatchTooLarge = 21,
+    /// sig_data is a `SigEnvelope` version, or a signing mode, this verifier
+    /// does not know.
+    UnsupportedVersion = 22,
+    /// The public key is one of `SMALL_ORDER_KEYS`.
+    SmallOrderKey = 23,
+    /// A key list is empty or has more than `MAX_ANY_OF_KEYS` keys. Shared
+    /// with the multisig verifier.
+    TooManyKeys = 25,
+    /// An any-of signature's `key_index` is past the end of the list.
+    KeyIndexOutOfRange = 26,
+    /// A SIWS message whose first line is not the sign-in header, with no
+    /// address line, or without exactly one "Nonce: " and "Issued At: "
+    /// field. 24, 27 and 28 are taken by the multisig verifier.
+    SiwsMalformed = 29,
+    /// A SIWS message for a domain other than the configured one, or any
+    /// SIWS message where none is configured.
+    SiwsDomainMismatch = 30,
+    /// `accept_admin` with no admin proposed. 31 is taken by the
+    /// personal_sign verifier.
+    NoPendingAdmin = 32,
+    /// The signature's `s` is not below the group order, so it is a
+    /// malleated copy of some canonical signature.
+    NonCanonicalSignature = 33,
 }
 
 #[contractimpl]
@@ -24,74 +331,1109 @@ impl Verifier for Ed25519Verifier {
     type KeyData = Bytes;
     type SigData = Bytes;
 
-    /// Verifies an Ed25519 signature over a prefixed message.
+    /// Verifies an Ed25519 signature over a prefixed message (bare, hashed as
+    /// Ledger signs it, in a Solana off-chain envelope, or hashed per
+    /// SEP-53), over a SIWS message carrying the payload as its nonce, or
+    /// over the payload itself for `Ed25519Signature::Raw`. key_data may
+    /// also be XDR of a list of up to `MAX_ANY_OF_KEYS` keys, with sig_data
+    /// an `Ed25519AnyOfSigData` naming the one that signed.
+    ///
+    /// Input that cannot carry a valid signature (wrong lengths, missing
+    /// prefix, a message for another payload, sig_data that decodes to
+    /// something else, a non-canonical signature) returns `false`, so the
+    /// account can fall back to other signers. key_data is checked before it
+    /// is decoded, but sig_data bytes that are not XDR at all and a signature
+    /// that does not verify still trap, in the host's deserializer and
+    /// `ed25519_verify`.
     fn verify(
         e: &Env,
         signature_payload: Bytes,
         key_data: Self::KeyData,
         sig_data: Self::SigData,
     ) -> bool {
-        // Decode sig_data from XDR
-        let sig_struct: Ed25519SigData =
-            Ed25519SigData::from_xdr(e, &sig_data).expect("sig_data must be valid Ed25519SigData");
+        check(e, signature_payload, key_data, sig_data).is_ok()
+    }
+}
 
-        // Extract public key to BytesN (already validated by stellar_accounts)
-        if key_data.len() != 32 {
-            panic!("key_data must be 32 bytes");
+#[contractimpl]
+impl Ed25519Verifier {
+    /// Sets the admin allowed to change the accepted prefixes and upgrade
+    /// the verifier, and the first prefix. `None` keeps the Phantom prefix,
+    /// as do instances deployed before prefixes were configurable; without
+    /// an admin neither the prefixes nor the code ever change.
+    ///
+    /// `accept_short_format` keeps accepting messages without the account
+    /// and network suffix, for signers that predate it. Raw and SEP-53
+    /// signatures have fixed formats and are unaffected.
+    ///
+    /// `siws_domain` is the domain SIWS messages must name; without one,
+    /// SIWS signatures are refused.
+    ///
+    /// `expected_key_len` pins the length of key_data, so that a deployment
+    /// for one wallet integration refuses any other key shape before
+    /// decoding or verifying anything; `None` accepts every form `verify`
+    /// documents.
+    pub fn __constructor(
+        e: Env,
+        admin: Option<Address>,
+        prefix: Option<Bytes>,
+        accept_short_format: bool,
+        siws_domain: Option<Bytes>,
+        expected_key_len: Option<u32>,
+    ) {
+        e.storage()
+            .instance()
+            .set(&DataKey::ShortFormat, &accept_short_format);
+        if let Some(admin) = admin {
+            e.storage().instance().set(&DataKey::Admin, &admin);
         }
-        let public_key: BytesN<32> = key_data
-            .try_into()
-            .unwrap_or_else(|_| panic!("failed to convert key_data"));
-
-        // Validate prefixed_message length
-        if sig_struct.prefixed_message.len() != TOTAL_LEN as u32 {
-            panic!("prefixed_message has wrong length");
+        if let Some(prefix) = prefix {
+            check_prefix_len(&e, &prefix);
+            e.storage()
+                .instance()
+                .set(&DataKey::Prefixes, &Vec::from_array(&e, [prefix]));
+        }
+        if let Some(domain) = siws_domain {
+            e.storage().instance().set(&DataKey::SiwsDomain, &domain);
         }
+        if let Some(len) = expected_key_len {
+            e.storage().instance().set(&DataKey::ExpectedKeyLen, &len);
+        }
+    }
 
-        // Convert to fixed-size buffer for fast validation 
-        let prefixed_msg_buf = sig_struct.prefixed_message.to_buffer::<TOTAL_LEN>();
-        let prefixed_msg_slice = prefixed_msg_buf.as_slice();
+    /// Accepts messages signed under `prefix` as well. Admin only.
+    pub fn add_prefix(e: Env, prefix: Bytes) {
+        require_admin(&e);
+        check_prefix_len(&e, &prefix);
+        let mut prefixes = auth_prefixes(&e);
+        if prefixes.contains(&prefix) {
+            panic_with_error!(&e, VerifierError::DuplicatePrefix)
+        }
+        if prefixes.len() >= MAX_PREFIXES {
+            panic_with_error!(&e, VerifierError::TooManyPrefixes)
+        }
+        prefixes.push_back(prefix);
+        e.storage().instance().set(&DataKey::Prefixes, &prefixes);
+    }
 
-        // Validate prefix using direct slice comparison
-        if &prefixed_msg_slice[0..PREFIX_LEN] != AUTH_PREFIX {
-            panic!("prefixed_message missing required prefix");
+    /// Stops accepting `prefix`. Admin only; the last prefix stays.
+    pub fn remove_prefix(e: Env, prefix: Bytes) {
+        require_admin(&e);
+        let mut prefixes = auth_prefixes(&e);
+        let Some(index) = prefixes.first_index_of(&prefix) else {
+            panic_with_error!(&e, VerifierError::PrefixNotFound)
+        };
+        if prefixes.len() == 1 {
+            panic_with_error!(&e, VerifierError::LastPrefix)
         }
+        prefixes.remove(index);
+        e.storage().instance().set(&DataKey::Prefixes, &prefixes);
+    }
+
+    /// The prefixes `verify` accepts in front of the hex payload.
+    pub fn prefixes(e: Env) -> Vec<Bytes> {
+        auth_prefixes(&e)
+    }
+
+    pub fn admin(e: Env) -> Option<Address> {
+        e.storage().instance().get(&DataKey::Admin)
+    }
 
-        // Convert signature_payload to array for fast hex encoding
-        if signature_payload.len() != PAYLOAD_LEN as u32 {
-            panic!("signature_payload must be 32 bytes");
+    /// Replaces the verifier's code with the uploaded wasm `new_wasm_hash`.
+    /// Storage, and so the prefixes and flags, stays as it is. Admin only.
+    pub fn upgrade(e: Env, new_wasm_hash: BytesN<32>) {
+        require_admin(&e);
+        e.deployer().update_current_contract_wasm(new_wasm_hash);
+    }
+
+    /// Proposes `new_admin`, who takes over once they call `accept_admin`,
+    /// so a mistyped address cannot take the role out of reach. Admin only;
+    /// a later proposal replaces this one.
+    pub fn set_admin(e: Env, new_admin: Address) {
+        require_admin(&e);
+        e.storage()
+            .instance()
+            .set(&DataKey::PendingAdmin, &new_admin);
+    }
+
+    /// Makes the proposed admin the admin. Authorized by the proposed admin.
+    pub fn accept_admin(e: Env) {
+        let pending: Address = e
+            .storage()
+            .instance()
+            .get(&DataKey::PendingAdmin)
+            .unwrap_or_else(|| panic_with_error!(&e, VerifierError::NoPendingAdmin));
+        pending.require_auth();
+        e.storage().instance().set(&DataKey::Admin, &pending);
+        e.storage().instance().remove(&DataKey::PendingAdmin);
+    }
+
+    /// The admin proposed by `set_admin` and not yet accepted.
+    pub fn pending_admin(e: Env) -> Option<Address> {
+        e.storage().instance().get(&DataKey::PendingAdmin)
+    }
+
+    /// Whether messages without the account and network suffix verify.
+    pub fn short_format_accepted(e: Env) -> bool {
+        short_format_accepted(&e)
+    }
+
+    /// The domain SIWS messages must sign in to, if SIWS is accepted.
+    pub fn siws_domain(e: Env) -> Option<Bytes> {
+        siws_domain(&e)
+    }
+
+    /// The settings fixed at deployment.
+    pub fn config(e: Env) -> VerifierConfig {
+        VerifierConfig {
+            expected_key_len: expected_key_len(&e),
+            accept_short_format: short_format_accepted(&e),
+            siws_domain: siws_domain(&e),
         }
-        let payload_array = signature_payload.to_buffer::<PAYLOAD_LEN>();
+    }
 
-        // Generate expected hex using direct array indexing
-        let mut expected_hex = [0u8; HEX_LEN];
-        hex_encode(&mut expected_hex, payload_array.as_slice());
+    /// `verify` that tells which check failed, for integrators and
+    /// simulation.
+    pub fn verify_checked(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> Result<(), VerifierError> {
+        check(&e, signature_payload, key_data, sig_data)
+    }
+
+    /// `verify` for a bare public key, typed so that a key of any other
+    /// length fails the call before it gets here.
+    pub fn verify_fixed(
+        e: Env,
+        signature_payload: Bytes,
+        public_key: BytesN<32>,
+        sig_data: Bytes,
+    ) -> bool {
+        check(&e, signature_payload, public_key.into(), sig_data).is_ok()
+    }
 
-        // Validate hex portion using direct slice comparison
-        if &prefixed_msg_slice[PREFIX_LEN..TOTAL_LEN] != &expected_hex[..] {
-            panic!("prefixed_message hex does not match payload");
+    /// `verify` that, on success, records the payload and ledger under the
+    /// sha256 of `key_data` and publishes `Verified`, so a watcher sees a
+    /// signer's activity even where the account publishes nothing. The
+    /// record is temporary and replaced by the key's next verification.
+    /// `verify` itself writes nothing.
+    pub fn verify_and_log(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> bool {
+        let key_hash: BytesN<32> = e.crypto().sha256(&key_data).into();
+        if check(&e, signature_payload.clone(), key_data, sig_data).is_err() {
+            return false;
         }
 
-        // All validation passed - verify signature
-        e.crypto()
-            .ed25519_verify(&public_key, &sig_struct.prefixed_message, &sig_struct.signature);
+        // Only a 32-byte payload verifies
+        let payload_hash = BytesN::<32>::try_from(signature_payload).unwrap();
+        let ledger = e.ledger().sequence();
+        let key = DataKey::LastVerification(key_hash.clone());
+        e.storage()
+            .temporary()
+            .set(&key, &(payload_hash.clone(), ledger));
+        e.storage()
+            .temporary()
+            .extend_ttl(&key, LOG_TTL_LEDGERS, LOG_TTL_LEDGERS);
 
+        Verified {
+            key_hash,
+            payload_hash,
+            ledger,
+        }
+        .publish(&e);
         true
     }
+
+    /// The payload and ledger of the last `verify_and_log` success for the
+    /// key_data hashing to `key_hash`, while the record lives.
+    pub fn last_verification(e: Env, key_hash: BytesN<32>) -> Option<(BytesN<32>, u32)> {
+        e.storage()
+            .temporary()
+            .get(&DataKey::LastVerification(key_hash))
+    }
+
+    /// `verify` for payloads of any length, such as a serialized intent. A
+    /// payload longer than 32 bytes is replaced by its sha256, so the signed
+    /// message carries the hex of the digest; a 32-byte payload is checked
+    /// exactly as `verify` checks it.
+    pub fn verify_any_len(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> bool {
+        let payload = if signature_payload.len() > PAYLOAD_LEN as u32 {
+            e.crypto().sha256(&signature_payload).to_bytes().into()
+        } else {
+            signature_payload
+        };
+        check(&e, payload, key_data, sig_data).is_ok()
+    }
+
+    /// `verify` for a prefixed signature passed as the struct itself rather
+    /// than XDR of it in `Bytes`, which saves decoding it again in the
+    /// contract. Same checks and results as `verify`.
+    pub fn verify_typed(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig: Ed25519SigData,
+    ) -> bool {
+        let expected = Expected::new(&e, signature_payload);
+        check_key_len(&expected, &key_data)
+            .and_then(|_| check_signature(&e, &expected, key_data, Ed25519Signature::Prefixed(sig)))
+            .is_ok()
+    }
+
+    /// Verify up to `MAX_BATCH` signatures over the same payload, so a
+    /// threshold rule pays for one call instead of one per signer. Returns
+    /// whether each item verifies, in order. The payload is encoded and the
+    /// prefixes read once for all items.
+    ///
+    /// An item that is malformed, signs another message or uses a prefix
+    /// the verifier lacks is false. A well-formed signature that does not
+    /// verify still traps the whole call in `ed25519_verify`, as it does for
+    /// `verify`; a contract cannot call itself to contain that.
+    pub fn batch_verify(e: Env, signature_payload: Bytes, items: Vec<BatchItem>) -> Vec<bool> {
+        if items.len() > MAX_BATCH {
+            panic_with_error!(&e, VerifierError::BatchTooLarge)
+        }
+
+        let expected = Expected::new(&e, signature_payload);
+        let mut results = Vec::new(&e);
+        for item in items.iter() {
+            results.push_back(check_with(&e, &expected, item.key_data, item.sig_data).is_ok());
+        }
+        results
+    }
+}
+
+/// What signatures over one payload are checked against, worked out once
+/// per call rather than once per signature.
+struct Expected {
+    payload: Bytes,
+    /// Hex and base64 of the payload, unless it is not `PAYLOAD_LEN` bytes.
+    encoded: Option<([u8; HEX_LEN], [u8; BASE64_LEN])>,
+    prefixes: Vec<Bytes>,
+    short_format: bool,
+    key_len: Option<u32>,
+}
+
+impl Expected {
+    fn new(e: &Env, payload: Bytes) -> Self {
+        let encoded = (payload.len() == PAYLOAD_LEN as u32).then(|| {
+            let payload_array = payload.to_buffer::<PAYLOAD_LEN>();
+            let mut hex = [0u8; HEX_LEN];
+            hex_encode(&mut hex, payload_array.as_slice());
+            let mut base64 = [0u8; BASE64_LEN];
+            base64_encode(&mut base64, payload_array.as_slice());
+            (hex, base64)
+        });
+
+        Expected {
+            payload,
+            encoded,
+            prefixes: auth_prefixes(e),
+            short_format: short_format_accepted(e),
+            key_len: expected_key_len(e),
+        }
+    }
+}
+
+fn check(
+    e: &Env,
+    signature_payload: Bytes,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    check_with(e, &Expected::new(e, signature_payload), key_data, sig_data)
+}
+
+fn check_with(
+    e: &Env,
+    expected: &Expected,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    check_key_len(expected, &key_data)?;
+    if key_data.len() != KEY_LEN && is_key_list(e, &key_data) {
+        if let Ok(keys) = Vec::<BytesN<32>>::from_xdr(e, &key_data) {
+            return check_any_of(e, expected, keys, sig_data);
+        }
+    }
+    check_sig_data(e, expected, key_data, sig_data)
+}
+
+/// Whether `key_data` is the XDR of a `Vec<BytesN<32>>`, checked byte by
+/// byte first: `from_xdr` traps on bytes that are not XDR rather than
+/// returning an error.
+fn is_key_list(e: &Env, key_data: &Bytes) -> bool {
+    // ScVal::Vec, Some(_), then each item is ScVal::Bytes of length 32
+    const LIST: [u8; 8] = [0, 0, 0, 16, 0, 0, 0, 1];
+    const KEY: [u8; 8] = [0, 0, 0, 13, 0, 0, 0, 32];
+    const ITEM_LEN: u32 = KEY.len() as u32 + 32;
+
+    let len = key_data.len();
+    if len < 12 || (len - 12) % ITEM_LEN != 0 || key_data.slice(..8) != Bytes::from_array(e, &LIST)
+    {
+        return false;
+    }
+    let count = (len - 12) / ITEM_LEN;
+    if key_data.slice(8..12) != Bytes::from_array(e, &count.to_be_bytes()) {
+        return false;
+    }
+    let key = Bytes::from_array(e, &KEY);
+    (0..count).all(|i| {
+        let at = 12 + i * ITEM_LEN;
+        key_data.slice(at..at + 8) == key
+    })
+}
+
+/// The pinned key length, checked before anything decodes key_data.
+fn check_key_len(expected: &Expected, key_data: &Bytes) -> Result<(), VerifierError> {
+    match expected.key_len {
+        Some(len) if key_data.len() != len => Err(VerifierError::BadKeyLength),
+        _ => Ok(()),
+    }
+}
+
+/// Decodes sig_data for a single key and checks it.
+fn check_sig_data(
+    e: &Env,
+    expected: &Expected,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    // Decode sig_data from XDR; the mode is its type, never its length
+    let signature = match SigEnvelope::from_xdr(e, &sig_data) {
+        Ok(SigEnvelope::V1(sig_struct)) => Ed25519Signature::Prefixed(sig_struct),
+        Ok(SigEnvelope::V2(signature)) => signature,
+        Err(_) => decode_unversioned(e, &sig_data)?,
+    };
+
+    check_signature(e, expected, key_data, signature)
+}
+
+/// The longest XDR of an `Ed25519KeyData`: one bound to an account address
+/// and to a binding.
+const MAX_KEY_DATA_LEN: usize = 184;
+
+/// Whether `key_data` is the XDR of an `Ed25519KeyData`, checked byte by byte
+/// first: `from_xdr` traps on bytes that are not XDR rather than returning
+/// an error.
+fn is_key_data_xdr(key_data: &Bytes) -> bool {
+    const VOID: [u8; 4] = [0, 0, 0, 1];
+    let len = key_data.len() as usize;
+    if len > MAX_KEY_DATA_LEN {
+        return false;
+    }
+    let mut buf = [0u8; MAX_KEY_DATA_LEN];
+    key_data.copy_into_slice(&mut buf[..len]);
+    let mut rest = &buf[..len];
+
+    // ScVal::Map, Some(_), of three entries keyed by symbol, in field order
+    take(&mut rest, &[0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 3])
+        && take(&mut rest, b"\0\0\0\x0f\0\0\0\x07account\0")
+        && (take(&mut rest, &VOID) || take_address(&mut rest))
+        && take(&mut rest, b"\0\0\0\x0f\0\0\0\x07binding\0")
+        && (take(&mut rest, &VOID) || take_key(&mut rest))
+        && take(&mut rest, b"\0\0\0\x0f\0\0\0\x06pubkey\0\0")
+        && take_key(&mut rest)
+        && rest.is_empty()
+}
+
+/// Moves `rest` past `prefix` if it starts with it.
+fn take(rest: &mut &[u8], prefix: &[u8]) -> bool {
+    match rest.strip_prefix(prefix) {
+        Some(tail) => {
+            *rest = tail;
+            true
+        }
+        None => false,
+    }
 }
 
-/// Fast hex encoding using direct array indexing.
-/// Each input byte becomes two hex characters (0-9, a-f).
-fn hex_encode(dst: &mut [u8], src: &[u8]) {
-    const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+/// Moves `rest` past 32 bytes, if it has them.
+fn take_32(rest: &mut &[u8]) -> bool {
+    match rest.get(32..) {
+        Some(tail) => {
+            *rest = tail;
+            true
+        }
+        None => false,
+    }
+}
+
+/// ScVal::Bytes of length 32.
+fn take_key(rest: &mut &[u8]) -> bool {
+    take(rest, &[0, 0, 0, 13, 0, 0, 0, 32]) && take_32(rest)
+}
+
+/// ScVal::Address of an Ed25519 account or of a contract.
+fn take_address(rest: &mut &[u8]) -> bool {
+    take(rest, &[0, 0, 0, 18])
+        && (take(rest, &[0, 0, 0, 0, 0, 0, 0, 0]) || take(rest, &[0, 0, 0, 1]))
+        && take_32(rest)
+}
+
+/// Checks sig_data naming which of `keys` signed, then that key's own
+/// signature.
+fn check_any_of(
+    e: &Env,
+    expected: &Expected,
+    keys: Vec<BytesN<32>>,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    if keys.is_empty() || keys.len() > MAX_ANY_OF_KEYS {
+        return Err(VerifierError::TooManyKeys);
+    }
+    let chosen =
+        Ed25519AnyOfSigData::from_xdr(e, &sig_data).map_err(|_| VerifierError::MalformedSigData)?;
+    let key = keys
+        .get(chosen.key_index)
+        .ok_or(VerifierError::KeyIndexOutOfRange)?;
+
+    // The list, not the key it names, is what a pinned length applies to
+    check_sig_data(e, expected, key.into(), chosen.sig_data)
+}
+
+/// sig_data from before `SigEnvelope`, in any of the forms it took.
+fn decode_unversioned(e: &Env, sig_data: &Bytes) -> Result<Ed25519Signature, VerifierError> {
+    if let Ok(signature) = Ed25519Signature::from_xdr(e, sig_data) {
+        return Ok(signature);
+    }
+    if let Ok(sig_struct) = Ed25519SigData::from_xdr(e, sig_data) {
+        return Ok(Ed25519Signature::Prefixed(sig_struct));
+    }
+    if let Ok(legacy) = LegacySigData::from_xdr(e, sig_data) {
+        return Ok(Ed25519Signature::Prefixed(Ed25519SigData {
+            prefixed_message: legacy.prefixed_message,
+            signature: legacy.signature,
+            expires_at: None,
+        }));
+    }
 
-    let mut di: usize = 0;
-    for &byte in src {
-        dst[di] = HEX_CHARS[(byte >> 4) as usize];
-        dst[di + 1] = HEX_CHARS[(byte & 0x0f) as usize];
-        di += 2;
+    // An enum value nothing above matched: a version or mode from a newer
+    // verifier
+    let tagged = Vec::<Val>::from_xdr(e, sig_data)
+        .ok()
+        .and_then(|values| values.first())
+        .is_some_and(|tag| Symbol::try_from_val(e, &tag).is_ok());
+    if tagged {
+        return Err(VerifierError::UnsupportedVersion);
+    }
+    Err(VerifierError::MalformedSigData)
+}
+
+/// Checks a decoded `signature`, however it reached the contract.
+fn check_signature(
+    e: &Env,
+    expected: &Expected,
+    key_data: Bytes,
+    signature: Ed25519Signature,
+) -> Result<(), VerifierError> {
+    // Attacker-sized input is measured before anything copies or slices it
+    if message_too_long(&signature) {
+        return Err(VerifierError::WrongMessageLength);
+    }
+
+    // Extract public key to BytesN, and what the key is bound to
+    let key = if key_data.len() == KEY_LEN {
+        Ed25519KeyData {
+            pubkey: BytesN::<32>::try_from(key_data).map_err(|_| VerifierError::BadKeyLength)?,
+            account: None,
+            binding: None,
+        }
+    } else if is_key_data_xdr(&key_data) {
+        Ed25519KeyData::from_xdr(e, &key_data).map_err(|_| VerifierError::BadKeyLength)?
+    } else {
+        return Err(VerifierError::BadKeyLength);
+    };
+    let public_key = key.pubkey;
+    if SMALL_ORDER_KEYS.contains(&public_key.to_array()) {
+        return Err(VerifierError::SmallOrderKey);
+    }
+    // Checked here rather than left to the host, which has never been shown
+    // to refuse them
+    if !is_canonical(signature_bytes(&signature)) {
+        return Err(VerifierError::NonCanonicalSignature);
+    }
+
+    let hashed = matches!(signature, Ed25519Signature::Hashed(_));
+    match signature {
+        Ed25519Signature::Prefixed(sig_struct) | Ed25519Signature::Hashed(sig_struct) => {
+            let message = strip_trailing_newline(&sig_struct.prefixed_message);
+            let message = strip_expiry(e, &message, sig_struct.expires_at)?;
+            let message = strip_binding(&message, key.binding)?;
+            check_bound_message(e, expected, &message, key.account)?;
+
+            // All validation passed - verify signature, over the plaintext
+            // checked above or its digest
+            if hashed {
+                let digest = e.crypto().sha256(&sig_struct.prefixed_message);
+                e.crypto().ed25519_verify(
+                    &public_key,
+                    &digest.to_bytes().into(),
+                    &sig_struct.signature,
+                );
+            } else {
+                e.crypto().ed25519_verify(
+                    &public_key,
+                    &sig_struct.prefixed_message,
+                    &sig_struct.signature,
+                );
+            }
+            Ok(())
+        }
+        Ed25519Signature::SolanaOffchain(sig_struct) => {
+            let message = solana_offchain_body(&sig_struct.envelope)?;
+            let message = strip_trailing_newline(&message);
+            let message = strip_binding(&message, key.binding)?;
+            check_bound_message(e, expected, &message, key.account)?;
+
+            // The wallet signed the envelope, header included
+            e.crypto()
+                .ed25519_verify(&public_key, &sig_struct.envelope, &sig_struct.signature);
+            Ok(())
+        }
+        Ed25519Signature::Sep53(sig_struct) => {
+            let message = strip_expiry(e, &sig_struct.prefixed_message, sig_struct.expires_at)?;
+            let message = strip_binding(&message, key.binding)?;
+            check_sep53_message(expected, &message)?;
+
+            let digest = e.crypto().sha256(&sig_struct.prefixed_message);
+            e.crypto().ed25519_verify(
+                &public_key,
+                &digest.to_bytes().into(),
+                &sig_struct.signature,
+            );
+            Ok(())
+        }
+        Ed25519Signature::Raw(signature) => {
+            // No message to carry the binding in
+            if key.binding.is_some() {
+                return Err(VerifierError::BindingMismatch);
+            }
+            if expected.encoded.is_none() {
+                return Err(VerifierError::WrongMessageLength);
+            }
+            e.crypto()
+                .ed25519_verify(&public_key, &expected.payload, &signature);
+            Ok(())
+        }
+        Ed25519Signature::Siws(sig_struct) => {
+            // SIWS fixes the message's fields, leaving no suffix for it
+            if key.binding.is_some() {
+                return Err(VerifierError::BindingMismatch);
+            }
+            check_siws_message(e, expected, &sig_struct.message)?;
+
+            // Signed as displayed, every line included
+            e.crypto()
+                .ed25519_verify(&public_key, &sig_struct.message, &sig_struct.signature);
+            Ok(())
+        }
+    }
+}
+
+/// Whether the message `signature` signs is longer than its mode allows.
+fn message_too_long(signature: &Ed25519Signature) -> bool {
+    let (message, max_len) = match signature {
+        Ed25519Signature::Prefixed(sig_struct)
+        | Ed25519Signature::Hashed(sig_struct)
+        | Ed25519Signature::Sep53(sig_struct) => (&sig_struct.prefixed_message, MAX_MESSAGE_LEN),
+        Ed25519Signature::SolanaOffchain(sig_struct) => {
+            (&sig_struct.envelope, SOLANA_HEADER_LEN + MAX_MESSAGE_LEN)
+        }
+        Ed25519Signature::Siws(sig_struct) => (&sig_struct.message, MAX_SIWS_LEN),
+        Ed25519Signature::Raw(_) => return false,
+    };
+    message.len() as usize > max_len
+}
+
+/// The 64 signature bytes of any mode.
+fn signature_bytes(signature: &Ed25519Signature) -> &BytesN<64> {
+    match signature {
+        Ed25519Signature::Prefixed(sig_struct)
+        | Ed25519Signature::Hashed(sig_struct)
+        | Ed25519Signature::Sep53(sig_struct) => &sig_struct.signature,
+        Ed25519Signature::SolanaOffchain(sig_struct) => &sig_struct.signature,
+        Ed25519Signature::Siws(sig_struct) => &sig_struct.signature,
+        Ed25519Signature::Raw(signature) => signature,
+    }
+}
+
+/// Whether the `s` half of `signature` is below `GROUP_ORDER`.
+fn is_canonical(signature: &BytesN<64>) -> bool {
+    let signature = signature.to_array();
+    // Little-endian, so the last byte is the most significant
+    for (byte, order) in signature[32..].iter().zip(GROUP_ORDER.iter()).rev() {
+        if byte != order {
+            return byte < order;
+        }
+    }
+    false
+}
+
+/// `message` without the one "\n" some wallet builds end the text they sign
+/// with. Only the checks skip it; the signature still covers it.
+fn strip_trailing_newline(message: &Bytes) -> Bytes {
+    match message.last() {
+        Some(b'\n') => message.slice(..message.len() - 1),
+        _ => message.clone(),
+    }
+}
+
+/// `message` without its "\nExpires: <expires_at>" suffix, once the suffix
+/// is checked against `expires_at` and the ledger time. Messages without an
+/// expiry are returned as they are.
+fn strip_expiry(e: &Env, message: &Bytes, expires_at: Option<u64>) -> Result<Bytes, VerifierError> {
+    let Some(expires_at) = expires_at else {
+        return Ok(message.clone());
+    };
+
+    let mut expected = [0u8; EXPIRES_LABEL.len() + MAX_U64_DIGITS];
+    expected[..EXPIRES_LABEL.len()].copy_from_slice(EXPIRES_LABEL);
+    let digits = decimal_encode(&mut expected[EXPIRES_LABEL.len()..], expires_at);
+    let expected = &expected[..EXPIRES_LABEL.len() + digits];
+
+    let suffix_len = expected.len() as u32;
+    if message.len() < suffix_len {
+        return Err(VerifierError::ExpiryMismatch);
+    }
+    let short_len = message.len() - suffix_len;
+    let mut suffix = [0u8; EXPIRES_LABEL.len() + MAX_U64_DIGITS];
+    message
+        .slice(short_len..)
+        .copy_into_slice(&mut suffix[..expected.len()]);
+    if suffix[..expected.len()] != *expected {
+        return Err(VerifierError::ExpiryMismatch);
+    }
+
+    if e.ledger().timestamp() > expires_at {
+        return Err(VerifierError::SignatureExpired);
+    }
+
+    Ok(message.slice(..short_len))
+}
+
+/// Writes `value` in decimal to the start of `dst`; returns the digit count.
+fn decimal_encode(dst: &mut [u8], mut value: u64) -> usize {
+    let mut digits = [0u8; MAX_U64_DIGITS];
+    let mut len = 0;
+    loop {
+        digits[len] = b'0' + (value % 10) as u8;
+        len += 1;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    for (i, digit) in digits[..len].iter().rev().enumerate() {
+        dst[i] = *digit;
+    }
+    len
+}
+
+/// `message` without its "\nFor: <hex of binding>" suffix, which a key with
+/// a binding requires. Messages for unbound keys are returned as they are.
+fn strip_binding(message: &Bytes, binding: Option<BytesN<32>>) -> Result<Bytes, VerifierError> {
+    const SUFFIX_LEN: usize = FOR_LABEL.len() + HEX_LEN;
+
+    let Some(binding) = binding else {
+        return Ok(message.clone());
+    };
+    if (message.len() as usize) < SUFFIX_LEN {
+        return Err(VerifierError::BindingMismatch);
+    }
+
+    let mut expected = [0u8; SUFFIX_LEN];
+    expected[..FOR_LABEL.len()].copy_from_slice(FOR_LABEL);
+    hex_encode(&mut expected[FOR_LABEL.len()..], &binding.to_array());
+    let short_len = message.len() - SUFFIX_LEN as u32;
+    if message
+        .slice(short_len..)
+        .to_buffer::<SUFFIX_LEN>()
+        .as_slice()
+        != expected
+    {
+        return Err(VerifierError::BindingMismatch);
+    }
+
+    Ok(message.slice(..short_len))
+}
+
+/// Checks `message` in either layout: the short one `check_message` takes,
+/// or that followed by "\nAccount: " + strkey of the key's bound account +
+/// "\nNetwork: " + hex of this network's id, so users see what they sign
+/// for and messages do not cross networks.
+fn check_bound_message(
+    e: &Env,
+    expected: &Expected,
+    message: &Bytes,
+    bound_account: Option<Address>,
+) -> Result<(), VerifierError> {
+    let total_len = message.len();
+    let short_len = total_len.saturating_sub(DOMAIN_SUFFIX_LEN as u32);
+    let is_bound = total_len > DOMAIN_SUFFIX_LEN as u32
+        && message.slice(short_len..short_len + ACCOUNT_LABEL.len() as u32)
+            == Bytes::from_slice(e, ACCOUNT_LABEL);
+
+    if !is_bound {
+        if !expected.short_format {
+            return Err(VerifierError::DomainMismatch);
+        }
+        return check_message(expected, message);
+    }
+
+    let Some(account) = bound_account else {
+        return Err(VerifierError::DomainMismatch);
+    };
+    let mut suffix = [0u8; DOMAIN_SUFFIX_LEN];
+    let (account_part, network_part) = suffix.split_at_mut(ACCOUNT_LABEL.len() + STRKEY_LEN);
+    account_part[..ACCOUNT_LABEL.len()].copy_from_slice(ACCOUNT_LABEL);
+    let strkey = account.to_string();
+    if strkey.len() as usize != STRKEY_LEN {
+        return Err(VerifierError::BadKeyLength);
+    }
+    strkey.copy_into_slice(&mut account_part[ACCOUNT_LABEL.len()..]);
+    network_part[..NETWORK_LABEL.len()].copy_from_slice(NETWORK_LABEL);
+    hex_encode(
+        &mut network_part[NETWORK_LABEL.len()..],
+        &e.ledger().network_id().to_array(),
+    );
+    if message
+        .slice(short_len..)
+        .to_buffer::<DOMAIN_SUFFIX_LEN>()
+        .as_slice()
+        != suffix
+    {
+        return Err(VerifierError::DomainMismatch);
+    }
+
+    check_message(expected, &message.slice(..short_len))
+}
+
+/// Checks that `message` is an accepted prefix followed by the encoded
+/// payload, with one copy of the message. The prefix is compared in that
+/// copy rather than sliced out into a new host object, and the body is read
+/// from it in place.
+fn check_message(expected: &Expected, message: &Bytes) -> Result<(), VerifierError> {
+    // The message layout is prefix + hex or prefix + base64, so its length
+    // picks the encoding and the prefix
+    let total_len = message.len() as usize;
+    if total_len > MAX_PREFIX_LEN + HEX_LEN {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let mut message_buf = [0u8; MAX_PREFIX_LEN + HEX_LEN];
+    message.copy_into_slice(&mut message_buf[..total_len]);
+    let message = &message_buf[..total_len];
+
+    let mut length_fits = false;
+    let mut layout = None;
+    'layouts: for body_len in [HEX_LEN, BASE64_LEN] {
+        let Some(prefix_len) = total_len.checked_sub(body_len) else {
+            continue;
+        };
+        for prefix in expected.prefixes.iter() {
+            if prefix.len() as usize == prefix_len {
+                length_fits = true;
+                if prefix_matches(&prefix, &message[..prefix_len]) {
+                    layout = Some((prefix_len, body_len));
+                    break 'layouts;
+                }
+            }
+        }
+    }
+    if !length_fits {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    // Any accepted prefix of that length will do
+    let Some((prefix_len, body_len)) = layout else {
+        return Err(VerifierError::MissingPrefix);
+    };
+
+    let Some((expected_hex, expected_base64)) = &expected.encoded else {
+        return Err(VerifierError::WrongMessageLength);
+    };
+
+    let body = &message[prefix_len..];
+    let matches = if body_len == HEX_LEN {
+        // Wallets differ in the case they print hex in. Only the comparison
+        // ignores case, the signature is checked over the bytes as signed.
+        body.eq_ignore_ascii_case(expected_hex)
+    } else {
+        body == expected_base64
+    };
+    if !matches {
+        return Err(VerifierError::PayloadMismatch);
+    }
+
+    Ok(())
+}
+
+/// Whether `prefix`, an accepted prefix of `presented`'s length, is
+/// `presented`.
+fn prefix_matches(prefix: &Bytes, presented: &[u8]) -> bool {
+    // Accepted prefixes are at most MAX_PREFIX_LEN
+    let mut prefix_buf = [0u8; MAX_PREFIX_LEN];
+    let prefix_buf = &mut prefix_buf[..presented.len()];
+    prefix.copy_into_slice(prefix_buf);
+    prefix_buf == presented
+}
+
+/// The `check_message` this crate used before: it slices each candidate
+/// prefix out on the host, then copies the whole message. Kept for
+/// `prop_message_checks_agree` and the budget test.
+#[cfg(test)]
+fn check_message_sliced(expected: &Expected, message: &Bytes) -> Result<(), VerifierError> {
+    // The message layout is prefix + hex or prefix + base64, so its length
+    // picks the encoding and the prefix
+    let total_len = message.len() as usize;
+    if total_len > MAX_PREFIX_LEN + HEX_LEN {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let mut length_fits = false;
+    let mut layout = None;
+    for body_len in [HEX_LEN, BASE64_LEN] {
+        let Some(prefix_len) = total_len.checked_sub(body_len) else {
+            continue;
+        };
+        let presented = message.slice(..prefix_len as u32);
+        for prefix in expected.prefixes.iter() {
+            if prefix.len() as usize == prefix_len {
+                length_fits = true;
+                if prefix == presented {
+                    layout = Some((prefix_len, body_len));
+                }
+            }
+        }
+        if layout.is_some() {
+            break;
+        }
+    }
+    if !length_fits {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    // Any accepted prefix of that length will do
+    let Some((prefix_len, body_len)) = layout else {
+        return Err(VerifierError::MissingPrefix);
+    };
+
+    // Copy into a fixed-size buffer for fast validation
+    let mut prefixed_msg_buf = [0u8; MAX_PREFIX_LEN + HEX_LEN];
+    let prefixed_msg_slice = &mut prefixed_msg_buf[..total_len];
+    message.copy_into_slice(prefixed_msg_slice);
+    let body = &prefixed_msg_slice[prefix_len..];
+
+    let Some((expected_hex, expected_base64)) = &expected.encoded else {
+        return Err(VerifierError::WrongMessageLength);
+    };
+
+    let matches = if body_len == HEX_LEN {
+        // Wallets differ in the case they print hex in. Only the comparison
+        // ignores case, the signature is checked over the bytes as signed.
+        body.eq_ignore_ascii_case(expected_hex)
+    } else {
+        body == expected_base64
+    };
+    if !matches {
+        return Err(VerifierError::PayloadMismatch);
+    }
+
+    Ok(())
+}
+
+/// Checks that `message` is the SEP-53 prefix followed by the lowercase hex
+/// of the payload. The configured prefixes do not apply: SEP-53 fixes its
+/// own.
+fn check_sep53_message(expected: &Expected, message: &Bytes) -> Result<(), VerifierError> {
+    const SEP53_LEN: usize = SEP53_PREFIX.len() + HEX_LEN;
+
+    if message.len() as usize != SEP53_LEN {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let Some((expected_hex, _)) = &expected.encoded else {
+        return Err(VerifierError::WrongMessageLength);
+    };
+    let message = message.to_buffer::<SEP53_LEN>();
+    let message = message.as_slice();
+    if &message[..SEP53_PREFIX.len()] != SEP53_PREFIX {
+        return Err(VerifierError::MissingPrefix);
+    }
+
+    if message[SEP53_PREFIX.len()..] != expected_hex[..] {
+        return Err(VerifierError::PayloadMismatch);
+    }
+
+    Ok(())
+}
+
+/// Checks a Sign-In-With-Solana `message`, read line by line:
+///
+/// - the first line is "<domain> wants you to sign in with your Solana
+///   account:", for the configured domain;
+/// - the second is the address, which is not read: the signature proves the
+///   key;
+/// - after those, "Nonce: " and "Issued At: " fields must each appear once,
+///   in any order and among any other lines. The nonce is the lowercase hex
+///   of the payload.
+///
+/// Other fields, "Expiration Time: " among them, are shown to the signer but
+/// not enforced.
+fn check_siws_message(e: &Env, expected: &Expected, message: &Bytes) -> Result<(), VerifierError> {
+    let total_len = message.len() as usize;
+    if total_len > MAX_SIWS_LEN {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let mut message_buf = [0u8; MAX_SIWS_LEN];
+    let message_slice = &mut message_buf[..total_len];
+    message.copy_into_slice(message_slice);
+    let mut lines = message_slice.split(|&byte| byte == b'\n');
+
+    let Some(domain) = lines
+        .next()
+        .and_then(|header| header.strip_suffix(SIWS_HEADER_SUFFIX))
+    else {
+        return Err(VerifierError::SiwsMalformed);
+    };
+    let Some(configured) = siws_domain(e) else {
+        return Err(VerifierError::SiwsDomainMismatch);
+    };
+    if configured != Bytes::from_slice(e, domain) {
+        return Err(VerifierError::SiwsDomainMismatch);
+    }
+    if lines.next().is_none_or(|address| address.is_empty()) {
+        return Err(VerifierError::SiwsMalformed);
+    }
+
+    let mut nonce = None;
+    let mut issued_at = false;
+    for line in lines {
+        if let Some(value) = line.strip_prefix(NONCE_LABEL) {
+            if nonce.replace(value).is_some() {
+                return Err(VerifierError::SiwsMalformed);
+            }
+        } else if line.starts_with(ISSUED_AT_LABEL) {
+            if issued_at {
+                return Err(VerifierError::SiwsMalformed);
+            }
+            issued_at = true;
+        }
+    }
+    let (Some(nonce), true) = (nonce, issued_at) else {
+        return Err(VerifierError::SiwsMalformed);
+    };
+
+    let Some((expected_hex, _)) = &expected.encoded else {
+        return Err(VerifierError::WrongMessageLength);
+    };
+    if nonce != expected_hex {
+        return Err(VerifierError::PayloadMismatch);
+    }
+
+    Ok(())
+}
+
+/// The message body of a Solana off-chain message envelope: signing domain,
+/// version, format and little-endian u16 length, then the body.
+fn solana_offchain_body(envelope: &Bytes) -> Result<Bytes, VerifierError> {
+    if (envelope.len() as usize) < SOLANA_HEADER_LEN {
+        return Err(VerifierError::BadEnvelope);
+    }
+    let header = envelope
+        .slice(..SOLANA_HEADER_LEN as u32)
+        .to_buffer::<SOLANA_HEADER_LEN>();
+    let header = header.as_slice();
+    let domain_len = SOLANA_SIGNING_DOMAIN.len();
+
+    if &header[..domain_len] != SOLANA_SIGNING_DOMAIN || header[domain_len] != 0 {
+        return Err(VerifierError::BadEnvelope);
+    }
+    // header[domain_len + 1] is the message format, which the body check
+    // makes moot
+    let declared_len = u16::from_le_bytes([header[domain_len + 2], header[domain_len + 3]]);
+    if envelope.len() as usize - SOLANA_HEADER_LEN != declared_len as usize {
+        return Err(VerifierError::BadEnvelope);
+    }
+
+    Ok(envelope.slice(SOLANA_HEADER_LEN as u32..))
+}
+
+fn auth_prefixes(e: &Env) -> Vec<Bytes> {
+    e.storage()
+        .instance()
+        .get(&DataKey::Prefixes)
+        .unwrap_or_else(|| Vec::from_array(e, [Bytes::from_slice(e, AUTH_PREFIX)]))
+}
+
+fn short_format_accepted(e: &Env) -> bool {
+    e.storage()
+        .instance()
+        .get(&DataKey::ShortFormat)
+        .unwrap_or(true)
+}
+
+fn siws_domain(e: &Env) -> Option<Bytes> {
+    e.storage().instance().get(&DataKey::SiwsDomain)
+}
+
+fn expected_key_len(e: &Env) -> Option<u32> {
+    e.storage().instance().get(&DataKey::ExpectedKeyLen)
+}
+
+fn check_prefix_len(e: &Env, prefix: &Bytes) {
+    if prefix.len() as usize > MAX_PREFIX_LEN {
+        panic_with_error!(e, VerifierError::PrefixTooLong)
+    }
+}
+
+fn require_admin(e: &Env) {
+    let admin: Address = e
+        .storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .unwrap_or_else(|| panic_with_error!(e, VerifierError::NoAdmin));
+    admin.require_auth();
+}
+
+/// Standard (RFC 4648, padded) base64 encoding of `src` into `dst`, which must
+/// be exactly `4 * ceil(src.len() / 3)` bytes.
+fn base64_encode(dst: &mut [u8], src: &[u8]) {
+    const BASE64_CHARS: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    for (chunk, out) in src.chunks(3).zip(dst.chunks_mut(4)) {
+        let b0 = chunk[0] as usize;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as usize;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as usize;
+
+        out[0] = BASE64_CHARS[b0 >> 2];
+        out[1] = BASE64_CHARS[((b0 & 0x03) << 4) | (b1 >> 4)];
+        out[2] = if chunk.len() > 1 {
+            BASE64_CHARS[((b1 & 0x0f) << 2) | (b2 >> 6)]
+        } else {
+            b'='
+        };
+        out[3] = if chunk.len() > 2 {
+            BASE64_CHARS[b2 & 0x3f]
+        } else {
+            b'='
+        };
     }
 }
 
 #[cfg(test)]
 mod test;
+#[cfg(test)]
+mod test_budget;
+#[cfg(test)]
+mod test_fuzz;
+#[cfg(test)]
+mod test_vectors;
@@ -1,6 +1,16 @@
 #![cfg(test)]
-use crate::{Ed25519SigData, Ed25519Verifier, Ed25519VerifierClient};
-use soroban_sdk::{xdr::ToXdr, Bytes, BytesN, Env};
+use crate::{
+    is_key_data_xdr, BatchItem, DataKey, Ed25519AnyOfSigData, Ed25519EnvelopeSigData,
+    Ed25519KeyData, Ed25519SigData, Ed25519Signature, Ed25519SiwsSigData, Ed25519Verifier,
+    Ed25519VerifierClient, SigEnvelope, VerifierConfig, VerifierError,
+};
+use latch_utils::{decoded_len, hex_decode, hex_encode, hex_len};
+use soroban_sdk::{
+    symbol_short,
+    testutils::{Address as _, Ledger as _, MockAuth, MockAuthInvoke},
+    xdr::{ContractCostType, FromXdr, ToXdr},
+    Address, Bytes, BytesN, Env, IntoVal, Map, Symbol, Val, Vec,
+};
 
 extern crate std;
 
@@ -9,19 +19,24 @@ const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
 
 /// Convert bytes to lowercase hex string (off-chain helper for tests)
 fn bytes_to_hex(bytes: &[u8]) -> std::vec::Vec<u8> {
-    const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
-    let mut result = std::vec::Vec::with_capacity(bytes.len() * 2);
-    for byte in bytes {
-        result.push(HEX_CHARS[(byte >> 4) as usize]);
-        result.push(HEX_CHARS[(byte & 0x0f) as usize]);
-    }
-    result
+    let mut hex = std::vec![0u8; hex_len(bytes.len())];
+    hex_encode(&mut hex, bytes);
+    hex
 }
 
 #[test]
 fn test_verify_valid_signature() {
     let env = Env::default();
-    let contract_id = env.register(Ed25519Verifier, ());
+    let contract_id = env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            None::<Bytes>,
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
     let client = Ed25519VerifierClient::new(&env, &contract_id);
 
     // Generate a keypair for testing
@@ -52,6 +67,7 @@ fn test_verify_valid_signature() {
     let sig_data = Ed25519SigData {
         prefixed_message: prefixed_msg,
         signature: sig,
+        expires_at: None,
     };
     let sig_data_bytes = sig_data.to_xdr(&env);
 
@@ -61,10 +77,18 @@ fn test_verify_valid_signature() {
 }
 
 #[test]
-#[should_panic(expected = "prefixed_message has wrong length")]
 fn test_verify_invalid_prefix() {
     let env = Env::default();
-    let contract_id = env.register(Ed25519Verifier, ());
+    let contract_id = env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            None::<Bytes>,
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
     let client = Ed25519VerifierClient::new(&env, &contract_id);
 
     let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
@@ -89,18 +113,27 @@ fn test_verify_invalid_prefix() {
     let sig_data = Ed25519SigData {
         prefixed_message: prefixed_msg,
         signature: sig,
+        expires_at: None,
     };
     let sig_data_bytes = sig_data.to_xdr(&env);
 
-    // Should panic - wrong prefix
-    client.verify(&payload, &public_key, &sig_data_bytes);
+    // Should reject - wrong prefix
+    assert!(!client.verify(&payload, &public_key, &sig_data_bytes));
 }
 
 #[test]
-#[should_panic(expected = "prefixed_message hex does not match payload")]
 fn test_verify_wrong_payload() {
     let env = Env::default();
-    let contract_id = env.register(Ed25519Verifier, ());
+    let contract_id = env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            None::<Bytes>,
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
     let client = Ed25519VerifierClient::new(&env, &contract_id);
 
     let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
@@ -127,18 +160,28 @@ fn test_verify_wrong_payload() {
     let sig_data = Ed25519SigData {
         prefixed_message: prefixed_msg,
         signature: sig,
+        expires_at: None,
     };
     let sig_data_bytes = sig_data.to_xdr(&env);
 
-    // Should panic - payload mismatch
-    client.verify(&wrong_payload, &public_key, &sig_data_bytes);
+    // Should reject - payload mismatch
+    assert!(!client.verify(&wrong_payload, &public_key, &sig_data_bytes));
 }
 
 #[test]
 #[should_panic]
 fn test_verify_wrong_signature() {
     let env = Env::default();
-    let contract_id = env.register(Ed25519Verifier, ());
+    let contract_id = env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            None::<Bytes>,
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
     let client = Ed25519VerifierClient::new(&env, &contract_id);
 
     let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
@@ -162,9 +205,2494 @@ fn test_verify_wrong_signature() {
     let sig_data = Ed25519SigData {
         prefixed_message: prefixed_msg,
         signature: sig,
+        expires_at: None,
     };
     let sig_data_bytes = sig_data.to_xdr(&env);
 
     // Should panic - invalid signature
     client.verify(&payload, &public_key, &sig_data_bytes);
 }
+
+#[test]
+fn test_verify_rejects_short_key_data() {
+    let env = Env::default();
+    let contract_id = env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            None::<Bytes>,
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
+    let client = Ed25519VerifierClient::new(&env, &contract_id);
+
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let payload_data: [u8; 32] = [4u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+
+    let mut prefixed_msg_vec = AUTH_PREFIX.to_vec();
+    prefixed_msg_vec.extend_from_slice(&bytes_to_hex(&payload_data));
+    use ed25519_dalek::Signer;
+    let signature_bytes: [u8; 64] = keypair.sign(&prefixed_msg_vec).to_bytes();
+    let sig_data_bytes = Ed25519SigData {
+        prefixed_message: Bytes::from_slice(&env, &prefixed_msg_vec),
+        signature: BytesN::from_array(&env, &signature_bytes),
+        expires_at: None,
+    }
+    .to_xdr(&env);
+
+    // Only 31 of the 32 key bytes
+    let public_key = Bytes::from_slice(&env, &keypair.verifying_key().to_bytes()[..31]);
+    assert!(!client.verify(&payload, &public_key, &sig_data_bytes));
+}
+
+#[test]
+fn test_verify_rejects_sig_data_of_another_type() {
+    let env = Env::default();
+    let contract_id = env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            None::<Bytes>,
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
+    let client = Ed25519VerifierClient::new(&env, &contract_id);
+
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let public_key = Bytes::from_slice(&env, &keypair.verifying_key().to_bytes());
+    let payload = Bytes::from_slice(&env, &[5u8; 32]);
+
+    // Valid XDR, but a symbol rather than an Ed25519SigData
+    let sig_data_bytes = symbol_short!("garbage").to_xdr(&env);
+    assert!(!client.verify(&payload, &public_key, &sig_data_bytes));
+}
+
+/// The message Phantom signs for `payload_data`.
+pub(crate) fn phantom_message(payload_data: &[u8; 32]) -> std::vec::Vec<u8> {
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(&bytes_to_hex(payload_data));
+    message
+}
+
+/// Signs `message` with a fresh key; returns the public key and sig_data.
+pub(crate) fn sign_message(env: &Env, message: &[u8]) -> (Bytes, Bytes) {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let sig_data = Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, message),
+        signature: BytesN::from_array(env, &keypair.sign(message).to_bytes()),
+        expires_at: None,
+    };
+    (
+        Bytes::from_slice(env, &keypair.verifying_key().to_bytes()),
+        sig_data.to_xdr(env),
+    )
+}
+
+#[test]
+fn test_verify_checked_accepts_valid_signature() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [6u8; 32];
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    client.verify_checked(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &sig_data,
+    );
+}
+
+#[test]
+fn test_verify_checked_reports_each_failure() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [7u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    let check = |payload: &Bytes, key_data: &Bytes, sig_data: &Bytes| match client
+        .try_verify_checked(payload, key_data, sig_data)
+    {
+        Err(Ok(error)) => error as u32,
+        other => panic!("expected a VerifierError, got {other:?}"),
+    };
+
+    let not_sig_data = symbol_short!("garbage").to_xdr(&env);
+    assert_eq!(check(&payload, &public_key, &not_sig_data), 1);
+    assert_eq!(VerifierError::MalformedSigData as u32, 1);
+
+    let short_key = public_key.slice(..31);
+    assert_eq!(check(&payload, &short_key, &sig_data), 2);
+    assert_eq!(VerifierError::BadKeyLength as u32, 2);
+
+    let (_, short_message) = sign_message(&env, &phantom_message(&payload_data)[..91]);
+    assert_eq!(check(&payload, &public_key, &short_message), 3);
+    assert_eq!(check(&payload.slice(..31), &public_key, &sig_data), 3);
+    assert_eq!(VerifierError::WrongMessageLength as u32, 3);
+
+    let mut unprefixed = phantom_message(&payload_data);
+    unprefixed[0] = b's';
+    let (_, unprefixed) = sign_message(&env, &unprefixed);
+    assert_eq!(check(&payload, &public_key, &unprefixed), 4);
+    assert_eq!(VerifierError::MissingPrefix as u32, 4);
+
+    let other_payload = Bytes::from_slice(&env, &[8u8; 32]);
+    assert_eq!(check(&other_payload, &public_key, &sig_data), 5);
+    assert_eq!(VerifierError::PayloadMismatch as u32, 5);
+}
+
+#[test]
+fn test_verify_checked_traps_on_bad_signature() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [9u8; 32];
+    let (_, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    let (other_key, _) = sign_message(&env, b"other");
+
+    // `BadSignature` is reserved; the host's ed25519_verify traps instead.
+    assert_eq!(VerifierError::BadSignature as u32, 6);
+    let result = client.try_verify_checked(
+        &Bytes::from_slice(&env, &payload_data),
+        &other_key,
+        &sig_data,
+    );
+    assert!(matches!(result, Err(Err(_))));
+}
+
+/// A fresh key and its Prefixed and Raw signatures for `payload_data`.
+pub(crate) fn sign_both_modes(
+    env: &Env,
+    payload_data: &[u8; 32],
+) -> (Bytes, Ed25519SigData, BytesN<64>) {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let message = phantom_message(payload_data);
+    let prefixed = Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, &message),
+        signature: BytesN::from_array(env, &keypair.sign(&message).to_bytes()),
+        expires_at: None,
+    };
+    let raw = BytesN::from_array(env, &keypair.sign(payload_data).to_bytes());
+    (
+        Bytes::from_slice(env, &keypair.verifying_key().to_bytes()),
+        prefixed,
+        raw,
+    )
+}
+
+#[test]
+fn test_verify_both_modes() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [10u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, prefixed, raw) = sign_both_modes(&env, &payload_data);
+
+    let prefixed = Ed25519Signature::Prefixed(prefixed).to_xdr(&env);
+    assert!(client.verify(&payload, &public_key, &prefixed));
+    let raw = Ed25519Signature::Raw(raw).to_xdr(&env);
+    assert!(client.verify(&payload, &public_key, &raw));
+}
+
+#[test]
+fn test_verify_raw_signature_presented_as_prefixed() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [11u8; 32];
+    let (public_key, mut prefixed, raw) = sign_both_modes(&env, &payload_data);
+
+    // The message checks pass, but the signature is not over it
+    prefixed.signature = raw;
+    let result = client.try_verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &Ed25519Signature::Prefixed(prefixed).to_xdr(&env),
+    );
+    assert!(matches!(result, Err(Err(_))));
+}
+
+#[test]
+fn test_verify_prefixed_signature_presented_as_raw() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [12u8; 32];
+    let (public_key, prefixed, _) = sign_both_modes(&env, &payload_data);
+
+    let result = client.try_verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &Ed25519Signature::Raw(prefixed.signature).to_xdr(&env),
+    );
+    assert!(matches!(result, Err(Err(_))));
+}
+
+#[test]
+fn test_custom_prefix_deployment() {
+    let env = Env::default();
+    let prefix = Bytes::from_slice(&env, b"Stellar Signed Message:\n");
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                Some(prefix.clone()),
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    assert_eq!(client.prefixes(), Vec::from_array(&env, [prefix]));
+
+    let payload_data = [13u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let mut message = b"Stellar Signed Message:\n".to_vec();
+    message.extend_from_slice(&bytes_to_hex(&payload_data));
+    let (public_key, sig_data) = sign_message(&env, &message);
+    assert!(client.verify(&payload, &public_key, &sig_data));
+
+    let (public_key, phantom_sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    assert!(!client.verify(&payload, &public_key, &phantom_sig_data));
+}
+
+#[test]
+fn test_default_prefix_is_phantom() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    assert_eq!(
+        client.prefixes(),
+        Vec::from_array(&env, [Bytes::from_slice(&env, AUTH_PREFIX)])
+    );
+}
+
+#[test]
+fn test_prefix_at_cap_is_accepted() {
+    let env = Env::default();
+    let prefix = Bytes::from_array(&env, &[b'a'; 64]);
+    let contract_id = env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            Some(prefix.clone()),
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
+    assert_eq!(
+        Ed25519VerifierClient::new(&env, &contract_id).prefixes(),
+        Vec::from_array(&env, [prefix])
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_prefix_over_cap_is_rejected() {
+    let env = Env::default();
+    env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            Some(Bytes::from_array(&env, &[b'a'; 65])),
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
+}
+
+fn pinned_verifier(env: &Env, expected_key_len: Option<u32>) -> Ed25519VerifierClient<'_> {
+    Ed25519VerifierClient::new(
+        env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                expected_key_len,
+            ),
+        ),
+    )
+}
+
+/// How many Ed25519 signatures the host has verified since the budget was
+/// last reset.
+fn ed25519_verifications(env: &Env) -> u64 {
+    env.cost_estimate()
+        .budget()
+        .tracker(ContractCostType::VerifyEd25519Sig)
+        .iterations
+}
+
+#[test]
+fn test_pinned_key_len_rejects_other_lengths_before_crypto() {
+    let env = Env::default();
+    let client = pinned_verifier(&env, Some(32));
+    assert_eq!(
+        client.config(),
+        VerifierConfig {
+            expected_key_len: Some(32),
+            accept_short_format: true,
+            siws_domain: None,
+        }
+    );
+
+    let payload_data = [68u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    // The signature is valid, but neither key_data is 32 bytes
+    let mut long_key = public_key.clone();
+    long_key.push_back(0);
+    let struct_key = key_data(&env, public_key.clone(), None, None);
+    for key in [long_key, struct_key] {
+        env.cost_estimate().budget().reset_default();
+        assert_eq!(
+            client.try_verify_checked(&payload, &key, &sig_data),
+            Err(Ok(VerifierError::BadKeyLength))
+        );
+        assert!(!client.verify(&payload, &key, &sig_data));
+        assert_eq!(ed25519_verifications(&env), 0);
+    }
+
+    env.cost_estimate().budget().reset_default();
+    assert!(client.verify(&payload, &public_key, &sig_data));
+    assert_eq!(ed25519_verifications(&env), 1);
+}
+
+#[test]
+fn test_key_data_that_is_not_xdr_is_refused() {
+    let env = Env::default();
+    let client = pinned_verifier(&env, None);
+    let payload_data = [69u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    // The lengths of secp256k1 and secp256r1 keys, and a bound key cut
+    // short, none of which `from_xdr` may see
+    let bound_key = key_data(&env, public_key, None, Some([0x22u8; 32]));
+    for key in [
+        Bytes::from_array(&env, &[7u8; 33]),
+        Bytes::from_array(&env, &[7u8; 65]),
+        bound_key.slice(..bound_key.len() - 1),
+    ] {
+        assert_eq!(
+            client.try_verify_checked(&payload, &key, &sig_data),
+            Err(Ok(VerifierError::BadKeyLength))
+        );
+        assert!(!client.verify(&payload, &key, &sig_data));
+    }
+}
+
+#[test]
+fn test_key_data_check_accepts_every_encoding() {
+    let env = Env::default();
+    let public_key = Bytes::from_array(&env, &[9u8; 32]);
+    let account = Address::from_str(
+        &env,
+        "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF",
+    );
+    for account in [None, Some(account), Some(Address::generate(&env))] {
+        for binding in [None, Some([0x22u8; 32])] {
+            let key = key_data(&env, public_key.clone(), account.clone(), binding);
+            assert!(is_key_data_xdr(&key), "{:?} {:?}", account, binding);
+        }
+    }
+}
+
+#[test]
+fn test_unpinned_key_len_accepts_every_key_form() {
+    let env = Env::default();
+    let client = pinned_verifier(&env, None);
+    assert_eq!(client.config().expected_key_len, None);
+
+    let payload_data = [68u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    assert!(client.verify(&payload, &public_key, &sig_data));
+    assert!(client.verify(&payload, &key_data(&env, public_key, None, None), &sig_data));
+}
+
+const OTHER_PREFIX: &[u8] = b"Stellar Signed Message:\n";
+
+/// A verifier administered by a fresh address, accepting the Phantom prefix.
+fn setup_with_admin(env: &Env) -> (Ed25519VerifierClient<'_>, Address) {
+    let admin = Address::generate(env);
+    let contract_id = env.register(
+        Ed25519Verifier,
+        (
+            Some(admin.clone()),
+            None::<Bytes>,
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
+    (Ed25519VerifierClient::new(env, &contract_id), admin)
+}
+
+#[test]
+fn test_verify_under_any_accepted_prefix() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    client.add_prefix(&Bytes::from_slice(&env, OTHER_PREFIX));
+
+    let payload_data = [14u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let mut other_message = OTHER_PREFIX.to_vec();
+    other_message.extend_from_slice(&bytes_to_hex(&payload_data));
+    for message in [phantom_message(&payload_data), other_message] {
+        let (public_key, sig_data) = sign_message(&env, &message);
+        assert!(client.verify(&payload, &public_key, &sig_data));
+    }
+
+    // Same length as the Phantom prefix, but not listed
+    let mut unlisted = b"Stellar Smart Account Auth!".to_vec();
+    unlisted.push(b'\n');
+    unlisted.extend_from_slice(&bytes_to_hex(&payload_data));
+    let (public_key, sig_data) = sign_message(&env, &unlisted);
+    assert_eq!(
+        client.try_verify_checked(&payload, &public_key, &sig_data),
+        Err(Ok(VerifierError::MissingPrefix))
+    );
+
+    client.remove_prefix(&Bytes::from_slice(&env, AUTH_PREFIX));
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    assert!(!client.verify(&payload, &public_key, &sig_data));
+}
+
+#[test]
+fn test_prefix_mutation_requires_admin() {
+    let env = Env::default();
+    let (client, admin) = setup_with_admin(&env);
+    let prefix = Bytes::from_slice(&env, OTHER_PREFIX);
+
+    // Signed by someone else
+    let intruder = Address::generate(&env);
+    assert!(client
+        .mock_auths(&[MockAuth {
+            address: &intruder,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "add_prefix",
+                args: (prefix.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_add_prefix(&prefix)
+        .is_err());
+    assert_eq!(client.prefixes().len(), 1);
+
+    client
+        .mock_auths(&[MockAuth {
+            address: &admin,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "add_prefix",
+                args: (prefix.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .add_prefix(&prefix);
+    assert_eq!(client.prefixes().len(), 2);
+    assert!(client.try_remove_prefix(&prefix).is_err());
+}
+
+#[test]
+fn test_prefixes_without_admin_are_fixed() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    assert_eq!(client.admin(), None);
+    assert_eq!(
+        client.try_add_prefix(&Bytes::from_slice(&env, OTHER_PREFIX)),
+        Err(Ok(VerifierError::NoAdmin.into()))
+    );
+}
+
+#[test]
+fn test_prefix_list_limits() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup_with_admin(&env);
+    let phantom = Bytes::from_slice(&env, AUTH_PREFIX);
+
+    assert_eq!(
+        client.try_add_prefix(&phantom),
+        Err(Ok(VerifierError::DuplicatePrefix.into()))
+    );
+    assert_eq!(
+        client.try_remove_prefix(&phantom),
+        Err(Ok(VerifierError::LastPrefix.into()))
+    );
+    assert_eq!(
+        client.try_remove_prefix(&Bytes::from_slice(&env, OTHER_PREFIX)),
+        Err(Ok(VerifierError::PrefixNotFound.into()))
+    );
+
+    for i in 1..4u8 {
+        client.add_prefix(&Bytes::from_array(&env, &[b'a' + i; 8]));
+    }
+    assert_eq!(
+        client.try_add_prefix(&Bytes::from_slice(&env, OTHER_PREFIX)),
+        Err(Ok(VerifierError::TooManyPrefixes.into()))
+    );
+}
+
+#[test]
+fn test_verify_hex_in_any_case() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [0xabu8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+
+    let lower = phantom_message(&payload_data);
+    let mut upper = lower.clone();
+    upper[AUTH_PREFIX.len()..].make_ascii_uppercase();
+    let mut mixed = lower.clone();
+    for (i, c) in mixed[AUTH_PREFIX.len()..].iter_mut().enumerate() {
+        if i % 2 == 0 {
+            c.make_ascii_uppercase();
+        }
+    }
+    assert_ne!(upper, lower);
+    assert_ne!(mixed, upper);
+
+    for message in [lower, upper, mixed] {
+        let (public_key, sig_data) = sign_message(&env, &message);
+        assert!(client.verify(&payload, &public_key, &sig_data));
+    }
+}
+
+#[test]
+fn test_verify_wrong_hex_digit_in_upper_case() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [0xabu8; 32];
+
+    let mut message = phantom_message(&payload_data);
+    message[AUTH_PREFIX.len()..].make_ascii_uppercase();
+    // "AB" -> "AC" in the last byte
+    *message.last_mut().unwrap() = b'C';
+    let (public_key, sig_data) = sign_message(&env, &message);
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &payload_data),
+            &public_key,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::PayloadMismatch))
+    );
+}
+
+/// "Stellar Smart Account Auth:\n" + base64(payload), as some Phantom
+/// integrations display it.
+pub(crate) fn phantom_base64_message(payload_data: &[u8; 32]) -> std::vec::Vec<u8> {
+    use base64::Engine;
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(
+        base64::engine::general_purpose::STANDARD
+            .encode(payload_data)
+            .as_bytes(),
+    );
+    message
+}
+
+#[test]
+fn test_verify_base64_message() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [0xfbu8; 32];
+    let message = phantom_base64_message(&payload_data);
+    assert_eq!(message.len(), 72);
+
+    let (public_key, sig_data) = sign_message(&env, &message);
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &sig_data
+    ));
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &[0xfcu8; 32]),
+            &public_key,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::PayloadMismatch))
+    );
+}
+
+#[test]
+fn test_verify_base64_content_at_hex_length() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [0xfbu8; 32];
+
+    // Padded out to 92 bytes, so it is read as hex
+    let mut message = phantom_base64_message(&payload_data);
+    message.extend_from_slice(&[b'A'; 20]);
+    let (public_key, sig_data) = sign_message(&env, &message);
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &payload_data),
+            &public_key,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::PayloadMismatch))
+    );
+}
+
+/// A Solana off-chain message envelope, v0 layout: "\xffsolana offchain",
+/// version, format (1 = limited UTF-8), u16 LE body length, body.
+pub(crate) fn solana_envelope(version: u8, declared_len: u16, body: &[u8]) -> std::vec::Vec<u8> {
+    let mut envelope = b"\xffsolana offchain".to_vec();
+    envelope.push(version);
+    envelope.push(1);
+    envelope.extend_from_slice(&declared_len.to_le_bytes());
+    envelope.extend_from_slice(body);
+    envelope
+}
+
+/// Signs `envelope` with a fresh key; returns the public key and sig_data.
+pub(crate) fn sign_envelope(env: &Env, envelope: &[u8]) -> (Bytes, Bytes) {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let sig_data = Ed25519Signature::SolanaOffchain(Ed25519EnvelopeSigData {
+        envelope: Bytes::from_slice(env, envelope),
+        signature: BytesN::from_array(env, &keypair.sign(envelope).to_bytes()),
+    });
+    (
+        Bytes::from_slice(env, &keypair.verifying_key().to_bytes()),
+        sig_data.to_xdr(env),
+    )
+}
+
+#[test]
+fn test_verify_solana_offchain_envelope() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [15u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let message = phantom_message(&payload_data);
+    let envelope = solana_envelope(0, message.len() as u16, &message);
+
+    let (public_key, sig_data) = sign_envelope(&env, &envelope);
+    assert!(client.verify(&payload, &public_key, &sig_data));
+}
+
+#[test]
+fn test_verify_solana_envelope_with_wrong_length_field() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [16u8; 32];
+    let message = phantom_message(&payload_data);
+    let envelope = solana_envelope(0, message.len() as u16 + 1, &message);
+
+    let (public_key, sig_data) = sign_envelope(&env, &envelope);
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &payload_data),
+            &public_key,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::BadEnvelope))
+    );
+}
+
+#[test]
+fn test_verify_solana_envelope_with_wrong_version() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [17u8; 32];
+    let message = phantom_message(&payload_data);
+    let envelope = solana_envelope(1, message.len() as u16, &message);
+
+    let (public_key, sig_data) = sign_envelope(&env, &envelope);
+    assert!(!client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &sig_data
+    ));
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &payload_data),
+            &public_key,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::BadEnvelope))
+    );
+}
+
+/// What the Stellar SDKs' SEP-53 `signMessage` does: sign
+/// sha256("Stellar Signed Message:\n" + message), here with the hex payload
+/// as the message. Returns the public key and sig_data.
+pub(crate) fn sign_sep53(env: &Env, payload_data: &[u8; 32]) -> (Bytes, Bytes) {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let mut message = b"Stellar Signed Message:\n".to_vec();
+    message.extend_from_slice(&bytes_to_hex(payload_data));
+    let message = Bytes::from_slice(env, &message);
+    let digest = env.crypto().sha256(&message).to_array();
+
+    let sig_data = Ed25519Signature::Sep53(Ed25519SigData {
+        prefixed_message: message,
+        signature: BytesN::from_array(env, &keypair.sign(&digest).to_bytes()),
+        expires_at: None,
+    });
+    (
+        Bytes::from_slice(env, &keypair.verifying_key().to_bytes()),
+        sig_data.to_xdr(env),
+    )
+}
+
+#[test]
+fn test_verify_sep53_signature() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [18u8; 32];
+
+    let (public_key, sig_data) = sign_sep53(&env, &payload_data);
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &sig_data
+    ));
+}
+
+#[test]
+fn test_verify_sep53_tampered_payload() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let (public_key, sig_data) = sign_sep53(&env, &[19u8; 32]);
+
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &[20u8; 32]),
+            &public_key,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::PayloadMismatch))
+    );
+}
+
+#[test]
+fn test_verify_sep53_requires_signature_over_digest() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [21u8; 32];
+    let mut message = b"Stellar Signed Message:\n".to_vec();
+    message.extend_from_slice(&bytes_to_hex(&payload_data));
+
+    // Signed over the message itself, as the Prefixed mode would be
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let sig_data = Ed25519Signature::Sep53(Ed25519SigData {
+        prefixed_message: Bytes::from_slice(&env, &message),
+        signature: BytesN::from_array(&env, &keypair.sign(&message).to_bytes()),
+        expires_at: None,
+    });
+    let result = client.try_verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &Bytes::from_slice(&env, &keypair.verifying_key().to_bytes()),
+        &sig_data.to_xdr(&env),
+    );
+    assert!(matches!(result, Err(Err(_))));
+}
+
+/// Strkey of `address` as bytes.
+fn strkey(address: &Address) -> std::vec::Vec<u8> {
+    let strkey = address.to_string();
+    let mut bytes = std::vec![0u8; strkey.len() as usize];
+    strkey.copy_into_slice(&mut bytes);
+    bytes
+}
+
+/// Phantom message for `payload_data` with the account and network suffix.
+fn bound_message(
+    payload_data: &[u8; 32],
+    account: &Address,
+    network_id: &[u8; 32],
+) -> std::vec::Vec<u8> {
+    let mut message = phantom_message(payload_data);
+    message.extend_from_slice(b"\nAccount: ");
+    message.extend_from_slice(&strkey(account));
+    message.extend_from_slice(b"\nNetwork: ");
+    message.extend_from_slice(&bytes_to_hex(network_id));
+    message
+}
+
+/// key_data tying `public_key` to an account and/or a binding.
+fn key_data(
+    env: &Env,
+    public_key: Bytes,
+    account: Option<Address>,
+    binding: Option<[u8; 32]>,
+) -> Bytes {
+    Ed25519KeyData {
+        pubkey: BytesN::try_from(public_key).unwrap(),
+        account,
+        binding: binding.map(|binding| BytesN::from_array(env, &binding)),
+    }
+    .to_xdr(env)
+}
+
+/// Signs `message` with a fresh key whose key_data binds it to `account`.
+fn sign_bound(env: &Env, account: &Address, message: &[u8]) -> (Bytes, Bytes) {
+    let (public_key, sig_data) = sign_message(env, message);
+    (
+        key_data(env, public_key, Some(account.clone()), None),
+        sig_data,
+    )
+}
+
+fn bound_verifier(env: &Env, accept_short_format: bool) -> Ed25519VerifierClient<'_> {
+    Ed25519VerifierClient::new(
+        env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                accept_short_format,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    )
+}
+
+#[test]
+fn test_verify_bound_message() {
+    let env = Env::default();
+    let client = bound_verifier(&env, false);
+    let account = Address::generate(&env);
+    let payload_data = [22u8; 32];
+    let message = bound_message(
+        &payload_data,
+        &account,
+        &env.ledger().network_id().to_array(),
+    );
+
+    let (key_data, sig_data) = sign_bound(&env, &account, &message);
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &key_data,
+        &sig_data
+    ));
+}
+
+#[test]
+fn test_verify_bound_message_for_another_network() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let account = Address::generate(&env);
+    let payload_data = [23u8; 32];
+    let message = bound_message(&payload_data, &account, &[0x42; 32]);
+
+    let (key_data, sig_data) = sign_bound(&env, &account, &message);
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &payload_data),
+            &key_data,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::DomainMismatch))
+    );
+}
+
+#[test]
+fn test_verify_bound_message_for_another_account() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [24u8; 32];
+    let message = bound_message(
+        &payload_data,
+        &Address::generate(&env),
+        &env.ledger().network_id().to_array(),
+    );
+
+    let (key_data, sig_data) = sign_bound(&env, &Address::generate(&env), &message);
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &payload_data),
+            &key_data,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::DomainMismatch))
+    );
+
+    // An unbound key cannot vouch for any account
+    let (public_key, _) = sign_message(&env, &message);
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &payload_data),
+            &public_key,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::DomainMismatch))
+    );
+}
+
+#[test]
+fn test_short_format_follows_deploy_flag() {
+    let env = Env::default();
+    let payload_data = [25u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let account = Address::generate(&env);
+    let (key_data, sig_data) = sign_bound(&env, &account, &phantom_message(&payload_data));
+
+    let migrating = bound_verifier(&env, true);
+    assert!(migrating.short_format_accepted());
+    assert!(migrating.verify(&payload, &key_data, &sig_data));
+
+    let strict = bound_verifier(&env, false);
+    assert!(!strict.short_format_accepted());
+    assert_eq!(
+        strict.try_verify_checked(&payload, &key_data, &sig_data),
+        Err(Ok(VerifierError::DomainMismatch))
+    );
+}
+
+/// sig_data as encoded before `expires_at` was added.
+#[soroban_sdk::contracttype]
+struct LegacySigData {
+    prefixed_message: Bytes,
+    signature: BytesN<64>,
+}
+
+#[test]
+fn test_verify_legacy_sig_data() {
+    use ed25519_dalek::Signer;
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [26u8; 32];
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let message = phantom_message(&payload_data);
+    let sig_data = LegacySigData {
+        prefixed_message: Bytes::from_slice(&env, &message),
+        signature: BytesN::from_array(&env, &keypair.sign(&message).to_bytes()),
+    };
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &Bytes::from_slice(&env, &keypair.verifying_key().to_bytes()),
+        &sig_data.to_xdr(&env),
+    ));
+}
+
+/// Signs `message` + "\nExpires: <text_expiry>" with a fresh key, declaring
+/// `expires_at` in the struct.
+fn sign_expiring(env: &Env, message: &[u8], text_expiry: u64, expires_at: u64) -> (Bytes, Bytes) {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let mut message = message.to_vec();
+    message.extend_from_slice(std::format!("\nExpires: {text_expiry}").as_bytes());
+    let sig_data = Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, &message),
+        signature: BytesN::from_array(env, &keypair.sign(&message).to_bytes()),
+        expires_at: Some(expires_at),
+    };
+    (
+        Bytes::from_slice(env, &keypair.verifying_key().to_bytes()),
+        sig_data.to_xdr(env),
+    )
+}
+
+#[test]
+fn test_verify_expiring_signature() {
+    let env = Env::default();
+    env.ledger().set_timestamp(1_000);
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [27u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_expiring(
+        &env,
+        &phantom_message(&payload_data),
+        1_700_000_000,
+        1_700_000_000,
+    );
+
+    assert!(client.verify(&payload, &public_key, &sig_data));
+
+    // Still good on the last second
+    env.ledger().set_timestamp(1_700_000_000);
+    assert!(client.verify(&payload, &public_key, &sig_data));
+
+    env.ledger().set_timestamp(1_700_000_001);
+    assert_eq!(
+        client.try_verify_checked(&payload, &public_key, &sig_data),
+        Err(Ok(VerifierError::SignatureExpired))
+    );
+}
+
+#[test]
+fn test_verify_expiry_text_must_match_field() {
+    let env = Env::default();
+    let client = Ed25519VerifierClient::new(
+        &env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    let payload_data = [28u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+
+    // The user saw one expiry, the struct claims a later one
+    let (public_key, sig_data) = sign_expiring(&env, &phantom_message(&payload_data), 100, 10_000);
+    assert_eq!(
+        client.try_verify_checked(&payload, &public_key, &sig_data),
+        Err(Ok(VerifierError::ExpiryMismatch))
+    );
+}
+
+/// Phantom message for `payload_data` ending in "\nFor: " + hex of `binding`.
+fn for_message(payload_data: &[u8; 32], binding: &[u8; 32]) -> std::vec::Vec<u8> {
+    let mut message = phantom_message(payload_data);
+    message.extend_from_slice(b"\nFor: ");
+    message.extend_from_slice(&bytes_to_hex(binding));
+    message
+}
+
+#[test]
+fn test_verify_message_for_bound_account() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [29u8; 32];
+    let binding = [0xa1; 32];
+    let (public_key, sig_data) = sign_message(&env, &for_message(&payload_data, &binding));
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &key_data(&env, public_key, None, Some(binding)),
+        &sig_data
+    ));
+}
+
+#[test]
+fn test_verify_message_for_another_binding() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [30u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &for_message(&payload_data, &[0xa1; 32]));
+
+    // Signed for one account, presented by another
+    assert_eq!(
+        client.try_verify_checked(
+            &payload,
+            &key_data(&env, public_key.clone(), None, Some([0xb2; 32])),
+            &sig_data
+        ),
+        Err(Ok(VerifierError::BindingMismatch))
+    );
+
+    // A message without the suffix does not satisfy a binding either
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    assert_eq!(
+        client.try_verify_checked(
+            &payload,
+            &key_data(&env, public_key, None, Some([0xa1; 32])),
+            &sig_data
+        ),
+        Err(Ok(VerifierError::BindingMismatch))
+    );
+}
+
+#[test]
+fn test_verify_unbound_key_data_ignores_binding() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [31u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    // Raw and struct-encoded key_data without a binding behave the same
+    assert!(client.verify(&payload, &public_key, &sig_data));
+    assert!(client.verify(&payload, &key_data(&env, public_key, None, None), &sig_data));
+}
+
+fn batch_item(key_data: Bytes, sig_data: Bytes) -> BatchItem {
+    BatchItem { key_data, sig_data }
+}
+
+#[test]
+fn test_batch_verify() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [32u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let message = phantom_message(&payload_data);
+
+    let (key_a, sig_a) = sign_message(&env, &message);
+    let (key_b, sig_b) = sign_message(&env, &message);
+    let both = [
+        batch_item(key_a.clone(), sig_a.clone()),
+        batch_item(key_b.clone(), sig_b.clone()),
+    ];
+    assert_eq!(
+        client.batch_verify(&payload, &Vec::from_array(&env, both)),
+        Vec::from_array(&env, [true, true])
+    );
+    assert_eq!(
+        client.batch_verify(&payload, &Vec::new(&env)),
+        Vec::<bool>::new(&env)
+    );
+
+    // Signed for another payload, under a prefix the verifier lacks, and
+    // sig_data of another type: each is false, the rest still verify
+    let (key_c, sig_c) = sign_message(&env, &phantom_message(&[33u8; 32]));
+    let mut other_prefix = OTHER_PREFIX.to_vec();
+    other_prefix.extend_from_slice(&bytes_to_hex(&payload_data));
+    let (key_d, sig_d) = sign_message(&env, &other_prefix);
+    let wrong_type = Bytes::from_slice(&env, &message).to_xdr(&env);
+    let items = Vec::from_array(
+        &env,
+        [
+            batch_item(key_a.clone(), sig_a.clone()),
+            batch_item(key_c, sig_c),
+            batch_item(key_d, sig_d),
+            batch_item(key_b, sig_b),
+            batch_item(key_a, wrong_type),
+        ],
+    );
+    assert_eq!(
+        client.batch_verify(&payload, &items),
+        Vec::from_array(&env, [true, false, false, true, false])
+    );
+}
+
+#[test]
+fn test_batch_verify_traps_on_forged_signature() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [32u8; 32];
+    let message = phantom_message(&payload_data);
+    let (key_a, sig_a) = sign_message(&env, &message);
+    let (key_b, _) = sign_message(&env, &message);
+
+    // Well formed, but key_a's signature under key_b
+    let items = Vec::from_array(
+        &env,
+        [batch_item(key_a, sig_a.clone()), batch_item(key_b, sig_a)],
+    );
+    assert!(client
+        .try_batch_verify(&Bytes::from_slice(&env, &payload_data), &items)
+        .is_err());
+}
+
+#[test]
+fn test_batch_verify_rejects_over_cap() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [34u8; 32];
+    let (key_data, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    let mut items = Vec::new(&env);
+    for _ in 0..17 {
+        items.push_back(batch_item(key_data.clone(), sig_data.clone()));
+    }
+    assert_eq!(
+        client.try_batch_verify(&Bytes::from_slice(&env, &payload_data), &items),
+        Err(Ok(VerifierError::BatchTooLarge.into()))
+    );
+
+    items.pop_back();
+    let results = client.batch_verify(&Bytes::from_slice(&env, &payload_data), &items);
+    assert_eq!(results.len(), 16);
+    assert!(results.iter().all(|verified| verified));
+}
+
+#[test]
+fn test_batch_verify_is_cheaper_than_singles() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [35u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let message = phantom_message(&payload_data);
+
+    let mut items = Vec::new(&env);
+    for _ in 0..4 {
+        let (key_data, sig_data) = sign_message(&env, &message);
+        items.push_back(batch_item(key_data, sig_data));
+    }
+
+    let budget = env.cost_estimate().budget();
+    budget.reset_default();
+    for item in items.iter() {
+        assert!(client.verify(&payload, &item.key_data, &item.sig_data));
+    }
+    let singles = budget.cpu_instruction_cost();
+
+    budget.reset_default();
+    let results = client.batch_verify(&payload, &items);
+    let batch = budget.cpu_instruction_cost();
+
+    assert_eq!(results, Vec::from_array(&env, [true; 4]));
+    std::println!("4 signatures: singles {singles} insns, batch {batch} insns");
+    assert!(
+        batch < singles,
+        "batch used {batch} CPU instructions, singles {singles}"
+    );
+}
+
+/// `sign_message`, returning the struct before it is encoded.
+fn sign_typed(env: &Env, message: &[u8]) -> (Bytes, Ed25519SigData) {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let sig = Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, message),
+        signature: BytesN::from_array(env, &keypair.sign(message).to_bytes()),
+        expires_at: None,
+    };
+    (
+        Bytes::from_slice(env, &keypair.verifying_key().to_bytes()),
+        sig,
+    )
+}
+
+#[test]
+fn test_verify_typed_agrees_with_verify() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [36u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let mut other_prefix = OTHER_PREFIX.to_vec();
+    other_prefix.extend_from_slice(&bytes_to_hex(&payload_data));
+
+    for (message, valid) in [
+        (phantom_message(&payload_data), true),
+        (phantom_base64_message(&payload_data), true),
+        (phantom_message(&[37u8; 32]), false),
+        (other_prefix, false),
+    ] {
+        let (public_key, sig) = sign_typed(&env, &message);
+        assert_eq!(client.verify_typed(&payload, &public_key, &sig), valid);
+        assert_eq!(
+            client.verify(&payload, &public_key, &sig.to_xdr(&env)),
+            valid
+        );
+    }
+}
+
+#[test]
+fn test_verify_typed_is_cheaper_than_verify() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [38u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig) = sign_typed(&env, &phantom_message(&payload_data));
+
+    let budget = env.cost_estimate().budget();
+    budget.reset_default();
+    assert!(client.verify_typed(&payload, &public_key, &sig));
+    let typed = budget.cpu_instruction_cost();
+
+    let sig_data = sig.to_xdr(&env);
+    budget.reset_default();
+    assert!(client.verify(&payload, &public_key, &sig_data));
+    let bytes = budget.cpu_instruction_cost();
+
+    std::println!("verify {bytes} insns, verify_typed {typed} insns");
+    assert!(
+        typed < bytes,
+        "verify_typed used {typed} CPU instructions, verify {bytes}"
+    );
+}
+
+/// Public key for seed [7; 32].
+const FIXTURE_KEY: &str = "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c";
+
+/// XDR of the bare `Ed25519SigData` that key signs for payload [39; 32],
+/// without an expiry: the encoding signers used before `SigEnvelope`.
+const V1_FIXTURE: &str = "\
+    0000001100000001000000030000000f0000000a657870697265735f61740000\
+    000000010000000f0000001070726566697865645f6d6573736167650000000d\
+    0000005c5374656c6c617220536d617274204163636f756e7420417574683a0a\
+    3237323732373237323732373237323732373237323732373237323732373237\
+    3237323732373237323732373237323732373237323732373237323732373237\
+    0000000f000000097369676e61747572650000000000000d00000040e0866d75\
+    9ab834c7fd4ecb1ff0a1abedadaf15c35e76e8a4b29b1b0e950289cb503c2e92\
+    da4a22304bf18a7bd4c0cc40de38c33ad725775b3bdc49bac072e00d";
+
+/// XDR of `Ed25519Signature::Prefixed` around the `V1_FIXTURE` struct: the
+/// tag is an explicit discriminant, a symbol, never read from lengths.
+const PREFIXED_FIXTURE: &str = "\
+    0000001000000001000000020000000f00000008507265666978656400000011\
+    00000001000000030000000f0000000a657870697265735f6174000000000001\
+    0000000f0000001070726566697865645f6d6573736167650000000d0000005c\
+    5374656c6c617220536d617274204163636f756e7420417574683a0a32373237\
+    3237323732373237323732373237323732373237323732373237323732373237\
+    3237323732373237323732373237323732373237323732373237323732000000\
+    0f000000097369676e61747572650000000000000d00000040e0866d759ab834\
+    c7fd4ecb1ff0a1abedadaf15c35e76e8a4b29b1b0e950289cb503c2e92da4a22\
+    304bf18a7bd4c0cc40de38c33ad725775b3bdc49bac072e00d";
+
+/// XDR of `Ed25519Signature::Raw`: `FIXTURE_KEY`'s signature over payload
+/// [39; 32] itself.
+const RAW_FIXTURE: &str = "\
+    0000001000000001000000020000000f00000003526177000000000d00000040\
+    c7e6ea42845f19417f03ea871f1294b0473a710697450841bd8f6f433e8e6ef4\
+    636cda7197dae42b62954f38cbc48eb09762b220343b2110c19382e4afdb2404";
+
+fn hex_to_bytes(env: &Env, hex: &str) -> Bytes {
+    let mut bytes = std::vec![0u8; decoded_len(hex.len())];
+    hex_decode(&mut bytes, hex.as_bytes()).unwrap();
+    Bytes::from_slice(env, &bytes)
+}
+
+/// The signature in `V1_FIXTURE`, made with today's code.
+fn v1_fixture_sig(env: &Env) -> Ed25519SigData {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+    let message = phantom_message(&[39u8; 32]);
+    Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, &message),
+        signature: BytesN::from_array(env, &keypair.sign(&message).to_bytes()),
+        expires_at: None,
+    }
+}
+
+#[test]
+fn test_v1_fixture_still_verifies() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload = Bytes::from_array(&env, &[39u8; 32]);
+    let public_key = hex_to_bytes(&env, FIXTURE_KEY);
+    let fixture = hex_to_bytes(&env, V1_FIXTURE);
+
+    // The encoding has not drifted, and verifies bare or wrapped
+    assert_eq!(v1_fixture_sig(&env).to_xdr(&env), fixture);
+    assert!(client.verify(&payload, &public_key, &fixture));
+    assert!(client.verify(
+        &payload,
+        &public_key,
+        &SigEnvelope::V1(v1_fixture_sig(&env)).to_xdr(&env)
+    ));
+}
+
+#[test]
+fn test_unknown_envelope_version() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload = Bytes::from_array(&env, &[39u8; 32]);
+    let public_key = hex_to_bytes(&env, FIXTURE_KEY);
+    let v3 = (symbol_short!("V3"), v1_fixture_sig(&env)).to_xdr(&env);
+
+    assert!(!client.verify(&payload, &public_key, &v3));
+    assert_eq!(
+        client.try_verify_checked(&payload, &public_key, &v3),
+        Err(Ok(VerifierError::UnsupportedVersion))
+    );
+}
+
+#[test]
+fn test_v2_envelope_round_trips() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload = Bytes::from_array(&env, &[39u8; 32]);
+    let v2 = SigEnvelope::V2(Ed25519Signature::Prefixed(v1_fixture_sig(&env))).to_xdr(&env);
+
+    let decoded = SigEnvelope::from_xdr(&env, &v2).unwrap();
+    assert!(matches!(
+        decoded,
+        SigEnvelope::V2(Ed25519Signature::Prefixed(_))
+    ));
+    assert_eq!(decoded.to_xdr(&env), v2);
+    assert!(client.verify(&payload, &hex_to_bytes(&env, FIXTURE_KEY), &v2));
+}
+
+#[test]
+fn test_verify_rejects_small_order_keys() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [40u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    let raw = Ed25519Signature::Raw(BytesN::from_array(&env, &[0u8; 64])).to_xdr(&env);
+
+    for key in [
+        "0100000000000000000000000000000000000000000000000000000000000000",
+        "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000080",
+        "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a",
+        "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa",
+        "26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc05",
+        "26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc85",
+    ] {
+        let key = hex_to_bytes(&env, key);
+        // Rejected before the signature is looked at, whatever it is
+        for sig_data in [&sig_data, &raw] {
+            assert!(!client.verify(&payload, &key, sig_data));
+            assert_eq!(
+                client.try_verify_checked(&payload, &key, sig_data),
+                Err(Ok(VerifierError::SmallOrderKey))
+            );
+        }
+    }
+
+    assert!(client.verify(&payload, &public_key, &sig_data));
+}
+
+#[test]
+fn test_verify_fixed_agrees_with_verify() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [41u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    let fixed = BytesN::<32>::try_from(public_key.clone()).unwrap();
+
+    assert!(client.verify(&payload, &public_key, &sig_data));
+    assert!(client.verify_fixed(&payload, &fixed, &sig_data));
+    assert!(!client.verify_fixed(&Bytes::from_array(&env, &[42u8; 32]), &fixed, &sig_data));
+}
+
+const SIWS_DOMAIN: &[u8] = b"example.com";
+
+fn siws_verifier(env: &Env) -> Ed25519VerifierClient<'_> {
+    Ed25519VerifierClient::new(
+        env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                Some(Bytes::from_slice(env, SIWS_DOMAIN)),
+                None::<u32>,
+            ),
+        ),
+    )
+}
+
+/// A SIWS message for `domain`, with `fields` after the address and
+/// statement, as a wallet would display it.
+fn siws_message(domain: &[u8], fields: &[&[u8]]) -> std::vec::Vec<u8> {
+    let mut message = domain.to_vec();
+    message.extend_from_slice(b" wants you to sign in with your Solana account:\n");
+    message.extend_from_slice(b"5Hb7EcUhS1XxPnV4tYwLbBTpHF6FTmnGaLDtTonoXDBF\n");
+    message.extend_from_slice(b"\nSign in to authorize this transaction.\n");
+    for field in fields {
+        message.push(b'\n');
+        message.extend_from_slice(field);
+    }
+    message
+}
+
+/// "Nonce: " + hex of `payload_data`.
+fn siws_nonce(payload_data: &[u8; 32]) -> std::vec::Vec<u8> {
+    let mut nonce = b"Nonce: ".to_vec();
+    nonce.extend_from_slice(&bytes_to_hex(payload_data));
+    nonce
+}
+
+/// Signs `message` in the SIWS mode. Returns the public key and sig_data.
+fn sign_siws(env: &Env, message: &[u8]) -> (Bytes, Bytes) {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let sig_data = Ed25519Signature::Siws(Ed25519SiwsSigData {
+        message: Bytes::from_slice(env, message),
+        signature: BytesN::from_array(env, &keypair.sign(message).to_bytes()),
+    });
+    (
+        Bytes::from_slice(env, &keypair.verifying_key().to_bytes()),
+        sig_data.to_xdr(env),
+    )
+}
+
+#[test]
+fn test_verify_siws_message() {
+    let env = Env::default();
+    let client = siws_verifier(&env);
+    let payload_data = [43u8; 32];
+    let nonce = siws_nonce(&payload_data);
+    let message = siws_message(
+        SIWS_DOMAIN,
+        &[
+            b"URI: https://example.com/login",
+            b"Version: 1",
+            b"Chain ID: mainnet",
+            &nonce,
+            b"Issued At: 2026-10-14T09:00:00.000Z",
+        ],
+    );
+
+    let (public_key, sig_data) = sign_siws(&env, &message);
+    assert_eq!(
+        client.siws_domain(),
+        Some(Bytes::from_slice(&env, SIWS_DOMAIN))
+    );
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &sig_data
+    ));
+}
+
+#[test]
+fn test_verify_siws_fields_in_any_order() {
+    let env = Env::default();
+    let client = siws_verifier(&env);
+    let payload_data = [44u8; 32];
+    let nonce = siws_nonce(&payload_data);
+    let message = siws_message(
+        SIWS_DOMAIN,
+        &[
+            b"Issued At: 2026-10-14T09:00:00.000Z",
+            &nonce,
+            b"Version: 1",
+        ],
+    );
+
+    let (public_key, sig_data) = sign_siws(&env, &message);
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &sig_data
+    ));
+}
+
+#[test]
+fn test_verify_siws_wrong_nonce() {
+    let env = Env::default();
+    let client = siws_verifier(&env);
+    let message = siws_message(
+        SIWS_DOMAIN,
+        &[
+            &siws_nonce(&[45u8; 32]),
+            b"Issued At: 2026-10-14T09:00:00.000Z",
+        ],
+    );
+    let (public_key, sig_data) = sign_siws(&env, &message);
+
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &[46u8; 32]),
+            &public_key,
+            &sig_data
+        ),
+        Err(Ok(VerifierError::PayloadMismatch))
+    );
+}
+
+#[test]
+fn test_verify_siws_wrong_domain() {
+    let env = Env::default();
+    let payload_data = [47u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let message = siws_message(
+        b"evil.example",
+        &[
+            &siws_nonce(&payload_data),
+            b"Issued At: 2026-10-14T09:00:00.000Z",
+        ],
+    );
+    let (public_key, sig_data) = sign_siws(&env, &message);
+
+    assert_eq!(
+        siws_verifier(&env).try_verify_checked(&payload, &public_key, &sig_data),
+        Err(Ok(VerifierError::SiwsDomainMismatch))
+    );
+
+    // Nor is any domain accepted where none is configured
+    let message = siws_message(
+        SIWS_DOMAIN,
+        &[
+            &siws_nonce(&payload_data),
+            b"Issued At: 2026-10-14T09:00:00.000Z",
+        ],
+    );
+    let (public_key, sig_data) = sign_siws(&env, &message);
+    assert_eq!(
+        bound_verifier(&env, true).try_verify_checked(&payload, &public_key, &sig_data),
+        Err(Ok(VerifierError::SiwsDomainMismatch))
+    );
+}
+
+#[test]
+fn test_verify_siws_malformed_messages() {
+    let env = Env::default();
+    let client = siws_verifier(&env);
+    let payload_data = [48u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let nonce = siws_nonce(&payload_data);
+    let issued_at: &[u8] = b"Issued At: 2026-10-14T09:00:00.000Z";
+
+    // The header must come first
+    let mut header_second = b"Version: 1\n".to_vec();
+    header_second.extend_from_slice(&siws_message(SIWS_DOMAIN, &[&nonce, issued_at]));
+
+    for message in [
+        header_second,
+        siws_message(SIWS_DOMAIN, &[issued_at]),
+        siws_message(SIWS_DOMAIN, &[&nonce]),
+        siws_message(SIWS_DOMAIN, &[&nonce, issued_at, &nonce]),
+    ] {
+        let (public_key, sig_data) = sign_siws(&env, &message);
+        assert!(!client.verify(&payload, &public_key, &sig_data));
+        assert_eq!(
+            client.try_verify_checked(&payload, &public_key, &sig_data),
+            Err(Ok(VerifierError::SiwsMalformed))
+        );
+    }
+}
+
+/// The code the upgrade tests swap the verifier to, whose only function,
+/// `version`, returns 2. build.rs builds it from the upgrade-target crate.
+const UPGRADE_TARGET_WASM: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/upgrade-target/wasm32-unknown-unknown/release/upgrade_target.wasm"
+));
+
+#[test]
+fn test_upgrade_keeps_storage() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup_with_admin(&env);
+    client.add_prefix(&Bytes::from_slice(&env, OTHER_PREFIX));
+    let prefixes = client.prefixes();
+
+    let wasm_hash = env.deployer().upload_contract_wasm(UPGRADE_TARGET_WASM);
+    client.upgrade(&wasm_hash);
+
+    let version: u32 =
+        env.invoke_contract(&client.address, &symbol_short!("version"), Vec::new(&env));
+    assert_eq!(version, 2);
+    env.as_contract(&client.address, || {
+        let storage = env.storage().instance();
+        assert_eq!(
+            storage.get::<_, Vec<Bytes>>(&DataKey::Prefixes),
+            Some(prefixes)
+        );
+        assert_eq!(storage.get::<_, Address>(&DataKey::Admin), Some(admin));
+        assert_eq!(storage.get::<_, bool>(&DataKey::ShortFormat), Some(true));
+    });
+}
+
+#[test]
+fn test_upgrade_requires_admin() {
+    let env = Env::default();
+    let (client, _) = setup_with_admin(&env);
+    let wasm_hash = env.deployer().upload_contract_wasm(UPGRADE_TARGET_WASM);
+
+    // Signed by someone else
+    let intruder = Address::generate(&env);
+    assert!(client
+        .mock_auths(&[MockAuth {
+            address: &intruder,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "upgrade",
+                args: (wasm_hash.clone(),).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_upgrade(&wasm_hash)
+        .is_err());
+
+    // Nor can a verifier without an admin be upgraded
+    let fixed = bound_verifier(&env, true);
+    env.mock_all_auths();
+    assert_eq!(
+        fixed.try_upgrade(&wasm_hash),
+        Err(Ok(VerifierError::NoAdmin.into()))
+    );
+
+    // Still the verifier
+    assert_eq!(client.prefixes().len(), 1);
+}
+
+#[test]
+fn test_admin_transfer_takes_two_steps() {
+    let env = Env::default();
+    let (client, admin) = setup_with_admin(&env);
+    let new_admin = Address::generate(&env);
+    // Authorizes the next call, and only it, by `address`
+    let mock = |address: &Address, fn_name: &str, args: Vec<Val>| {
+        env.mock_auths(&[MockAuth {
+            address,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name,
+                args,
+                sub_invokes: &[],
+            },
+        }]);
+    };
+
+    assert_eq!(
+        client.try_accept_admin(),
+        Err(Ok(VerifierError::NoPendingAdmin.into()))
+    );
+
+    // Only the admin may propose
+    mock(&new_admin, "set_admin", (new_admin.clone(),).into_val(&env));
+    assert!(client.try_set_admin(&new_admin).is_err());
+
+    mock(&admin, "set_admin", (new_admin.clone(),).into_val(&env));
+    client.set_admin(&new_admin);
+    assert_eq!(client.admin(), Some(admin.clone()));
+    assert_eq!(client.pending_admin(), Some(new_admin.clone()));
+
+    // Only the proposed admin may accept
+    mock(&admin, "accept_admin", Vec::new(&env));
+    assert!(client.try_accept_admin().is_err());
+    assert_eq!(client.admin(), Some(admin.clone()));
+
+    mock(&new_admin, "accept_admin", Vec::new(&env));
+    client.accept_admin();
+    assert_eq!(client.admin(), Some(new_admin.clone()));
+    assert_eq!(client.pending_admin(), None);
+
+    // The old admin is out
+    let prefix = Bytes::from_slice(&env, OTHER_PREFIX);
+    mock(&admin, "add_prefix", (prefix.clone(),).into_val(&env));
+    assert!(client.try_add_prefix(&prefix).is_err());
+}
+
+/// sig_data for `message` with a signature that never verifies.
+pub(crate) fn unsigned_sig_data(env: &Env, message: &[u8]) -> Bytes {
+    Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, message),
+        signature: BytesN::from_array(env, &[0u8; 64]),
+        expires_at: None,
+    }
+    .to_xdr(env)
+}
+
+#[test]
+fn test_verify_rejects_messages_of_the_wrong_length() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [49u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let message = phantom_message(&payload_data);
+    let public_key = Bytes::from_array(&env, &[9u8; 32]);
+
+    let mut long = message.clone();
+    long.push(b'0');
+    for message in [
+        std::vec::Vec::new(),
+        message[..91].to_vec(),
+        long,
+        std::vec![b'a'; 10 * 1024],
+    ] {
+        // Refused before the signature is looked at
+        let sig_data = unsigned_sig_data(&env, &message);
+        assert!(!client.verify(&payload, &public_key, &sig_data));
+        assert_eq!(
+            client.try_verify_checked(&payload, &public_key, &sig_data),
+            Err(Ok(VerifierError::WrongMessageLength))
+        );
+    }
+}
+
+#[test]
+fn test_verify_any_len_hashes_long_payloads() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload = Bytes::from_slice(&env, &[50u8; 100]);
+    let digest = env.crypto().sha256(&payload).to_array();
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&digest));
+
+    assert!(client.verify_any_len(&payload, &public_key, &sig_data));
+    // The strict entry points still want the 32 bytes themselves
+    assert!(!client.verify(&payload, &public_key, &sig_data));
+    assert_eq!(
+        client.try_verify_checked(&payload, &public_key, &sig_data),
+        Err(Ok(VerifierError::WrongMessageLength))
+    );
+}
+
+#[test]
+fn test_verify_any_len_keeps_the_32_byte_path() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [51u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    assert!(client.verify(&payload, &public_key, &sig_data));
+    assert!(client.verify_any_len(&payload, &public_key, &sig_data));
+
+    // A 32-byte payload is never hashed
+    let digest = env.crypto().sha256(&payload).to_array();
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&digest));
+    assert!(!client.verify_any_len(&payload, &public_key, &sig_data));
+}
+
+#[test]
+fn test_verify_with_and_without_trailing_newline() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [52u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let message = phantom_message(&payload_data);
+    let mut with_newline = message.clone();
+    with_newline.push(b'\n');
+
+    for message in [message, with_newline] {
+        // Verified over the message as presented, newline included
+        let (public_key, sig_data) = sign_message(&env, &message);
+        assert!(client.verify(&payload, &public_key, &sig_data));
+    }
+}
+
+#[test]
+fn test_verify_rejects_other_trailing_whitespace() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [53u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+
+    for suffix in [&b" "[..], b"\n\n", b"\r\n", b"\t"] {
+        let mut message = phantom_message(&payload_data);
+        message.extend_from_slice(suffix);
+        let (public_key, sig_data) = sign_message(&env, &message);
+        assert!(!client.verify(&payload, &public_key, &sig_data));
+        assert_eq!(
+            client.try_verify_checked(&payload, &public_key, &sig_data),
+            Err(Ok(VerifierError::WrongMessageLength))
+        );
+    }
+}
+
+#[test]
+fn test_mode_fixtures() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload = Bytes::from_array(&env, &[39u8; 32]);
+    let public_key = hex_to_bytes(&env, FIXTURE_KEY);
+    let prefixed = hex_to_bytes(&env, PREFIXED_FIXTURE);
+    let raw = hex_to_bytes(&env, RAW_FIXTURE);
+
+    // The encodings have not drifted
+    assert_eq!(
+        Ed25519Signature::Prefixed(v1_fixture_sig(&env)).to_xdr(&env),
+        prefixed
+    );
+    let Ed25519Signature::Raw(signature) = Ed25519Signature::from_xdr(&env, &raw).unwrap() else {
+        panic!("raw fixture decoded to another mode");
+    };
+    assert_eq!(Ed25519Signature::Raw(signature).to_xdr(&env), raw);
+
+    assert!(client.verify(&payload, &public_key, &prefixed));
+    assert!(client.verify(&payload, &public_key, &raw));
+}
+
+/// Both ways of signing the Phantom message for `payload_data`: over the
+/// message, and over its sha256 as Ledger does. Returns the public key and
+/// the two `Ed25519SigData`.
+fn sign_plain_and_hashed(
+    env: &Env,
+    payload_data: &[u8; 32],
+) -> (Bytes, Ed25519SigData, Ed25519SigData) {
+    use ed25519_dalek::Signer;
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let message = phantom_message(payload_data);
+    let digest = env
+        .crypto()
+        .sha256(&Bytes::from_slice(env, &message))
+        .to_array();
+    let sig_data = |signed: &[u8]| Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, &message),
+        signature: BytesN::from_array(env, &keypair.sign(signed).to_bytes()),
+        expires_at: None,
+    };
+    (
+        Bytes::from_slice(env, &keypair.verifying_key().to_bytes()),
+        sig_data(&message),
+        sig_data(&digest),
+    )
+}
+
+#[test]
+fn test_verify_hashed_signature() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [54u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, plain, hashed) = sign_plain_and_hashed(&env, &payload_data);
+
+    assert!(client.verify(
+        &payload,
+        &public_key,
+        &Ed25519Signature::Hashed(hashed).to_xdr(&env)
+    ));
+    assert!(client.verify(
+        &payload,
+        &public_key,
+        &Ed25519Signature::Prefixed(plain).to_xdr(&env)
+    ));
+}
+
+#[test]
+fn test_verify_hashed_flag_must_match_the_signature() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [55u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, plain, hashed) = sign_plain_and_hashed(&env, &payload_data);
+
+    // The message checks pass either way; the signature is over the other
+    // bytes
+    for sig_data in [
+        Ed25519Signature::Hashed(plain).to_xdr(&env),
+        Ed25519Signature::Prefixed(hashed).to_xdr(&env),
+    ] {
+        let result = client.try_verify(&payload, &public_key, &sig_data);
+        assert!(matches!(result, Err(Err(_))));
+    }
+}
+
+#[test]
+fn test_verify_hashed_checks_the_plaintext() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let (public_key, _, hashed) = sign_plain_and_hashed(&env, &[56u8; 32]);
+
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &[57u8; 32]),
+            &public_key,
+            &Ed25519Signature::Hashed(hashed).to_xdr(&env)
+        ),
+        Err(Ok(VerifierError::PayloadMismatch))
+    );
+}
+
+/// key_data listing `keys`, any one of which may sign.
+fn any_of_key_data(env: &Env, keys: &[Bytes]) -> Bytes {
+    let mut list = Vec::<BytesN<32>>::new(env);
+    for key in keys {
+        list.push_back(key.clone().try_into().unwrap());
+    }
+    list.to_xdr(env)
+}
+
+fn any_of_sig_data(env: &Env, key_index: u32, sig_data: Bytes) -> Bytes {
+    Ed25519AnyOfSigData {
+        key_index,
+        sig_data,
+    }
+    .to_xdr(env)
+}
+
+#[test]
+fn test_verify_any_of_second_key() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [58u8; 32];
+    let (laptop, _) = sign_message(&env, &phantom_message(&payload_data));
+    let (phone, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &any_of_key_data(&env, &[laptop, phone]),
+        &any_of_sig_data(&env, 1, sig_data),
+    ));
+}
+
+#[test]
+fn test_verify_any_of_key_not_listed() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [59u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (laptop, _) = sign_message(&env, &phantom_message(&payload_data));
+    let (phone, _) = sign_message(&env, &phantom_message(&payload_data));
+    let (_, stranger_sig) = sign_message(&env, &phantom_message(&payload_data));
+    let key_data = any_of_key_data(&env, &[laptop, phone]);
+
+    // Host traps on a signature that does not verify
+    for key_index in [0, 1] {
+        let result = client.try_verify(
+            &payload,
+            &key_data,
+            &any_of_sig_data(&env, key_index, stranger_sig.clone()),
+        );
+        assert!(matches!(result, Err(Err(_))));
+    }
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data, &any_of_sig_data(&env, 2, stranger_sig)),
+        Err(Ok(VerifierError::KeyIndexOutOfRange))
+    );
+}
+
+#[test]
+fn test_verify_any_of_keeps_the_bare_key() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [60u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    assert!(client.verify(&payload, &public_key, &sig_data));
+    assert!(client.verify(
+        &payload,
+        &any_of_key_data(&env, &[public_key]),
+        &any_of_sig_data(&env, 0, sig_data),
+    ));
+}
+
+#[test]
+fn test_verify_any_of_key_count() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [61u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let mut keys = std::vec::Vec::new();
+    let mut sig_data = Bytes::new(&env);
+    for _ in 0..5 {
+        let (key, sig) = sign_message(&env, &phantom_message(&payload_data));
+        keys.push(key);
+        sig_data = sig;
+    }
+
+    for listed in [&keys[..0], &keys[..]] {
+        assert_eq!(
+            client.try_verify_checked(
+                &payload,
+                &any_of_key_data(&env, listed),
+                &any_of_sig_data(&env, 4, sig_data.clone()),
+            ),
+            Err(Ok(VerifierError::TooManyKeys))
+        );
+    }
+    // Four is the most a list takes
+    assert!(client.verify(
+        &payload,
+        &any_of_key_data(&env, &keys[1..]),
+        &any_of_sig_data(&env, 3, sig_data),
+    ));
+}
+
+#[test]
+fn test_verify_any_of_refuses_key_data_that_is_not_a_list() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [63u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+
+    // A list's length but not its bytes, and a list cut short, fall through
+    // to the single-key path, which refuses them too
+    let list = any_of_key_data(&env, &[public_key]);
+    for key in [
+        Bytes::from_array(&env, &[7u8; 52]),
+        list.slice(..list.len() - 1),
+    ] {
+        assert_eq!(
+            client.try_verify_checked(&payload, &key, &sig_data),
+            Err(Ok(VerifierError::BadKeyLength))
+        );
+        assert!(!client.verify(&payload, &key, &sig_data));
+    }
+}
+
+#[test]
+fn test_verify_and_log_records_success() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [62u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    let key_hash: BytesN<32> = env.crypto().sha256(&public_key).into();
+    env.ledger().set_sequence_number(1_000);
+
+    assert!(client.verify_and_log(&payload, &public_key, &sig_data));
+    let payload_hash = BytesN::from_array(&env, &payload_data);
+    assert_eq!(
+        client.last_verification(&key_hash),
+        Some((payload_hash.clone(), 1_000))
+    );
+    assert_eq!(
+        env.events().all(),
+        soroban_sdk::vec![
+            &env,
+            (
+                client.address.clone(),
+                (Symbol::new(&env, "verified"), key_hash).into_val(&env),
+                Map::<Symbol, Val>::from_array(
+                    &env,
+                    [
+                        (Symbol::new(&env, "ledger"), 1_000u32.into_val(&env)),
+                        (
+                            Symbol::new(&env, "payload_hash"),
+                            payload_hash.into_val(&env)
+                        ),
+                    ]
+                )
+                .into_val(&env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_verify_and_log_skips_failures() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [63u8; 32];
+    let (public_key, sig_data) = sign_message(&env, &phantom_message(&payload_data));
+    let key_hash: BytesN<32> = env.crypto().sha256(&public_key).into();
+
+    // Signed for another payload
+    assert!(!client.verify_and_log(
+        &Bytes::from_slice(&env, &[64u8; 32]),
+        &public_key,
+        &sig_data
+    ));
+    assert!(env.events().all().is_empty());
+    assert_eq!(client.last_verification(&key_hash), None);
+
+    // Plain verify writes nothing
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &payload_data),
+        &public_key,
+        &sig_data
+    ));
+    assert!(env.events().all().is_empty());
+    assert_eq!(client.last_verification(&key_hash), None);
+}
+
+#[test]
+fn test_verify_and_log_keeps_the_latest() {
+    use ed25519_dalek::Signer;
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let keypair = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let public_key = Bytes::from_slice(&env, &keypair.verifying_key().to_bytes());
+    let key_hash: BytesN<32> = env.crypto().sha256(&public_key).into();
+    let sign = |payload_data: &[u8; 32]| {
+        let message = phantom_message(payload_data);
+        Ed25519SigData {
+            prefixed_message: Bytes::from_slice(&env, &message),
+            signature: BytesN::from_array(&env, &keypair.sign(&message).to_bytes()),
+            expires_at: None,
+        }
+        .to_xdr(&env)
+    };
+
+    for (payload_data, ledger) in [([65u8; 32], 100), ([66u8; 32], 200)] {
+        env.ledger().set_sequence_number(ledger);
+        assert!(client.verify_and_log(
+            &Bytes::from_slice(&env, &payload_data),
+            &public_key,
+            &sign(&payload_data)
+        ));
+    }
+    assert_eq!(
+        client.last_verification(&key_hash),
+        Some((BytesN::from_array(&env, &[66u8; 32]), 200))
+    );
+}
+
+/// The malleated twin of `signature`: `s + L`, which the curve equation
+/// cannot tell from `s`.
+pub(crate) fn malleate(signature: &[u8; 64]) -> [u8; 64] {
+    const GROUP_ORDER: [u8; 32] = [
+        0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde,
+        0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x10,
+    ];
+    let mut twin = *signature;
+    let mut carry = 0u16;
+    for (byte, order) in twin[32..].iter_mut().zip(GROUP_ORDER) {
+        let sum = *byte as u16 + order as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    // s < L < 2^253, so s + L still fits
+    assert_eq!(carry, 0);
+    twin
+}
+
+#[test]
+fn test_verify_rejects_malleated_signature() {
+    let env = Env::default();
+    let client = bound_verifier(&env, true);
+    let payload_data = [67u8; 32];
+    let payload = Bytes::from_slice(&env, &payload_data);
+    let (public_key, prefixed, raw) = sign_both_modes(&env, &payload_data);
+
+    let twin_prefixed = Ed25519SigData {
+        prefixed_message: prefixed.prefixed_message.clone(),
+        signature: BytesN::from_array(&env, &malleate(&prefixed.signature.to_array())),
+        expires_at: None,
+    };
+    let twin_raw = BytesN::from_array(&env, &malleate(&raw.to_array()));
+    for (original, twin) in [
+        (
+            Ed25519Signature::Prefixed(prefixed),
+            Ed25519Signature::Prefixed(twin_prefixed),
+        ),
+        (Ed25519Signature::Raw(raw), Ed25519Signature::Raw(twin_raw)),
+    ] {
+        assert!(client.verify(&payload, &public_key, &original.to_xdr(&env)));
+        let twin = twin.to_xdr(&env);
+        assert!(!client.verify(&payload, &public_key, &twin));
+        assert_eq!(
+            client.try_verify_checked(&payload, &public_key, &twin),
+            Err(Ok(VerifierError::NonCanonicalSignature))
+        );
+    }
+}
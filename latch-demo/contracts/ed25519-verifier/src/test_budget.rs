@@ -0,0 +1,202 @@
+#![cfg(test)]
+// Budget regression tests for `verify`, one per signing mode and one for
+// oversized input, plus side-by-sides of the buffered message comparison
+// against reading the message byte by byte, and of the in-place message
+// check against the sliced one it replaced.
+use crate::test::{
+    phantom_base64_message, phantom_message, sign_both_modes, sign_envelope, sign_message,
+    sign_sep53, solana_envelope, unsigned_sig_data,
+};
+use crate::{
+    check_message, check_message_sliced, Ed25519Signature, Ed25519Verifier, Ed25519VerifierClient,
+    Expected,
+};
+use soroban_sdk::{xdr::ToXdr, Address, Bytes, Env};
+
+extern crate std;
+
+/// CPU instructions one `verify` may use, against the network's 100M per
+/// transaction. Well above any mode today; a new format that needs more
+/// should say why.
+const VERIFY_CPU_CEILING: u64 = 5_000_000;
+
+/// Memory bytes one `verify` may use, against the network's 40 MiB per
+/// transaction.
+const VERIFY_MEM_CEILING: u64 = 1024 * 1024;
+
+const PAYLOAD: [u8; 32] = [7u8; 32];
+
+fn setup(env: &Env) -> Ed25519VerifierClient<'_> {
+    Ed25519VerifierClient::new(
+        env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    )
+}
+
+/// Runs `verify` on a valid vector under a fresh budget and checks it
+/// against the ceilings.
+fn assert_within_budget(env: &Env, mode: &str, key_data: &Bytes, sig_data: &Bytes) {
+    let client = setup(env);
+    let payload = Bytes::from_slice(env, &PAYLOAD);
+
+    let budget = env.cost_estimate().budget();
+    budget.reset_default();
+    assert!(client.verify(&payload, key_data, sig_data));
+    let cpu = budget.cpu_instruction_cost();
+    let mem = budget.memory_bytes_cost();
+
+    std::println!("verify ({mode}): cpu {cpu} insns, mem {mem} bytes");
+    assert!(
+        cpu <= VERIFY_CPU_CEILING,
+        "verify ({mode}) used {cpu} CPU instructions, ceiling is {VERIFY_CPU_CEILING}"
+    );
+    assert!(
+        mem <= VERIFY_MEM_CEILING,
+        "verify ({mode}) used {mem} memory bytes, ceiling is {VERIFY_MEM_CEILING}"
+    );
+}
+
+#[test]
+fn test_verify_hex_stays_within_budget() {
+    let env = Env::default();
+    let (key_data, sig_data) = sign_message(&env, &phantom_message(&PAYLOAD));
+    assert_within_budget(&env, "hex", &key_data, &sig_data);
+}
+
+#[test]
+fn test_verify_base64_stays_within_budget() {
+    let env = Env::default();
+    let (key_data, sig_data) = sign_message(&env, &phantom_base64_message(&PAYLOAD));
+    assert_within_budget(&env, "base64", &key_data, &sig_data);
+}
+
+#[test]
+fn test_verify_raw_stays_within_budget() {
+    let env = Env::default();
+    let (key_data, _, raw) = sign_both_modes(&env, &PAYLOAD);
+    let sig_data = Ed25519Signature::Raw(raw).to_xdr(&env);
+    assert_within_budget(&env, "raw", &key_data, &sig_data);
+}
+
+#[test]
+fn test_verify_sep53_stays_within_budget() {
+    let env = Env::default();
+    let (key_data, sig_data) = sign_sep53(&env, &PAYLOAD);
+    assert_within_budget(&env, "sep53", &key_data, &sig_data);
+}
+
+#[test]
+fn test_verify_solana_envelope_stays_within_budget() {
+    let env = Env::default();
+    let body = phantom_message(&PAYLOAD);
+    let (key_data, sig_data) = sign_envelope(&env, &solana_envelope(0, body.len() as u16, &body));
+    assert_within_budget(&env, "solana", &key_data, &sig_data);
+}
+
+/// How many times the happy path an oversized message may cost to refuse.
+/// Decoding sig_data is linear in its size, and sig_data that is not an
+/// envelope is decoded once per older format it may be; nothing after the
+/// decoding should be.
+const OVERSIZED_COST_FACTOR: u64 = 4;
+
+#[test]
+fn test_oversized_message_is_refused_cheaply() {
+    let env = Env::default();
+    let client = setup(&env);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let (key_data, sig_data) = sign_message(&env, &phantom_message(&PAYLOAD));
+    let oversized = unsigned_sig_data(&env, &[b'a'; 10 * 1024]);
+    let budget = env.cost_estimate().budget();
+
+    budget.reset_default();
+    assert!(client.verify(&payload, &key_data, &sig_data));
+    let happy = budget.cpu_instruction_cost();
+
+    budget.reset_default();
+    assert!(!client.verify(&payload, &key_data, &oversized));
+    let refused = budget.cpu_instruction_cost();
+
+    std::println!("verify: happy path {happy} insns, 10 KB message {refused} insns");
+    assert!(
+        refused <= OVERSIZED_COST_FACTOR * happy,
+        "refusing a 10 KB message used {refused} CPU instructions, {OVERSIZED_COST_FACTOR}x the happy path is {}",
+        OVERSIZED_COST_FACTOR * happy
+    );
+}
+
+/// The verifier's approach: one `to_buffer` copy, then a slice comparison.
+fn buffered_matches(message: &Bytes, expected: &[u8]) -> bool {
+    const LEN: usize = 92;
+    message.len() as usize == LEN && message.to_buffer::<LEN>().as_slice() == expected
+}
+
+/// The naive approach: a host call per byte.
+fn naive_matches(message: &Bytes, expected: &[u8]) -> bool {
+    message.len() as usize == expected.len()
+        && message
+            .iter()
+            .zip(expected.iter())
+            .all(|(byte, &want)| byte == want)
+}
+
+#[test]
+fn test_buffered_comparison_beats_byte_iteration() {
+    let env = Env::default();
+    let expected = phantom_message(&PAYLOAD);
+    let message = Bytes::from_slice(&env, &expected);
+    let budget = env.cost_estimate().budget();
+
+    budget.reset_default();
+    assert!(buffered_matches(&message, &expected));
+    let buffered = budget.cpu_instruction_cost();
+
+    budget.reset_default();
+    assert!(naive_matches(&message, &expected));
+    let naive = budget.cpu_instruction_cost();
+
+    std::println!(
+        "{}-byte message comparison: buffered {buffered} insns, byte by byte {naive} insns",
+        expected.len()
+    );
+    assert!(buffered < naive);
+}
+
+#[test]
+fn test_in_place_message_check_beats_sliced() {
+    let env = Env::default();
+    let contract_id = setup(&env).address;
+
+    for (layout, message) in [
+        ("hex", phantom_message(&PAYLOAD)),
+        ("base64", phantom_base64_message(&PAYLOAD)),
+    ] {
+        let message = Bytes::from_slice(&env, &message);
+        let (in_place, sliced) = env.as_contract(&contract_id, || {
+            let expected = Expected::new(&env, Bytes::from_slice(&env, &PAYLOAD));
+            let budget = env.cost_estimate().budget();
+
+            budget.reset_default();
+            assert_eq!(check_message(&expected, &message), Ok(()));
+            let in_place = budget.cpu_instruction_cost();
+
+            budget.reset_default();
+            assert_eq!(check_message_sliced(&expected, &message), Ok(()));
+            (in_place, budget.cpu_instruction_cost())
+        });
+
+        std::println!("{layout} message check: in place {in_place} insns, sliced {sliced} insns");
+        assert!(
+            in_place < sliced,
+            "in-place {layout} check used {in_place} CPU instructions, sliced {sliced}"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+#![cfg(test)]
+// Property tests for the parsing of attacker-controlled input: whatever the
+// bytes, `verify_checked` either returns a `VerifierError` or traps where
+// `verify` documents it may (bytes that are not XDR at all, a signature
+// that does not verify), never in its own slicing.
+use crate::test::{phantom_base64_message, phantom_message};
+use crate::{
+    check_message, check_message_sliced, DataKey, Ed25519SigData, Ed25519Verifier,
+    Ed25519VerifierClient, Expected, VerifierError, AUTH_PREFIX,
+};
+use proptest::prelude::*;
+use soroban_sdk::{
+    xdr::{Limits, ReadXdr, ScVal, ToXdr},
+    Address, Bytes, BytesN, Env, Vec as SorobanVec,
+};
+
+extern crate std;
+use std::vec::Vec;
+
+const PREFIX_LEN: usize = 28;
+
+/// So long that a message of the hex layout under the Phantom prefix is
+/// also one of the base64 layout under this.
+const LONG_PREFIX: &[u8; 48] = b"Stellar Smart Account Auth (forty-eight bytes):\n";
+
+fn setup(env: &Env) -> Ed25519VerifierClient<'_> {
+    Ed25519VerifierClient::new(
+        env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    )
+}
+
+/// How `verify_checked` came back.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Verified,
+    Rejected(VerifierError),
+    Trapped,
+}
+
+fn verify_checked(
+    payload: &[u8],
+    key_data: &[u8],
+    sig_data: impl FnOnce(&Env) -> Bytes,
+) -> Outcome {
+    let env = Env::default();
+    let client = setup(&env);
+    let sig_data = sig_data(&env);
+    match client.try_verify_checked(
+        &Bytes::from_slice(&env, payload),
+        &Bytes::from_slice(&env, key_data),
+        &sig_data,
+    ) {
+        Ok(_) => Outcome::Verified,
+        Err(Ok(error)) => Outcome::Rejected(error),
+        Err(Err(_)) => Outcome::Trapped,
+    }
+}
+
+/// sig_data whose fields are arbitrary but whose encoding is valid.
+fn struct_sig_data(env: &Env, message: &[u8], signature: &[u8; 64]) -> Bytes {
+    Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, message),
+        signature: BytesN::from_array(env, signature),
+        expires_at: None,
+    }
+    .to_xdr(env)
+}
+
+/// What `verify_checked` says of `message` for `payload`, with a signature
+/// that would never verify.
+fn outcome(payload: &[u8], message: &[u8]) -> Outcome {
+    verify_checked(payload, &[9u8; 32], |env| {
+        struct_sig_data(env, message, &[0u8; 64])
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn prop_arbitrary_sig_data_bytes(
+        payload in prop::collection::vec(any::<u8>(), 0..64),
+        key in any::<[u8; 32]>(),
+        sig_data in prop::collection::vec(any::<u8>(), 0..300),
+    ) {
+        let decodes = ScVal::from_xdr(&sig_data, Limits::none()).is_ok();
+        let outcome = verify_checked(&payload, &key, |env| Bytes::from_slice(env, &sig_data));
+
+        // Only the host's deserializer may trap, and only on non-XDR
+        match outcome {
+            Outcome::Rejected(_) => {}
+            Outcome::Trapped => prop_assert!(!decodes),
+            Outcome::Verified => prop_assert!(false, "arbitrary bytes verified"),
+        }
+    }
+
+    #[test]
+    fn prop_arbitrary_fields(
+        payload in prop::collection::vec(any::<u8>(), 0..64),
+        key in any::<[u8; 32]>(),
+        message in prop::collection::vec(any::<u8>(), 0..200),
+        signature in any::<[u8; 64]>(),
+    ) {
+        let outcome = verify_checked(&payload, &key, |env| {
+            struct_sig_data(env, &message, &signature)
+        });
+        prop_assert!(matches!(outcome, Outcome::Rejected(_)), "{:?}", outcome);
+    }
+
+    #[test]
+    fn prop_valid_message_for_payload_of_any_other_length(
+        payload in prop::collection::vec(any::<u8>(), 0..64),
+    ) {
+        prop_assume!(payload.len() != 32);
+        // Right for [7; 32], but the payload is not 32 bytes
+        prop_assert_eq!(
+            outcome(&payload, &phantom_message(&[7u8; 32])),
+            Outcome::Rejected(VerifierError::WrongMessageLength)
+        );
+    }
+
+    #[test]
+    fn prop_wrong_prefix_byte(index in 0..PREFIX_LEN, byte in any::<u8>()) {
+        let payload = [7u8; 32];
+        let mut message = phantom_message(&payload);
+        prop_assume!(message[index] != byte);
+        message[index] = byte;
+
+        prop_assert_eq!(outcome(&payload, &message), Outcome::Rejected(VerifierError::MissingPrefix));
+    }
+
+    #[test]
+    fn prop_non_hex_in_hex_region(index in 0..64usize, byte in any::<u8>()) {
+        prop_assume!(!byte.is_ascii_hexdigit());
+        // A newline last is the trailing newline wallets may add, which
+        // leaves the hex a character short
+        prop_assume!(index < 63 || byte != b'\n');
+        let payload = [7u8; 32];
+        let mut message = phantom_message(&payload);
+        message[PREFIX_LEN + index] = byte;
+
+        prop_assert_eq!(outcome(&payload, &message), Outcome::Rejected(VerifierError::PayloadMismatch));
+    }
+
+    #[test]
+    fn prop_wrong_byte_in_base64_region(index in 0..44usize, byte in any::<u8>()) {
+        let payload = [7u8; 32];
+        let mut message = phantom_base64_message(&payload);
+        prop_assume!(message[PREFIX_LEN + index] != byte);
+        prop_assume!(index < 43 || byte != b'\n');
+        message[PREFIX_LEN + index] = byte;
+
+        prop_assert_eq!(outcome(&payload, &message), Outcome::Rejected(VerifierError::PayloadMismatch));
+    }
+
+    #[test]
+    fn prop_message_lengths_around_the_layouts(len in 0..200usize) {
+        // Prefix, then as much hex as fits: never an accepted layout unless
+        // it is exactly the hex one
+        let full = phantom_message(&[7u8; 32]);
+        let message: Vec<u8> = full.iter().copied().cycle().take(len).collect();
+        prop_assume!(len != full.len());
+
+        let outcome = outcome(&[7u8; 32], &message);
+        prop_assert!(matches!(outcome, Outcome::Rejected(_)), "{:?}", outcome);
+    }
+
+    #[test]
+    fn prop_message_checks_agree(
+        template in 0..4usize,
+        edits in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..3),
+        len_delta in -2i32..=2,
+        payload_len in 31..=32usize,
+    ) {
+        let payload = [7u8; 32];
+        let mut message = match template {
+            0 => phantom_message(&payload),
+            1 => phantom_base64_message(&payload),
+            2 => {
+                let mut message = phantom_message(&payload);
+                message[PREFIX_LEN..].make_ascii_uppercase();
+                message
+            }
+            _ => {
+                let mut message = LONG_PREFIX.to_vec();
+                message.extend_from_slice(&phantom_base64_message(&payload)[PREFIX_LEN..]);
+                message
+            }
+        };
+        for (index, byte) in edits {
+            let index = index.index(message.len());
+            message[index] = byte;
+        }
+        let len = (message.len() as i32 + len_delta) as usize;
+        message.resize(len, b'a');
+
+        let env = Env::default();
+        let contract_id = setup(&env).address;
+        env.as_contract(&contract_id, || {
+            let prefixes = SorobanVec::from_array(
+                &env,
+                [
+                    Bytes::from_slice(&env, AUTH_PREFIX),
+                    Bytes::from_slice(&env, LONG_PREFIX),
+                ],
+            );
+            env.storage().instance().set(&DataKey::Prefixes, &prefixes);
+            let expected = Expected::new(&env, Bytes::from_slice(&env, &payload[..payload_len]));
+            let message = Bytes::from_slice(&env, &message);
+
+            prop_assert_eq!(
+                check_message(&expected, &message),
+                check_message_sliced(&expected, &message)
+            );
+            Ok(())
+        })?;
+    }
+}
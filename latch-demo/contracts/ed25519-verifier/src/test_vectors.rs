@@ -0,0 +1,459 @@
+#![cfg(test)]
+// Golden vectors for the signing side: testdata/ed25519_vectors.json lists
+// keys, messages and signatures made from fixed seeds, each with the outcome
+// `verify` must give. Ed25519 signing is deterministic, so `generate` always
+// builds the same file; `regenerate_vectors` rewrites it after the cases
+// below change.
+use crate::test::malleate;
+use crate::{
+    check_message, check_message_sliced, Ed25519KeyData, Ed25519SigData, Ed25519Verifier,
+    Ed25519VerifierClient, Expected, VerifierError,
+};
+use base64::Engine as _;
+use ed25519_dalek::{Signer as _, SigningKey};
+use latch_utils::{decoded_len, hex_decode, hex_encode, hex_len};
+use serde::{Deserialize, Serialize};
+use soroban_sdk::{
+    symbol_short, testutils::Ledger as _, xdr::ToXdr, Address, Bytes, BytesN, Env, Symbol, Vec,
+};
+
+extern crate std;
+use std::{string::String, vec::Vec as StdVec};
+
+const FIXTURE: &str = include_str!("../testdata/ed25519_vectors.json");
+const FIXTURE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/ed25519_vectors.json");
+
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+/// Seed of the key the vectors are signed with.
+const SIGNER_SEED: [u8; 32] = [42u8; 32];
+/// Seed of a key that signs where it should not.
+const OTHER_SEED: [u8; 32] = [43u8; 32];
+const LEDGER_TIMESTAMP: u64 = 1_700_000_000;
+const EXPIRES_AT: u64 = LEDGER_TIMESTAMP + 600;
+const BINDING: [u8; 32] = [0x22u8; 32];
+/// 56 characters: an unbound key refuses the account suffix before reading
+/// the strkey.
+const ACCOUNT_STRKEY: &[u8] = b"GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF";
+/// The identity point, the first of the small-order keys.
+const IDENTITY_KEY: [u8; 32] = {
+    let mut key = [0u8; 32];
+    key[0] = 1;
+    key
+};
+
+/// The fixture file.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct Fixture {
+    /// The prefix the verifier is deployed with.
+    prefix: String,
+    /// The ledger time the vectors are checked at.
+    ledger_timestamp: u64,
+    vectors: StdVec<Vector>,
+}
+
+/// One case. Binary fields are lowercase hex.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct Vector {
+    name: String,
+    /// key_data as `verify` takes it.
+    key: String,
+    payload: String,
+    prefixed_message: String,
+    signature: String,
+    expires_at: Option<u64>,
+    /// sig_data to pass instead of the `Ed25519SigData` of the fields above,
+    /// for the cases about its encoding.
+    sig_data: Option<String>,
+    valid: bool,
+    /// The `VerifierError` `verify_checked` returns. `BadSignature` cases
+    /// trap in the host instead, as `verify` documents.
+    failure: Option<String>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = std::vec![0u8; hex_len(bytes.len())];
+    hex_encode(&mut hex, bytes);
+    String::from_utf8(hex).unwrap()
+}
+
+fn xdr_hex(xdr: &Bytes) -> String {
+    hex(&xdr.iter().collect::<StdVec<u8>>())
+}
+
+fn from_hex(env: &Env, hex: &str) -> Bytes {
+    let mut bytes = std::vec![0u8; decoded_len(hex.len())];
+    hex_decode(&mut bytes, hex.as_bytes()).unwrap();
+    Bytes::from_slice(env, &bytes)
+}
+
+/// Every vector, from the seeds above.
+fn generate() -> Fixture {
+    let env = Env::default();
+    let signer = SigningKey::from_bytes(&SIGNER_SEED);
+    let other = SigningKey::from_bytes(&OTHER_SEED);
+    let public_key = signer.verifying_key().to_bytes();
+    let payload: [u8; 32] = core::array::from_fn(|i| i as u8);
+    let other_payload: [u8; 32] = core::array::from_fn(|i| 32 + i as u8);
+    let bound_key = Ed25519KeyData {
+        pubkey: BytesN::from_array(&env, &public_key),
+        account: None,
+        binding: Some(BytesN::from_array(&env, &BINDING)),
+    }
+    .to_xdr(&env);
+
+    let message =
+        |payload: &[u8], suffix: &[u8]| [AUTH_PREFIX, hex(payload).as_bytes(), suffix].concat();
+    let expires = |at: u64| std::format!("\nExpires: {at}").into_bytes();
+    let for_suffix = [b"\nFor: ".as_slice(), hex(&BINDING).as_bytes()].concat();
+    let account_suffix = [
+        b"\nAccount: ".as_slice(),
+        ACCOUNT_STRKEY,
+        b"\nNetwork: ".as_slice(),
+        hex(&[0u8; 32]).as_bytes(),
+    ]
+    .concat();
+    let base64 = |payload: &[u8]| {
+        [
+            AUTH_PREFIX,
+            base64::engine::general_purpose::STANDARD
+                .encode(payload)
+                .as_bytes(),
+        ]
+        .concat()
+    };
+    let hex_message = message(&payload, b"");
+
+    // `message` signed by the signer, for `payload`
+    let valid = |name: &str, message: &[u8]| Vector {
+        name: name.into(),
+        key: hex(&public_key),
+        payload: hex(&payload),
+        prefixed_message: String::from_utf8(message.to_vec()).unwrap(),
+        signature: hex(&signer.sign(message).to_bytes()),
+        expires_at: None,
+        sig_data: None,
+        valid: true,
+        failure: None,
+    };
+    let fails = |failure: &str, vector: Vector| Vector {
+        valid: false,
+        failure: Some(failure.into()),
+        ..vector
+    };
+    let mut tampered = signer.sign(&hex_message).to_bytes();
+    tampered[0] ^= 1;
+
+    let vectors = std::vec![
+        valid("hex", &hex_message),
+        valid(
+            "hex_upper_case",
+            &[AUTH_PREFIX, hex(&payload).to_uppercase().as_bytes()].concat(),
+        ),
+        valid("base64", &base64(&payload)),
+        valid("trailing_newline", &message(&payload, b"\n")),
+        Vector {
+            expires_at: Some(EXPIRES_AT),
+            ..valid("expiry", &message(&payload, &expires(EXPIRES_AT)))
+        },
+        Vector {
+            key: xdr_hex(&bound_key),
+            ..valid("bound_key", &message(&payload, &for_suffix))
+        },
+        fails(
+            "MalformedSigData",
+            Vector {
+                sig_data: Some(xdr_hex(&symbol_short!("garbage").to_xdr(&env))),
+                ..valid("sig_data_not_a_signature", &hex_message)
+            },
+        ),
+        fails(
+            "UnsupportedVersion",
+            Vector {
+                sig_data: Some(xdr_hex(
+                    &Vec::from_array(&env, [Symbol::new(&env, "V9")]).to_xdr(&env),
+                )),
+                ..valid("sig_data_of_unknown_version", &hex_message)
+            },
+        ),
+        fails(
+            "BadKeyLength",
+            Vector {
+                key: xdr_hex(&7u32.to_xdr(&env)),
+                ..valid("key_data_not_a_key", &hex_message)
+            },
+        ),
+        fails(
+            "SmallOrderKey",
+            Vector {
+                key: hex(&IDENTITY_KEY),
+                ..valid("small_order_key", &hex_message)
+            },
+        ),
+        fails(
+            "WrongMessageLength",
+            valid(
+                "hex_one_character_short",
+                &hex_message[..hex_message.len() - 1]
+            ),
+        ),
+        fails(
+            "WrongMessageLength",
+            Vector {
+                payload: hex(&payload[..31]),
+                ..valid("payload_not_32_bytes", &hex_message)
+            },
+        ),
+        fails(
+            "WrongMessageLength",
+            valid("message_over_cap", &message(&payload, &[b'a'; 278])),
+        ),
+        fails(
+            "WrongMessageLength",
+            valid("suffix_on_unbound_key", &message(&payload, &for_suffix)),
+        ),
+        fails(
+            "MissingPrefix",
+            valid(
+                "wrong_prefix",
+                &[
+                    b"Stellar Smart Account Auth;\n".as_slice(),
+                    hex(&payload).as_bytes()
+                ]
+                .concat(),
+            ),
+        ),
+        fails(
+            "PayloadMismatch",
+            valid("hex_of_other_payload", &message(&other_payload, b"")),
+        ),
+        fails(
+            "PayloadMismatch",
+            valid("base64_of_other_payload", &base64(&other_payload)),
+        ),
+        fails(
+            "PayloadMismatch",
+            valid(
+                "non_hex_character",
+                &[&hex_message[..hex_message.len() - 1], b"g".as_slice()].concat(),
+            ),
+        ),
+        fails(
+            "DomainMismatch",
+            valid(
+                "account_suffix_on_unbound_key",
+                &message(&payload, &account_suffix),
+            ),
+        ),
+        fails(
+            "ExpiryMismatch",
+            Vector {
+                expires_at: Some(EXPIRES_AT),
+                ..valid("expiry_suffix_missing", &hex_message)
+            },
+        ),
+        fails(
+            "ExpiryMismatch",
+            Vector {
+                expires_at: Some(EXPIRES_AT),
+                ..valid(
+                    "expiry_suffix_for_other_time",
+                    &message(&payload, &expires(EXPIRES_AT + 1)),
+                )
+            },
+        ),
+        fails(
+            "SignatureExpired",
+            Vector {
+                expires_at: Some(LEDGER_TIMESTAMP - 1),
+                ..valid(
+                    "expired",
+                    &message(&payload, &expires(LEDGER_TIMESTAMP - 1)),
+                )
+            },
+        ),
+        fails(
+            "BindingMismatch",
+            Vector {
+                key: xdr_hex(&bound_key),
+                ..valid("binding_suffix_missing", &hex_message)
+            },
+        ),
+        fails(
+            "NonCanonicalSignature",
+            Vector {
+                signature: hex(&malleate(&signer.sign(&hex_message).to_bytes())),
+                ..valid("signature_not_canonical", &hex_message)
+            },
+        ),
+        fails(
+            "BadSignature",
+            Vector {
+                signature: hex(&other.sign(&hex_message).to_bytes()),
+                ..valid("signed_by_other_key", &hex_message)
+            },
+        ),
+        fails(
+            "BadSignature",
+            Vector {
+                signature: hex(&signer.sign(&message(&other_payload, b"")).to_bytes()),
+                ..valid("signature_over_other_message", &hex_message)
+            },
+        ),
+        fails(
+            "BadSignature",
+            Vector {
+                signature: hex(&tampered),
+                ..valid("signature_tampered", &hex_message)
+            },
+        ),
+    ];
+
+    Fixture {
+        prefix: String::from_utf8(AUTH_PREFIX.to_vec()).unwrap(),
+        ledger_timestamp: LEDGER_TIMESTAMP,
+        vectors,
+    }
+}
+
+fn load() -> Fixture {
+    serde_json::from_str(FIXTURE).unwrap()
+}
+
+fn verifier_error(name: &str) -> VerifierError {
+    match name {
+        "MalformedSigData" => VerifierError::MalformedSigData,
+        "BadKeyLength" => VerifierError::BadKeyLength,
+        "WrongMessageLength" => VerifierError::WrongMessageLength,
+        "MissingPrefix" => VerifierError::MissingPrefix,
+        "PayloadMismatch" => VerifierError::PayloadMismatch,
+        "DomainMismatch" => VerifierError::DomainMismatch,
+        "ExpiryMismatch" => VerifierError::ExpiryMismatch,
+        "SignatureExpired" => VerifierError::SignatureExpired,
+        "BindingMismatch" => VerifierError::BindingMismatch,
+        "UnsupportedVersion" => VerifierError::UnsupportedVersion,
+        "SmallOrderKey" => VerifierError::SmallOrderKey,
+        "NonCanonicalSignature" => VerifierError::NonCanonicalSignature,
+        _ => panic!("unknown failure {name}"),
+    }
+}
+
+/// The verifier the vectors are checked against, at the fixture's ledger
+/// time.
+fn deploy<'a>(env: &'a Env, fixture: &Fixture) -> Ed25519VerifierClient<'a> {
+    env.ledger().set_timestamp(fixture.ledger_timestamp);
+    Ed25519VerifierClient::new(
+        env,
+        &env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                Some(Bytes::from_slice(env, fixture.prefix.as_bytes())),
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    )
+}
+
+#[test]
+fn test_vectors() {
+    let fixture = load();
+    let env = Env::default();
+    let client = deploy(&env, &fixture);
+
+    for vector in &fixture.vectors {
+        let name = &vector.name;
+        assert_eq!(vector.valid, vector.failure.is_none(), "{name}");
+        let payload = from_hex(&env, &vector.payload);
+        let key_data = from_hex(&env, &vector.key);
+        let sig_data = match &vector.sig_data {
+            Some(sig_data) => from_hex(&env, sig_data),
+            None => Ed25519SigData {
+                prefixed_message: Bytes::from_slice(&env, vector.prefixed_message.as_bytes()),
+                signature: from_hex(&env, &vector.signature).try_into().unwrap(),
+                expires_at: vector.expires_at,
+            }
+            .to_xdr(&env),
+        };
+
+        match vector.failure.as_deref() {
+            None => assert!(client.verify(&payload, &key_data, &sig_data), "{name}"),
+            Some("BadSignature") => {
+                let result = client.try_verify(&payload, &key_data, &sig_data);
+                assert!(matches!(result, Err(Err(_))), "{name}");
+            }
+            Some(failure) => {
+                assert!(!client.verify(&payload, &key_data, &sig_data), "{name}");
+                assert_eq!(
+                    client.try_verify_checked(&payload, &key_data, &sig_data),
+                    Err(Ok(verifier_error(failure))),
+                    "{name}"
+                );
+            }
+        }
+    }
+}
+
+/// `check_message` and the `check_message_sliced` it replaced give the same
+/// result for every vector's message.
+#[test]
+fn test_vectors_message_checks_agree() {
+    let fixture = load();
+    let env = Env::default();
+    let client = deploy(&env, &fixture);
+    env.as_contract(&client.address, || {
+        for vector in &fixture.vectors {
+            let expected = Expected::new(&env, from_hex(&env, &vector.payload));
+            let message = Bytes::from_slice(&env, vector.prefixed_message.as_bytes());
+            assert_eq!(
+                check_message(&expected, &message),
+                check_message_sliced(&expected, &message),
+                "{}",
+                vector.name
+            );
+        }
+    });
+}
+
+#[test]
+fn test_vectors_cover_each_failure() {
+    let fixture = load();
+    for failure in [
+        "MalformedSigData",
+        "BadKeyLength",
+        "WrongMessageLength",
+        "MissingPrefix",
+        "PayloadMismatch",
+        "BadSignature",
+        "DomainMismatch",
+        "ExpiryMismatch",
+        "SignatureExpired",
+        "BindingMismatch",
+        "UnsupportedVersion",
+        "SmallOrderKey",
+        "NonCanonicalSignature",
+    ] {
+        assert!(
+            fixture
+                .vectors
+                .iter()
+                .any(|vector| vector.failure.as_deref() == Some(failure)),
+            "no vector fails with {failure}"
+        );
+    }
+}
+
+#[test]
+fn test_vectors_match_generator() {
+    assert_eq!(load(), generate());
+}
+
+/// Rewrites the fixture from `generate`:
+/// `cargo test -p ed25519-verifier regenerate_vectors -- --ignored`.
+#[test]
+#[ignore]
+fn regenerate_vectors() {
+    let json = serde_json::to_string_pretty(&generate()).unwrap();
+    std::fs::write(FIXTURE_PATH, json + "\n").unwrap();
+}
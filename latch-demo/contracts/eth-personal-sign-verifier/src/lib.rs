@@ -0,0 +1,157 @@
+#![no_std]
+use latch_utils::{hex_encode, hex_len};
+use soroban_sdk::{contract, contracterror, contractimpl, Bytes, BytesN, Env};
+use stellar_accounts::verifiers::Verifier;
+
+/// The prefix prepended to the auth payload hash, shared with the Ed25519
+/// verifier.
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+const PAYLOAD_LEN: usize = 32;
+const HEX_LEN: usize = hex_len(PAYLOAD_LEN);
+const MESSAGE_LEN: usize = AUTH_PREFIX.len() + HEX_LEN; // 92 bytes
+/// EIP-191 version 0x45, which `personal_sign` signs under.
+const EIP191_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
+/// `MESSAGE_LEN` in decimal, as EIP-191 puts it after the prefix.
+const MESSAGE_LEN_DIGITS: &[u8] = b"92";
+const _: () = assert!(MESSAGE_LEN == 92);
+const SIGNED_LEN: usize = EIP191_PREFIX.len() + MESSAGE_LEN_DIGITS.len() + MESSAGE_LEN;
+const ADDRESS_LEN: u32 = 20;
+/// `r || s || v`.
+const SIGNATURE_LEN: u32 = 65;
+/// Half the secp256k1 group order, big-endian. Signatures with a larger `s`
+/// are the malleable twin of a low-s one.
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+#[contract]
+pub struct EthPersonalSignVerifier;
+
+/// The check `verify_checked` failed at. Codes 1-6 match the Ed25519
+/// verifier's and 7 the secp256k1 verifier's; 31 on are this verifier's
+/// own.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum VerifierError {
+    /// sig_data is not a 65-byte `r || s || v` signature.
+    MalformedSigData = 1,
+    /// key_data is not a 20-byte Ethereum address.
+    BadKeyLength = 2,
+    /// The payload is not 32 bytes.
+    WrongMessageLength = 3,
+    /// The address recovered from the signature is not key_data.
+    BadSignature = 6,
+    /// `s` is above half the group order. Rejected rather than normalized,
+    /// which would also flip `v`; Ethereum wallets sign low-s, as EIP-2
+    /// requires of transactions.
+    HighS = 7,
+    /// `v` is none of 27, 28, 0 or 1.
+    BadRecoveryId = 31,
+}
+
+#[contractimpl]
+impl Verifier for EthPersonalSignVerifier {
+    type KeyData = Bytes;
+    type SigData = Bytes;
+
+    /// Verifies a `personal_sign` signature, as MetaMask and other Ethereum
+    /// wallets produce, over "Stellar Smart Account Auth:\n" + hex of the
+    /// payload, by recovering the signer's address and comparing it to
+    /// `key_data`.
+    ///
+    /// The message is built here rather than taken from sig_data, so there
+    /// is nothing in it to check. A signature that recovers some other
+    /// address (e.g. under the wrong `v`) returns `false`; a signature no
+    /// key can be recovered from traps in the host.
+    fn verify(
+        e: &Env,
+        signature_payload: Bytes,
+        key_data: Self::KeyData,
+        sig_data: Self::SigData,
+    ) -> bool {
+        check(e, signature_payload, key_data, sig_data).is_ok()
+    }
+}
+
+#[contractimpl]
+impl EthPersonalSignVerifier {
+    /// `verify` that tells which check failed, for integrators and
+    /// simulation.
+    pub fn verify_checked(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> Result<(), VerifierError> {
+        check(&e, signature_payload, key_data, sig_data)
+    }
+}
+
+fn check(
+    e: &Env,
+    signature_payload: Bytes,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    if sig_data.len() != SIGNATURE_LEN {
+        return Err(VerifierError::MalformedSigData);
+    }
+    if key_data.len() != ADDRESS_LEN {
+        return Err(VerifierError::BadKeyLength);
+    }
+    if signature_payload.len() != PAYLOAD_LEN as u32 {
+        return Err(VerifierError::WrongMessageLength);
+    }
+
+    let signature = sig_data.to_buffer::<65>();
+    let signature = signature.as_slice();
+    // Wallets give v as 27 or 28; hardware wallets and some libraries as the
+    // bare recovery id
+    let recovery_id = match signature[64] {
+        27 | 28 => signature[64] - 27,
+        0 | 1 => signature[64],
+        _ => return Err(VerifierError::BadRecoveryId),
+    };
+    // Big-endian, so byte order is numeric order.
+    if signature[32..64] > HALF_ORDER[..] {
+        return Err(VerifierError::HighS);
+    }
+    let mut compact = [0u8; 64];
+    compact.copy_from_slice(&signature[..64]);
+
+    let payload_array = signature_payload.to_buffer::<PAYLOAD_LEN>();
+    let mut signed = [0u8; SIGNED_LEN];
+    let (header, message) = signed.split_at_mut(SIGNED_LEN - MESSAGE_LEN);
+    header[..EIP191_PREFIX.len()].copy_from_slice(EIP191_PREFIX);
+    header[EIP191_PREFIX.len()..].copy_from_slice(MESSAGE_LEN_DIGITS);
+    message[..AUTH_PREFIX.len()].copy_from_slice(AUTH_PREFIX);
+    hex_encode(&mut message[AUTH_PREFIX.len()..], payload_array.as_slice());
+
+    let digest = e.crypto().keccak256(&Bytes::from_array(e, &signed));
+    let recovered = e.crypto().secp256k1_recover(
+        &digest,
+        &BytesN::from_array(e, &compact),
+        recovery_id as u32,
+    );
+
+    if address(e, &recovered) != key_data.to_buffer::<20>().as_slice() {
+        return Err(VerifierError::BadSignature);
+    }
+
+    Ok(())
+}
+
+/// The Ethereum address of an uncompressed key (`04 || x || y`): the last
+/// 20 bytes of keccak256(x || y).
+fn address(e: &Env, key: &BytesN<65>) -> [u8; 20] {
+    let key: Bytes = key.clone().into();
+    let hash = e.crypto().keccak256(&key.slice(1..)).to_array();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,213 @@
+#![cfg(test)]
+use crate::{EthPersonalSignVerifier, EthPersonalSignVerifierClient, VerifierError};
+use k256::{
+    ecdsa::{Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use latch_utils::{hex_decode, hex_encode, hex_len};
+use soroban_sdk::{Bytes, Env};
+
+extern crate std;
+
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+
+const PAYLOAD: [u8; 32] = [1u8; 32];
+
+/// The well-known address of private key 1.
+const KEY_ONE_ADDRESS: &str = "7e5f4552091a69125d5dfcb7b8c2659029395bdf";
+
+/// Convert bytes to lowercase hex string (off-chain helper for tests)
+fn bytes_to_hex(bytes: &[u8]) -> std::vec::Vec<u8> {
+    let mut hex = std::vec![0u8; hex_len(bytes.len())];
+    hex_encode(&mut hex, bytes);
+    hex
+}
+
+fn signing_key(seed: u8) -> SigningKey {
+    let mut secret = [0u8; 32];
+    secret[31] = seed;
+    SigningKey::from_slice(&secret).unwrap()
+}
+
+/// `key`'s Ethereum address: the last 20 bytes of keccak256(x || y).
+fn eth_address(env: &Env, key: &SigningKey) -> Bytes {
+    let point = key.verifying_key().as_affine().to_encoded_point(false);
+    let hash = env
+        .crypto()
+        .keccak256(&Bytes::from_slice(env, &point.as_bytes()[1..]))
+        .to_array();
+    Bytes::from_slice(env, &hash[12..])
+}
+
+/// What `personal_sign` of "Stellar Smart Account Auth:\n" + hex(payload)
+/// returns: `r || s || v`, with v as 27 or 28.
+fn personal_sign(env: &Env, key: &SigningKey, payload: &[u8; 32]) -> [u8; 65] {
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(&bytes_to_hex(payload));
+    let mut signed = b"\x19Ethereum Signed Message:\n".to_vec();
+    signed.extend_from_slice(std::format!("{}", message.len()).as_bytes());
+    signed.extend_from_slice(&message);
+    let digest = env
+        .crypto()
+        .keccak256(&Bytes::from_slice(env, &signed))
+        .to_array();
+
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&digest).unwrap();
+    let mut sig = [0u8; 65];
+    sig[..64].copy_from_slice(&signature.to_bytes());
+    sig[64] = 27 + recovery_id.to_byte();
+    sig
+}
+
+fn setup(env: &Env) -> EthPersonalSignVerifierClient<'_> {
+    let contract_id = env.register(EthPersonalSignVerifier, ());
+    EthPersonalSignVerifierClient::new(env, &contract_id)
+}
+
+#[test]
+fn test_address_derivation() {
+    let env = Env::default();
+    let mut expected = [0u8; 20];
+    hex_decode(&mut expected, KEY_ONE_ADDRESS.as_bytes()).unwrap();
+
+    assert_eq!(
+        eth_address(&env, &signing_key(1)),
+        Bytes::from_array(&env, &expected)
+    );
+}
+
+#[test]
+fn test_verify_valid_signature() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let sig = personal_sign(&env, &key, &PAYLOAD);
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &eth_address(&env, &key),
+        &Bytes::from_array(&env, &sig),
+    ));
+
+    // The bare recovery id is accepted for v as well
+    let mut bare = sig;
+    bare[64] -= 27;
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &eth_address(&env, &key),
+        &Bytes::from_array(&env, &bare),
+    ));
+}
+
+#[test]
+fn test_verify_wrong_v() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let address = eth_address(&env, &key);
+    let sig = personal_sign(&env, &key, &PAYLOAD);
+
+    // The other parity recovers a different key, not a trap
+    let mut flipped = sig;
+    flipped[64] ^= 1;
+    assert!(!client.verify(&payload, &address, &Bytes::from_array(&env, &flipped)));
+    assert_eq!(
+        client.try_verify_checked(&payload, &address, &Bytes::from_array(&env, &flipped)),
+        Err(Ok(VerifierError::BadSignature))
+    );
+
+    for v in [2u8, 26, 29, 37] {
+        let mut bad = sig;
+        bad[64] = v;
+        assert_eq!(
+            client.try_verify_checked(&payload, &address, &Bytes::from_array(&env, &bad)),
+            Err(Ok(VerifierError::BadRecoveryId))
+        );
+    }
+}
+
+#[test]
+fn test_verify_address_mismatch() {
+    let env = Env::default();
+    let client = setup(&env);
+    let sig = Bytes::from_array(&env, &personal_sign(&env, &signing_key(7), &PAYLOAD));
+
+    // Signed by one key, checked against another's address
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &PAYLOAD),
+            &eth_address(&env, &signing_key(8)),
+            &sig
+        ),
+        Err(Ok(VerifierError::BadSignature))
+    );
+}
+
+#[test]
+fn test_verify_wrong_payload() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let sig = Bytes::from_array(&env, &personal_sign(&env, &key, &PAYLOAD));
+
+    assert!(!client.verify(
+        &Bytes::from_slice(&env, &[2u8; 32]),
+        &eth_address(&env, &key),
+        &sig,
+    ));
+}
+
+#[test]
+fn test_verify_checked_reports_lengths() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let address = eth_address(&env, &key);
+    let sig = Bytes::from_array(&env, &personal_sign(&env, &key, &PAYLOAD));
+
+    assert_eq!(
+        client.try_verify_checked(&payload, &address, &sig),
+        Ok(Ok(()))
+    );
+    assert_eq!(
+        client.try_verify_checked(&payload, &address, &sig.slice(..64)),
+        Err(Ok(VerifierError::MalformedSigData))
+    );
+    assert_eq!(
+        client.try_verify_checked(&payload, &address.slice(..19), &sig),
+        Err(Ok(VerifierError::BadKeyLength))
+    );
+    assert_eq!(
+        client.try_verify_checked(&payload.slice(..31), &address, &sig),
+        Err(Ok(VerifierError::WrongMessageLength))
+    );
+}
+
+#[test]
+fn test_verify_rejects_high_s() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let signature = personal_sign(&env, &key, &PAYLOAD);
+
+    // (r, n - s) with v flipped recovers the same address off-chain
+    let low = Signature::from_slice(&signature[..64]).unwrap();
+    let (r, s) = low.split_scalars();
+    let high = Signature::from_scalars(r, -s).unwrap();
+    assert!(high.normalize_s().is_some());
+    let mut twin = [0u8; 65];
+    twin[..64].copy_from_slice(&high.to_bytes());
+    twin[64] = signature[64] ^ 1;
+
+    let address = eth_address(&env, &key);
+    assert!(client.verify(&payload, &address, &Bytes::from_array(&env, &signature)));
+    let twin = Bytes::from_array(&env, &twin);
+    assert!(!client.verify(&payload, &address, &twin));
+    assert_eq!(
+        client.try_verify_checked(&payload, &address, &twin),
+        Err(Ok(VerifierError::HighS))
+    );
+}
@@ -0,0 +1,210 @@
+#![no_std]
+use latch_utils::{hex_encode, hex_len};
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, xdr::FromXdr, Bytes, BytesN, Env, Vec,
+};
+use stellar_accounts::verifiers::Verifier;
+
+/// The prefix prepended to the auth payload hash, shared with the Ed25519
+/// verifier.
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+const PREFIX_LEN: usize = 28; // "Stellar Smart Account Auth:\n" = 28 bytes
+const PAYLOAD_LEN: usize = 32;
+const HEX_LEN: usize = hex_len(PAYLOAD_LEN);
+const TOTAL_LEN: usize = PREFIX_LEN + HEX_LEN; // 92 bytes
+/// Keys in one set. Each signature's key is tracked in a `u32` bitmask, so
+/// this must stay at or under 32.
+const MAX_KEYS: u32 = 8;
+/// The order L of the Ed25519 base point, little-endian as signatures encode
+/// `s`.
+const GROUP_ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+#[contract]
+pub struct MultisigEd25519Verifier;
+
+/// key_data, as XDR: a quorum of devices that together count as one signer.
+#[contracttype]
+pub struct MultisigKeyData {
+    pub keys: Vec<BytesN<32>>,
+    /// How many distinct keys must sign.
+    pub threshold: u32,
+}
+
+/// One device's signature. sig_data is XDR of a `Vec` of these.
+#[contracttype]
+pub struct MultisigSigData {
+    /// Which of `MultisigKeyData::keys` signed. Ed25519 verification traps
+    /// on a mismatch, so the key cannot be found by trying each in turn.
+    pub key_index: u32,
+    pub prefixed_message: Bytes,
+    pub signature: BytesN<64>,
+}
+
+/// The check `verify_checked` failed at. Codes 1-6 and 33 match the
+/// Ed25519 verifier's; 24-28 are this verifier's own.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum VerifierError {
+    /// sig_data is XDR of something other than a `Vec<MultisigSigData>`.
+    MalformedSigData = 1,
+    /// key_data is XDR of something other than `MultisigKeyData`.
+    BadKeyLength = 2,
+    /// A prefixed message is not prefix + 64 hex characters, or the payload
+    /// is not 32 bytes.
+    WrongMessageLength = 3,
+    MissingPrefix = 4,
+    /// A message's hex is not the payload's.
+    PayloadMismatch = 5,
+    /// Never returned by this verifier: `ed25519_verify` traps on a bad
+    /// signature. Kept so the codes line up across verifiers.
+    BadSignature = 6,
+    /// The threshold is 0 or more than the number of keys.
+    BadThreshold = 24,
+    /// The set has no keys or more than `MAX_KEYS`.
+    TooManyKeys = 25,
+    /// A signature's `key_index` is past the end of the set.
+    KeyIndexOutOfRange = 26,
+    /// A key appears twice in the set, or signed twice.
+    DuplicateKey = 27,
+    /// Fewer distinct keys signed than the threshold.
+    BelowThreshold = 28,
+    /// A signature's `s` is not below the group order, so it is a malleated
+    /// copy of some canonical signature.
+    NonCanonicalSignature = 33,
+}
+
+#[contractimpl]
+impl Verifier for MultisigEd25519Verifier {
+    type KeyData = Bytes;
+    type SigData = Bytes;
+
+    /// Verifies that at least `threshold` distinct keys of the set signed
+    /// the prefixed message for the payload.
+    ///
+    /// Input that cannot make the quorum (a bad threshold, a key used twice,
+    /// too few signatures, a message for another payload) returns `false`.
+    /// Bytes that are not XDR at all and a signature that does not verify
+    /// still trap, in the host's deserializer and `ed25519_verify`.
+    fn verify(
+        e: &Env,
+        signature_payload: Bytes,
+        key_data: Self::KeyData,
+        sig_data: Self::SigData,
+    ) -> bool {
+        check(e, signature_payload, key_data, sig_data).is_ok()
+    }
+}
+
+#[contractimpl]
+impl MultisigEd25519Verifier {
+    /// `verify` that tells which check failed, for integrators and
+    /// simulation.
+    pub fn verify_checked(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> Result<(), VerifierError> {
+        check(&e, signature_payload, key_data, sig_data)
+    }
+}
+
+fn check(
+    e: &Env,
+    signature_payload: Bytes,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    let key_set =
+        MultisigKeyData::from_xdr(e, &key_data).map_err(|_| VerifierError::BadKeyLength)?;
+    let signatures = Vec::<MultisigSigData>::from_xdr(e, &sig_data)
+        .map_err(|_| VerifierError::MalformedSigData)?;
+
+    let keys = key_set.keys;
+    if keys.is_empty() || keys.len() > MAX_KEYS {
+        return Err(VerifierError::TooManyKeys);
+    }
+    if key_set.threshold == 0 || key_set.threshold > keys.len() {
+        return Err(VerifierError::BadThreshold);
+    }
+    // Two copies of one key would let one device count twice
+    for (i, key) in keys.iter().enumerate() {
+        if keys.iter().skip(i + 1).any(|other| other == key) {
+            return Err(VerifierError::DuplicateKey);
+        }
+    }
+    if signatures.len() < key_set.threshold {
+        return Err(VerifierError::BelowThreshold);
+    }
+
+    if signature_payload.len() != PAYLOAD_LEN as u32 {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let payload_array = signature_payload.to_buffer::<PAYLOAD_LEN>();
+    let mut expected_hex = [0u8; HEX_LEN];
+    hex_encode(&mut expected_hex, payload_array.as_slice());
+
+    // Every message is checked before any signature, so a bad one fails
+    // cleanly rather than after some verifications were paid for
+    let mut signed: u32 = 0;
+    for sig in signatures.iter() {
+        if sig.key_index >= keys.len() {
+            return Err(VerifierError::KeyIndexOutOfRange);
+        }
+        let bit = 1 << sig.key_index;
+        if signed & bit != 0 {
+            return Err(VerifierError::DuplicateKey);
+        }
+        signed |= bit;
+        check_message(&sig.prefixed_message, &expected_hex)?;
+        if !is_canonical(&sig.signature) {
+            return Err(VerifierError::NonCanonicalSignature);
+        }
+    }
+
+    for sig in signatures.iter() {
+        e.crypto().ed25519_verify(
+            &keys.get_unchecked(sig.key_index),
+            &sig.prefixed_message,
+            &sig.signature,
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks that `message` is the auth prefix followed by `expected_hex`.
+fn check_message(message: &Bytes, expected_hex: &[u8; HEX_LEN]) -> Result<(), VerifierError> {
+    if message.len() != TOTAL_LEN as u32 {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let message = message.to_buffer::<TOTAL_LEN>();
+    let message = message.as_slice();
+    if &message[..PREFIX_LEN] != AUTH_PREFIX {
+        return Err(VerifierError::MissingPrefix);
+    }
+    if message[PREFIX_LEN..] != expected_hex[..] {
+        return Err(VerifierError::PayloadMismatch);
+    }
+
+    Ok(())
+}
+
+/// Whether the `s` half of `signature` is below `GROUP_ORDER`.
+fn is_canonical(signature: &BytesN<64>) -> bool {
+    let signature = signature.to_array();
+    // Little-endian, so the last byte is the most significant
+    for (byte, order) in signature[32..].iter().zip(GROUP_ORDER.iter()).rev() {
+        if byte != order {
+            return byte < order;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test;
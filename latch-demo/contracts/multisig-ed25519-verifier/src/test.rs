@@ -0,0 +1,249 @@
+#![cfg(test)]
+use crate::{
+    MultisigEd25519Verifier, MultisigEd25519VerifierClient, MultisigKeyData, MultisigSigData,
+    VerifierError,
+};
+use ed25519_dalek::{Signer, SigningKey};
+use latch_utils::{hex_encode, hex_len};
+use soroban_sdk::{xdr::ToXdr, Bytes, BytesN, Env, Vec};
+
+extern crate std;
+
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+
+const PAYLOAD: [u8; 32] = [1u8; 32];
+
+/// CPU instructions a 2-of-3 check may use, a tenth of the network's 100M
+/// per transaction.
+const TWO_OF_THREE_CPU_CEILING: u64 = 10_000_000;
+
+/// Convert bytes to lowercase hex string (off-chain helper for tests)
+fn bytes_to_hex(bytes: &[u8]) -> std::vec::Vec<u8> {
+    let mut hex = std::vec![0u8; hex_len(bytes.len())];
+    hex_encode(&mut hex, bytes);
+    hex
+}
+
+fn devices() -> [SigningKey; 3] {
+    [1u8, 2, 3].map(|seed| SigningKey::from_bytes(&[seed; 32]))
+}
+
+fn key_data(env: &Env, devices: &[SigningKey], threshold: u32) -> Bytes {
+    let mut keys = Vec::new(env);
+    for device in devices {
+        keys.push_back(BytesN::from_array(env, &device.verifying_key().to_bytes()));
+    }
+    MultisigKeyData { keys, threshold }.to_xdr(env)
+}
+
+/// `device`, at `key_index` in the set, signing the message for `PAYLOAD`.
+fn sign(env: &Env, device: &SigningKey, key_index: u32) -> MultisigSigData {
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(&bytes_to_hex(&PAYLOAD));
+    MultisigSigData {
+        key_index,
+        prefixed_message: Bytes::from_slice(env, &message),
+        signature: BytesN::from_array(env, &device.sign(&message).to_bytes()),
+    }
+}
+
+fn sig_data(env: &Env, sigs: impl IntoIterator<Item = MultisigSigData>) -> Bytes {
+    let mut all = Vec::new(env);
+    for sig in sigs {
+        all.push_back(sig);
+    }
+    all.to_xdr(env)
+}
+
+fn setup(env: &Env) -> MultisigEd25519VerifierClient<'_> {
+    let contract_id = env.register(MultisigEd25519Verifier, ());
+    MultisigEd25519VerifierClient::new(env, &contract_id)
+}
+
+#[test]
+fn test_two_of_three() {
+    let env = Env::default();
+    let client = setup(&env);
+    let devices = devices();
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &devices, 2),
+        &sig_data(
+            &env,
+            [sign(&env, &devices[0], 0), sign(&env, &devices[2], 2)]
+        ),
+    ));
+}
+
+#[test]
+fn test_same_key_twice_does_not_count_twice() {
+    let env = Env::default();
+    let client = setup(&env);
+    let devices = devices();
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let keys = key_data(&env, &devices, 2);
+
+    let twice = sig_data(
+        &env,
+        [sign(&env, &devices[1], 1), sign(&env, &devices[1], 1)],
+    );
+    assert!(!client.verify(&payload, &keys, &twice));
+    assert_eq!(
+        client.try_verify_checked(&payload, &keys, &twice),
+        Err(Ok(VerifierError::DuplicateKey))
+    );
+
+    // Nor does one device listed twice in the set
+    let doubled = key_data(&env, &[devices[0].clone(), devices[0].clone()], 2);
+    let both = sig_data(
+        &env,
+        [sign(&env, &devices[0], 0), sign(&env, &devices[0], 1)],
+    );
+    assert_eq!(
+        client.try_verify_checked(&payload, &doubled, &both),
+        Err(Ok(VerifierError::DuplicateKey))
+    );
+}
+
+#[test]
+fn test_threshold_above_key_count() {
+    let env = Env::default();
+    let client = setup(&env);
+    let devices = devices();
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let key_data = key_data(&env, &devices, 4);
+    let all = sig_data(&env, [0, 1, 2].map(|i| sign(&env, &devices[i], i as u32)));
+
+    assert!(!client.verify(&payload, &key_data, &all));
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data, &all),
+        Err(Ok(VerifierError::BadThreshold))
+    );
+}
+
+#[test]
+fn test_below_threshold() {
+    let env = Env::default();
+    let client = setup(&env);
+    let devices = devices();
+
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &PAYLOAD),
+            &key_data(&env, &devices, 2),
+            &sig_data(&env, [sign(&env, &devices[0], 0)]),
+        ),
+        Err(Ok(VerifierError::BelowThreshold))
+    );
+}
+
+#[test]
+fn test_key_index_out_of_range() {
+    let env = Env::default();
+    let client = setup(&env);
+    let devices = devices();
+
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &PAYLOAD),
+            &key_data(&env, &devices, 1),
+            &sig_data(&env, [sign(&env, &devices[0], 3)]),
+        ),
+        Err(Ok(VerifierError::KeyIndexOutOfRange))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_signature_under_the_wrong_index() {
+    let env = Env::default();
+    let client = setup(&env);
+    let devices = devices();
+
+    // Host traps on a signature that does not verify
+    client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &devices, 2),
+        &sig_data(
+            &env,
+            [sign(&env, &devices[0], 1), sign(&env, &devices[1], 0)],
+        ),
+    );
+}
+
+#[test]
+fn test_two_of_three_stays_within_budget() {
+    let env = Env::default();
+    let client = setup(&env);
+    let devices = devices();
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let key_data = key_data(&env, &devices, 2);
+    let sig_data = sig_data(
+        &env,
+        [sign(&env, &devices[0], 0), sign(&env, &devices[1], 1)],
+    );
+
+    let budget = env.cost_estimate().budget();
+    budget.reset_default();
+    assert!(client.verify(&payload, &key_data, &sig_data));
+    let cpu = budget.cpu_instruction_cost();
+
+    std::println!("2-of-3 verify: cpu {cpu} insns");
+    assert!(
+        cpu <= TWO_OF_THREE_CPU_CEILING,
+        "2-of-3 verify used {cpu} CPU instructions, ceiling is {TWO_OF_THREE_CPU_CEILING}"
+    );
+}
+
+/// `sig` with `s + L` in place of `s`, which verifies the same in the curve
+/// equation.
+fn malleate(env: &Env, sig: MultisigSigData) -> MultisigSigData {
+    const GROUP_ORDER: [u8; 32] = [
+        0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde,
+        0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x10,
+    ];
+    let mut twin = sig.signature.to_array();
+    let mut carry = 0u16;
+    for (byte, order) in twin[32..].iter_mut().zip(GROUP_ORDER) {
+        let sum = *byte as u16 + order as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    assert_eq!(carry, 0);
+    MultisigSigData {
+        signature: BytesN::from_array(env, &twin),
+        ..sig
+    }
+}
+
+#[test]
+fn test_malleated_signature_is_rejected() {
+    let env = Env::default();
+    let client = setup(&env);
+    let devices = devices();
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let keys = key_data(&env, &devices, 2);
+
+    assert!(client.verify(
+        &payload,
+        &keys,
+        &sig_data(
+            &env,
+            [sign(&env, &devices[0], 0), sign(&env, &devices[1], 1)]
+        ),
+    ));
+    let twin = sig_data(
+        &env,
+        [
+            sign(&env, &devices[0], 0),
+            malleate(&env, sign(&env, &devices[1], 1)),
+        ],
+    );
+    assert!(!client.verify(&payload, &keys, &twin));
+    assert_eq!(
+        client.try_verify_checked(&payload, &keys, &twin),
+        Err(Ok(VerifierError::NonCanonicalSignature))
+    );
+}
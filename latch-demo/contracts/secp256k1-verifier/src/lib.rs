@@ -0,0 +1,175 @@
+#![no_std]
+use latch_utils::{hex_encode, hex_len};
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, xdr::FromXdr, Bytes, BytesN, Env,
+};
+use stellar_accounts::verifiers::Verifier;
+
+/// The prefix prepended to the auth payload hash, shared with the Ed25519
+/// verifier.
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+const PREFIX_LEN: usize = 28; // "Stellar Smart Account Auth:\n" = 28 bytes
+const PAYLOAD_LEN: usize = 32;
+const HEX_LEN: usize = hex_len(PAYLOAD_LEN);
+const TOTAL_LEN: usize = PREFIX_LEN + HEX_LEN; // 92 bytes
+const COMPRESSED_KEY_LEN: u32 = 33;
+const UNCOMPRESSED_KEY_LEN: u32 = 65;
+
+/// Half the secp256k1 group order, big-endian. Signatures with a larger `s`
+/// are the malleable twin of a low-s one.
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+#[contract]
+pub struct Secp256k1Verifier;
+
+/// Signature data containing the prefixed message, the compact signature and
+/// the id needed to recover the signing key from it.
+#[contracttype]
+pub struct Secp256k1SigData {
+    pub prefixed_message: Bytes,
+    /// `r || s`, with `s` in the low half of the curve order.
+    pub signature: BytesN<64>,
+    pub recovery_id: u32,
+}
+
+/// The check `verify_checked` failed at. Codes match the Ed25519 verifier's,
+/// and 7 the secp256r1 verifier's.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum VerifierError {
+    /// sig_data is XDR of something other than `Secp256k1SigData`.
+    MalformedSigData = 1,
+    /// key_data is neither a 33-byte compressed nor a 65-byte uncompressed
+    /// SEC1 public key.
+    BadKeyLength = 2,
+    /// The prefixed message is not prefix + 64 hex characters, or the
+    /// payload is not 32 bytes.
+    WrongMessageLength = 3,
+    MissingPrefix = 4,
+    /// The message's hex is not the payload's.
+    PayloadMismatch = 5,
+    /// The key recovered from the signature is not key_data.
+    BadSignature = 6,
+    /// `s` is above half the group order. High-s signatures are rejected
+    /// rather than normalized, which would also flip the recovery id;
+    /// signers must normalize, as k256 and libsecp256k1 do by default.
+    HighS = 7,
+}
+
+#[contractimpl]
+impl Verifier for Secp256k1Verifier {
+    type KeyData = Bytes;
+    type SigData = Bytes;
+
+    /// Verifies a secp256k1 signature over the sha256 of a prefixed message
+    /// by recovering the signing key and comparing it to `key_data`.
+    ///
+    /// Input that cannot carry a valid signature, including a high-s
+    /// signature, and a signature that
+    /// recovers some other key (e.g. under the wrong recovery id), return
+    /// `false`. Bytes that are not XDR at all, a recovery id above 3 and a
+    /// signature no key can be recovered from trap in the host.
+    fn verify(
+        e: &Env,
+        signature_payload: Bytes,
+        key_data: Self::KeyData,
+        sig_data: Self::SigData,
+    ) -> bool {
+        check(e, signature_payload, key_data, sig_data).is_ok()
+    }
+}
+
+#[contractimpl]
+impl Secp256k1Verifier {
+    /// `verify` that tells which check failed, for integrators and
+    /// simulation.
+    pub fn verify_checked(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> Result<(), VerifierError> {
+        check(&e, signature_payload, key_data, sig_data)
+    }
+}
+
+fn check(
+    e: &Env,
+    signature_payload: Bytes,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    // Decode sig_data from XDR
+    let sig_struct =
+        Secp256k1SigData::from_xdr(e, &sig_data).map_err(|_| VerifierError::MalformedSigData)?;
+
+    if key_data.len() != COMPRESSED_KEY_LEN && key_data.len() != UNCOMPRESSED_KEY_LEN {
+        return Err(VerifierError::BadKeyLength);
+    }
+
+    // Validate prefixed_message length
+    if sig_struct.prefixed_message.len() != TOTAL_LEN as u32 {
+        return Err(VerifierError::WrongMessageLength);
+    }
+
+    let prefixed_msg_buf = sig_struct.prefixed_message.to_buffer::<TOTAL_LEN>();
+    let prefixed_msg_slice = prefixed_msg_buf.as_slice();
+
+    if &prefixed_msg_slice[0..PREFIX_LEN] != AUTH_PREFIX {
+        return Err(VerifierError::MissingPrefix);
+    }
+
+    if signature_payload.len() != PAYLOAD_LEN as u32 {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let payload_array = signature_payload.to_buffer::<PAYLOAD_LEN>();
+
+    let mut expected_hex = [0u8; HEX_LEN];
+    hex_encode(&mut expected_hex, payload_array.as_slice());
+
+    if &prefixed_msg_slice[PREFIX_LEN..TOTAL_LEN] != &expected_hex[..] {
+        return Err(VerifierError::PayloadMismatch);
+    }
+
+    // Big-endian, so byte order is numeric order.
+    let signature = sig_struct.signature.to_array();
+    if signature[32..] > HALF_ORDER[..] {
+        return Err(VerifierError::HighS);
+    }
+
+    // sha256 rather than keccak256: the message is Stellar's, not an
+    // Ethereum personal_sign payload, and sha256 is what the rest of the
+    // auth flow hashes with.
+    let digest = e.crypto().sha256(&sig_struct.prefixed_message);
+    let recovered =
+        e.crypto()
+            .secp256k1_recover(&digest, &sig_struct.signature, sig_struct.recovery_id);
+
+    if !key_matches(&recovered, &key_data) {
+        return Err(VerifierError::BadSignature);
+    }
+
+    Ok(())
+}
+
+/// Whether the recovered uncompressed key (`04 || x || y`) is `key_data`, in
+/// whichever SEC1 encoding `key_data` uses.
+fn key_matches(recovered: &BytesN<65>, key_data: &Bytes) -> bool {
+    let recovered = recovered.to_array();
+    if key_data.len() == UNCOMPRESSED_KEY_LEN {
+        return key_data.to_buffer::<65>().as_slice() == &recovered[..];
+    }
+
+    // Compressed keys are the x coordinate behind 02 (even y) or 03 (odd y).
+    let mut compressed = [0u8; 33];
+    compressed[0] = 0x02 | (recovered[64] & 1);
+    compressed[1..].copy_from_slice(&recovered[1..33]);
+    key_data.to_buffer::<33>().as_slice() == &compressed[..]
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,194 @@
+#![cfg(test)]
+use crate::{Secp256k1SigData, Secp256k1Verifier, Secp256k1VerifierClient, VerifierError};
+use k256::{
+    ecdsa::{Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use latch_utils::{hex_encode, hex_len};
+use soroban_sdk::{xdr::ToXdr, Bytes, BytesN, Env};
+
+extern crate std;
+
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+
+const PAYLOAD: [u8; 32] = [1u8; 32];
+
+/// Convert bytes to lowercase hex string (off-chain helper for tests)
+fn bytes_to_hex(bytes: &[u8]) -> std::vec::Vec<u8> {
+    let mut hex = std::vec![0u8; hex_len(bytes.len())];
+    hex_encode(&mut hex, bytes);
+    hex
+}
+
+fn signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_slice(&[seed; 32]).unwrap()
+}
+
+/// SEC1 encoding of `key`'s public key.
+fn key_data(env: &Env, key: &SigningKey, compress: bool) -> Bytes {
+    let point = key.verifying_key().as_affine().to_encoded_point(compress);
+    Bytes::from_slice(env, point.as_bytes())
+}
+
+/// Signs sha256("Stellar Smart Account Auth:\n" + hex(payload)) off-chain and
+/// returns the sig_data plus the recovery id k256 reported.
+fn sign(env: &Env, key: &SigningKey, payload: &[u8; 32]) -> (Secp256k1SigData, u32) {
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(&bytes_to_hex(payload));
+    let prefixed_message = Bytes::from_slice(env, &message);
+    let digest = env.crypto().sha256(&prefixed_message).to_array();
+
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&digest).unwrap();
+    let signature: [u8; 64] = signature.to_bytes().into();
+    let recovery_id = recovery_id.to_byte() as u32;
+
+    (
+        Secp256k1SigData {
+            prefixed_message,
+            signature: BytesN::from_array(env, &signature),
+            recovery_id,
+        },
+        recovery_id,
+    )
+}
+
+fn setup(env: &Env) -> Secp256k1VerifierClient<'_> {
+    let contract_id = env.register(Secp256k1Verifier, ());
+    Secp256k1VerifierClient::new(env, &contract_id)
+}
+
+#[test]
+fn test_verify_valid_signature_compressed_key() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let (sig_data, _) = sign(&env, &key, &PAYLOAD);
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &key, true),
+        &sig_data.to_xdr(&env),
+    ));
+}
+
+#[test]
+fn test_verify_valid_signature_uncompressed_key() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let (sig_data, _) = sign(&env, &key, &PAYLOAD);
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &key, false),
+        &sig_data.to_xdr(&env),
+    ));
+}
+
+#[test]
+fn test_verify_wrong_key() {
+    let env = Env::default();
+    let client = setup(&env);
+    let (sig_data, _) = sign(&env, &signing_key(7), &PAYLOAD);
+
+    // Signed by one key, checked against another
+    assert!(!client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &signing_key(8), true),
+        &sig_data.to_xdr(&env),
+    ));
+}
+
+#[test]
+fn test_verify_wrong_recovery_id() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let (mut sig_data, recovery_id) = sign(&env, &key, &PAYLOAD);
+
+    // The other parity recovers a different key, not a trap
+    sig_data.recovery_id = recovery_id ^ 1;
+    assert!(!client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &key, true),
+        &sig_data.to_xdr(&env),
+    ));
+}
+
+#[test]
+fn test_verify_wrong_payload() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let (sig_data, _) = sign(&env, &key, &PAYLOAD);
+
+    assert!(!client.verify(
+        &Bytes::from_slice(&env, &[2u8; 32]),
+        &key_data(&env, &key, true),
+        &sig_data.to_xdr(&env),
+    ));
+}
+
+#[test]
+fn test_verify_checked_reports_error_codes() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let (sig_data, _) = sign(&env, &key, &PAYLOAD);
+    let sig_data = sig_data.to_xdr(&env);
+
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data(&env, &key, true), &sig_data),
+        Ok(Ok(()))
+    );
+    assert_eq!(
+        client.try_verify_checked(&payload, &Bytes::from_array(&env, &[2u8; 32]), &sig_data),
+        Err(Ok(VerifierError::BadKeyLength))
+    );
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data(&env, &signing_key(8), true), &sig_data),
+        Err(Ok(VerifierError::BadSignature))
+    );
+    assert_eq!(
+        client.try_verify_checked(
+            &payload,
+            &key_data(&env, &key, true),
+            &payload.clone().to_xdr(&env)
+        ),
+        Err(Ok(VerifierError::MalformedSigData))
+    );
+}
+
+#[test]
+fn test_verify_rejects_high_s() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let (sig_data, recovery_id) = sign(&env, &key, &PAYLOAD);
+
+    // (r, n - s) under the other parity recovers the same key off-chain
+    let low = Signature::from_slice(&sig_data.signature.to_array()).unwrap();
+    let (r, s) = low.split_scalars();
+    let high = Signature::from_scalars(r, -s).unwrap();
+    assert!(high.normalize_s().is_some());
+    let high: [u8; 64] = high.to_bytes().into();
+    let twin = Secp256k1SigData {
+        prefixed_message: sig_data.prefixed_message.clone(),
+        signature: BytesN::from_array(&env, &high),
+        recovery_id: recovery_id ^ 1,
+    }
+    .to_xdr(&env);
+
+    assert!(client.verify(
+        &payload,
+        &key_data(&env, &key, true),
+        &sig_data.to_xdr(&env)
+    ));
+    assert!(!client.verify(&payload, &key_data(&env, &key, true), &twin));
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data(&env, &key, true), &twin),
+        Err(Ok(VerifierError::HighS))
+    );
+}
@@ -0,0 +1,143 @@
+#![no_std]
+use latch_utils::{hex_encode, hex_len};
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, xdr::FromXdr, Bytes, BytesN, Env,
+};
+use stellar_accounts::verifiers::Verifier;
+
+/// The prefix prepended to the auth payload hash, shared with the Ed25519
+/// verifier.
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+const PREFIX_LEN: usize = 28; // "Stellar Smart Account Auth:\n" = 28 bytes
+const PAYLOAD_LEN: usize = 32;
+const HEX_LEN: usize = hex_len(PAYLOAD_LEN);
+const TOTAL_LEN: usize = PREFIX_LEN + HEX_LEN; // 92 bytes
+
+/// Half the P-256 group order, big-endian. Signatures with a larger `s` are
+/// the malleable twin of a low-s one.
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0x80, 0x00, 0x00, 0x00, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xde, 0x73, 0x7d, 0x56, 0xd3, 0x8b, 0xcf, 0x42, 0x79, 0xdc, 0xe5, 0x61, 0x7e, 0x31, 0x92, 0xa8,
+];
+
+#[contract]
+pub struct Secp256r1Verifier;
+
+/// Signature data containing both the prefixed message and signature.
+#[contracttype]
+pub struct Secp256r1SigData {
+    pub prefixed_message: Bytes,
+    /// Raw `r || s` over sha256 of `prefixed_message`.
+    pub signature: BytesN<64>,
+}
+
+/// The check `verify_checked` failed at. Codes 1-6 match the Ed25519
+/// verifier's.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum VerifierError {
+    /// sig_data is XDR of something other than `Secp256r1SigData`.
+    MalformedSigData = 1,
+    /// key_data is not a 65-byte uncompressed SEC1 public key.
+    BadKeyLength = 2,
+    /// The prefixed message is not prefix + 64 hex characters, or the
+    /// payload is not 32 bytes.
+    WrongMessageLength = 3,
+    MissingPrefix = 4,
+    /// The message's hex is not the payload's.
+    PayloadMismatch = 5,
+    /// Never returned by this verifier: `secp256r1_verify` traps on a bad
+    /// signature. Kept so the codes line up across verifiers.
+    BadSignature = 6,
+    /// `s` is above half the group order. High-s signatures are rejected
+    /// rather than normalized, as the host does; signers must normalize.
+    HighS = 7,
+}
+
+#[contractimpl]
+impl Verifier for Secp256r1Verifier {
+    type KeyData = Bytes;
+    type SigData = Bytes;
+
+    /// Verifies a P-256 signature over the sha256 of a prefixed message.
+    ///
+    /// Input that cannot carry a valid signature, including a high-s
+    /// signature, returns `false`. Bytes that are not XDR at all, a key that
+    /// is not on the curve and a signature that does not verify still trap
+    /// in the host.
+    fn verify(
+        e: &Env,
+        signature_payload: Bytes,
+        key_data: Self::KeyData,
+        sig_data: Self::SigData,
+    ) -> bool {
+        check(e, signature_payload, key_data, sig_data).is_ok()
+    }
+}
+
+#[contractimpl]
+impl Secp256r1Verifier {
+    /// `verify` that tells which check failed, for integrators and
+    /// simulation.
+    pub fn verify_checked(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> Result<(), VerifierError> {
+        check(&e, signature_payload, key_data, sig_data)
+    }
+}
+
+fn check(
+    e: &Env,
+    signature_payload: Bytes,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    // Decode sig_data from XDR
+    let sig_struct =
+        Secp256r1SigData::from_xdr(e, &sig_data).map_err(|_| VerifierError::MalformedSigData)?;
+
+    let public_key = BytesN::<65>::try_from(key_data).map_err(|_| VerifierError::BadKeyLength)?;
+
+    // Validate prefixed_message length
+    if sig_struct.prefixed_message.len() != TOTAL_LEN as u32 {
+        return Err(VerifierError::WrongMessageLength);
+    }
+
+    let prefixed_msg_buf = sig_struct.prefixed_message.to_buffer::<TOTAL_LEN>();
+    let prefixed_msg_slice = prefixed_msg_buf.as_slice();
+
+    if &prefixed_msg_slice[0..PREFIX_LEN] != AUTH_PREFIX {
+        return Err(VerifierError::MissingPrefix);
+    }
+
+    if signature_payload.len() != PAYLOAD_LEN as u32 {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let payload_array = signature_payload.to_buffer::<PAYLOAD_LEN>();
+
+    let mut expected_hex = [0u8; HEX_LEN];
+    hex_encode(&mut expected_hex, payload_array.as_slice());
+
+    if &prefixed_msg_slice[PREFIX_LEN..TOTAL_LEN] != &expected_hex[..] {
+        return Err(VerifierError::PayloadMismatch);
+    }
+
+    // Big-endian, so byte order is numeric order.
+    let signature = sig_struct.signature.to_array();
+    if signature[32..] > HALF_ORDER[..] {
+        return Err(VerifierError::HighS);
+    }
+
+    let digest = e.crypto().sha256(&sig_struct.prefixed_message);
+    e.crypto()
+        .secp256r1_verify(&public_key, &digest, &sig_struct.signature);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,150 @@
+#![cfg(test)]
+use crate::{Secp256r1SigData, Secp256r1Verifier, Secp256r1VerifierClient, VerifierError};
+use latch_utils::{hex_encode, hex_len};
+use p256::{
+    ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use soroban_sdk::{xdr::ToXdr, Bytes, BytesN, Env};
+
+extern crate std;
+
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+
+const PAYLOAD: [u8; 32] = [1u8; 32];
+
+/// Convert bytes to lowercase hex string (off-chain helper for tests)
+fn bytes_to_hex(bytes: &[u8]) -> std::vec::Vec<u8> {
+    let mut hex = std::vec![0u8; hex_len(bytes.len())];
+    hex_encode(&mut hex, bytes);
+    hex
+}
+
+fn signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_slice(&[seed; 32]).unwrap()
+}
+
+/// Uncompressed SEC1 encoding of `key`'s public key.
+fn key_data(env: &Env, key: &SigningKey) -> Bytes {
+    let point = key.verifying_key().as_affine().to_encoded_point(false);
+    Bytes::from_slice(env, point.as_bytes())
+}
+
+/// Signs sha256("Stellar Smart Account Auth:\n" + hex(payload)) off-chain
+/// and returns the prefixed message with the low-s signature.
+fn sign(env: &Env, key: &SigningKey, payload: &[u8; 32]) -> (Bytes, Signature) {
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(&bytes_to_hex(payload));
+    let prefixed_message = Bytes::from_slice(env, &message);
+    let digest = env.crypto().sha256(&prefixed_message).to_array();
+
+    let signature: Signature = key.sign_prehash(&digest).unwrap();
+    (
+        prefixed_message,
+        signature.normalize_s().unwrap_or(signature),
+    )
+}
+
+fn sig_data(env: &Env, prefixed_message: Bytes, signature: &Signature) -> Bytes {
+    let signature: [u8; 64] = signature.to_bytes().into();
+    Secp256r1SigData {
+        prefixed_message,
+        signature: BytesN::from_array(env, &signature),
+    }
+    .to_xdr(env)
+}
+
+fn setup(env: &Env) -> Secp256r1VerifierClient<'_> {
+    let contract_id = env.register(Secp256r1Verifier, ());
+    Secp256r1VerifierClient::new(env, &contract_id)
+}
+
+#[test]
+fn test_verify_valid_signature() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let (message, signature) = sign(&env, &key, &PAYLOAD);
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &key),
+        &sig_data(&env, message, &signature),
+    ));
+}
+
+#[test]
+fn test_verify_rejects_high_s() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let (message, signature) = sign(&env, &key, &PAYLOAD);
+
+    // (r, n - s) verifies just as well off-chain, but is not accepted
+    let (r, s) = signature.split_scalars();
+    let high = Signature::from_scalars(r, -s).unwrap();
+    assert!(high.normalize_s().is_some());
+
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let high = sig_data(&env, message, &high);
+    assert!(!client.verify(&payload, &key_data(&env, &key), &high));
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data(&env, &key), &high),
+        Err(Ok(VerifierError::HighS))
+    );
+}
+
+#[test]
+fn test_verify_rejects_compressed_key() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let (message, signature) = sign(&env, &key, &PAYLOAD);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let compressed = Bytes::from_slice(
+        &env,
+        key.verifying_key()
+            .as_affine()
+            .to_encoded_point(true)
+            .as_bytes(),
+    );
+    let sig_data = sig_data(&env, message, &signature);
+
+    assert!(!client.verify(&payload, &compressed, &sig_data));
+    assert_eq!(
+        client.try_verify_checked(&payload, &compressed, &sig_data),
+        Err(Ok(VerifierError::BadKeyLength))
+    );
+}
+
+#[test]
+fn test_verify_wrong_payload() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let (message, signature) = sign(&env, &key, &PAYLOAD);
+
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &[2u8; 32]),
+            &key_data(&env, &key),
+            &sig_data(&env, message, &signature),
+        ),
+        Err(Ok(VerifierError::PayloadMismatch))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_verify_wrong_key() {
+    let env = Env::default();
+    let client = setup(&env);
+    let (message, signature) = sign(&env, &signing_key(7), &PAYLOAD);
+
+    // Host traps on a signature that does not verify
+    client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &signing_key(8)),
+        &sig_data(&env, message, &signature),
+    );
+}
@@ -0,0 +1,523 @@
+//! Authorization path of the Phantom account.
+//!
+//! Follows the same model as `stellar_accounts::smart_account::do_check_auth`
+//! (verify every presented signature, then match each auth context against
+//! the context rules) but keeps the matching in this crate so the account can
+//! layer its own checks on top.
+use soroban_sdk::{
+    auth::{Context, ContractExecutable},
+    contractevent, contracttype,
+    crypto::Hash,
+    symbol_short, Address, Bytes, BytesN, Env, Error, IntoVal, Symbol, TryFromVal, Vec,
+};
+use stellar_accounts::smart_account::{
+    get_context_rule, get_context_rules, ContextRule, ContextRuleType, Signatures, Signer,
+};
+
+use crate::{
+    storage::{self, RateWindow},
+    PhantomAccountError, STORAGE_VERSION,
+};
+
+/// Published after every successful `__check_auth`, unless turned off with
+/// `set_auth_events`.
+#[contractevent(topics = ["latch", "auth"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuthEvent {
+    pub signature_payload: BytesN<32>,
+    /// The rule that authorized each auth context, in context order.
+    pub context_rule_ids: Vec<u32>,
+    pub signature_count: u32,
+}
+
+/// Published when an expired rule authorizes a context inside the expiry
+/// grace, as a prompt to renew it.
+#[contractevent(topics = ["grace_used"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GraceUsed {
+    #[topic]
+    pub context_rule_id: u32,
+    pub valid_until: u32,
+}
+
+/// Why a candidate rule did not authorize a context.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RuleFailure {
+    /// Too few of the rule's signers signed.
+    SignerMismatch,
+    /// Past its `valid_until` and the expiry grace.
+    Expired,
+    /// Turned off with `set_rule_active`.
+    Inactive,
+    /// The called function is not on the rule's function filter.
+    FunctionNotAllowed,
+    /// A policy's `can_enforce` returned false.
+    PolicyRejected,
+    /// The verifier of one of the rule's signers rejected its signature.
+    VerifierFalse,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleDiagnosis {
+    pub context_rule_id: u32,
+    pub failure: RuleFailure,
+}
+
+/// Published by a failing `__check_auth` when `set_debug_events` is on,
+/// for `ExternalVerificationFailed` and `UnvalidatedContext`.
+///
+/// The failed call rolls it back with everything else, so it only shows up
+/// in diagnostic output such as simulation results.
+#[contractevent(topics = ["latch", "auth_failed"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuthFailed {
+    pub signature_payload: BytesN<32>,
+    pub error_code: u32,
+    /// The candidate rules that were considered and why each failed.
+    pub rules: Vec<RuleDiagnosis>,
+}
+
+/// Published by a `__check_auth` that fails with `UnknownSigner`, naming
+/// the signatures map entries that are on no candidate rule.
+///
+/// Like `AuthFailed` it is rolled back with the failed call, and shows up
+/// in diagnostic output such as simulation results.
+#[contractevent(topics = ["latch", "unknown_signer"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownSignerRejected {
+    pub signature_payload: BytesN<32>,
+    pub signers: Vec<Signer>,
+}
+
+pub fn check_auth(
+    e: &Env,
+    signature_payload: &Hash<32>,
+    signatures: &Signatures,
+    auth_contexts: &Vec<Context>,
+) -> Result<(), PhantomAccountError> {
+    if storage::is_closed(e) {
+        return Err(PhantomAccountError::AccountClosed);
+    }
+    if storage::get_storage_version(e) < STORAGE_VERSION {
+        return Err(PhantomAccountError::MigrationRequired);
+    }
+    let unknown = unknown_signers(e, &signatures.0.keys(), auth_contexts);
+    if !unknown.is_empty() {
+        UnknownSignerRejected {
+            signature_payload: signature_payload.to_bytes(),
+            signers: unknown,
+        }
+        .publish(e);
+        return Err(PhantomAccountError::UnknownSigner);
+    }
+    let (authenticated, rejected) = authenticate(e, signature_payload, signatures);
+
+    let mut context_rule_ids = Vec::new(e);
+    for context in auth_contexts.iter() {
+        // A rejected signature alone does not fail the auth, as long as the
+        // other signers still satisfy a rule.
+        let (rule, rule_signers) =
+            match_rule(e, &context, &authenticated).map_err(|rules| match rejected.first() {
+                Some(signer) => fail(
+                    e,
+                    signature_payload,
+                    PhantomAccountError::ExternalVerificationFailed,
+                    || rules_with_signer(e, auth_contexts, &signer),
+                ),
+                None => fail(
+                    e,
+                    signature_payload,
+                    PhantomAccountError::UnvalidatedContext,
+                    || rules,
+                ),
+            })?;
+        check_amount_cap(e, &context, rule.id)?;
+        consume_rate_limit(e, rule.id)?;
+        enforce_policies(e, &context, &rule_signers, &rule);
+        record_use(e, rule.id);
+        if let Some(valid_until) = expired_at(e, &rule) {
+            GraceUsed {
+                context_rule_id: rule.id,
+                valid_until,
+            }
+            .publish(e);
+        }
+        context_rule_ids.push_back(rule.id);
+    }
+
+    if storage::auth_events_enabled(e) {
+        AuthEvent {
+            signature_payload: signature_payload.to_bytes(),
+            context_rule_ids,
+            signature_count: signatures.0.len(),
+        }
+        .publish(e);
+    }
+    Ok(())
+}
+
+/// Publishes `AuthFailed` for `error` if debug events are on, and returns
+/// `error`.
+fn fail(
+    e: &Env,
+    signature_payload: &Hash<32>,
+    error: PhantomAccountError,
+    rules: impl FnOnce() -> Vec<RuleDiagnosis>,
+) -> PhantomAccountError {
+    if storage::debug_events_enabled(e) {
+        AuthFailed {
+            signature_payload: signature_payload.to_bytes(),
+            error_code: error as u32,
+            rules: rules(),
+        }
+        .publish(e);
+    }
+    error
+}
+
+/// The candidate rules of `auth_contexts` that `signer`, whose verifier
+/// rejected the signature, is on.
+fn rules_with_signer(e: &Env, auth_contexts: &Vec<Context>, signer: &Signer) -> Vec<RuleDiagnosis> {
+    let mut rules = Vec::new(e);
+    for context in auth_contexts.iter() {
+        for rule in candidate_rules(e, &context).iter() {
+            if rule.signers.contains(signer) {
+                rules.push_back(RuleDiagnosis {
+                    context_rule_id: rule.id,
+                    failure: RuleFailure::VerifierFalse,
+                });
+            }
+        }
+    }
+    rules
+}
+
+/// The entries of `signers` that are not on any candidate rule of
+/// `auth_contexts`. Such a signature could never count, and usually means
+/// the wallet signed with a key that has been rotated out.
+pub fn unknown_signers(
+    e: &Env,
+    signers: &Vec<Signer>,
+    auth_contexts: &Vec<Context>,
+) -> Vec<Signer> {
+    let mut known = Vec::new(e);
+    for context in auth_contexts.iter() {
+        for rule in candidate_rules(e, &context).iter() {
+            known.append(&rule.signers);
+        }
+    }
+
+    let mut unknown = Vec::new(e);
+    for signer in signers.iter() {
+        if !known.contains(&signer) {
+            unknown.push_back(signer);
+        }
+    }
+    unknown
+}
+
+/// Verifies every entry of the signatures map and returns the signers that
+/// authenticated and those whose verifier rejected the signature.
+///
+/// External signers are checked by their verifier contract. Delegated
+/// signers (other smart accounts) sign by authorizing this account's
+/// `__check_auth` call with the payload as its only argument, which recurses
+/// into their own `__check_auth`.
+fn authenticate(
+    e: &Env,
+    signature_payload: &Hash<32>,
+    signatures: &Signatures,
+) -> (Vec<Signer>, Vec<Signer>) {
+    let payload = signature_payload.to_bytes();
+    let mut signers = Vec::new(e);
+    let mut rejected = Vec::new(e);
+
+    for (signer, sig_data) in signatures.0.iter() {
+        match &signer {
+            Signer::External(verifier, key_data) => {
+                let payload = payload.clone().into();
+                let verified = verify_external(e, verifier, &payload, key_data, &sig_data)
+                    || storage::get_fallback_verifier(e, &signer).is_some_and(|fallback| {
+                        verify_external(e, &fallback, &payload, key_data, &sig_data)
+                    });
+                if !verified {
+                    rejected.push_back(signer);
+                    continue;
+                }
+            }
+            Signer::Delegated(account) => {
+                account.require_auth_for_args((payload.clone(),).into_val(e));
+            }
+        }
+        signers.push_back(signer);
+    }
+    (signers, rejected)
+}
+
+/// Whether `verifier` accepts the signature; a trapping verifier counts as
+/// rejecting it, so a fallback verifier still gets a chance.
+fn verify_external(
+    e: &Env,
+    verifier: &Address,
+    payload: &Bytes,
+    key_data: &Bytes,
+    sig_data: &Bytes,
+) -> bool {
+    let result = e.try_invoke_contract::<bool, Error>(
+        verifier,
+        &symbol_short!("verify"),
+        (payload.clone(), key_data.clone(), sig_data.clone()).into_val(e),
+    );
+    matches!(result, Ok(Ok(true)))
+}
+
+/// Rules that may authorize `context`, the ones scoped to it and the default
+/// rules, by descending priority and then ascending id.
+fn candidate_rules(e: &Env, context: &Context) -> Vec<ContextRule> {
+    let scoped = match context {
+        Context::Contract(c) => ContextRuleType::CallContract(c.contract.clone()),
+        Context::CreateContractHostFn(c) => wasm_rule_type(&c.executable),
+        Context::CreateContractWithCtorHostFn(c) => wasm_rule_type(&c.executable),
+    };
+
+    let mut matching = get_context_rules(e, &scoped);
+    matching.append(&get_context_rules(e, &ContextRuleType::Default));
+
+    // Insertion sort; an account only has a handful of rules.
+    let mut rules: Vec<ContextRule> = Vec::new(e);
+    let mut priorities: Vec<u32> = Vec::new(e);
+    for rule in matching.iter() {
+        let priority = storage::get_rule_priority(e, rule.id);
+        let mut index = rules.len();
+        for i in 0..rules.len() {
+            let other = priorities.get_unchecked(i);
+            if priority > other || (priority == other && rule.id < rules.get_unchecked(i).id) {
+                index = i;
+                break;
+            }
+        }
+        rules.insert(index, rule);
+        priorities.insert(index, priority);
+    }
+    rules
+}
+
+fn wasm_rule_type(executable: &ContractExecutable) -> ContextRuleType {
+    match executable {
+        ContractExecutable::Wasm(wasm_hash) => ContextRuleType::CreateContract(wasm_hash.clone()),
+    }
+}
+
+/// First candidate rule that is satisfied and not past its expiry grace,
+/// together with the rule signers that authenticated; or why each candidate
+/// failed.
+///
+/// A rule without policies needs its threshold of unexpired signers (all of
+/// them by default, and at least one); a rule with policies is satisfied when every policy
+/// reports it can enforce the context.
+fn match_rule(
+    e: &Env,
+    context: &Context,
+    authenticated: &Vec<Signer>,
+) -> Result<(ContextRule, Vec<Signer>), Vec<RuleDiagnosis>> {
+    let mut misses = Vec::new(e);
+    let mut miss = |rule: &ContextRule, failure| {
+        misses.push_back(RuleDiagnosis {
+            context_rule_id: rule.id,
+            failure,
+        })
+    };
+
+    for rule in candidate_rules(e, context).iter() {
+        if is_past_grace(e, &rule) {
+            miss(&rule, RuleFailure::Expired);
+            continue;
+        }
+        if !storage::is_rule_active(e, rule.id) {
+            miss(&rule, RuleFailure::Inactive);
+            continue;
+        }
+        if !allows_function(e, rule.id, context) {
+            miss(&rule, RuleFailure::FunctionNotAllowed);
+            continue;
+        }
+        let rule = if crate::finalize_expired_rotation(e, rule.id) {
+            get_context_rule(e, rule.id)
+        } else {
+            rule
+        };
+
+        let (rule_signers, signed, slots) = rule_signers(e, &rule, authenticated);
+        if rule.policies.is_empty() {
+            // A rule whose signers have all expired is satisfied by no one,
+            // not by an empty signatures map
+            let threshold = storage::get_threshold(e, rule.id).unwrap_or(slots).max(1);
+            if slots > 0 && signed >= threshold {
+                return Ok((rule, rule_signers));
+            }
+            miss(&rule, RuleFailure::SignerMismatch);
+        } else if rule
+            .policies
+            .iter()
+            .all(|policy| can_enforce(e, &policy, context, &rule_signers, &rule))
+        {
+            return Ok((rule, rule_signers));
+        } else {
+            miss(&rule, RuleFailure::PolicyRejected);
+        }
+    }
+    Err(misses)
+}
+
+/// The unexpired signers of `rule` that authenticated, how many signer
+/// slots they fill and how many slots the rule has.
+///
+/// While a key rotation is in its grace period the old and new key share a
+/// slot: either one signing fills it.
+fn rule_signers(
+    e: &Env,
+    rule: &ContextRule,
+    authenticated: &Vec<Signer>,
+) -> (Vec<Signer>, u32, u32) {
+    let rotation = storage::get_key_rotation(e, rule.id);
+    let counts = |signer: &Signer| {
+        rule.signers.contains(signer) && !storage::is_signer_expired(e, rule.id, signer)
+    };
+
+    let mut signers = Vec::new(e);
+    let mut signed = 0;
+    let mut slots = 0;
+    for signer in rule.signers.iter() {
+        if !counts(&signer) {
+            continue;
+        }
+        let is_signed = authenticated.contains(&signer);
+        if is_signed {
+            signers.push_back(signer.clone());
+        }
+
+        // The new key of a rotation is folded into the old key's slot.
+        let counterpart = match &rotation {
+            Some(rotation) if rotation.new_key == signer && counts(&rotation.old_key) => continue,
+            Some(rotation) if rotation.old_key == signer && counts(&rotation.new_key) => {
+                Some(&rotation.new_key)
+            }
+            _ => None,
+        };
+        slots += 1;
+        if is_signed || counterpart.is_some_and(|key| authenticated.contains(key)) {
+            signed += 1;
+        }
+    }
+    (signers, signed, slots)
+}
+
+/// Whether the function filter of `rule_id`, if any, lets `context` through.
+/// Only contract calls can match a non-empty filter.
+fn allows_function(e: &Env, rule_id: u32, context: &Context) -> bool {
+    let allowed = storage::get_allowed_functions(e, rule_id);
+    if allowed.is_empty() {
+        return true;
+    }
+    match context {
+        Context::Contract(call) => allowed.contains(&call.fn_name),
+        _ => false,
+    }
+}
+
+/// The `valid_until` of `rule` if it has passed.
+fn expired_at(e: &Env, rule: &ContextRule) -> Option<u32> {
+    rule.valid_until
+        .filter(|until| *until < e.ledger().sequence())
+}
+
+/// Whether `rule` expired longer ago than the expiry grace.
+fn is_past_grace(e: &Env, rule: &ContextRule) -> bool {
+    matches!(
+        rule.valid_until,
+        Some(until) if until.saturating_add(storage::get_expiry_grace(e)) < e.ledger().sequence()
+    )
+}
+
+fn can_enforce(
+    e: &Env,
+    policy: &Address,
+    context: &Context,
+    signers: &Vec<Signer>,
+    rule: &ContextRule,
+) -> bool {
+    e.invoke_contract(
+        policy,
+        &Symbol::new(e, "can_enforce"),
+        (
+            context.clone(),
+            signers.clone(),
+            rule.clone(),
+            e.current_contract_address(),
+        )
+            .into_val(e),
+    )
+}
+
+/// Rejects a call above the amount cap of `rule_id`, if it has one.
+fn check_amount_cap(e: &Env, context: &Context, rule_id: u32) -> Result<(), PhantomAccountError> {
+    let (Some(cap), Context::Contract(call)) = (storage::get_amount_cap(e, rule_id), context)
+    else {
+        return Ok(());
+    };
+
+    let amount = call
+        .args
+        .get(cap.arg_index)
+        .and_then(|arg| i128::try_from_val(e, &arg).ok())
+        .ok_or(PhantomAccountError::InvalidCapArgument)?;
+    if amount > cap.max_amount {
+        return Err(PhantomAccountError::AmountExceedsCap);
+    }
+    Ok(())
+}
+
+/// Counts one use of `rule_id` against its rate limit, if it has one.
+fn consume_rate_limit(e: &Env, rule_id: u32) -> Result<(), PhantomAccountError> {
+    let Some(limit) = storage::get_rate_limit(e, rule_id) else {
+        return Ok(());
+    };
+
+    let index = e.ledger().sequence() / limit.window_ledgers;
+    let mut window = storage::get_rate_window(e, rule_id)
+        .filter(|window| window.index == index)
+        .unwrap_or(RateWindow { index, count: 0 });
+    if window.count >= limit.max_per_window {
+        return Err(PhantomAccountError::RateLimitExceeded);
+    }
+
+    window.count += 1;
+    storage::set_rate_window(e, rule_id, &window);
+    Ok(())
+}
+
+/// Bumps the usage stats of `rule_id`; one entry write per use.
+fn record_use(e: &Env, rule_id: u32) {
+    let mut stats = storage::get_rule_stats(e, rule_id);
+    stats.use_count += 1;
+    stats.last_used_ledger = e.ledger().sequence();
+    storage::set_rule_stats(e, rule_id, &stats);
+}
+
+fn enforce_policies(e: &Env, context: &Context, signers: &Vec<Signer>, rule: &ContextRule) {
+    for policy in rule.policies.iter() {
+        e.invoke_contract::<()>(
+            &policy,
+            &symbol_short!("enforce"),
+            (
+                context.clone(),
+                signers.clone(),
+                rule.clone(),
+                e.current_contract_address(),
+            )
+                .into_val(e),
+        );
+    }
+}
@@ -1,24 +1,137 @@
 #![no_std]
+use latch_utils::{hex_encode, hex_len};
 use soroban_sdk::{
     auth::{Context, CustomAccountInterface},
-    contract, contractimpl,
+    contract, contracterror, contractevent, contractimpl, contracttype,
     crypto::Hash,
-    Address, Bytes, BytesN, Env, Map, String, Val, Vec,
+    panic_with_error, symbol_short,
+    token::TokenClient,
+    xdr::{FromXdr, ToXdr},
+    Address, Bytes, BytesN, Env, IntoVal, Map, String, Symbol, Val, Vec,
 };
 use stellar_accounts::smart_account::{
-    add_context_rule, do_check_auth,
-    ContextRule, ContextRuleType, Signatures, Signer, SmartAccountError,
+    add_context_rule,
+    ContextRule, ContextRuleType, Signatures, Signer,
 };
 
+mod auth;
+mod storage;
+
+pub use auth::{
+    AuthEvent, AuthFailed, GraceUsed, RuleDiagnosis, RuleFailure, UnknownSignerRejected,
+};
+pub use storage::{AmountCap, KeyRotation, RateLimit, RuleStats, RuleTemplate};
+
+/// Most calls a single `execute_batch` may make.
+pub const MAX_BATCH_LEN: u32 = 8;
+
+/// Rule cap until `set_max_rules` is called.
+pub const DEFAULT_MAX_RULES: u32 = 50;
+
+/// Longest signer label, in bytes.
+pub const MAX_LABEL_LEN: u32 = 32;
+
+/// Longest chain of delegated accounts allowed below an account.
+pub const MAX_DELEGATION_DEPTH: u32 = 2;
+
+/// Most rule templates an account may save.
+pub const MAX_TEMPLATES: u32 = 10;
+
+/// Longest expiry grace, in ledgers (about a minute and a half).
+pub const MAX_EXPIRY_GRACE: u32 = 17;
+
+/// Layout version of the data the account keeps next to its context rules.
+/// Written at initialization; `migrate` brings older accounts up to it.
+pub const STORAGE_VERSION: u32 = 3;
+
 #[contract]
 pub struct PhantomSmartAccount;
 
+/// Errors raised by the Phantom account.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum PhantomAccountError {
+    /// The change would leave the account (or a rule) unable to authorize.
+    WouldBrickAccount = 4000,
+    /// No context rule authorizes one of the auth contexts.
+    UnvalidatedContext = 4001,
+    /// A verifier contract rejected an External signer's signature, and the
+    /// signers that did verify satisfy no rule.
+    ExternalVerificationFailed = 4002,
+    /// Delegating to the account would exceed `MAX_DELEGATION_DEPTH`.
+    DelegationTooDeep = 4003,
+    /// The account cannot delegate to itself.
+    DelegationCycle = 4004,
+    /// The matched rule has been used `max_per_window` times this window.
+    RateLimitExceeded = 4005,
+    /// Rate limits need a non-zero cap and window.
+    InvalidRateLimit = 4006,
+    /// Stored data is older than `STORAGE_VERSION`; call `migrate` first.
+    MigrationRequired = 4007,
+    /// A rule needs at least one signer or one policy.
+    EmptyRule = 4008,
+    /// The same signer appears twice in a rule's signers.
+    DuplicateSigner = 4009,
+    /// The signer is not on the context rule.
+    SignerNotFound = 4010,
+    /// The rule already has a key rotation in its grace period.
+    RotationPending = 4011,
+    /// `execute` cannot target the account itself.
+    SelfInvocation = 4012,
+    /// `execute_batch` needs at least one call.
+    EmptyBatch = 4013,
+    /// `execute_batch` got more than `MAX_BATCH_LEN` calls.
+    BatchTooLong = 4014,
+    /// `initialize_signed` got a signature the verifier did not accept.
+    InvalidInitSignature = 4015,
+    /// The account already has context rules.
+    AlreadyInitialized = 4016,
+    /// The call's amount is above the rule's amount cap.
+    AmountExceedsCap = 4017,
+    /// The call has no `i128` at the amount cap's argument index.
+    InvalidCapArgument = 4018,
+    /// The nonce is not the account's current `get_nonce`.
+    BadNonce = 4019,
+    /// Thresholds must be between 1 and the rule's unexpired signer count.
+    InvalidThreshold = 4020,
+    /// Signer labels are at most `MAX_LABEL_LEN` bytes.
+    LabelTooLong = 4021,
+    /// The initialization target is the account itself.
+    TargetIsAccount = 4022,
+    /// The initialization target is the verifier contract.
+    TargetIsVerifier = 4023,
+    /// The account already holds `get_max_rules` context rules.
+    TooManyRules = 4024,
+    /// The signer already has a fallback verifier.
+    FallbackVerifierExists = 4025,
+    /// Only external signers are checked by a verifier.
+    NotExternalSigner = 4026,
+    /// A signatures map entry is not a signer of any rule for the auth
+    /// contexts; the `UnknownSignerRejected` event names it.
+    UnknownSigner = 4027,
+    /// The account was wound down.
+    AccountClosed = 4028,
+    /// The expiry grace is at most `MAX_EXPIRY_GRACE` ledgers, and a key
+    /// rotation's grace at most the storage's max TTL.
+    GraceTooLong = 4029,
+    /// `initialize_generic` needs non-empty key data.
+    EmptyKeyData = 4030,
+    /// The account already has `MAX_TEMPLATES` rule templates.
+    TooManyTemplates = 4031,
+    /// No rule template has that name.
+    TemplateNotFound = 4032,
+    /// A management call came in while another was calling out to a
+    /// policy, token or `execute` target.
+    Reentrancy = 4033,
+}
+
 // ── CustomAccountInterface ──────────────────────────────────────────────────
 
 #[contractimpl]
 impl CustomAccountInterface for PhantomSmartAccount {
     type Signature = Signatures;
-    type Error = SmartAccountError;
+    type Error = PhantomAccountError;
 
     fn __check_auth(
         e: Env,
@@ -26,8 +139,20 @@ impl CustomAccountInterface for PhantomSmartAccount {
         signatures: Signatures,
         auth_contexts: Vec<Context>,
     ) -> Result<(), Self::Error> {
-        do_check_auth(&e, &signature_payload, &signatures, &auth_contexts)?;
-        Ok(())
+        auth::check_auth(&e, &signature_payload, &signatures, &auth_contexts)
+    }
+}
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// The `signers` that `__check_auth` would reject with `UnknownSigner`
+    /// for `auth_contexts`, to check a signatures map before submitting it.
+    pub fn unknown_signers(
+        e: Env,
+        signers: Vec<Signer>,
+        auth_contexts: Vec<Context>,
+    ) -> Vec<Signer> {
+        auth::unknown_signers(&e, &signers, &auth_contexts)
     }
 }
 
@@ -55,13 +180,18 @@ impl stellar_accounts::smart_account::SmartAccount for PhantomSmartAccount {
         signers: Vec<Signer>,
         policies: Map<Address, Val>,
     ) -> ContextRule {
-        stellar_accounts::smart_account::add_context_rule(
-            e, &context_type, &name, valid_until, &signers, &policies,
-        )
+        require_self_auth(e);
+        guarded(e, || {
+            create_rule(e, &context_type, &name, valid_until, &signers, &policies)
+        })
     }
 
     fn update_context_rule_name(e: &Env, context_rule_id: u32, name: String) -> ContextRule {
-        stellar_accounts::smart_account::update_context_rule_name(e, context_rule_id, &name)
+        require_self_auth(e);
+        let rule =
+            stellar_accounts::smart_account::update_context_rule_name(e, context_rule_id, &name);
+        storage::set_rule_name(e, context_rule_id, &name);
+        rule
     }
 
     fn update_context_rule_valid_until(
@@ -69,37 +199,82 @@ impl stellar_accounts::smart_account::SmartAccount for PhantomSmartAccount {
         context_rule_id: u32,
         valid_until: Option<u32>,
     ) -> ContextRule {
+        require_self_auth(e);
         stellar_accounts::smart_account::update_context_rule_valid_until(
             e, context_rule_id, valid_until,
         )
     }
 
     fn remove_context_rule(e: &Env, context_rule_id: u32) {
-        stellar_accounts::smart_account::remove_context_rule(e, context_rule_id)
+        require_self_auth(e);
+        guarded(e, || remove_context_rule_guarded(e, context_rule_id, false))
     }
 
     fn add_signer(e: &Env, context_rule_id: u32, signer: Signer) {
+        require_self_auth(e);
         stellar_accounts::smart_account::add_signer(e, context_rule_id, &signer)
     }
 
     fn remove_signer(e: &Env, context_rule_id: u32, signer: Signer) {
-        stellar_accounts::smart_account::remove_signer(e, context_rule_id, &signer)
+        require_self_auth(e);
+        remove_signer_guarded(e, context_rule_id, &signer, false)
     }
 
     fn add_policy(e: &Env, context_rule_id: u32, policy: Address, install_param: Val) {
-        stellar_accounts::smart_account::add_policy(e, context_rule_id, &policy, install_param)
+        require_self_auth(e);
+        // The policy's install hook runs in the middle of this mutation
+        guarded(e, || {
+            stellar_accounts::smart_account::add_policy(e, context_rule_id, &policy, install_param)
+        })
     }
 
     fn remove_policy(e: &Env, context_rule_id: u32, policy: Address) {
-        stellar_accounts::smart_account::remove_policy(e, context_rule_id, &policy)
+        require_self_auth(e);
+        guarded(e, || {
+            stellar_accounts::smart_account::remove_policy(e, context_rule_id, &policy)
+        })
     }
 }
 
+// ── Reentrancy ──────────────────────────────────────────────────────────────
+
+/// Requires the account's own auth for a management call, after
+/// `reject_reentry`.
+fn require_self_auth(e: &Env) {
+    reject_reentry(e);
+    e.current_contract_address().require_auth();
+}
+
+/// Fails with `Reentrancy` while a management call is inside `guarded`,
+/// as when a policy's install hook calls back into the account. The host
+/// already rejects a contract calling back into one on the stack; the flag
+/// keeps the account safe without relying on that. Reads are not checked,
+/// so policies may still read the account.
+fn reject_reentry(e: &Env) {
+    if storage::is_busy(e) {
+        panic_with_error!(e, PhantomAccountError::Reentrancy)
+    }
+}
+
+/// Runs `f`, the part of a management call that calls other contracts
+/// mid-mutation, with other management calls refused until it returns.
+fn guarded<T>(e: &Env, f: impl FnOnce() -> T) -> T {
+    storage::set_busy(e, true);
+    let result = f();
+    storage::set_busy(e, false);
+    result
+}
+
 // ── Initialization ──────────────────────────────────────────────────────────
 
 #[contractimpl]
 impl PhantomSmartAccount {
-    /// Call once after deploy to register the Phantom key.
+    /// Call once after deploy to register the Phantom key; later calls fail
+    /// with `AlreadyInitialized`.
+    ///
+    /// Installs two rules for the key: one scoped to `counter`, covering
+    /// all of its functions (`increment`, `increment_by`, `decrement`), and
+    /// a "self-admin" rule scoped to the account's own address.
     ///
     /// # Arguments
     /// * `verifier`   - Address of the deployed Ed25519Verifier contract
@@ -111,25 +286,1222 @@ impl PhantomSmartAccount {
         public_key: BytesN<32>,
         counter: Address,
     ) {
-        // Signer::External(verifier_address, raw_pubkey_bytes)
-        let signer = Signer::External(
-            verifier,
-            Bytes::from_slice(&e, &public_key.to_array()),
+        install_phantom_key(&e, verifier, public_key, counter);
+    }
+
+    /// `initialize` for any key type: `key_data` is stored as is in a
+    /// `Signer::External` of `verifier`, which alone decides what it must
+    /// look like (a 65-byte secp256r1 point, a WebAuthn credential, ...).
+    ///
+    /// Installs a rule named `name` scoped to `target`, and the self-admin
+    /// rule for the same key.
+    pub fn initialize_generic(
+        e: Env,
+        verifier: Address,
+        key_data: Bytes,
+        target: Address,
+        name: String,
+    ) {
+        install_key(&e, verifier, key_data, target, &name);
+    }
+
+    /// `initialize_generic` with the verifier looked up in `registry` under
+    /// `key_type` (`ed25519`, `secp256k1`, ...) rather than passed in.
+    ///
+    /// The address is resolved once and stored in the signer, so
+    /// re-registering the key type later does not move this account's
+    /// signers. An unknown key type fails the call.
+    pub fn initialize_from_registry(
+        e: Env,
+        registry: Address,
+        key_type: Symbol,
+        key_data: Bytes,
+        target: Address,
+        name: String,
+    ) {
+        let verifier: Address = e.invoke_contract(
+            &registry,
+            &symbol_short!("resolve"),
+            (key_type,).into_val(&e),
+        );
+        install_key(&e, verifier, key_data, target, &name);
+    }
+
+    /// `initialize` followed by `counter.increment(account)`, proving the new
+    /// rules work before the transaction lands.
+    ///
+    /// The call itself must be authorized by the account, which goes
+    /// through `__check_auth` against the rules just installed. If the key
+    /// or verifier is wrong that auth fails and the initialization reverts
+    /// with it.
+    pub fn initialize_and_ping(
+        e: Env,
+        verifier: Address,
+        public_key: BytesN<32>,
+        counter: Address,
+    ) -> u64 {
+        install_phantom_key(&e, verifier, public_key, counter.clone());
+
+        let account = e.current_contract_address();
+        account.require_auth();
+        e.invoke_contract(
+            &counter,
+            &symbol_short!("increment"),
+            (account,).into_val(&e),
+        )
+    }
+
+    /// `initialize` that anyone may submit, but only with the parameters the
+    /// key owner signed off on.
+    ///
+    /// `init_sig` is a Phantom signature over `init_payload(verifier,
+    /// public_key, counter)`, checked by `verifier` against `public_key`. The
+    /// payload covers the account's own address, so the signature cannot be
+    /// replayed to initialize a different account.
+    pub fn initialize_signed(
+        e: Env,
+        verifier: Address,
+        public_key: BytesN<32>,
+        counter: Address,
+        init_sig: BytesN<64>,
+    ) {
+        let payload = Self::init_payload(
+            e.clone(),
+            verifier.clone(),
+            public_key.clone(),
+            counter.clone(),
+        );
+        let sig_data = PhantomSigData {
+            prefixed_message: phantom_message(&e, &payload),
+            signature: init_sig,
+            expires_at: None,
+        };
+        let verified: bool = e.invoke_contract(
+            &verifier,
+            &symbol_short!("verify"),
+            (
+                Bytes::from(payload),
+                Bytes::from_slice(&e, &public_key.to_array()),
+                sig_data.to_xdr(&e),
+            )
+                .into_val(&e),
+        );
+        if !verified {
+            panic_with_error!(&e, PhantomAccountError::InvalidInitSignature)
+        }
+
+        install_phantom_key(&e, verifier, public_key, counter);
+    }
+
+    /// Hash the key owner signs to approve `initialize_signed` with these
+    /// parameters on this account.
+    pub fn init_payload(
+        e: Env,
+        verifier: Address,
+        public_key: BytesN<32>,
+        counter: Address,
+    ) -> BytesN<32> {
+        let params = (e.current_contract_address(), verifier, public_key, counter);
+        e.crypto().sha256(&params.to_xdr(&e)).to_bytes()
+    }
+}
+
+/// Mirror of the verifier's `Ed25519SigData`, for building its sig_data.
+#[contracttype(export = false)]
+struct PhantomSigData {
+    prefixed_message: Bytes,
+    signature: BytesN<64>,
+    expires_at: Option<u64>,
+}
+
+/// The message Phantom signs for `payload`: the auth prefix followed by the
+/// payload in lowercase hex.
+fn phantom_message(e: &Env, payload: &BytesN<32>) -> Bytes {
+    let mut hex = [0u8; hex_len(32)];
+    hex_encode(&mut hex, &payload.to_array());
+
+    let mut message = Bytes::from_slice(e, b"Stellar Smart Account Auth:\n");
+    message.extend_from_slice(&hex);
+    message
+}
+
+/// Installs the Phantom key's counter and self-admin rules on an account
+/// that has none yet.
+fn install_phantom_key(e: &Env, verifier: Address, public_key: BytesN<32>, counter: Address) {
+    install_key(
+        e,
+        verifier,
+        public_key.into(),
+        counter,
+        &String::from_str(e, "phantom-signer"),
+    );
+}
+
+/// Installs a rule named `name` for `key_data` scoped to `target`, and the
+/// self-admin rule for the same key, on an account that has none yet.
+///
+/// `target` must be neither the account (whose rule would shadow the
+/// self-admin rule) nor the verifier.
+fn install_key(e: &Env, verifier: Address, key_data: Bytes, target: Address, name: &String) {
+    if storage::is_closed(e) {
+        panic_with_error!(e, PhantomAccountError::AccountClosed)
+    }
+    // Anyone may submit the initialization, so it must not run twice.
+    if !storage::get_rule_ids(e).is_empty() {
+        panic_with_error!(e, PhantomAccountError::AlreadyInitialized)
+    }
+    if target == e.current_contract_address() {
+        panic_with_error!(e, PhantomAccountError::TargetIsAccount)
+    }
+    if target == verifier {
+        panic_with_error!(e, PhantomAccountError::TargetIsVerifier)
+    }
+    if key_data.is_empty() {
+        panic_with_error!(e, PhantomAccountError::EmptyKeyData)
+    }
+
+    // Signer::External(verifier_address, raw_pubkey_bytes)
+    let signer = Signer::External(verifier, key_data);
+
+    let signers = Vec::from_array(e, [signer]);
+    let policies: Map<Address, Val> = Map::new(e);
+
+    storage::set_storage_version(e, STORAGE_VERSION);
+
+    create_rule(
+        e,
+        &ContextRuleType::CallContract(target),
+        name,
+        None,      // no expiry for demo
+        &signers,
+        &policies,
+    );
+
+    // Same key, scoped to the account itself, so management calls
+    // (add_signer, remove_policy, ...) have a rule to authorize against.
+    create_rule(
+        e,
+        &ContextRuleType::CallContract(e.current_contract_address()),
+        &String::from_str(e, "self-admin"),
+        None,
+        &signers,
+        &policies,
+    );
+}
+
+/// Creates a rule through `stellar_accounts` and records it in the
+/// account's own rule index.
+///
+/// Rejects rules that could never authorize anything (no signers and no
+/// policies), signer lists with duplicates, and rules past the rule cap.
+fn create_rule(
+    e: &Env,
+    context_type: &ContextRuleType,
+    name: &String,
+    valid_until: Option<u32>,
+    signers: &Vec<Signer>,
+    policies: &Map<Address, Val>,
+) -> ContextRule {
+    if signers.is_empty() && policies.is_empty() {
+        panic_with_error!(e, PhantomAccountError::EmptyRule)
+    }
+    for (i, signer) in signers.iter().enumerate() {
+        if signers.first_index_of(&signer) != Some(i as u32) {
+            panic_with_error!(e, PhantomAccountError::DuplicateSigner)
+        }
+    }
+
+    if stellar_accounts::smart_account::get_context_rules_count(e) >= storage::get_max_rules(e) {
+        panic_with_error!(e, PhantomAccountError::TooManyRules)
+    }
+
+    let rule = add_context_rule(e, context_type, name, valid_until, signers, policies);
+    storage::index_rule(e, rule.id, &rule.name);
+    storage::set_rule_active(e, rule.id, true);
+    rule
+}
+
+// ── Rule cap ────────────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Caps how many context rules the account may hold. Lowering the cap
+    /// below the current count keeps the existing rules but blocks new ones.
+    pub fn set_max_rules(e: Env, max: u32) {
+        require_self_auth(&e);
+        storage::set_max_rules(&e, max);
+    }
+
+    pub fn get_max_rules(e: Env) -> u32 {
+        storage::get_max_rules(&e)
+    }
+}
+
+// ── Account config ──────────────────────────────────────────────────────────
+
+/// Id and name of a context rule.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleSummary {
+    pub id: u32,
+    pub name: String,
+}
+
+/// Overview of the account for UIs, see `get_config`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountConfig {
+    pub rule_count: u32,
+    /// In creation order.
+    pub rules: Vec<RuleSummary>,
+    /// Whether `wind_down` has run, after which the account authorizes
+    /// nothing.
+    pub closed: bool,
+    pub storage_version: u32,
+    pub nonce: u64,
+}
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// The account's rules and settings in one call. Built from the
+    /// account's own rule index, without loading any rule.
+    pub fn get_config(e: Env) -> AccountConfig {
+        let names = storage::get_rule_names(&e);
+        let mut rules = Vec::new(&e);
+        for id in storage::get_rule_ids(&e).iter() {
+            rules.push_back(RuleSummary {
+                id,
+                name: names.get(id).unwrap_or_else(|| String::from_str(&e, "")),
+            });
+        }
+
+        AccountConfig {
+            rule_count: rules.len(),
+            rules,
+            closed: storage::is_closed(&e),
+            storage_version: storage::get_storage_version(&e),
+            nonce: storage::get_nonce(&e),
+        }
+    }
+}
+
+// ── Storage migration ───────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    pub fn storage_version(e: Env) -> u32 {
+        storage::get_storage_version(&e)
+    }
+
+    /// Upgrades data written by older versions of the contract to
+    /// `STORAGE_VERSION`, one version at a time. A no-op once current.
+    ///
+    /// Permissionless: `__check_auth` refuses to run on an outdated layout,
+    /// so the account could not authorize this call itself, and each step
+    /// only rewrites existing state into its current shape.
+    pub fn migrate(e: Env) {
+        let mut version = storage::get_storage_version(&e);
+        while version < STORAGE_VERSION {
+            match version {
+                1 => storage::backfill_rule_active_flags(&e),
+                2 => storage::backfill_rule_names(&e),
+                _ => {}
+            }
+            version += 1;
+        }
+        storage::set_storage_version(&e, version);
+    }
+
+    /// Inactive rules stay installed but never authorize anything.
+    ///
+    /// Fails with `WouldBrickAccount` when no other active rule could still
+    /// authorize calls to the account itself, which every management
+    /// entrypoint (this one included) needs.
+    pub fn set_rule_active(e: Env, context_rule_id: u32, active: bool) {
+        require_self_auth(&e);
+        // Panics with ContextRuleNotFound for unknown ids.
+        stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+
+        if !active && !admin_remains(&e, context_rule_id) {
+            panic_with_error!(&e, PhantomAccountError::WouldBrickAccount)
+        }
+        storage::set_rule_active(&e, context_rule_id, active);
+    }
+
+    pub fn is_rule_active(e: Env, context_rule_id: u32) -> bool {
+        storage::is_rule_active(&e, context_rule_id)
+    }
+}
+
+/// Whether an active rule other than `context_rule_id` can still authorize
+/// calls to the account itself.
+fn admin_remains(e: &Env, context_rule_id: u32) -> bool {
+    storage::get_rule_ids(e).iter().any(|id| {
+        id != context_rule_id && storage::is_rule_active(e, id) && manages_account(e, id)
+    })
+}
+
+/// Whether `context_rule_id` can authorize calls to the account itself: a
+/// default rule, or one scoped to the account.
+fn manages_account(e: &Env, context_rule_id: u32) -> bool {
+    match stellar_accounts::smart_account::get_context_rule(e, context_rule_id).context_type {
+        ContextRuleType::Default => true,
+        ContextRuleType::CallContract(target) => target == e.current_contract_address(),
+        ContextRuleType::CreateContract(_) => false,
+    }
+}
+
+// ── Delegated signers ───────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Adds another Latch smart account as a signer of `context_rule_id`.
+    ///
+    /// The delegate signs by authorizing this account's `__check_auth` call,
+    /// so its own rules (scoped to this account's address) decide. Chains are
+    /// capped at `MAX_DELEGATION_DEPTH`; a cycle that slips past the check
+    /// (e.g. B later delegating back to A) is rejected at auth time by the
+    /// host's contract re-entry protection.
+    pub fn add_delegated_signer(e: Env, context_rule_id: u32, account: Address) {
+        require_self_auth(&e);
+        if account == e.current_contract_address() {
+            panic_with_error!(&e, PhantomAccountError::DelegationCycle)
+        }
+
+        let depth = PhantomSmartAccountClient::new(&e, &account).delegation_depth() + 1;
+        if depth > MAX_DELEGATION_DEPTH {
+            panic_with_error!(&e, PhantomAccountError::DelegationTooDeep)
+        }
+        if depth > storage::get_delegation_depth(&e) {
+            storage::set_delegation_depth(&e, depth);
+        }
+
+        stellar_accounts::smart_account::add_signer(
+            &e,
+            context_rule_id,
+            &Signer::Delegated(account),
+        )
+    }
+
+    /// Number of delegated accounts below this one in its longest chain.
+    pub fn delegation_depth(e: Env) -> u32 {
+        storage::get_delegation_depth(&e)
+    }
+}
+
+// ── Ed25519 signers ─────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Adds an Ed25519 key checked by `verifier` as a signer of
+    /// `context_rule_id`.
+    ///
+    /// `add_signer` takes key_data as `Bytes`, so a key of the wrong length
+    /// is only caught when it first fails to authorize. Here the key is
+    /// `BytesN<32>`, and anything else fails the call itself.
+    pub fn add_ed25519_signer(
+        e: Env,
+        context_rule_id: u32,
+        verifier: Address,
+        public_key: BytesN<32>,
+    ) {
+        require_self_auth(&e);
+        stellar_accounts::smart_account::add_signer(
+            &e,
+            context_rule_id,
+            &Signer::External(verifier, public_key.into()),
+        )
+    }
+}
+
+// ── Rate limits ─────────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Caps how many auth contexts `context_rule_id` may authorize per window
+    /// of `window_ledgers` ledgers.
+    pub fn set_rule_rate_limit(
+        e: Env,
+        context_rule_id: u32,
+        max_per_window: u32,
+        window_ledgers: u32,
+    ) {
+        require_self_auth(&e);
+        if max_per_window == 0 || window_ledgers == 0 {
+            panic_with_error!(&e, PhantomAccountError::InvalidRateLimit)
+        }
+        // Panics with ContextRuleNotFound for unknown ids.
+        stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+
+        storage::set_rate_limit(
+            &e,
+            context_rule_id,
+            &RateLimit {
+                max_per_window,
+                window_ledgers,
+            },
+        );
+    }
+
+    /// Restores unlimited use of `context_rule_id`.
+    pub fn remove_rule_rate_limit(e: Env, context_rule_id: u32) {
+        require_self_auth(&e);
+        storage::remove_rate_limit(&e, context_rule_id);
+    }
+
+    pub fn get_rule_rate_limit(e: Env, context_rule_id: u32) -> Option<RateLimit> {
+        storage::get_rate_limit(&e, context_rule_id)
+    }
+}
+
+// ── Thresholds ──────────────────────────────────────────────────────────────
+
+/// Published when a rule's signature threshold changes.
+#[contractevent(topics = ["threshold_updated"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ThresholdUpdated {
+    #[topic]
+    pub context_rule_id: u32,
+    pub old_threshold: u32,
+    pub new_threshold: u32,
+}
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Sets how many of `context_rule_id`'s signers must sign (the m in
+    /// m-of-n). Applies to rules without policies, which need every signer
+    /// until a threshold is set.
+    ///
+    /// Expired signers can no longer sign, so the threshold may not exceed
+    /// the number of unexpired ones.
+    pub fn update_threshold(e: Env, context_rule_id: u32, threshold: u32) {
+        require_self_auth(&e);
+        let rule = stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        let mut unexpired = 0;
+        for signer in rule.signers.iter() {
+            if !storage::is_signer_expired(&e, context_rule_id, &signer) {
+                unexpired += 1;
+            }
+        }
+        if threshold == 0 || threshold > unexpired {
+            panic_with_error!(&e, PhantomAccountError::InvalidThreshold)
+        }
+
+        let old_threshold =
+            storage::get_threshold(&e, context_rule_id).unwrap_or(rule.signers.len());
+        storage::set_threshold(&e, context_rule_id, threshold);
+        ThresholdUpdated {
+            context_rule_id,
+            old_threshold,
+            new_threshold: threshold,
+        }
+        .publish(&e);
+    }
+
+    /// Signatures `context_rule_id` needs; its signer count if no threshold
+    /// was set.
+    pub fn get_threshold(e: Env, context_rule_id: u32) -> u32 {
+        storage::get_threshold(&e, context_rule_id).unwrap_or_else(|| {
+            stellar_accounts::smart_account::get_context_rule(&e, context_rule_id)
+                .signers
+                .len()
+        })
+    }
+}
+
+// ── Amount caps ─────────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Rejects calls authorized by `context_rule_id` whose argument
+    /// `arg_index` (an `i128` amount) is above `max_amount`.
+    pub fn set_call_amount_cap(e: Env, context_rule_id: u32, max_amount: i128, arg_index: u32) {
+        require_self_auth(&e);
+        // Panics with ContextRuleNotFound for unknown ids.
+        stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+
+        storage::set_amount_cap(
+            &e,
+            context_rule_id,
+            &AmountCap {
+                max_amount,
+                arg_index,
+            },
         );
+    }
+
+    pub fn remove_call_amount_cap(e: Env, context_rule_id: u32) {
+        require_self_auth(&e);
+        storage::remove_amount_cap(&e, context_rule_id);
+    }
+
+    pub fn get_call_amount_cap(e: Env, context_rule_id: u32) -> Option<AmountCap> {
+        storage::get_amount_cap(&e, context_rule_id)
+    }
+}
+
+// ── Function filters ────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Restricts `context_rule_id` to calls of the functions in `fns`. An
+    /// empty list lifts the restriction.
+    pub fn set_allowed_functions(e: Env, context_rule_id: u32, fns: Vec<Symbol>) {
+        require_self_auth(&e);
+        // Panics with ContextRuleNotFound for unknown ids.
+        stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        storage::set_allowed_functions(&e, context_rule_id, &fns);
+    }
+
+    /// Functions `context_rule_id` is restricted to; empty if unrestricted.
+    pub fn get_allowed_functions(e: Env, context_rule_id: u32) -> Vec<Symbol> {
+        storage::get_allowed_functions(&e, context_rule_id)
+    }
+}
+
+// ── Auth events ─────────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Turns the `AuthEvent` published by `__check_auth` on or off. On by
+    /// default.
+    pub fn set_auth_events(e: Env, enabled: bool) {
+        require_self_auth(&e);
+        storage::set_auth_events_enabled(&e, enabled);
+    }
+
+    pub fn auth_events_enabled(e: Env) -> bool {
+        storage::auth_events_enabled(&e)
+    }
+
+    /// Turns the `AuthFailed` event of a failing `__check_auth` on or off.
+    /// Off by default.
+    pub fn set_debug_events(e: Env, enabled: bool) {
+        require_self_auth(&e);
+        storage::set_debug_events_enabled(&e, enabled);
+    }
+
+    pub fn debug_events_enabled(e: Env) -> bool {
+        storage::debug_events_enabled(&e)
+    }
+}
+
+// ── Expiry grace ────────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Lets a rule keep authorizing for `ledgers` ledgers after its
+    /// `valid_until`, so a transaction simulated just before expiry still
+    /// lands. Each use inside the grace publishes `GraceUsed`. 0, the
+    /// default, expires rules strictly.
+    pub fn set_expiry_grace(e: Env, ledgers: u32) {
+        require_self_auth(&e);
+        if ledgers > MAX_EXPIRY_GRACE {
+            panic_with_error!(&e, PhantomAccountError::GraceTooLong)
+        }
+        storage::set_expiry_grace(&e, ledgers);
+    }
+
+    pub fn get_expiry_grace(e: Env) -> u32 {
+        storage::get_expiry_grace(&e)
+    }
+}
+
+// ── Rule stats ──────────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// How often `context_rule_id` authorized a context, and when last.
+    pub fn get_rule_stats(e: Env, context_rule_id: u32) -> RuleStats {
+        storage::get_rule_stats(&e, context_rule_id)
+    }
+}
+
+// ── Rule priority ───────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Sets the order in which `context_rule_id` is tried among the rules that
+    /// match a context: higher priority first, ties by lower rule id.
+    pub fn set_rule_priority(e: Env, context_rule_id: u32, priority: u32) {
+        require_self_auth(&e);
+        // Panics with ContextRuleNotFound for unknown ids.
+        stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        storage::set_rule_priority(&e, context_rule_id, priority);
+    }
+
+    pub fn get_rule_priority(e: Env, context_rule_id: u32) -> u32 {
+        storage::get_rule_priority(&e, context_rule_id)
+    }
+}
+
+// ── Signer expiry ───────────────────────────────────────────────────────────
+
+/// A signer of a context rule with its account-side settings.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignerInfo {
+    pub signer: Signer,
+    /// Set with `label_signer`.
+    pub label: Option<String>,
+    /// Last ledger at which the signer counts; `None` if it does not expire.
+    pub valid_until: Option<u32>,
+    /// How much the signer counts towards the rule's threshold.
+    pub weight: u32,
+    /// The keys an external signer's key_data lists when any one of them
+    /// may sign for it; `None` for a single key or another kind of signer.
+    pub keys: Option<Vec<BytesN<32>>>,
+}
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Expires `signer` on `context_rule_id` after ledger `valid_until`,
+    /// independently of the rule's own expiry. An expired signer's
+    /// signature no longer counts, and the rule no longer waits for it.
+    /// `None` clears the expiry.
+    pub fn set_signer_expiry(
+        e: Env,
+        context_rule_id: u32,
+        signer: Signer,
+        valid_until: Option<u32>,
+    ) {
+        require_self_auth(&e);
+        let rule = stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        if !rule.signers.contains(&signer) {
+            panic_with_error!(&e, PhantomAccountError::SignerNotFound)
+        }
+        storage::set_signer_expiry(&e, context_rule_id, &signer, valid_until);
+    }
+
+    /// Same as `get_signers_detailed`.
+    pub fn get_signers(e: Env, context_rule_id: u32) -> Vec<SignerInfo> {
+        Self::get_signers_detailed(e, context_rule_id)
+    }
+}
+
+// ── Fallback verifiers ──────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Lets `fallback` verify `signer`'s signatures when the signer's own
+    /// verifier rejects them or traps, e.g. after it was archived. One
+    /// fallback per signer; it applies wherever the signer is used.
+    pub fn add_fallback_verifier(e: Env, context_rule_id: u32, signer: Signer, fallback: Address) {
+        require_self_auth(&e);
+        if !matches!(signer, Signer::External(..)) {
+            panic_with_error!(&e, PhantomAccountError::NotExternalSigner)
+        }
+        let rule = stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        if !rule.signers.contains(&signer) {
+            panic_with_error!(&e, PhantomAccountError::SignerNotFound)
+        }
+        if storage::get_fallback_verifier(&e, &signer).is_some() {
+            panic_with_error!(&e, PhantomAccountError::FallbackVerifierExists)
+        }
+        storage::set_fallback_verifier(&e, &signer, &fallback);
+    }
+
+    pub fn remove_fallback_verifier(e: Env, context_rule_id: u32, signer: Signer) {
+        require_self_auth(&e);
+        let rule = stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        if !rule.signers.contains(&signer) {
+            panic_with_error!(&e, PhantomAccountError::SignerNotFound)
+        }
+        storage::remove_fallback_verifier(&e, &signer);
+    }
+
+    pub fn get_fallback_verifier(e: Env, signer: Signer) -> Option<Address> {
+        storage::get_fallback_verifier(&e, &signer)
+    }
+}
 
-        let signers = Vec::from_array(&e, [signer]);
-        let policies: Map<Address, Val> = Map::new(&e);
+// ── Signer labels ───────────────────────────────────────────────────────────
 
-        add_context_rule(
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Names `signer` on `context_rule_id` for display, in at most
+    /// `MAX_LABEL_LEN` bytes. The label goes away with the signer.
+    pub fn label_signer(e: Env, context_rule_id: u32, signer: Signer, label: String) {
+        require_self_auth(&e);
+        if label.len() > MAX_LABEL_LEN {
+            panic_with_error!(&e, PhantomAccountError::LabelTooLong)
+        }
+        let rule = stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        if !rule.signers.contains(&signer) {
+            panic_with_error!(&e, PhantomAccountError::SignerNotFound)
+        }
+        storage::set_signer_label(&e, context_rule_id, &signer, &label);
+    }
+
+    pub fn get_signers_detailed(e: Env, context_rule_id: u32) -> Vec<SignerInfo> {
+        let rule = stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        let mut signers = Vec::new(&e);
+        for signer in rule.signers.iter() {
+            signers.push_back(SignerInfo {
+                label: storage::get_signer_label(&e, context_rule_id, &signer),
+                valid_until: storage::get_signer_expiry(&e, context_rule_id, &signer),
+                // Every signer fills one slot of the threshold.
+                weight: 1,
+                keys: any_of_keys(&e, &signer),
+                signer,
+            });
+        }
+        signers
+    }
+}
+
+// ── Key rotation ────────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Replaces `old_key` with `new_key` on `context_rule_id` immediately.
+    /// A `new_key` holding a key another signer of the rule already holds,
+    /// bare or in an any-of list, is refused as a duplicate.
+    pub fn rotate_key(e: Env, context_rule_id: u32, old_key: Signer, new_key: Signer) {
+        require_self_auth(&e);
+        start_rotation(&e, context_rule_id, &old_key, &new_key);
+        stellar_accounts::smart_account::remove_signer(&e, context_rule_id, &old_key);
+        storage::remove_signer_data(&e, context_rule_id, &old_key);
+    }
+
+    /// Adds `new_key` now and keeps `old_key` valid for another
+    /// `grace_ledgers` ledgers, during which either key fills the old key's
+    /// place in the rule. Once the grace period is over the old key is
+    /// purged by the next auth attempt or by `finalize_rotation`. Fails with
+    /// `GraceTooLong` for a grace longer than the storage's max TTL.
+    pub fn rotate_key_with_grace(
+        e: Env,
+        context_rule_id: u32,
+        old_key: Signer,
+        new_key: Signer,
+        grace_ledgers: u32,
+    ) {
+        require_self_auth(&e);
+        finalize_expired_rotation(&e, context_rule_id);
+        if storage::get_key_rotation(&e, context_rule_id).is_some() {
+            panic_with_error!(&e, PhantomAccountError::RotationPending)
+        }
+        if grace_ledgers == 0 {
+            return Self::rotate_key(e, context_rule_id, old_key, new_key);
+        }
+        let Some(old_key_until) = e
+            .ledger()
+            .sequence()
+            .checked_add(grace_ledgers)
+            .filter(|_| grace_ledgers <= e.storage().max_ttl())
+        else {
+            panic_with_error!(&e, PhantomAccountError::GraceTooLong)
+        };
+        start_rotation(&e, context_rule_id, &old_key, &new_key);
+
+        storage::set_signer_expiry(&e, context_rule_id, &old_key, Some(old_key_until));
+        storage::set_key_rotation(
             &e,
-            &ContextRuleType::CallContract(counter),
-            &String::from_str(&e, "phantom-signer"),
-            None,      // no expiry for demo
-            &signers,
-            &policies,
+            context_rule_id,
+            &KeyRotation {
+                old_key,
+                new_key,
+            },
+        );
+    }
+
+    /// Purges the old key of a rotation whose grace period is over.
+    /// Permissionless; returns `false` (and changes nothing) when there is no
+    /// such rotation.
+    pub fn finalize_rotation(e: Env, context_rule_id: u32) -> bool {
+        finalize_expired_rotation(&e, context_rule_id)
+    }
+
+    pub fn get_key_rotation(e: Env, context_rule_id: u32) -> Option<KeyRotation> {
+        storage::get_key_rotation(&e, context_rule_id)
+    }
+}
+
+fn start_rotation(e: &Env, context_rule_id: u32, old_key: &Signer, new_key: &Signer) {
+    let rule = stellar_accounts::smart_account::get_context_rule(e, context_rule_id);
+    if !rule.signers.contains(old_key) {
+        panic_with_error!(e, PhantomAccountError::SignerNotFound)
+    }
+    if rule.signers.contains(new_key) {
+        panic_with_error!(e, PhantomAccountError::DuplicateSigner)
+    }
+    // A key listed in two signers would fill two slots of the threshold
+    for signer in rule.signers.iter() {
+        if &signer != old_key && shares_key(e, &signer, new_key) {
+            panic_with_error!(e, PhantomAccountError::DuplicateSigner)
+        }
+    }
+    stellar_accounts::smart_account::add_signer(e, context_rule_id, new_key);
+}
+
+/// The keys of an external signer whose key_data is a list of 32-byte keys
+/// rather than one key.
+fn any_of_keys(e: &Env, signer: &Signer) -> Option<Vec<BytesN<32>>> {
+    match signer {
+        Signer::External(_, key_data) if is_key_list(e, key_data) => {
+            Vec::<BytesN<32>>::from_xdr(e, key_data).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Whether `key_data` is the XDR of a `Vec<BytesN<32>>`, checked byte by
+/// byte first: `from_xdr` traps on bytes that are not XDR, and the key_data
+/// of every other verifier (secp256k1, secp256r1, WebAuthn, Ethereum) is not.
+fn is_key_list(e: &Env, key_data: &Bytes) -> bool {
+    // ScVal::Vec, Some(_), then each item is ScVal::Bytes of length 32
+    const LIST: [u8; 8] = [0, 0, 0, 16, 0, 0, 0, 1];
+    const KEY: [u8; 8] = [0, 0, 0, 13, 0, 0, 0, 32];
+    const KEY_LEN: u32 = KEY.len() as u32 + 32;
+
+    let len = key_data.len();
+    if len < 12
+        || (len - 12) % KEY_LEN != 0
+        || key_data.slice(..8) != Bytes::from_array(e, &LIST)
+    {
+        return false;
+    }
+    let count = (len - 12) / KEY_LEN;
+    if key_data.slice(8..12) != Bytes::from_array(e, &count.to_be_bytes()) {
+        return false;
+    }
+    let key = Bytes::from_array(e, &KEY);
+    (0..count).all(|i| {
+        let at = 12 + i * KEY_LEN;
+        key_data.slice(at..at + 8) == key
+    })
+}
+
+/// Whether `a` and `b` use the same verifier and hold a key in common,
+/// each key bare or in a list.
+fn shares_key(e: &Env, a: &Signer, b: &Signer) -> bool {
+    let (Signer::External(verifier_a, key_a), Signer::External(verifier_b, key_b)) = (a, b) else {
+        return false;
+    };
+    if verifier_a != verifier_b {
+        return false;
+    }
+    let held = |signer: &Signer, key_data: &Bytes| {
+        any_of_keys(e, signer).or_else(|| {
+            BytesN::<32>::try_from(key_data.clone())
+                .ok()
+                .map(|key| Vec::from_array(e, [key]))
+        })
+    };
+    let (Some(keys_a), Some(keys_b)) = (held(a, key_a), held(b, key_b)) else {
+        return false;
+    };
+    keys_a.iter().any(|key| keys_b.contains(&key))
+}
+
+/// Removes the old key of `context_rule_id`'s rotation once it expired.
+pub(crate) fn finalize_expired_rotation(e: &Env, context_rule_id: u32) -> bool {
+    let Some(rotation) = storage::get_key_rotation(e, context_rule_id) else {
+        return false;
+    };
+    if !storage::is_signer_expired(e, context_rule_id, &rotation.old_key) {
+        return false;
+    }
+
+    stellar_accounts::smart_account::remove_signer(e, context_rule_id, &rotation.old_key);
+    storage::remove_signer_data(e, context_rule_id, &rotation.old_key);
+    true
+}
+
+// ── Rule templates ──────────────────────────────────────────────────────────
+
+/// What `instantiate_template` takes per instance.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TemplateOverrides {
+    pub name: String,
+    pub signers: Vec<Signer>,
+}
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Saves `template` under `name`, replacing any template of that name.
+    pub fn save_template(e: Env, name: Symbol, template: RuleTemplate) {
+        require_self_auth(&e);
+        let mut templates = storage::get_templates(&e);
+        if !templates.contains_key(name.clone()) && templates.len() >= MAX_TEMPLATES {
+            panic_with_error!(&e, PhantomAccountError::TooManyTemplates)
+        }
+        templates.set(name, template);
+        storage::set_templates(&e, &templates);
+    }
+
+    pub fn delete_template(e: Env, name: Symbol) {
+        require_self_auth(&e);
+        let mut templates = storage::get_templates(&e);
+        if templates.remove(name).is_none() {
+            panic_with_error!(&e, PhantomAccountError::TemplateNotFound)
+        }
+        storage::set_templates(&e, &templates);
+    }
+
+    pub fn get_template(e: Env, name: Symbol) -> Option<RuleTemplate> {
+        storage::get_templates(&e).get(name)
+    }
+
+    /// Creates a rule shaped by template `name`, with the name and signers
+    /// from `overrides`. The rule expires `valid_for` ledgers from now.
+    pub fn instantiate_template(e: Env, name: Symbol, overrides: TemplateOverrides) -> ContextRule {
+        require_self_auth(&e);
+        let Some(template) = storage::get_templates(&e).get(name) else {
+            panic_with_error!(&e, PhantomAccountError::TemplateNotFound)
+        };
+        let valid_until = template
+            .valid_for
+            .map(|ledgers| e.ledger().sequence() + ledgers);
+        guarded(&e, || {
+            create_rule(
+                &e,
+                &template.context_type,
+                &overrides.name,
+                valid_until,
+                &overrides.signers,
+                &template.policies,
+            )
+        })
+    }
+}
+
+// ── Verifier replacement ────────────────────────────────────────────────────
+
+/// Published for each signer `replace_verifier` moved to the new verifier.
+#[contractevent(topics = ["verifier_replaced"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifierReplaced {
+    #[topic]
+    pub context_rule_id: u32,
+    pub old_signer: Signer,
+    pub new_signer: Signer,
+}
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Points every signer of `context_rule_id` that is checked by
+    /// `old_verifier` at `new_verifier` instead, e.g. after deploying a new
+    /// version of the verifier. Key data, labels, expiries, fallback
+    /// verifiers and pending rotations carry over; signers of other
+    /// verifiers are left alone.
+    pub fn replace_verifier(
+        e: Env,
+        context_rule_id: u32,
+        old_verifier: Address,
+        new_verifier: Address,
+    ) {
+        require_self_auth(&e);
+        let rule = stellar_accounts::smart_account::get_context_rule(&e, context_rule_id);
+        if old_verifier == new_verifier {
+            return;
+        }
+
+        for old_signer in rule.signers.iter() {
+            let Signer::External(verifier, key_data) = &old_signer else {
+                continue;
+            };
+            if *verifier != old_verifier {
+                continue;
+            }
+            let new_signer = Signer::External(new_verifier.clone(), key_data.clone());
+            if rule.signers.contains(&new_signer) {
+                panic_with_error!(&e, PhantomAccountError::DuplicateSigner)
+            }
+
+            // Add before removing so the rule never drops below its threshold.
+            stellar_accounts::smart_account::add_signer(&e, context_rule_id, &new_signer);
+            storage::move_signer_data(&e, context_rule_id, &old_signer, &new_signer);
+            stellar_accounts::smart_account::remove_signer(&e, context_rule_id, &old_signer);
+            VerifierReplaced {
+                context_rule_id,
+                old_signer,
+                new_signer,
+            }
+            .publish(&e);
+        }
+    }
+}
+
+// ── Relayed execution ───────────────────────────────────────────────────────
+
+/// One call of an `execute_batch`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CallSpec {
+    pub target: Address,
+    pub func: Symbol,
+    pub args: Vec<Val>,
+}
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Calls `target.func(args)` as the account and returns its result.
+    ///
+    /// The account authorizes the `(target, func, args, nonce)` tuple, so
+    /// whoever submits the transaction (and pays its fees) cannot change the
+    /// call. `nonce` must equal `get_nonce`, which then moves on by one, so
+    /// the same authorization cannot run twice.
+    pub fn execute(e: Env, target: Address, func: Symbol, args: Vec<Val>, nonce: u64) -> Val {
+        let account = e.current_contract_address();
+        reject_self_invocation(&e, &target);
+        reject_reentry(&e);
+        account.require_auth_for_args(
+            (target.clone(), func.clone(), args.clone(), nonce).into_val(&e),
         );
+        consume_nonce(&e, nonce);
+
+        guarded(&e, || e.invoke_contract(&target, &func, args))
+    }
+
+    /// Runs `calls` in order under one authorization and returns their
+    /// results. A trapping call reverts the whole batch. Uses the same nonce
+    /// as `execute`.
+    pub fn execute_batch(e: Env, calls: Vec<CallSpec>, nonce: u64) -> Vec<Val> {
+        if calls.is_empty() {
+            panic_with_error!(&e, PhantomAccountError::EmptyBatch)
+        }
+        if calls.len() > MAX_BATCH_LEN {
+            panic_with_error!(&e, PhantomAccountError::BatchTooLong)
+        }
+        for call in calls.iter() {
+            reject_self_invocation(&e, &call.target);
+        }
+        require_self_auth(&e);
+        consume_nonce(&e, nonce);
+
+        guarded(&e, || {
+            let mut results = Vec::new(&e);
+            for call in calls.iter() {
+                results.push_back(e.invoke_contract::<Val>(&call.target, &call.func, call.args));
+            }
+            results
+        })
+    }
+
+    pub fn get_nonce(e: Env) -> u64 {
+        storage::get_nonce(&e)
+    }
+}
+
+fn consume_nonce(e: &Env, nonce: u64) {
+    let expected = storage::get_nonce(e);
+    if nonce != expected {
+        panic_with_error!(e, PhantomAccountError::BadNonce)
     }
+    storage::set_nonce(e, expected + 1);
+}
+
+fn reject_self_invocation(e: &Env, target: &Address) {
+    if *target == e.current_contract_address() {
+        panic_with_error!(e, PhantomAccountError::SelfInvocation)
+    }
+}
+
+// ── Wind-down ───────────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// Closes the account for good: sends the full balance of each of
+    /// `tokens` to `sweep_to`, removes every context rule and makes
+    /// `__check_auth` (and so every management call) fail from then on.
+    /// A failing transfer aborts the whole wind-down.
+    pub fn wind_down(e: Env, sweep_to: Address, tokens: Vec<Address>) {
+        let account = e.current_contract_address();
+        require_self_auth(&e);
+
+        guarded(&e, || {
+            for token in tokens.iter() {
+                let token = TokenClient::new(&e, &token);
+                let balance = token.balance(&account);
+                if balance > 0 {
+                    token.transfer(&account, &sweep_to, &balance);
+                }
+            }
+
+            for rule_id in storage::get_rule_ids(&e).iter() {
+                let rule = stellar_accounts::smart_account::get_context_rule(&e, rule_id);
+                stellar_accounts::smart_account::remove_context_rule(&e, rule_id);
+                storage::remove_rule_data(&e, &rule);
+            }
+        });
+        storage::set_closed(&e);
+    }
+
+    pub fn is_closed(e: Env) -> bool {
+        storage::is_closed(&e)
+    }
+}
+
+// ── Guarded removal ─────────────────────────────────────────────────────────
+
+#[contractimpl]
+impl PhantomSmartAccount {
+    /// `remove_signer` that can override the `WouldBrickAccount` guard.
+    ///
+    /// `stellar_accounts` does not keep rules with neither signers nor
+    /// policies, so force-removing the last signer of a policy-less rule
+    /// removes the rule itself.
+    pub fn remove_signer_with_force(e: Env, context_rule_id: u32, signer: Signer, force: bool) {
+        require_self_auth(&e);
+        remove_signer_guarded(&e, context_rule_id, &signer, force)
+    }
+
+    /// `remove_context_rule` that can override the `WouldBrickAccount` guard,
+    /// for intentionally destroying the account.
+    pub fn remove_context_rule_with_force(e: Env, context_rule_id: u32, force: bool) {
+        require_self_auth(&e);
+        guarded(&e, || {
+            remove_context_rule_guarded(&e, context_rule_id, force)
+        })
+    }
+}
+
+/// Removing the last active rule that can call the account itself, the final
+/// rule included, leaves nothing that can ever manage the account.
+fn remove_context_rule_guarded(e: &Env, context_rule_id: u32, force: bool) {
+    if !force && !admin_remains(e, context_rule_id) {
+        panic_with_error!(e, PhantomAccountError::WouldBrickAccount)
+    }
+    let rule = stellar_accounts::smart_account::get_context_rule(e, context_rule_id);
+    stellar_accounts::smart_account::remove_context_rule(e, context_rule_id);
+    storage::remove_rule_data(e, &rule);
+}
+
+/// Removing the only signer of a rule without policies makes the rule dead.
+fn remove_signer_guarded(e: &Env, context_rule_id: u32, signer: &Signer, force: bool) {
+    let rule = stellar_accounts::smart_account::get_context_rule(e, context_rule_id);
+    let last_signer =
+        rule.policies.is_empty() && rule.signers.len() == 1 && rule.signers.contains(signer);
+
+    if !last_signer {
+        // Lower the threshold first rather than leave it unreachable.
+        let threshold = storage::get_threshold(e, context_rule_id);
+        if threshold.is_some_and(|threshold| threshold >= rule.signers.len()) {
+            panic_with_error!(e, PhantomAccountError::InvalidThreshold)
+        }
+        stellar_accounts::smart_account::remove_signer(e, context_rule_id, signer);
+        storage::remove_signer_data(e, context_rule_id, signer);
+        return;
+    }
+    if !force {
+        panic_with_error!(e, PhantomAccountError::WouldBrickAccount)
+    }
+    remove_context_rule_guarded(e, context_rule_id, true)
 }
 
 #[cfg(test)]
 mod test;
+#[cfg(test)]
+mod test_access;
+#[cfg(test)]
+mod test_budget;
+#[cfg(test)]
+mod test_e2e;
+#[cfg(test)]
+mod test_layout;
+#[cfg(test)]
+mod test_rule_model;
+#[cfg(test)]
+mod testutils;
@@ -0,0 +1,571 @@
+//! Account state kept next to the `stellar_accounts` context rules.
+use soroban_sdk::{contracttype, Address, Env, Map, String, Symbol, Val, Vec};
+use stellar_accounts::smart_account::{get_context_rule, ContextRule, ContextRuleType, Signer};
+
+use crate::DEFAULT_MAX_RULES;
+
+#[contracttype]
+#[derive(Clone)]
+pub enum PhantomStorageKey {
+    /// Longest chain of delegated accounts below this account.
+    DelegationDepth,
+    /// `RateLimit` of a context rule.
+    RateLimit(u32),
+    /// Usage of a context rule in its current rate-limit window.
+    RateWindow(u32),
+    /// Layout version of this data, see `STORAGE_VERSION`.
+    StorageVersion,
+    /// Ids of all context rules, in creation order.
+    RuleIds,
+    /// Names of all context rules by id (since version 3).
+    RuleNames,
+    /// Whether a context rule may authorize (since version 2).
+    RuleActive(u32),
+    /// Last ledger at which a signer of a rule counts.
+    SignerExpiry(u32, Signer),
+    /// Display name of a signer of a rule.
+    SignerLabel(u32, Signer),
+    /// `KeyRotation` of a rule still in its grace period.
+    KeyRotation(u32),
+    /// Evaluation priority of a rule; higher goes first.
+    RulePriority(u32),
+    /// `AmountCap` checked against calls a rule authorizes.
+    AmountCap(u32),
+    /// Function names a rule is restricted to.
+    AllowedFunctions(u32),
+    /// Nonce the next `execute` / `execute_batch` must carry.
+    Nonce,
+    /// Signatures a policy-less rule needs, when not all of them.
+    Threshold(u32),
+    /// `RuleStats` of a rule.
+    RuleStats(u32),
+    /// Most context rules the account may hold.
+    MaxRules,
+    /// Set when `set_auth_events` turned the auth event off.
+    AuthEventsDisabled,
+    /// Verifier tried when an external signer's own verifier rejects.
+    FallbackVerifier(Signer),
+    /// Set for good by `wind_down`.
+    Closed,
+    /// Ledgers a rule keeps authorizing past its `valid_until`.
+    ExpiryGrace,
+    /// `RuleTemplate`s by name.
+    Templates,
+    /// Set when `set_debug_events` turned the `AuthFailed` event on.
+    DebugEvents,
+    /// Set while a management call is calling other contracts.
+    Busy,
+}
+
+/// A rotation from `old_key` to `new_key`; the old key's expiry ends it.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyRotation {
+    pub old_key: Signer,
+    pub new_key: Signer,
+}
+
+/// At most `max_per_window` uses of a rule per `window_ledgers` ledgers.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    pub max_per_window: u32,
+    pub window_ledgers: u32,
+}
+
+/// Largest amount a rule may authorize per call, read from argument
+/// `arg_index` of the called function.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AmountCap {
+    pub max_amount: i128,
+    pub arg_index: u32,
+}
+
+/// How often, and when last, a rule authorized a context.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RuleStats {
+    pub use_count: u64,
+    pub last_used_ledger: u32,
+}
+
+/// Shape of a rule that `instantiate_template` creates again and again; the
+/// signers and name are given per instance.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleTemplate {
+    pub context_type: ContextRuleType,
+    /// Ledgers from instantiation until the rule expires; `None` for no
+    /// expiry.
+    pub valid_for: Option<u32>,
+    /// Policies with their install params.
+    pub policies: Map<Address, Val>,
+}
+
+/// Uses counted in window `index` (`ledger sequence / window_ledgers`).
+///
+/// One entry per rule, overwritten when a new window starts.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RateWindow {
+    pub index: u32,
+    pub count: u32,
+}
+
+pub fn get_delegation_depth(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&PhantomStorageKey::DelegationDepth)
+        .unwrap_or(0)
+}
+
+pub fn set_delegation_depth(e: &Env, depth: u32) {
+    e.storage()
+        .instance()
+        .set(&PhantomStorageKey::DelegationDepth, &depth);
+}
+
+pub fn get_rate_limit(e: &Env, rule_id: u32) -> Option<RateLimit> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::RateLimit(rule_id))
+}
+
+pub fn set_rate_limit(e: &Env, rule_id: u32, limit: &RateLimit) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::RateLimit(rule_id), limit);
+}
+
+pub fn remove_rate_limit(e: &Env, rule_id: u32) {
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::RateLimit(rule_id));
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::RateWindow(rule_id));
+}
+
+pub fn get_amount_cap(e: &Env, rule_id: u32) -> Option<AmountCap> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::AmountCap(rule_id))
+}
+
+pub fn set_amount_cap(e: &Env, rule_id: u32, cap: &AmountCap) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::AmountCap(rule_id), cap);
+}
+
+pub fn remove_amount_cap(e: &Env, rule_id: u32) {
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::AmountCap(rule_id));
+}
+
+/// Empty when the rule may call any function.
+pub fn get_allowed_functions(e: &Env, rule_id: u32) -> Vec<Symbol> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::AllowedFunctions(rule_id))
+        .unwrap_or_else(|| Vec::new(e))
+}
+
+pub fn set_allowed_functions(e: &Env, rule_id: u32, fns: &Vec<Symbol>) {
+    let key = PhantomStorageKey::AllowedFunctions(rule_id);
+    if fns.is_empty() {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, fns);
+    }
+}
+
+/// `None` means every signer of the rule is needed.
+pub fn get_threshold(e: &Env, rule_id: u32) -> Option<u32> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::Threshold(rule_id))
+}
+
+pub fn set_threshold(e: &Env, rule_id: u32, threshold: u32) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::Threshold(rule_id), &threshold);
+}
+
+pub fn get_rule_stats(e: &Env, rule_id: u32) -> RuleStats {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::RuleStats(rule_id))
+        .unwrap_or_default()
+}
+
+pub fn set_rule_stats(e: &Env, rule_id: u32, stats: &RuleStats) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::RuleStats(rule_id), stats);
+}
+
+pub fn get_nonce(e: &Env) -> u64 {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::Nonce)
+        .unwrap_or(0)
+}
+
+pub fn set_nonce(e: &Env, nonce: u64) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::Nonce, &nonce);
+}
+
+pub fn get_rate_window(e: &Env, rule_id: u32) -> Option<RateWindow> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::RateWindow(rule_id))
+}
+
+pub fn set_rate_window(e: &Env, rule_id: u32, window: &RateWindow) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::RateWindow(rule_id), window);
+}
+
+/// Accounts from before versioning count as version 1.
+pub fn get_storage_version(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&PhantomStorageKey::StorageVersion)
+        .unwrap_or(1)
+}
+
+pub fn set_storage_version(e: &Env, version: u32) {
+    e.storage()
+        .instance()
+        .set(&PhantomStorageKey::StorageVersion, &version);
+}
+
+pub fn get_max_rules(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&PhantomStorageKey::MaxRules)
+        .unwrap_or(DEFAULT_MAX_RULES)
+}
+
+pub fn set_max_rules(e: &Env, max: u32) {
+    e.storage()
+        .instance()
+        .set(&PhantomStorageKey::MaxRules, &max);
+}
+
+pub fn auth_events_enabled(e: &Env) -> bool {
+    !e.storage()
+        .instance()
+        .has(&PhantomStorageKey::AuthEventsDisabled)
+}
+
+pub fn set_auth_events_enabled(e: &Env, enabled: bool) {
+    if enabled {
+        e.storage()
+            .instance()
+            .remove(&PhantomStorageKey::AuthEventsDisabled);
+    } else {
+        e.storage()
+            .instance()
+            .set(&PhantomStorageKey::AuthEventsDisabled, &true);
+    }
+}
+
+pub fn debug_events_enabled(e: &Env) -> bool {
+    e.storage().instance().has(&PhantomStorageKey::DebugEvents)
+}
+
+pub fn set_debug_events_enabled(e: &Env, enabled: bool) {
+    if enabled {
+        e.storage()
+            .instance()
+            .set(&PhantomStorageKey::DebugEvents, &true);
+    } else {
+        e.storage()
+            .instance()
+            .remove(&PhantomStorageKey::DebugEvents);
+    }
+}
+
+pub fn get_expiry_grace(e: &Env) -> u32 {
+    e.storage()
+        .instance()
+        .get(&PhantomStorageKey::ExpiryGrace)
+        .unwrap_or(0)
+}
+
+pub fn set_expiry_grace(e: &Env, ledgers: u32) {
+    if ledgers == 0 {
+        e.storage()
+            .instance()
+            .remove(&PhantomStorageKey::ExpiryGrace);
+    } else {
+        e.storage()
+            .instance()
+            .set(&PhantomStorageKey::ExpiryGrace, &ledgers);
+    }
+}
+
+pub fn get_templates(e: &Env) -> Map<Symbol, RuleTemplate> {
+    e.storage()
+        .instance()
+        .get(&PhantomStorageKey::Templates)
+        .unwrap_or_else(|| Map::new(e))
+}
+
+pub fn set_templates(e: &Env, templates: &Map<Symbol, RuleTemplate>) {
+    e.storage()
+        .instance()
+        .set(&PhantomStorageKey::Templates, templates);
+}
+
+pub fn is_closed(e: &Env) -> bool {
+    e.storage().instance().has(&PhantomStorageKey::Closed)
+}
+
+pub fn set_closed(e: &Env) {
+    e.storage()
+        .instance()
+        .set(&PhantomStorageKey::Closed, &true);
+}
+
+pub fn is_busy(e: &Env) -> bool {
+    e.storage().instance().has(&PhantomStorageKey::Busy)
+}
+
+pub fn set_busy(e: &Env, busy: bool) {
+    if busy {
+        e.storage().instance().set(&PhantomStorageKey::Busy, &true);
+    } else {
+        e.storage().instance().remove(&PhantomStorageKey::Busy);
+    }
+}
+
+pub fn get_rule_ids(e: &Env) -> Vec<u32> {
+    e.storage()
+        .instance()
+        .get(&PhantomStorageKey::RuleIds)
+        .unwrap_or_else(|| Vec::new(e))
+}
+
+/// Adds a new rule to the rule index.
+pub fn index_rule(e: &Env, rule_id: u32, name: &String) {
+    let mut ids = get_rule_ids(e);
+    ids.push_back(rule_id);
+    e.storage()
+        .instance()
+        .set(&PhantomStorageKey::RuleIds, &ids);
+    set_rule_name(e, rule_id, name);
+}
+
+fn unindex_rule(e: &Env, rule_id: u32) {
+    let mut ids = get_rule_ids(e);
+    if let Some(index) = ids.first_index_of(rule_id) {
+        ids.remove(index);
+        e.storage()
+            .instance()
+            .set(&PhantomStorageKey::RuleIds, &ids);
+    }
+
+    let mut names = get_rule_names(e);
+    names.remove(rule_id);
+    e.storage()
+        .instance()
+        .set(&PhantomStorageKey::RuleNames, &names);
+}
+
+pub fn get_rule_names(e: &Env) -> Map<u32, String> {
+    e.storage()
+        .instance()
+        .get(&PhantomStorageKey::RuleNames)
+        .unwrap_or_else(|| Map::new(e))
+}
+
+pub fn set_rule_name(e: &Env, rule_id: u32, name: &String) {
+    let mut names = get_rule_names(e);
+    names.set(rule_id, name.clone());
+    e.storage()
+        .instance()
+        .set(&PhantomStorageKey::RuleNames, &names);
+}
+
+/// Rules without a flag (version 1 data) are active.
+pub fn is_rule_active(e: &Env, rule_id: u32) -> bool {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::RuleActive(rule_id))
+        .unwrap_or(true)
+}
+
+pub fn set_rule_active(e: &Env, rule_id: u32, active: bool) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::RuleActive(rule_id), &active);
+}
+
+/// Rules without a priority have priority 0.
+pub fn get_rule_priority(e: &Env, rule_id: u32) -> u32 {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::RulePriority(rule_id))
+        .unwrap_or(0)
+}
+
+pub fn set_rule_priority(e: &Env, rule_id: u32, priority: u32) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::RulePriority(rule_id), &priority);
+}
+
+/// Version 1 → 2: give every indexed rule an explicit `active` flag.
+pub fn backfill_rule_active_flags(e: &Env) {
+    for rule_id in get_rule_ids(e).iter() {
+        let key = PhantomStorageKey::RuleActive(rule_id);
+        if !e.storage().persistent().has(&key) {
+            e.storage().persistent().set(&key, &true);
+        }
+    }
+}
+
+/// Version 2 → 3: index the name of every indexed rule.
+pub fn backfill_rule_names(e: &Env) {
+    for rule_id in get_rule_ids(e).iter() {
+        set_rule_name(e, rule_id, &get_context_rule(e, rule_id).name);
+    }
+}
+
+pub fn get_signer_expiry(e: &Env, rule_id: u32, signer: &Signer) -> Option<u32> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::SignerExpiry(rule_id, signer.clone()))
+}
+
+/// `None` clears the expiry.
+pub fn set_signer_expiry(e: &Env, rule_id: u32, signer: &Signer, valid_until: Option<u32>) {
+    let key = PhantomStorageKey::SignerExpiry(rule_id, signer.clone());
+    match valid_until {
+        Some(until) => e.storage().persistent().set(&key, &until),
+        None => e.storage().persistent().remove(&key),
+    }
+}
+
+pub fn is_signer_expired(e: &Env, rule_id: u32, signer: &Signer) -> bool {
+    matches!(get_signer_expiry(e, rule_id, signer), Some(until) if until < e.ledger().sequence())
+}
+
+pub fn get_signer_label(e: &Env, rule_id: u32, signer: &Signer) -> Option<String> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::SignerLabel(rule_id, signer.clone()))
+}
+
+pub fn set_signer_label(e: &Env, rule_id: u32, signer: &Signer, label: &String) {
+    e.storage().persistent().set(
+        &PhantomStorageKey::SignerLabel(rule_id, signer.clone()),
+        label,
+    );
+}
+
+pub fn get_fallback_verifier(e: &Env, signer: &Signer) -> Option<Address> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::FallbackVerifier(signer.clone()))
+}
+
+pub fn set_fallback_verifier(e: &Env, signer: &Signer, fallback: &Address) {
+    e.storage().persistent().set(
+        &PhantomStorageKey::FallbackVerifier(signer.clone()),
+        fallback,
+    );
+}
+
+pub fn remove_fallback_verifier(e: &Env, signer: &Signer) {
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::FallbackVerifier(signer.clone()));
+}
+
+pub fn get_key_rotation(e: &Env, rule_id: u32) -> Option<KeyRotation> {
+    e.storage()
+        .persistent()
+        .get(&PhantomStorageKey::KeyRotation(rule_id))
+}
+
+pub fn set_key_rotation(e: &Env, rule_id: u32, rotation: &KeyRotation) {
+    e.storage()
+        .persistent()
+        .set(&PhantomStorageKey::KeyRotation(rule_id), rotation);
+}
+
+/// Drops everything stored alongside a signer removed from a rule.
+pub fn remove_signer_data(e: &Env, rule_id: u32, signer: &Signer) {
+    set_signer_expiry(e, rule_id, signer, None);
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::SignerLabel(rule_id, signer.clone()));
+
+    let in_rotation = get_key_rotation(e, rule_id)
+        .is_some_and(|rotation| rotation.old_key == *signer || rotation.new_key == *signer);
+    if in_rotation {
+        e.storage()
+            .persistent()
+            .remove(&PhantomStorageKey::KeyRotation(rule_id));
+    }
+}
+
+/// Hands the per-signer data of `from` on `rule_id` over to `to`, which
+/// replaces it on the rule.
+///
+/// Fallback verifiers are keyed by signer alone, so `from` keeps its own for
+/// any other rule it is on.
+pub fn move_signer_data(e: &Env, rule_id: u32, from: &Signer, to: &Signer) {
+    set_signer_expiry(e, rule_id, to, get_signer_expiry(e, rule_id, from));
+    if let Some(label) = get_signer_label(e, rule_id, from) {
+        set_signer_label(e, rule_id, to, &label);
+    }
+    if let Some(fallback) = get_fallback_verifier(e, from) {
+        if get_fallback_verifier(e, to).is_none() {
+            set_fallback_verifier(e, to, &fallback);
+        }
+    }
+    if let Some(mut rotation) = get_key_rotation(e, rule_id) {
+        if rotation.old_key == *from {
+            rotation.old_key = to.clone();
+        }
+        if rotation.new_key == *from {
+            rotation.new_key = to.clone();
+        }
+        set_key_rotation(e, rule_id, &rotation);
+    }
+    remove_signer_data(e, rule_id, from);
+}
+
+/// Drops everything stored alongside a removed context rule.
+pub fn remove_rule_data(e: &Env, rule: &ContextRule) {
+    remove_rate_limit(e, rule.id);
+    remove_amount_cap(e, rule.id);
+    set_allowed_functions(e, rule.id, &Vec::new(e));
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::Threshold(rule.id));
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::RuleStats(rule.id));
+    unindex_rule(e, rule.id);
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::RuleActive(rule.id));
+    e.storage()
+        .persistent()
+        .remove(&PhantomStorageKey::RulePriority(rule.id));
+    for signer in rule.signers.iter() {
+        remove_signer_data(e, rule.id, &signer);
+    }
+}
@@ -1,3 +1,3147 @@
 #![cfg(test)]
-// Tests for smart-account will require integration testing with the other contracts
-// For now, we just verify the contract compiles correctly
+// Integration tests: the account is registered next to the real Ed25519Verifier
+// and Counter contracts, and auth goes through `__check_auth` with genuine
+// Phantom-style signatures instead of `mock_all_auths`.
+use crate::{
+    storage, AccountConfig, AmountCap, CallSpec, KeyRotation, PhantomAccountError,
+    PhantomSmartAccount, PhantomSmartAccountClient, RateLimit, RuleDiagnosis, RuleFailure,
+    RuleStats, RuleSummary, RuleTemplate, SignerInfo, TemplateOverrides, DEFAULT_MAX_RULES,
+    MAX_BATCH_LEN, MAX_DELEGATION_DEPTH, MAX_EXPIRY_GRACE, MAX_LABEL_LEN, MAX_TEMPLATES,
+    STORAGE_VERSION,
+};
+use counter::{Counter, CounterClient};
+use ed25519_dalek::{Signer as _, SigningKey};
+use ed25519_verifier::{Ed25519AnyOfSigData, Ed25519SigData, Ed25519Verifier};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use latch_utils::{hex_encode, hex_len};
+use secp256k1_verifier::{Secp256k1SigData, Secp256k1Verifier};
+use soroban_sdk::{
+    auth::{Context, ContractContext},
+    contract, contractimpl, symbol_short,
+    testutils::{Address as _, Ledger as _},
+    token::{StellarAssetClient, TokenClient},
+    xdr::{
+        ContractEventBody, Hash, HashIdPreimage, HashIdPreimageSorobanAuthorization,
+        InvokeContractArgs, Limits, ScAddress, ScSymbol, ScVal, SorobanAddressCredentials,
+        SorobanAuthorizationEntry, SorobanAuthorizedFunction, SorobanAuthorizedInvocation,
+        SorobanCredentials, ToXdr, VecM, WriteXdr,
+    },
+    Address, Bytes, BytesN, Env, IntoVal, InvokeError, Map, String, Symbol, TryFromVal, Val, Vec,
+};
+use stellar_accounts::smart_account::{ContextRule, ContextRuleType, Signatures, Signer};
+use verifier_registry::{VerifierRegistry, VerifierRegistryClient};
+
+extern crate std;
+
+/// Prefix that matches what Phantom wallet expects
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+
+/// Convert bytes to lowercase hex string (off-chain helper for tests)
+fn bytes_to_hex(bytes: &[u8]) -> std::vec::Vec<u8> {
+    let mut hex = std::vec![0u8; hex_len(bytes.len())];
+    hex_encode(&mut hex, bytes);
+    hex
+}
+
+/// Sign `payload` the way Phantom does and encode the result as verifier sig_data.
+pub(crate) fn phantom_sig_data(env: &Env, key: &SigningKey, payload: &[u8; 32]) -> Bytes {
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(&bytes_to_hex(payload));
+    let signature = phantom_signature(key, payload);
+
+    Ed25519SigData {
+        prefixed_message: Bytes::from_slice(env, &message),
+        signature: BytesN::from_array(env, &signature),
+        expires_at: None,
+    }
+    .to_xdr(env)
+}
+
+/// Raw Ed25519 signature over the Phantom-prefixed `payload`.
+pub(crate) fn phantom_signature(key: &SigningKey, payload: &[u8; 32]) -> [u8; 64] {
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(&bytes_to_hex(payload));
+    key.sign(&message).to_bytes()
+}
+
+pub(crate) fn phantom_signer(env: &Env, verifier: &Address, key: &SigningKey) -> Signer {
+    Signer::External(
+        verifier.clone(),
+        Bytes::from_slice(env, &key.verifying_key().to_bytes()),
+    )
+}
+
+fn sc_address(env: &Env, address: &Address) -> ScAddress {
+    let val: Val = address.into_val(env);
+    match ScVal::try_from_val(env, &val).unwrap() {
+        ScVal::Address(address) => address,
+        _ => unreachable!(),
+    }
+}
+
+/// A root invocation of `contract.fn_name(args)` with no sub-invocations.
+pub(crate) fn contract_invocation(
+    env: &Env,
+    contract: &Address,
+    fn_name: &str,
+    args: Vec<Val>,
+) -> SorobanAuthorizedInvocation {
+    let args: std::vec::Vec<ScVal> = args
+        .iter()
+        .map(|arg| ScVal::try_from_val(env, &arg).unwrap())
+        .collect();
+
+    SorobanAuthorizedInvocation {
+        function: SorobanAuthorizedFunction::ContractFn(InvokeContractArgs {
+            contract_address: sc_address(env, contract),
+            function_name: ScSymbol(fn_name.try_into().unwrap()),
+            args: args.try_into().unwrap(),
+        }),
+        sub_invocations: VecM::default(),
+    }
+}
+
+/// The payload the host hands to `__check_auth` for an auth entry.
+pub(crate) fn signature_payload(
+    env: &Env,
+    nonce: i64,
+    signature_expiration_ledger: u32,
+    invocation: &SorobanAuthorizedInvocation,
+) -> [u8; 32] {
+    let preimage = HashIdPreimage::SorobanAuthorization(HashIdPreimageSorobanAuthorization {
+        network_id: Hash(env.ledger().network_id().to_array()),
+        nonce,
+        signature_expiration_ledger,
+        invocation: invocation.clone(),
+    });
+    let preimage = WriteXdr::to_xdr(&preimage, Limits::none()).unwrap();
+    env.crypto()
+        .sha256(&Bytes::from_slice(env, &preimage))
+        .to_array()
+}
+
+/// Builds an address-credential auth entry for `account`, signing the
+/// resulting payload with `sign`.
+pub(crate) fn auth_entry(
+    env: &Env,
+    account: &Address,
+    nonce: i64,
+    invocation: SorobanAuthorizedInvocation,
+    sign: impl FnOnce(&[u8; 32]) -> Signatures,
+) -> SorobanAuthorizationEntry {
+    let signature_expiration_ledger = env.ledger().sequence() + 100;
+    let payload = signature_payload(env, nonce, signature_expiration_ledger, &invocation);
+    let signatures: Val = sign(&payload).into_val(env);
+
+    SorobanAuthorizationEntry {
+        credentials: SorobanCredentials::Address(SorobanAddressCredentials {
+            address: sc_address(env, account),
+            nonce,
+            signature_expiration_ledger,
+            signature: ScVal::try_from_val(env, &signatures).unwrap(),
+        }),
+        root_invocation: invocation,
+    }
+}
+
+/// Policy that accepts every context it is asked about.
+/// Token whose transfers always trap.
+#[contract]
+pub(crate) struct TrapToken;
+
+#[contractimpl]
+impl TrapToken {
+    pub fn balance(_e: Env, _id: Address) -> i128 {
+        1
+    }
+
+    pub fn transfer(_e: Env, _from: Address, _to: Address, _amount: i128) {
+        panic!("transfer disabled");
+    }
+}
+
+/// Verifier that rejects every signature.
+#[contract]
+pub(crate) struct RejectVerifier;
+
+#[contractimpl]
+impl RejectVerifier {
+    pub fn verify(_e: Env, _signature_payload: Bytes, _key_data: Bytes, _sig_data: Bytes) -> bool {
+        false
+    }
+}
+
+#[contract]
+pub(crate) struct AllowPolicy;
+
+#[contractimpl]
+impl AllowPolicy {
+    pub fn can_enforce(
+        _e: Env,
+        _context: Context,
+        _authenticated_signers: Vec<Signer>,
+        _context_rule: ContextRule,
+        _smart_account: Address,
+    ) -> bool {
+        true
+    }
+
+    pub fn enforce(
+        _e: Env,
+        _context: Context,
+        _authenticated_signers: Vec<Signer>,
+        _context_rule: ContextRule,
+        _smart_account: Address,
+    ) {
+    }
+
+    pub fn install(
+        _e: Env,
+        _install_params: Val,
+        _context_rule: ContextRule,
+        _smart_account: Address,
+    ) {
+    }
+
+    pub fn uninstall(_e: Env, _context_rule: ContextRule, _smart_account: Address) {}
+}
+
+/// Policy whose install hook calls back into the account to remove itself.
+#[contract]
+pub(crate) struct ReentrantPolicy;
+
+#[contractimpl]
+impl ReentrantPolicy {
+    pub fn install(
+        e: Env,
+        _install_params: Val,
+        context_rule: ContextRule,
+        smart_account: Address,
+    ) {
+        PhantomSmartAccountClient::new(&e, &smart_account)
+            .remove_policy(&context_rule.id, &e.current_contract_address());
+    }
+
+    pub fn uninstall(_e: Env, _context_rule: ContextRule, _smart_account: Address) {}
+}
+
+/// Policy whose install hook only reads: the rule it is given and the
+/// counter passed as install parameter.
+#[contract]
+pub(crate) struct ReadingPolicy;
+
+#[contractimpl]
+impl ReadingPolicy {
+    pub fn install(
+        e: Env,
+        install_params: Val,
+        context_rule: ContextRule,
+        _smart_account: Address,
+    ) {
+        let counter = Address::try_from_val(&e, &install_params).unwrap();
+        assert!(!context_rule.signers.is_empty());
+        CounterClient::new(&e, &counter).get();
+    }
+
+    pub fn uninstall(_e: Env, _context_rule: ContextRule, _smart_account: Address) {}
+}
+
+/// Id of the first rule on `account` scoped to `contract`.
+pub(crate) fn rule_for(account: &PhantomSmartAccountClient, contract: &Address) -> u32 {
+    account
+        .get_context_rules(&ContextRuleType::CallContract(contract.clone()))
+        .get(0)
+        .unwrap()
+        .id
+}
+
+pub(crate) struct Setup {
+    pub env: Env,
+    pub key: SigningKey,
+    pub verifier: Address,
+    pub counter: Address,
+    pub account: PhantomSmartAccountClient<'static>,
+    nonce: core::cell::Cell<i64>,
+}
+
+impl Setup {
+    /// Registers verifier, counter and account, then initializes the account
+    /// with a deterministic Phantom key.
+    pub fn new() -> Self {
+        let env = Env::default();
+        let verifier = env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        );
+        let counter = env.register(Counter, (None::<Address>,));
+        let account_id = env.register(PhantomSmartAccount, ());
+        let account = PhantomSmartAccountClient::new(&env, &account_id);
+
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        account.initialize(
+            &verifier,
+            &BytesN::from_array(&env, &key.verifying_key().to_bytes()),
+            &counter,
+        );
+
+        Setup {
+            env,
+            key,
+            verifier,
+            counter,
+            account,
+            nonce: core::cell::Cell::new(0),
+        }
+    }
+
+    /// Registers another account sharing this setup's verifier, with its
+    /// Phantom rule scoped to `target`.
+    pub fn new_account(
+        &self,
+        key: &SigningKey,
+        target: &Address,
+    ) -> PhantomSmartAccountClient<'static> {
+        let account_id = self.env.register(PhantomSmartAccount, ());
+        let account = PhantomSmartAccountClient::new(&self.env, &account_id);
+        account.initialize(
+            &self.verifier,
+            &BytesN::from_array(&self.env, &key.verifying_key().to_bytes()),
+            target,
+        );
+        account
+    }
+
+    /// Id of the rule scoped to the counter contract.
+    pub fn counter_rule(&self) -> u32 {
+        rule_for(&self.account, &self.counter)
+    }
+
+    /// Id of the self-admin rule.
+    pub fn admin_rule(&self) -> u32 {
+        rule_for(&self.account, &self.account.address)
+    }
+
+    pub fn signer(&self) -> Signer {
+        phantom_signer(&self.env, &self.verifier, &self.key)
+    }
+
+    /// Signatures map containing only the account's Phantom key.
+    pub fn sign(&self, payload: &[u8; 32]) -> Signatures {
+        self.sign_with(&[&self.key], payload)
+    }
+
+    pub fn sign_with(&self, keys: &[&SigningKey], payload: &[u8; 32]) -> Signatures {
+        let mut map = Map::new(&self.env);
+        for key in keys {
+            map.set(
+                phantom_signer(&self.env, &self.verifier, key),
+                phantom_sig_data(&self.env, key, payload),
+            );
+        }
+        Signatures(map)
+    }
+
+    /// `counter.increment(account)`, the context the counter rule is for.
+    pub fn increment_context(&self) -> Context {
+        Context::Contract(ContractContext {
+            contract: self.counter.clone(),
+            fn_name: symbol_short!("increment"),
+            args: (self.account.address.clone(),).into_val(&self.env),
+        })
+    }
+
+    /// `counter.increment_by(account, amount)`, which the counter rule
+    /// covers as well.
+    pub fn increment_by_context(&self, amount: u32) -> Context {
+        Context::Contract(ContractContext {
+            contract: self.counter.clone(),
+            fn_name: Symbol::new(&self.env, "increment_by"),
+            args: (self.account.address.clone(), amount).into_val(&self.env),
+        })
+    }
+
+    /// Runs `__check_auth` directly for `contexts`, signed by the Phantom key.
+    pub fn check_auth(
+        &self,
+        contexts: &[Context],
+    ) -> Result<(), Result<PhantomAccountError, InvokeError>> {
+        self.check_auth_with(&[&self.key], contexts)
+    }
+
+    pub fn check_auth_with(
+        &self,
+        keys: &[&SigningKey],
+        contexts: &[Context],
+    ) -> Result<(), Result<PhantomAccountError, InvokeError>> {
+        self.check_auth_signed(self.sign_with(keys, &[1u8; 32]), contexts)
+    }
+
+    /// Runs `__check_auth` for `contexts` with a prepared signatures map over
+    /// the payload `[1; 32]`.
+    pub fn check_auth_signed(
+        &self,
+        signatures: Signatures,
+        contexts: &[Context],
+    ) -> Result<(), Result<PhantomAccountError, InvokeError>> {
+        let mut auth_contexts = Vec::new(&self.env);
+        for context in contexts {
+            auth_contexts.push_back(context.clone());
+        }
+
+        self.env.try_invoke_contract_check_auth(
+            &self.account.address,
+            &BytesN::from_array(&self.env, &[1u8; 32]),
+            signatures.into_val(&self.env),
+            &auth_contexts,
+        )
+    }
+
+    fn next_nonce(&self) -> i64 {
+        let nonce = self.nonce.get();
+        self.nonce.set(nonce + 1);
+        nonce
+    }
+
+    /// Authorizes the next `contract.fn_name(args)` call with the account's
+    /// Phantom key.
+    pub fn authorize(&self, contract: &Address, fn_name: &str, args: Vec<Val>) {
+        self.authorize_with(contract, fn_name, args, |payload| self.sign(payload));
+    }
+
+    pub fn authorize_with(
+        &self,
+        contract: &Address,
+        fn_name: &str,
+        args: Vec<Val>,
+        sign: impl FnOnce(&[u8; 32]) -> Signatures,
+    ) {
+        let invocation = contract_invocation(&self.env, contract, fn_name, args);
+        let entry = auth_entry(
+            &self.env,
+            &self.account.address,
+            self.next_nonce(),
+            invocation,
+            sign,
+        );
+        self.env.set_auths(&[entry]);
+    }
+}
+
+#[test]
+fn test_initialize_creates_self_admin_rule() {
+    let s = Setup::new();
+
+    let rules = s
+        .account
+        .get_context_rules(&ContextRuleType::CallContract(s.account.address.clone()));
+    assert_eq!(rules.len(), 1);
+
+    let rule = rules.get(0).unwrap();
+    assert_eq!(rule.name, String::from_str(&s.env, "self-admin"));
+    assert_eq!(rule.signers, Vec::from_array(&s.env, [s.signer()]));
+    assert_eq!(s.account.get_context_rules_count(), 2);
+}
+
+#[test]
+fn test_add_signer_authorized_by_self_admin_rule() {
+    let s = Setup::new();
+    let counter_rule = s
+        .account
+        .get_context_rules(&ContextRuleType::CallContract(s.counter.clone()))
+        .get(0)
+        .unwrap();
+
+    let laptop_key = SigningKey::from_bytes(&[9u8; 32]);
+    let laptop = phantom_signer(&s.env, &s.verifier, &laptop_key);
+
+    // No mock_all_auths: the host routes the account's own require_auth
+    // through __check_auth, which matches the self-admin rule.
+    s.authorize(
+        &s.account.address,
+        "add_signer",
+        (counter_rule.id, laptop.clone()).into_val(&s.env),
+    );
+    s.account.add_signer(&counter_rule.id, &laptop);
+
+    let signers = s.account.get_context_rule(&counter_rule.id).signers;
+    assert_eq!(signers.len(), 2);
+    assert!(signers.contains(&laptop));
+}
+
+#[test]
+fn test_add_signer_without_auth_fails() {
+    let s = Setup::new();
+    let laptop = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]));
+
+    let rule_id = s.counter_rule();
+    assert!(s.account.try_add_signer(&rule_id, &laptop).is_err());
+    assert_eq!(s.account.get_context_rule(&rule_id).signers.len(), 1);
+}
+
+#[test]
+fn test_remove_last_signer_blocked() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+
+    s.authorize(
+        &s.account.address,
+        "remove_signer",
+        (rule_id, s.signer()).into_val(&s.env),
+    );
+    let res = s.account.try_remove_signer(&rule_id, &s.signer());
+
+    assert_eq!(res, Err(Ok(PhantomAccountError::WouldBrickAccount.into())));
+    assert_eq!(s.account.get_context_rule(&rule_id).signers.len(), 1);
+}
+
+#[test]
+fn test_remove_last_signer_forced_drops_rule() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+
+    s.authorize(
+        &s.account.address,
+        "remove_signer_with_force",
+        (rule_id, s.signer(), true).into_val(&s.env),
+    );
+    s.account
+        .remove_signer_with_force(&rule_id, &s.signer(), &true);
+
+    assert!(s
+        .account
+        .get_context_rules(&ContextRuleType::CallContract(s.counter.clone()))
+        .is_empty());
+    assert_eq!(s.account.get_context_rules_count(), 1);
+}
+
+#[test]
+fn test_remove_signer_allowed_when_another_signer_remains() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    let laptop = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]));
+
+    s.authorize(
+        &s.account.address,
+        "add_signer",
+        (rule_id, laptop.clone()).into_val(&s.env),
+    );
+    s.account.add_signer(&rule_id, &laptop);
+
+    s.authorize(
+        &s.account.address,
+        "remove_signer",
+        (rule_id, s.signer()).into_val(&s.env),
+    );
+    s.account.remove_signer(&rule_id, &s.signer());
+
+    let signers = s.account.get_context_rule(&rule_id).signers;
+    assert_eq!(signers, Vec::from_array(&s.env, [laptop]));
+}
+
+#[test]
+fn test_remove_signer_allowed_when_policy_remains() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    let policy = s.env.register(AllowPolicy, ());
+    let install_param: Val = ().into_val(&s.env);
+
+    s.authorize(
+        &s.account.address,
+        "add_policy",
+        (rule_id, policy.clone(), install_param).into_val(&s.env),
+    );
+    s.account.add_policy(&rule_id, &policy, &install_param);
+
+    s.authorize(
+        &s.account.address,
+        "remove_signer",
+        (rule_id, s.signer()).into_val(&s.env),
+    );
+    s.account.remove_signer(&rule_id, &s.signer());
+
+    let rule = s.account.get_context_rule(&rule_id);
+    assert!(rule.signers.is_empty());
+    assert_eq!(rule.policies, Vec::from_array(&s.env, [policy]));
+}
+
+#[test]
+fn test_remove_final_rule_blocked() {
+    let s = Setup::new();
+    let counter_rule = s.counter_rule();
+    let admin_rule = s.admin_rule();
+
+    // The first removal is fine: the self-admin rule still remains.
+    s.authorize(
+        &s.account.address,
+        "remove_context_rule",
+        (counter_rule,).into_val(&s.env),
+    );
+    s.account.remove_context_rule(&counter_rule);
+
+    s.authorize(
+        &s.account.address,
+        "remove_context_rule",
+        (admin_rule,).into_val(&s.env),
+    );
+    let res = s.account.try_remove_context_rule(&admin_rule);
+
+    assert_eq!(res, Err(Ok(PhantomAccountError::WouldBrickAccount.into())));
+    assert_eq!(s.account.get_context_rules_count(), 1);
+}
+
+#[test]
+fn test_remove_last_admin_rule_blocked() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    // The counter rule remains but cannot call the account
+    assert_eq!(
+        s.account.try_remove_context_rule(&s.admin_rule()),
+        Err(Ok(PhantomAccountError::WouldBrickAccount.into()))
+    );
+    assert_eq!(s.account.get_context_rules_count(), 2);
+
+    // A default rule can, so the self-admin rule may then go
+    s.account.add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "backup"),
+        &None,
+        &Vec::from_array(&s.env, [s.signer()]),
+        &Map::new(&s.env),
+    );
+    s.account.remove_context_rule(&s.admin_rule());
+    assert_eq!(s.account.get_context_rules_count(), 2);
+}
+
+#[test]
+fn test_remove_final_rule_forced() {
+    let s = Setup::new();
+    let counter_rule = s.counter_rule();
+    let admin_rule = s.admin_rule();
+
+    s.authorize(
+        &s.account.address,
+        "remove_context_rule",
+        (counter_rule,).into_val(&s.env),
+    );
+    s.account.remove_context_rule(&counter_rule);
+
+    s.authorize(
+        &s.account.address,
+        "remove_context_rule_with_force",
+        (admin_rule, true).into_val(&s.env),
+    );
+    s.account.remove_context_rule_with_force(&admin_rule, &true);
+
+    assert_eq!(s.account.get_context_rules_count(), 0);
+}
+
+#[test]
+fn test_delegated_signer_authorizes_two_levels_down() {
+    let s = Setup::new();
+    let org = &s.account;
+    let rule_id = s.counter_rule();
+
+    // The member's rule is scoped to the org: that is the contract whose
+    // `__check_auth` the member ends up authorizing.
+    let member_key = SigningKey::from_bytes(&[11u8; 32]);
+    let member = s.new_account(&member_key, &org.address);
+
+    s.env.mock_all_auths();
+    org.add_delegated_signer(&rule_id, &member.address);
+    org.remove_signer(&rule_id, &s.signer());
+    assert_eq!(
+        org.get_context_rule(&rule_id).signers,
+        Vec::from_array(&s.env, [Signer::Delegated(member.address.clone())])
+    );
+
+    // org -> member -> member's Phantom key
+    let increment = contract_invocation(
+        &s.env,
+        &s.counter,
+        "increment",
+        (org.address.clone(),).into_val(&s.env),
+    );
+    let mut org_payload = [0u8; 32];
+    let org_entry = auth_entry(&s.env, &org.address, 1, increment, |payload| {
+        org_payload = *payload;
+        Signatures(Map::from_array(
+            &s.env,
+            [(
+                Signer::Delegated(member.address.clone()),
+                Bytes::new(&s.env),
+            )],
+        ))
+    });
+
+    let check_auth = contract_invocation(
+        &s.env,
+        &org.address,
+        "__check_auth",
+        (BytesN::from_array(&s.env, &org_payload),).into_val(&s.env),
+    );
+    let member_entry = auth_entry(&s.env, &member.address, 2, check_auth, |payload| {
+        s.sign_with(&[&member_key], payload)
+    });
+
+    s.env.set_auths(&[org_entry, member_entry]);
+    let counter = CounterClient::new(&s.env, &s.counter);
+    assert_eq!(counter.increment(&org.address), 1);
+}
+
+#[test]
+fn test_delegation_depth_limit() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    let a = s.new_account(&SigningKey::from_bytes(&[21u8; 32]), &s.counter);
+    let b = s.new_account(&SigningKey::from_bytes(&[22u8; 32]), &s.counter);
+    let c = s.new_account(&SigningKey::from_bytes(&[23u8; 32]), &s.counter);
+
+    // a -> b -> c
+    b.add_delegated_signer(&rule_for(&b, &s.counter), &c.address);
+    a.add_delegated_signer(&rule_for(&a, &s.counter), &b.address);
+    assert_eq!(b.delegation_depth(), 1);
+    assert_eq!(a.delegation_depth(), MAX_DELEGATION_DEPTH);
+
+    let res = s
+        .account
+        .try_add_delegated_signer(&s.counter_rule(), &a.address);
+    assert_eq!(res, Err(Ok(PhantomAccountError::DelegationTooDeep.into())));
+}
+
+#[test]
+fn test_delegating_to_self_rejected() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    let res = s
+        .account
+        .try_add_delegated_signer(&s.counter_rule(), &s.account.address);
+    assert_eq!(res, Err(Ok(PhantomAccountError::DelegationCycle.into())));
+}
+
+#[test]
+fn test_add_ed25519_signer() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let key = SigningKey::from_bytes(&[24u8; 32]);
+
+    s.account.add_ed25519_signer(
+        &rule_id,
+        &s.verifier,
+        &BytesN::from_array(&s.env, &key.verifying_key().to_bytes()),
+    );
+
+    // Stored exactly as a 32-byte key added through add_signer would be
+    let rule = s.account.get_context_rule(&rule_id);
+    assert!(rule
+        .signers
+        .contains(phantom_signer(&s.env, &s.verifier, &key)));
+}
+
+#[test]
+fn test_add_ed25519_signer_rejects_short_key() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let signers_before = s.account.get_context_rule(&rule_id).signers.len();
+
+    // A 31-byte key cannot even be passed as the argument
+    let res = s.env.try_invoke_contract::<(), soroban_sdk::Error>(
+        &s.account.address,
+        &Symbol::new(&s.env, "add_ed25519_signer"),
+        (
+            rule_id,
+            s.verifier.clone(),
+            Bytes::from_array(&s.env, &[7u8; 31]),
+        )
+            .into_val(&s.env),
+    );
+    assert!(res.is_err());
+    assert_eq!(
+        s.account.get_context_rule(&rule_id).signers.len(),
+        signers_before
+    );
+}
+
+#[test]
+fn test_rate_limit_blocks_use_past_cap() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    s.env.mock_all_auths();
+    s.account.set_rule_rate_limit(&rule_id, &2, &100);
+    assert_eq!(
+        s.account.get_rule_rate_limit(&rule_id),
+        Some(RateLimit {
+            max_per_window: 2,
+            window_ledgers: 100,
+        })
+    );
+
+    let context = s.increment_context();
+    assert_eq!(s.check_auth(&[context.clone()]), Ok(()));
+    assert_eq!(s.check_auth(&[context.clone()]), Ok(()));
+    assert_eq!(
+        s.check_auth(&[context]),
+        Err(Ok(PhantomAccountError::RateLimitExceeded))
+    );
+}
+
+#[test]
+fn test_rate_limit_resets_in_next_window() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    s.env.mock_all_auths();
+    s.account.set_rule_rate_limit(&rule_id, &2, &100);
+
+    let context = s.increment_context();
+    s.check_auth(&[context.clone()]).unwrap();
+    s.check_auth(&[context.clone()]).unwrap();
+    assert!(s.check_auth(&[context.clone()]).is_err());
+
+    s.env
+        .ledger()
+        .set_sequence_number(s.env.ledger().sequence() + 100);
+    assert_eq!(s.check_auth(&[context.clone()]), Ok(()));
+    assert_eq!(s.check_auth(&[context]), Ok(()));
+}
+
+#[test]
+fn test_removing_rate_limit_restores_unlimited_use() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    s.env.mock_all_auths();
+    s.account.set_rule_rate_limit(&rule_id, &1, &100);
+
+    let context = s.increment_context();
+    s.check_auth(&[context.clone()]).unwrap();
+    assert!(s.check_auth(&[context.clone()]).is_err());
+
+    s.account.remove_rule_rate_limit(&rule_id);
+    assert_eq!(s.account.get_rule_rate_limit(&rule_id), None);
+    for _ in 0..3 {
+        assert_eq!(s.check_auth(&[context.clone()]), Ok(()));
+    }
+}
+
+#[test]
+fn test_rate_limit_rejects_zero_values() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    s.env.mock_all_auths();
+
+    assert_eq!(
+        s.account.try_set_rule_rate_limit(&rule_id, &0, &100),
+        Err(Ok(PhantomAccountError::InvalidRateLimit.into()))
+    );
+    assert_eq!(
+        s.account.try_set_rule_rate_limit(&rule_id, &2, &0),
+        Err(Ok(PhantomAccountError::InvalidRateLimit.into()))
+    );
+}
+
+#[test]
+fn test_initialize_writes_storage_version() {
+    let s = Setup::new();
+    assert_eq!(s.account.storage_version(), STORAGE_VERSION);
+    assert!(s.account.is_rule_active(&s.counter_rule()));
+}
+
+/// Rewrites the account's data into the version 1 shape: no `active` flags
+/// and no rule names.
+fn write_v1_layout(s: &Setup) {
+    s.env.as_contract(&s.account.address, || {
+        storage::set_storage_version(&s.env, 1);
+        s.env
+            .storage()
+            .instance()
+            .remove(&storage::PhantomStorageKey::RuleNames);
+        for rule_id in storage::get_rule_ids(&s.env).iter() {
+            s.env
+                .storage()
+                .persistent()
+                .remove(&storage::PhantomStorageKey::RuleActive(rule_id));
+        }
+    });
+}
+
+fn has_active_flag(s: &Setup, rule_id: u32) -> bool {
+    s.env.as_contract(&s.account.address, || {
+        s.env
+            .storage()
+            .persistent()
+            .has(&storage::PhantomStorageKey::RuleActive(rule_id))
+    })
+}
+
+#[test]
+fn test_check_auth_requires_migration() {
+    let s = Setup::new();
+    write_v1_layout(&s);
+
+    assert_eq!(
+        s.check_auth(&[s.increment_context()]),
+        Err(Ok(PhantomAccountError::MigrationRequired))
+    );
+}
+
+#[test]
+fn test_migrate_backfills_active_flags() {
+    let s = Setup::new();
+    write_v1_layout(&s);
+    assert!(!has_active_flag(&s, s.counter_rule()));
+
+    // No auth: an outdated account could not authorize the call.
+    s.account.migrate();
+
+    assert_eq!(s.account.storage_version(), STORAGE_VERSION);
+    assert!(has_active_flag(&s, s.counter_rule()));
+    assert!(has_active_flag(&s, s.admin_rule()));
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+}
+
+#[test]
+fn test_migrate_is_idempotent() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_rule_active(&s.counter_rule(), &false);
+
+    // Already current: must not reset the flag that was set explicitly.
+    s.account.migrate();
+    s.account.migrate();
+
+    assert_eq!(s.account.storage_version(), STORAGE_VERSION);
+    assert!(!s.account.is_rule_active(&s.counter_rule()));
+}
+
+#[test]
+fn test_inactive_rule_does_not_authorize() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_rule_active(&s.counter_rule(), &false);
+    assert_eq!(
+        s.check_auth(&[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+
+    s.account.set_rule_active(&s.counter_rule(), &true);
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+}
+
+#[test]
+fn test_deactivating_last_active_rule_blocked() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_rule_active(&s.counter_rule(), &false);
+
+    assert_eq!(
+        s.account.try_set_rule_active(&s.admin_rule(), &false),
+        Err(Ok(PhantomAccountError::WouldBrickAccount.into()))
+    );
+}
+
+#[test]
+fn test_deactivating_last_admin_rule_blocked() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    // The counter rule stays active but cannot call the account
+    assert_eq!(
+        s.account.try_set_rule_active(&s.admin_rule(), &false),
+        Err(Ok(PhantomAccountError::WouldBrickAccount.into()))
+    );
+    assert!(s.account.is_rule_active(&s.admin_rule()));
+
+    // A default rule can, so the self-admin rule may then go
+    let backup = s.account.add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "backup"),
+        &None,
+        &Vec::from_array(&s.env, [s.signer()]),
+        &Map::new(&s.env),
+    );
+    s.account.set_rule_active(&s.admin_rule(), &false);
+    assert_eq!(
+        s.account.try_set_rule_active(&backup.id, &false),
+        Err(Ok(PhantomAccountError::WouldBrickAccount.into()))
+    );
+}
+
+#[test]
+fn test_add_context_rule_rejects_empty_rule() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    let res = s.account.try_add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "empty"),
+        &None,
+        &Vec::new(&s.env),
+        &Map::new(&s.env),
+    );
+    assert_eq!(res, Err(Ok(PhantomAccountError::EmptyRule.into())));
+    assert_eq!(s.account.get_context_rules_count(), 2);
+}
+
+#[test]
+fn test_add_context_rule_rejects_duplicate_signers() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    let res = s.account.try_add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "dup"),
+        &None,
+        &Vec::from_array(&s.env, [s.signer(), s.signer()]),
+        &Map::new(&s.env),
+    );
+    assert_eq!(res, Err(Ok(PhantomAccountError::DuplicateSigner.into())));
+}
+
+#[test]
+fn test_add_context_rule_allows_policy_only_rule() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let policy = s.env.register(AllowPolicy, ());
+    let install_param: Val = ().into_val(&s.env);
+
+    let rule = s.account.add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "policy-only"),
+        &None,
+        &Vec::new(&s.env),
+        &Map::from_array(&s.env, [(policy.clone(), install_param)]),
+    );
+    assert!(rule.signers.is_empty());
+    assert_eq!(rule.policies, Vec::from_array(&s.env, [policy]));
+    assert_eq!(s.account.get_context_rules_count(), 3);
+}
+
+#[test]
+fn test_rotation_accepts_both_keys_during_grace() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    let new_key = SigningKey::from_bytes(&[9u8; 32]);
+    let new_signer = phantom_signer(&s.env, &s.verifier, &new_key);
+    s.env.mock_all_auths();
+    s.account
+        .rotate_key_with_grace(&rule_id, &s.signer(), &new_signer, &50);
+
+    assert_eq!(
+        s.account.get_key_rotation(&rule_id),
+        Some(KeyRotation {
+            old_key: s.signer(),
+            new_key: new_signer,
+        })
+    );
+    let context = s.increment_context();
+    assert_eq!(s.check_auth_with(&[&s.key], &[context.clone()]), Ok(()));
+    assert_eq!(s.check_auth_with(&[&new_key], &[context]), Ok(()));
+}
+
+#[test]
+fn test_rotation_retires_old_key_after_grace() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    let new_key = SigningKey::from_bytes(&[9u8; 32]);
+    let new_signer = phantom_signer(&s.env, &s.verifier, &new_key);
+    s.env.mock_all_auths();
+    s.account
+        .rotate_key_with_grace(&rule_id, &s.signer(), &new_signer, &50);
+
+    s.env
+        .ledger()
+        .set_sequence_number(s.env.ledger().sequence() + 51);
+    let context = s.increment_context();
+    assert_eq!(
+        s.check_auth_with(&[&s.key], &[context.clone()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+    assert_eq!(s.check_auth_with(&[&new_key], &[context]), Ok(()));
+
+    assert_eq!(
+        s.account.get_context_rule(&rule_id).signers,
+        Vec::from_array(&s.env, [new_signer])
+    );
+    assert_eq!(s.account.get_key_rotation(&rule_id), None);
+}
+
+#[test]
+fn test_rotation_grace_capped_at_max_ttl() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    let new_signer = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]));
+    s.env.mock_all_auths();
+    let max_ttl = s
+        .env
+        .as_contract(&s.account.address, || s.env.storage().max_ttl());
+
+    for grace in [max_ttl + 1, u32::MAX] {
+        assert_eq!(
+            s.account
+                .try_rotate_key_with_grace(&rule_id, &s.signer(), &new_signer, &grace),
+            Err(Ok(PhantomAccountError::GraceTooLong.into()))
+        );
+    }
+    assert_eq!(s.account.get_key_rotation(&rule_id), None);
+
+    s.account
+        .rotate_key_with_grace(&rule_id, &s.signer(), &new_signer, &max_ttl);
+    assert!(s.account.get_key_rotation(&rule_id).is_some());
+}
+
+#[test]
+fn test_finalize_rotation_only_after_grace() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    let new_signer = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]));
+    s.env.mock_all_auths();
+    assert!(!s.account.finalize_rotation(&rule_id));
+
+    s.account
+        .rotate_key_with_grace(&rule_id, &s.signer(), &new_signer, &50);
+    assert!(!s.account.finalize_rotation(&rule_id));
+    assert_eq!(
+        s.account
+            .try_rotate_key_with_grace(&rule_id, &new_signer, &s.signer(), &50),
+        Err(Ok(PhantomAccountError::RotationPending.into()))
+    );
+
+    s.env
+        .ledger()
+        .set_sequence_number(s.env.ledger().sequence() + 51);
+    assert!(s.account.finalize_rotation(&rule_id));
+    assert_eq!(
+        s.account.get_context_rule(&rule_id).signers,
+        Vec::from_array(&s.env, [new_signer])
+    );
+}
+
+#[test]
+fn test_rotate_key_swaps_immediately() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    let new_key = SigningKey::from_bytes(&[9u8; 32]);
+    let new_signer = phantom_signer(&s.env, &s.verifier, &new_key);
+    s.env.mock_all_auths();
+    s.account.rotate_key(&rule_id, &s.signer(), &new_signer);
+
+    let context = s.increment_context();
+    assert_eq!(
+        s.check_auth_with(&[&s.key], &[context.clone()]),
+        Err(Ok(PhantomAccountError::UnknownSigner))
+    );
+    assert_eq!(s.check_auth_with(&[&new_key], &[context]), Ok(()));
+}
+
+#[test]
+fn test_rotate_key_rejects_unknown_old_key() {
+    let s = Setup::new();
+    let stranger = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]));
+    s.env.mock_all_auths();
+
+    assert_eq!(
+        s.account
+            .try_rotate_key(&s.counter_rule(), &stranger, &s.signer()),
+        Err(Ok(PhantomAccountError::SignerNotFound.into()))
+    );
+}
+
+/// A signer any one of `keys` may sign for.
+fn any_of_signer(env: &Env, verifier: &Address, keys: &[&SigningKey]) -> Signer {
+    let mut list = Vec::<BytesN<32>>::new(env);
+    for key in keys {
+        list.push_back(BytesN::from_array(env, &key.verifying_key().to_bytes()));
+    }
+    Signer::External(verifier.clone(), list.to_xdr(env))
+}
+
+#[test]
+fn test_rotate_to_any_of_signer() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    let phone = SigningKey::from_bytes(&[9u8; 32]);
+    let either = any_of_signer(&s.env, &s.verifier, &[&s.key, &phone]);
+    s.env.mock_all_auths();
+    // The list may keep the key it replaces
+    s.account.rotate_key(&rule_id, &s.signer(), &either);
+
+    let signers = s.account.get_signers_detailed(&rule_id);
+    assert_eq!(signers.len(), 1);
+    assert_eq!(
+        signers.get_unchecked(0).keys,
+        Some(Vec::from_array(
+            &s.env,
+            [
+                BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes()),
+                BytesN::from_array(&s.env, &phone.verifying_key().to_bytes()),
+            ]
+        ))
+    );
+
+    let sig_data = Ed25519AnyOfSigData {
+        key_index: 1,
+        sig_data: phantom_sig_data(&s.env, &phone, &[1u8; 32]),
+    };
+    let signatures = Signatures(Map::from_array(&s.env, [(either, sig_data.to_xdr(&s.env))]));
+    assert_eq!(
+        s.check_auth_signed(signatures, &[s.increment_context()]),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_rotate_key_rejects_key_held_by_another_signer() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (laptop_key, _) = add_laptop_signer(&s);
+    let phone = SigningKey::from_bytes(&[10u8; 32]);
+    let with_laptop = any_of_signer(&s.env, &s.verifier, &[&phone, &laptop_key]);
+
+    assert_eq!(
+        s.account
+            .try_rotate_key(&s.counter_rule(), &s.signer(), &with_laptop),
+        Err(Ok(PhantomAccountError::DuplicateSigner.into()))
+    );
+}
+
+/// Adds a default rule signed by `key` and returns its id.
+fn add_default_rule(s: &Setup, key: &SigningKey) -> u32 {
+    s.account
+        .add_context_rule(
+            &ContextRuleType::Default,
+            &String::from_str(&s.env, "default"),
+            &None,
+            &Vec::from_array(&s.env, [phantom_signer(&s.env, &s.verifier, key)]),
+            &Map::new(&s.env),
+        )
+        .id
+}
+
+/// The `AuthEvent` of a `Setup::check_auth_with` call.
+fn auth_event(s: &Setup, rule_ids: &[u32], signature_count: u32) -> (Address, Vec<Val>, Val) {
+    let mut ids = Vec::<u32>::new(&s.env);
+    for id in rule_ids {
+        ids.push_back(*id);
+    }
+    let data = Map::<Symbol, Val>::from_array(
+        &s.env,
+        [
+            (
+                Symbol::new(&s.env, "context_rule_ids"),
+                ids.into_val(&s.env),
+            ),
+            (
+                Symbol::new(&s.env, "signature_count"),
+                signature_count.into_val(&s.env),
+            ),
+            (
+                Symbol::new(&s.env, "signature_payload"),
+                BytesN::from_array(&s.env, &[1u8; 32]).into_val(&s.env),
+            ),
+        ],
+    );
+    (
+        s.account.address.clone(),
+        (Symbol::new(&s.env, "latch"), Symbol::new(&s.env, "auth")).into_val(&s.env),
+        data.into_val(&s.env),
+    )
+}
+
+/// Asserts the last `__check_auth`, signed by one key, matched `rule_id` for
+/// its only context.
+fn assert_matched_rule(s: &Setup, rule_id: u32) {
+    assert_eq!(
+        s.env.events().all(),
+        soroban_sdk::vec![&s.env, auth_event(s, &[rule_id], 1)]
+    );
+}
+
+#[test]
+fn test_higher_priority_default_rule_wins_over_scoped() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let default_rule = add_default_rule(&s, &s.key);
+
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    assert_matched_rule(&s, s.counter_rule());
+
+    s.account.set_rule_priority(&default_rule, &10);
+    assert_eq!(s.account.get_rule_priority(&default_rule), 10);
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    assert_matched_rule(&s, default_rule);
+}
+
+#[test]
+fn test_swapping_priorities_flips_matched_rule() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let default_rule = add_default_rule(&s, &s.key);
+
+    s.account.set_rule_priority(&s.counter_rule(), &5);
+    s.account.set_rule_priority(&default_rule, &1);
+    s.check_auth(&[s.increment_context()]).unwrap();
+    assert_matched_rule(&s, s.counter_rule());
+
+    s.account.set_rule_priority(&s.counter_rule(), &1);
+    s.account.set_rule_priority(&default_rule, &5);
+    s.check_auth(&[s.increment_context()]).unwrap();
+    assert_matched_rule(&s, default_rule);
+}
+
+#[test]
+fn test_unsatisfied_high_priority_rule_falls_through() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let other_key = SigningKey::from_bytes(&[9u8; 32]);
+    let default_rule = add_default_rule(&s, &other_key);
+    s.account.set_rule_priority(&default_rule, &10);
+
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    assert_matched_rule(&s, s.counter_rule());
+
+    assert_eq!(
+        s.check_auth_with(&[&other_key], &[s.increment_context()]),
+        Ok(())
+    );
+    assert_matched_rule(&s, default_rule);
+}
+
+#[test]
+fn test_execute_relays_authorized_call() {
+    let s = Setup::new();
+    let func = symbol_short!("increment");
+    let args: Vec<Val> = (s.account.address.clone(),).into_val(&s.env);
+
+    // The relayer only submits; the Phantom key signs the execute call.
+    s.authorize(
+        &s.account.address,
+        "execute",
+        (s.counter.clone(), func.clone(), args.clone(), 0u64).into_val(&s.env),
+    );
+    let result = s.account.execute(&s.counter, &func, &args, &0);
+
+    assert_eq!(u64::try_from_val(&s.env, &result).unwrap(), 1);
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 1);
+    assert_eq!(s.account.get_nonce(), 1);
+}
+
+#[test]
+fn test_execute_relays_increment_by() {
+    let s = Setup::new();
+    let func = Symbol::new(&s.env, "increment_by");
+    let args: Vec<Val> = (s.account.address.clone(), 5u32).into_val(&s.env);
+
+    s.authorize(
+        &s.account.address,
+        "execute",
+        (s.counter.clone(), func.clone(), args.clone(), 0u64).into_val(&s.env),
+    );
+    let result = s.account.execute(&s.counter, &func, &args, &0);
+
+    assert_eq!(u64::try_from_val(&s.env, &result).unwrap(), 5);
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 5);
+}
+
+#[test]
+fn test_execute_without_auth_fails() {
+    let s = Setup::new();
+    let args: Vec<Val> = (s.account.address.clone(),).into_val(&s.env);
+
+    assert!(s
+        .account
+        .try_execute(&s.counter, &symbol_short!("increment"), &args, &0)
+        .is_err());
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 0);
+}
+
+#[test]
+fn test_execute_rejects_self_call() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let laptop = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]));
+    let args: Vec<Val> = (s.counter_rule(), laptop).into_val(&s.env);
+
+    assert_eq!(
+        s.account.try_execute(
+            &s.account.address,
+            &Symbol::new(&s.env, "add_signer"),
+            &args,
+            &0
+        ),
+        Err(Ok(PhantomAccountError::SelfInvocation.into()))
+    );
+}
+
+#[test]
+fn test_execute_nonces_increase_sequentially() {
+    let s = Setup::new();
+    let func = symbol_short!("increment");
+    let args: Vec<Val> = (s.account.address.clone(),).into_val(&s.env);
+
+    for nonce in 0..3u64 {
+        assert_eq!(s.account.get_nonce(), nonce);
+        s.authorize(
+            &s.account.address,
+            "execute",
+            (s.counter.clone(), func.clone(), args.clone(), nonce).into_val(&s.env),
+        );
+        s.account.execute(&s.counter, &func, &args, &nonce);
+    }
+    assert_eq!(s.account.get_nonce(), 3);
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 3);
+}
+
+#[test]
+fn test_execute_replay_fails_with_bad_nonce() {
+    let s = Setup::new();
+    let func = symbol_short!("increment");
+    let args: Vec<Val> = (s.account.address.clone(),).into_val(&s.env);
+    let authorized: Vec<Val> =
+        (s.counter.clone(), func.clone(), args.clone(), 0u64).into_val(&s.env);
+
+    s.authorize(&s.account.address, "execute", authorized.clone());
+    s.account.execute(&s.counter, &func, &args, &0);
+
+    // The same signed tuple, resubmitted under a fresh auth entry.
+    s.authorize(&s.account.address, "execute", authorized);
+    assert_eq!(
+        s.account.try_execute(&s.counter, &func, &args, &0),
+        Err(Ok(PhantomAccountError::BadNonce.into()))
+    );
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 1);
+}
+
+fn increment_call(s: &Setup) -> CallSpec {
+    CallSpec {
+        target: s.counter.clone(),
+        func: symbol_short!("increment"),
+        args: (s.account.address.clone(),).into_val(&s.env),
+    }
+}
+
+#[test]
+fn test_execute_batch_runs_all_calls() {
+    let s = Setup::new();
+    let calls = Vec::from_array(&s.env, [increment_call(&s), increment_call(&s)]);
+
+    s.authorize(
+        &s.account.address,
+        "execute_batch",
+        (calls.clone(), 0u64).into_val(&s.env),
+    );
+    let results = s.account.execute_batch(&calls, &0);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        u64::try_from_val(&s.env, &results.get(1).unwrap()).unwrap(),
+        2
+    );
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 2);
+}
+
+#[test]
+fn test_execute_batch_reverts_when_a_call_traps() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let missing = CallSpec {
+        target: s.counter.clone(),
+        func: symbol_short!("missing"),
+        args: Vec::new(&s.env),
+    };
+    let calls = Vec::from_array(&s.env, [increment_call(&s), missing]);
+
+    assert!(s.account.try_execute_batch(&calls, &0).is_err());
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 0);
+}
+
+#[test]
+fn test_execute_batch_rejects_empty_and_oversized_batches() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    assert_eq!(
+        s.account.try_execute_batch(&Vec::new(&s.env), &0),
+        Err(Ok(PhantomAccountError::EmptyBatch.into()))
+    );
+
+    let mut calls = Vec::new(&s.env);
+    for _ in 0..=MAX_BATCH_LEN {
+        calls.push_back(increment_call(&s));
+    }
+    assert_eq!(
+        s.account.try_execute_batch(&calls, &0),
+        Err(Ok(PhantomAccountError::BatchTooLong.into()))
+    );
+}
+
+/// Adds a laptop key next to the Phantom key on the counter rule.
+fn add_laptop_signer(s: &Setup) -> (SigningKey, Signer) {
+    let laptop_key = SigningKey::from_bytes(&[9u8; 32]);
+    let laptop = phantom_signer(&s.env, &s.verifier, &laptop_key);
+    s.account.add_signer(&s.counter_rule(), &laptop);
+    (laptop_key, laptop)
+}
+
+#[test]
+fn test_expired_signer_no_longer_counts() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (laptop_key, laptop) = add_laptop_signer(&s);
+    let until = s.env.ledger().sequence() + 10;
+    s.account
+        .set_signer_expiry(&s.counter_rule(), &laptop, &Some(until));
+
+    let context = s.increment_context();
+    assert_eq!(
+        s.check_auth_with(&[&s.key, &laptop_key], &[context.clone()]),
+        Ok(())
+    );
+
+    s.env.ledger().set_sequence_number(until + 1);
+    assert_eq!(
+        s.check_auth_with(&[&laptop_key], &[context.clone()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+    assert_eq!(s.check_auth_with(&[&s.key], &[context]), Ok(()));
+}
+
+#[test]
+fn test_rule_whose_signers_all_expired_authorizes_nothing() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let until = s.env.ledger().sequence() + 10;
+    s.account
+        .set_signer_expiry(&s.admin_rule(), &s.signer(), &Some(until));
+    s.env.ledger().set_sequence_number(until + 1);
+
+    // A call on the account itself, which only the self-admin rule covers
+    let context = Context::Contract(ContractContext {
+        contract: s.account.address.clone(),
+        fn_name: Symbol::new(&s.env, "add_signer"),
+        args: Vec::new(&s.env),
+    });
+    assert_eq!(
+        s.check_auth_signed(Signatures(Map::new(&s.env)), &[context]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+}
+
+#[test]
+fn test_clearing_signer_expiry_restores_validity() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (laptop_key, laptop) = add_laptop_signer(&s);
+    let until = s.env.ledger().sequence() + 10;
+    s.account
+        .set_signer_expiry(&s.counter_rule(), &laptop, &Some(until));
+    s.env.ledger().set_sequence_number(until + 1);
+
+    s.account
+        .set_signer_expiry(&s.counter_rule(), &laptop, &None);
+    let context = s.increment_context();
+    assert_eq!(
+        s.check_auth_with(&[&s.key], &[context.clone()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+    assert_eq!(
+        s.check_auth_with(&[&s.key, &laptop_key], &[context]),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_get_signers_exposes_expiry_until_removal() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let (_, laptop) = add_laptop_signer(&s);
+    s.account.set_signer_expiry(&rule_id, &laptop, &Some(500));
+
+    assert_eq!(
+        s.account.get_signers(&rule_id),
+        Vec::from_array(
+            &s.env,
+            [
+                SignerInfo {
+                    signer: s.signer(),
+                    label: None,
+                    valid_until: None,
+                    weight: 1,
+                    keys: None,
+                },
+                SignerInfo {
+                    signer: laptop.clone(),
+                    label: None,
+                    valid_until: Some(500),
+                    weight: 1,
+                    keys: None,
+                },
+            ]
+        )
+    );
+
+    s.account.remove_signer(&rule_id, &laptop);
+    let expiry = s.env.as_contract(&s.account.address, || {
+        storage::get_signer_expiry(&s.env, rule_id, &laptop)
+    });
+    assert_eq!(expiry, None);
+}
+
+#[test]
+fn test_set_signer_expiry_rejects_unknown_signer() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let stranger = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]));
+
+    assert_eq!(
+        s.account
+            .try_set_signer_expiry(&s.counter_rule(), &stranger, &Some(500)),
+        Err(Ok(PhantomAccountError::SignerNotFound.into()))
+    );
+}
+
+/// Registers an uninitialized account and signs its init parameters.
+fn signed_init(s: &Setup) -> (PhantomSmartAccountClient<'static>, BytesN<32>, BytesN<64>) {
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+    let public_key = BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes());
+
+    let payload = account.init_payload(&s.verifier, &public_key, &s.counter);
+    let init_sig = BytesN::from_array(&s.env, &phantom_signature(&s.key, &payload.to_array()));
+    (account, public_key, init_sig)
+}
+
+#[test]
+fn test_initialize_signed_installs_key() {
+    let s = Setup::new();
+    let (account, public_key, init_sig) = signed_init(&s);
+
+    account.initialize_signed(&s.verifier, &public_key, &s.counter, &init_sig);
+    assert_eq!(account.get_context_rules_count(), 2);
+    assert_eq!(
+        account
+            .get_context_rule(&rule_for(&account, &s.counter))
+            .signers,
+        Vec::from_array(&s.env, [s.signer()])
+    );
+
+    assert_eq!(
+        account.try_initialize_signed(&s.verifier, &public_key, &s.counter, &init_sig),
+        Err(Ok(PhantomAccountError::AlreadyInitialized.into()))
+    );
+}
+
+#[test]
+fn test_initialize_signed_rejects_tampered_counter() {
+    let s = Setup::new();
+    let (account, public_key, init_sig) = signed_init(&s);
+    let other_counter = s.env.register(Counter, (None::<Address>,));
+
+    assert!(account
+        .try_initialize_signed(&s.verifier, &public_key, &other_counter, &init_sig)
+        .is_err());
+    assert_eq!(account.get_context_rules_count(), 0);
+}
+
+#[test]
+fn test_initialize_signed_rejects_replay_on_other_account() {
+    let s = Setup::new();
+    let (_, public_key, init_sig) = signed_init(&s);
+    let (other_account, _, _) = signed_init(&s);
+
+    assert!(other_account
+        .try_initialize_signed(&s.verifier, &public_key, &s.counter, &init_sig)
+        .is_err());
+    assert_eq!(other_account.get_context_rules_count(), 0);
+}
+
+/// Registers a rule for a stand-in token contract, signed by the Phantom key.
+fn add_token_rule(s: &Setup) -> (Address, u32) {
+    let token = Address::generate(&s.env);
+    let rule = s.account.add_context_rule(
+        &ContextRuleType::CallContract(token.clone()),
+        &String::from_str(&s.env, "token"),
+        &None,
+        &Vec::from_array(&s.env, [s.signer()]),
+        &Map::new(&s.env),
+    );
+    (token, rule.id)
+}
+
+fn transfer_context(s: &Setup, token: &Address, amount: Val) -> Context {
+    let to = Address::generate(&s.env);
+    Context::Contract(ContractContext {
+        contract: token.clone(),
+        fn_name: symbol_short!("transfer"),
+        args: Vec::from_array(
+            &s.env,
+            [
+                s.account.address.into_val(&s.env),
+                to.into_val(&s.env),
+                amount,
+            ],
+        ),
+    })
+}
+
+#[test]
+fn test_amount_cap_allows_calls_up_to_cap() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (token, rule_id) = add_token_rule(&s);
+    s.account.set_call_amount_cap(&rule_id, &100, &2);
+    assert_eq!(
+        s.account.get_call_amount_cap(&rule_id),
+        Some(AmountCap {
+            max_amount: 100,
+            arg_index: 2,
+        })
+    );
+
+    let amount = 100i128.into_val(&s.env);
+    assert_eq!(
+        s.check_auth(&[transfer_context(&s, &token, amount)]),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_amount_cap_rejects_larger_amounts() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (token, rule_id) = add_token_rule(&s);
+    s.account.set_call_amount_cap(&rule_id, &100, &2);
+
+    let amount = 101i128.into_val(&s.env);
+    assert_eq!(
+        s.check_auth(&[transfer_context(&s, &token, amount)]),
+        Err(Ok(PhantomAccountError::AmountExceedsCap))
+    );
+}
+
+#[test]
+fn test_amount_cap_rejects_non_numeric_argument() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (token, rule_id) = add_token_rule(&s);
+    s.account.set_call_amount_cap(&rule_id, &100, &1);
+
+    // Argument 1 is the recipient address.
+    let amount = 1i128.into_val(&s.env);
+    assert_eq!(
+        s.check_auth(&[transfer_context(&s, &token, amount)]),
+        Err(Ok(PhantomAccountError::InvalidCapArgument))
+    );
+}
+
+#[test]
+fn test_removing_amount_cap_restores_unrestricted_calls() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (token, rule_id) = add_token_rule(&s);
+    s.account.set_call_amount_cap(&rule_id, &100, &2);
+    s.account.remove_call_amount_cap(&rule_id);
+
+    assert_eq!(s.account.get_call_amount_cap(&rule_id), None);
+    let amount = 1_000_000i128.into_val(&s.env);
+    assert_eq!(
+        s.check_auth(&[transfer_context(&s, &token, amount)]),
+        Ok(())
+    );
+}
+
+fn reset_context(s: &Setup) -> Context {
+    Context::Contract(ContractContext {
+        contract: s.counter.clone(),
+        fn_name: symbol_short!("reset"),
+        args: Vec::new(&s.env),
+    })
+}
+
+#[test]
+fn test_allowed_functions_restrict_rule() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let fns = Vec::from_array(&s.env, [symbol_short!("increment")]);
+    s.account.set_allowed_functions(&rule_id, &fns);
+    assert_eq!(s.account.get_allowed_functions(&rule_id), fns);
+
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    assert_eq!(
+        s.check_auth(&[reset_context(&s)]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+}
+
+#[test]
+fn test_counter_rule_authorizes_increment_by() {
+    let s = Setup::new();
+    assert_eq!(s.check_auth(&[s.increment_by_context(5)]), Ok(()));
+
+    // A rule limited to increment and increment_by still covers it
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    s.account.set_allowed_functions(
+        &rule_id,
+        &Vec::from_array(
+            &s.env,
+            [
+                symbol_short!("increment"),
+                Symbol::new(&s.env, "increment_by"),
+            ],
+        ),
+    );
+    assert_eq!(s.check_auth(&[s.increment_by_context(5)]), Ok(()));
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    assert_eq!(
+        s.check_auth(&[reset_context(&s)]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+}
+
+#[test]
+fn test_clearing_allowed_functions_restores_full_access() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    s.account.set_allowed_functions(
+        &rule_id,
+        &Vec::from_array(&s.env, [symbol_short!("increment")]),
+    );
+
+    s.account.set_allowed_functions(&rule_id, &Vec::new(&s.env));
+    assert!(s.account.get_allowed_functions(&rule_id).is_empty());
+    assert_eq!(s.check_auth(&[reset_context(&s)]), Ok(()));
+}
+
+#[test]
+fn test_raising_threshold_requires_more_signatures() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let (laptop_key, _) = add_laptop_signer(&s);
+    s.account.update_threshold(&rule_id, &1);
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+
+    s.account.update_threshold(&rule_id, &2);
+    assert_eq!(s.account.get_threshold(&rule_id), 2);
+    assert_eq!(
+        s.env.events().all(),
+        soroban_sdk::vec![
+            &s.env,
+            (
+                s.account.address.clone(),
+                (Symbol::new(&s.env, "threshold_updated"), rule_id).into_val(&s.env),
+                Map::<Symbol, Val>::from_array(
+                    &s.env,
+                    [
+                        (Symbol::new(&s.env, "new_threshold"), 2u32.into_val(&s.env)),
+                        (Symbol::new(&s.env, "old_threshold"), 1u32.into_val(&s.env)),
+                    ]
+                )
+                .into_val(&s.env),
+            ),
+        ]
+    );
+
+    assert_eq!(
+        s.check_auth(&[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+    assert_eq!(
+        s.check_auth_with(&[&s.key, &laptop_key], &[s.increment_context()]),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_update_threshold_rejects_invalid_values() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let (_, laptop) = add_laptop_signer(&s);
+
+    for threshold in [0, 3] {
+        assert_eq!(
+            s.account.try_update_threshold(&rule_id, &threshold),
+            Err(Ok(PhantomAccountError::InvalidThreshold.into()))
+        );
+    }
+
+    // An expired signer cannot help reach the threshold.
+    s.account.set_signer_expiry(&rule_id, &laptop, &Some(10));
+    s.env.ledger().set_sequence_number(11);
+    assert_eq!(
+        s.account.try_update_threshold(&rule_id, &2),
+        Err(Ok(PhantomAccountError::InvalidThreshold.into()))
+    );
+}
+
+#[test]
+fn test_signer_label_round_trip() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let label = String::from_str(&s.env, "Phantom (phone)");
+    s.account.label_signer(&rule_id, &s.signer(), &label);
+
+    let signers = s.account.get_signers_detailed(&rule_id);
+    assert_eq!(signers.len(), 1);
+    assert_eq!(signers.get(0).unwrap().label, Some(label));
+}
+
+#[test]
+fn test_signer_label_removed_with_signer() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let (_, laptop) = add_laptop_signer(&s);
+    s.account
+        .label_signer(&rule_id, &laptop, &String::from_str(&s.env, "laptop"));
+
+    s.account.remove_signer(&rule_id, &laptop);
+    s.account.add_signer(&rule_id, &laptop);
+    let signers = s.account.get_signers_detailed(&rule_id);
+    assert_eq!(signers.get(1).unwrap().signer, laptop);
+    assert_eq!(signers.get(1).unwrap().label, None);
+}
+
+#[test]
+fn test_signer_label_length_capped() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let longest = [b'a'; MAX_LABEL_LEN as usize];
+    let too_long = [b'a'; MAX_LABEL_LEN as usize + 1];
+
+    s.account
+        .label_signer(&rule_id, &s.signer(), &String::from_bytes(&s.env, &longest));
+    assert_eq!(
+        s.account.try_label_signer(
+            &rule_id,
+            &s.signer(),
+            &String::from_bytes(&s.env, &too_long)
+        ),
+        Err(Ok(PhantomAccountError::LabelTooLong.into()))
+    );
+}
+
+#[test]
+fn test_initialize_rejects_account_as_target() {
+    let s = Setup::new();
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+    let public_key = BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes());
+
+    assert_eq!(
+        account.try_initialize(&s.verifier, &public_key, &account_id),
+        Err(Ok(PhantomAccountError::TargetIsAccount.into()))
+    );
+    assert_eq!(account.get_context_rules_count(), 0);
+}
+
+#[test]
+fn test_initialize_rejects_verifier_as_target() {
+    let s = Setup::new();
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+    let public_key = BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes());
+
+    assert_eq!(
+        account.try_initialize(&s.verifier, &public_key, &s.verifier),
+        Err(Ok(PhantomAccountError::TargetIsVerifier.into()))
+    );
+    assert_eq!(account.get_context_rules_count(), 0);
+}
+
+#[test]
+fn test_initialize_accepts_distinct_target() {
+    let s = Setup::new();
+    let other_counter = s.env.register(Counter, (None::<Address>,));
+    let account = s.new_account(&s.key, &other_counter);
+
+    assert_eq!(account.get_context_rules_count(), 2);
+    rule_for(&account, &other_counter);
+}
+
+#[test]
+fn test_rule_stats_count_each_use() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    assert_eq!(s.account.get_rule_stats(&rule_id), RuleStats::default());
+
+    s.check_auth(&[s.increment_context()]).unwrap();
+    s.env.ledger().set_sequence_number(42);
+    s.check_auth(&[s.increment_context()]).unwrap();
+
+    assert_eq!(
+        s.account.get_rule_stats(&rule_id),
+        RuleStats {
+            use_count: 2,
+            last_used_ledger: 42,
+        }
+    );
+    assert_eq!(s.account.get_rule_stats(&s.admin_rule()).use_count, 0);
+}
+
+#[test]
+fn test_rule_stats_survive_signer_changes() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    s.check_auth(&[s.increment_context()]).unwrap();
+
+    s.env.mock_all_auths();
+    let (_, laptop) = add_laptop_signer(&s);
+    s.account.remove_signer(&rule_id, &laptop);
+    assert_eq!(s.account.get_rule_stats(&rule_id).use_count, 1);
+}
+
+#[test]
+fn test_rule_stats_removed_with_rule() {
+    let s = Setup::new();
+    let rule_id = s.counter_rule();
+    s.check_auth(&[s.increment_context()]).unwrap();
+
+    s.env.mock_all_auths();
+    s.account.remove_context_rule(&rule_id);
+    let has_stats = s.env.as_contract(&s.account.address, || {
+        s.env
+            .storage()
+            .persistent()
+            .has(&storage::PhantomStorageKey::RuleStats(rule_id))
+    });
+    assert!(!has_stats);
+}
+
+fn ping_args(s: &Setup, verifier: &Address) -> Vec<Val> {
+    let public_key = BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes());
+    (verifier.clone(), public_key, s.counter.clone()).into_val(&s.env)
+}
+
+#[test]
+fn test_initialize_and_ping_increments_counter() {
+    let s = Setup::new();
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+    let public_key = BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes());
+
+    let invocation = contract_invocation(
+        &s.env,
+        &account_id,
+        "initialize_and_ping",
+        ping_args(&s, &s.verifier),
+    );
+    s.env
+        .set_auths(&[auth_entry(&s.env, &account_id, 0, invocation, |payload| {
+            s.sign(payload)
+        })]);
+    let count = account.initialize_and_ping(&s.verifier, &public_key, &s.counter);
+
+    assert_eq!(count, 1);
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 1);
+    assert_eq!(account.get_context_rules_count(), 2);
+}
+
+#[test]
+fn test_initialize_and_ping_rolls_back_on_failed_auth() {
+    let s = Setup::new();
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+    let public_key = BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes());
+    // Not a verifier: the installed signer can never verify.
+    let wrong_verifier = s.env.register(Counter, (None::<Address>,));
+
+    let invocation = contract_invocation(
+        &s.env,
+        &account_id,
+        "initialize_and_ping",
+        ping_args(&s, &wrong_verifier),
+    );
+    s.env
+        .set_auths(&[auth_entry(&s.env, &account_id, 0, invocation, |payload| {
+            s.sign(payload)
+        })]);
+
+    assert!(account
+        .try_initialize_and_ping(&wrong_verifier, &public_key, &s.counter)
+        .is_err());
+    assert_eq!(account.get_context_rules_count(), 0);
+    assert_eq!(CounterClient::new(&s.env, &s.counter).get(), 0);
+}
+
+#[test]
+fn test_rule_cap_blocks_creation() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    assert_eq!(s.account.get_max_rules(), DEFAULT_MAX_RULES);
+    s.account.set_max_rules(&3);
+
+    add_default_rule(&s, &s.key);
+    let res = s.account.try_add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "one too many"),
+        &None,
+        &Vec::from_array(&s.env, [s.signer()]),
+        &Map::new(&s.env),
+    );
+    assert_eq!(res, Err(Ok(PhantomAccountError::TooManyRules.into())));
+    assert_eq!(s.account.get_context_rules_count(), 3);
+}
+
+#[test]
+fn test_removing_rule_frees_a_slot() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_max_rules(&3);
+    let rule_id = add_default_rule(&s, &s.key);
+
+    s.account.remove_context_rule(&rule_id);
+    add_default_rule(&s, &s.key);
+    assert_eq!(s.account.get_context_rules_count(), 3);
+}
+
+#[test]
+fn test_lowering_rule_cap_keeps_existing_rules() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_max_rules(&1);
+
+    assert_eq!(s.account.get_context_rules_count(), 2);
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    let res = s.account.try_add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "blocked"),
+        &None,
+        &Vec::from_array(&s.env, [s.signer()]),
+        &Map::new(&s.env),
+    );
+    assert_eq!(res, Err(Ok(PhantomAccountError::TooManyRules.into())));
+}
+
+#[test]
+fn test_auth_event_published_on_success() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (laptop_key, _) = add_laptop_signer(&s);
+    let contexts = [s.increment_context(), s.increment_context()];
+
+    assert_eq!(s.check_auth_with(&[&s.key, &laptop_key], &contexts), Ok(()));
+    let rule_id = s.counter_rule();
+    assert_eq!(
+        s.env.events().all(),
+        soroban_sdk::vec![&s.env, auth_event(&s, &[rule_id, rule_id], 2)]
+    );
+}
+
+#[test]
+fn test_auth_event_not_published_on_failure() {
+    let s = Setup::new();
+    let stranger = SigningKey::from_bytes(&[9u8; 32]);
+
+    assert!(s
+        .check_auth_with(&[&stranger], &[s.increment_context()])
+        .is_err());
+    assert!(s.env.events().all().is_empty());
+}
+
+#[test]
+fn test_auth_events_can_be_turned_off() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_auth_events(&false);
+    assert!(!s.account.auth_events_enabled());
+
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    assert!(s.env.events().all().is_empty());
+
+    s.account.set_auth_events(&true);
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    assert_matched_rule(&s, s.counter_rule());
+}
+
+/// Adds a default rule whose only signer is the Phantom key pinned to a
+/// rejecting primary verifier.
+fn add_rejected_signer(s: &Setup) -> (u32, Signer) {
+    let primary = s.env.register(RejectVerifier, ());
+    let signer = Signer::External(
+        primary,
+        Bytes::from_slice(&s.env, &s.key.verifying_key().to_bytes()),
+    );
+    let rule = s.account.add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "pinned"),
+        &None,
+        &Vec::from_array(&s.env, [signer.clone()]),
+        &Map::new(&s.env),
+    );
+    (rule.id, signer)
+}
+
+/// Signatures map with the Phantom key's signature under `signer`.
+fn sign_as(s: &Setup, signer: &Signer) -> Signatures {
+    Signatures(Map::from_array(
+        &s.env,
+        [(signer.clone(), phantom_sig_data(&s.env, &s.key, &[1u8; 32]))],
+    ))
+}
+
+/// A call only the default rule covers.
+fn unscoped_context(s: &Setup) -> Context {
+    Context::Contract(ContractContext {
+        contract: Address::generate(&s.env),
+        fn_name: symbol_short!("reset"),
+        args: Vec::new(&s.env),
+    })
+}
+
+#[test]
+fn test_fallback_verifier_accepts_when_primary_rejects() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (rule_id, signer) = add_rejected_signer(&s);
+    assert_eq!(
+        s.check_auth_signed(sign_as(&s, &signer), &[unscoped_context(&s)]),
+        Err(Ok(PhantomAccountError::ExternalVerificationFailed))
+    );
+
+    s.account
+        .add_fallback_verifier(&rule_id, &signer, &s.verifier);
+    assert_eq!(
+        s.account.get_fallback_verifier(&signer),
+        Some(s.verifier.clone())
+    );
+    assert_eq!(
+        s.check_auth_signed(sign_as(&s, &signer), &[unscoped_context(&s)]),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_fallback_verifier_rejecting_too_fails() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (rule_id, signer) = add_rejected_signer(&s);
+    let fallback = s.env.register(RejectVerifier, ());
+    s.account
+        .add_fallback_verifier(&rule_id, &signer, &fallback);
+
+    assert_eq!(
+        s.check_auth_signed(sign_as(&s, &signer), &[unscoped_context(&s)]),
+        Err(Ok(PhantomAccountError::ExternalVerificationFailed))
+    );
+    assert_eq!(
+        s.account
+            .try_add_fallback_verifier(&rule_id, &signer, &s.verifier),
+        Err(Ok(PhantomAccountError::FallbackVerifierExists.into()))
+    );
+}
+
+#[test]
+fn test_removing_fallback_verifier_restores_primary_only() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (rule_id, signer) = add_rejected_signer(&s);
+    s.account
+        .add_fallback_verifier(&rule_id, &signer, &s.verifier);
+
+    s.account.remove_fallback_verifier(&rule_id, &signer);
+    assert_eq!(s.account.get_fallback_verifier(&signer), None);
+    assert_eq!(
+        s.check_auth_signed(sign_as(&s, &signer), &[unscoped_context(&s)]),
+        Err(Ok(PhantomAccountError::ExternalVerificationFailed))
+    );
+}
+
+/// Asserts `get_config` agrees with the full rule getters.
+fn assert_config_matches_rules(s: &Setup) {
+    let config = s.account.get_config();
+    assert_eq!(config.rule_count, s.account.get_context_rules_count());
+    assert_eq!(config.rules.len(), config.rule_count);
+    for summary in config.rules.iter() {
+        assert_eq!(s.account.get_context_rule(&summary.id).name, summary.name);
+    }
+}
+
+#[test]
+fn test_get_config_after_initialize() {
+    let s = Setup::new();
+    assert_eq!(
+        s.account.get_config(),
+        AccountConfig {
+            rule_count: 2,
+            rules: Vec::from_array(
+                &s.env,
+                [
+                    RuleSummary {
+                        id: s.counter_rule(),
+                        name: String::from_str(&s.env, "phantom-signer"),
+                    },
+                    RuleSummary {
+                        id: s.admin_rule(),
+                        name: String::from_str(&s.env, "self-admin"),
+                    },
+                ]
+            ),
+            closed: false,
+            storage_version: STORAGE_VERSION,
+            nonce: 0,
+        }
+    );
+}
+
+#[test]
+fn test_get_config_tracks_rule_changes() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    let rule_id = add_default_rule(&s, &s.key);
+    assert_config_matches_rules(&s);
+
+    s.account
+        .update_context_rule_name(&rule_id, &String::from_str(&s.env, "renamed"));
+    assert_config_matches_rules(&s);
+    assert_eq!(
+        s.account.get_config().rules.get(2).unwrap().name,
+        String::from_str(&s.env, "renamed")
+    );
+
+    s.account.remove_context_rule(&s.counter_rule());
+    assert_config_matches_rules(&s);
+    assert_eq!(s.account.get_config().rule_count, 2);
+}
+
+#[test]
+fn test_migrate_backfills_rule_names() {
+    let s = Setup::new();
+    write_v1_layout(&s);
+    s.account.migrate();
+    assert_config_matches_rules(&s);
+}
+
+#[test]
+fn test_unknown_signer_entry_rejected() {
+    let s = Setup::new();
+    let stranger = SigningKey::from_bytes(&[9u8; 32]);
+
+    assert_eq!(
+        s.check_auth_with(&[&s.key, &stranger], &[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnknownSigner))
+    );
+}
+
+#[test]
+fn test_unknown_signer_event_names_entry() {
+    let s = Setup::new();
+    let stranger_key = SigningKey::from_bytes(&[9u8; 32]);
+    let stranger = phantom_signer(&s.env, &s.verifier, &stranger_key);
+
+    assert_eq!(
+        s.check_auth_with(&[&s.key, &stranger_key], &[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnknownSigner))
+    );
+    let events = latch_events(&s, "unknown_signer");
+    assert_eq!(events.len(), 1);
+    let signers = events[0].get(Symbol::new(&s.env, "signers")).unwrap();
+    assert_eq!(
+        Vec::<Signer>::try_from_val(&s.env, &signers).unwrap(),
+        Vec::from_array(&s.env, [stranger])
+    );
+}
+
+#[test]
+fn test_exact_signer_set_passes() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let (laptop_key, _) = add_laptop_signer(&s);
+
+    assert_eq!(
+        s.check_auth_with(&[&s.key, &laptop_key], &[s.increment_context()]),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_unknown_signers_identifies_entry() {
+    let s = Setup::new();
+    let stranger = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]));
+    let contexts = Vec::from_array(&s.env, [s.increment_context()]);
+
+    assert_eq!(
+        s.account.unknown_signers(
+            &Vec::from_array(&s.env, [s.signer(), stranger.clone()]),
+            &contexts
+        ),
+        Vec::from_array(&s.env, [stranger])
+    );
+    assert!(s
+        .account
+        .unknown_signers(&Vec::from_array(&s.env, [s.signer()]), &contexts)
+        .is_empty());
+}
+
+/// Registers a Stellar asset contract and mints `amount` to the account.
+fn funded_token(s: &Setup, amount: i128) -> Address {
+    let admin = Address::generate(&s.env);
+    let token = s.env.register_stellar_asset_contract_v2(admin).address();
+    StellarAssetClient::new(&s.env, &token).mint(&s.account.address, &amount);
+    token
+}
+
+#[test]
+fn test_wind_down_sweeps_and_closes() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let first = funded_token(&s, 100);
+    let second = funded_token(&s, 7);
+    let sweep_to = Address::generate(&s.env);
+
+    s.account.wind_down(
+        &sweep_to,
+        &Vec::from_array(&s.env, [first.clone(), second.clone()]),
+    );
+
+    for (token, amount) in [(first, 100), (second, 7)] {
+        let token = TokenClient::new(&s.env, &token);
+        assert_eq!(token.balance(&s.account.address), 0);
+        assert_eq!(token.balance(&sweep_to), amount);
+    }
+    assert_eq!(s.account.get_context_rules_count(), 0);
+    assert!(s.account.is_closed());
+    let config = s.account.get_config();
+    assert!(config.closed);
+    assert_eq!(config.rule_count, 0);
+}
+
+#[test]
+fn test_wound_down_account_rejects_auth_for_good() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account
+        .wind_down(&Address::generate(&s.env), &Vec::new(&s.env));
+
+    assert_eq!(
+        s.check_auth(&[s.increment_context()]),
+        Err(Ok(PhantomAccountError::AccountClosed))
+    );
+    let public_key = BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes());
+    assert_eq!(
+        s.account
+            .try_initialize(&s.verifier, &public_key, &s.counter),
+        Err(Ok(PhantomAccountError::AccountClosed.into()))
+    );
+}
+
+#[test]
+fn test_wind_down_aborts_on_trapping_token() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let token = funded_token(&s, 100);
+    let trap = s.env.register(TrapToken, ());
+
+    let res = s.account.try_wind_down(
+        &Address::generate(&s.env),
+        &Vec::from_array(&s.env, [token.clone(), trap]),
+    );
+    assert!(res.is_err());
+
+    assert_eq!(
+        TokenClient::new(&s.env, &token).balance(&s.account.address),
+        100
+    );
+    assert_eq!(s.account.get_context_rules_count(), 2);
+    assert!(!s.account.is_closed());
+}
+
+/// Expires the counter rule at the current ledger, which is returned.
+fn expire_counter_rule(s: &Setup) -> u32 {
+    let until = s.env.ledger().sequence();
+    s.account
+        .update_context_rule_valid_until(&s.counter_rule(), &Some(until));
+    until
+}
+
+#[test]
+fn test_expired_rule_authorizes_inside_grace() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_auth_events(&false);
+    s.account.set_expiry_grace(&MAX_EXPIRY_GRACE);
+    let until = expire_counter_rule(&s);
+    s.env.ledger().set_sequence_number(until + MAX_EXPIRY_GRACE);
+
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    assert_eq!(
+        s.env.events().all(),
+        soroban_sdk::vec![
+            &s.env,
+            (
+                s.account.address.clone(),
+                (Symbol::new(&s.env, "grace_used"), s.counter_rule()).into_val(&s.env),
+                Map::<Symbol, Val>::from_array(
+                    &s.env,
+                    [(Symbol::new(&s.env, "valid_until"), until.into_val(&s.env))]
+                )
+                .into_val(&s.env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_expired_rule_rejected_after_grace() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_expiry_grace(&5);
+    let until = expire_counter_rule(&s);
+    s.env.ledger().set_sequence_number(until + 6);
+
+    assert_eq!(
+        s.check_auth(&[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+}
+
+#[test]
+fn test_zero_grace_expires_rules_strictly() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_expiry_grace(&5);
+    s.account.set_expiry_grace(&0);
+    assert_eq!(s.account.get_expiry_grace(), 0);
+    let until = expire_counter_rule(&s);
+
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+    s.env.ledger().set_sequence_number(until + 1);
+    assert_eq!(
+        s.check_auth(&[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+}
+
+#[test]
+fn test_set_expiry_grace_rejects_above_cap() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    assert_eq!(
+        s.account.try_set_expiry_grace(&(MAX_EXPIRY_GRACE + 1)),
+        Err(Ok(PhantomAccountError::GraceTooLong.into()))
+    );
+    assert_eq!(s.account.get_expiry_grace(), 0);
+}
+
+#[test]
+fn test_initialize_generic_stores_key_data_as_is() {
+    let s = Setup::new();
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+    // An uncompressed secp256r1 point.
+    let key_data = Bytes::from_array(&s.env, &[4u8; 65]);
+
+    account.initialize_generic(
+        &s.verifier,
+        &key_data,
+        &s.counter,
+        &String::from_str(&s.env, "p256-signer"),
+    );
+
+    let rule = account.get_context_rule(&rule_for(&account, &s.counter));
+    assert_eq!(
+        rule.signers,
+        Vec::from_array(&s.env, [Signer::External(s.verifier.clone(), key_data)])
+    );
+    assert_eq!(rule.name, String::from_str(&s.env, "p256-signer"));
+    let names: std::vec::Vec<String> = account
+        .get_config()
+        .rules
+        .iter()
+        .map(|summary| summary.name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            String::from_str(&s.env, "p256-signer"),
+            String::from_str(&s.env, "self-admin"),
+        ]
+    );
+}
+
+#[test]
+fn test_initialize_generic_rejects_empty_key_data() {
+    let s = Setup::new();
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+
+    assert_eq!(
+        account.try_initialize_generic(
+            &s.verifier,
+            &Bytes::new(&s.env),
+            &s.counter,
+            &String::from_str(&s.env, "empty"),
+        ),
+        Err(Ok(PhantomAccountError::EmptyKeyData.into()))
+    );
+    assert_eq!(account.get_context_rules_count(), 0);
+}
+
+/// A registry resolving `ed25519` to the setup's verifier.
+fn ed25519_registry(s: &Setup) -> VerifierRegistryClient<'static> {
+    let registry = VerifierRegistryClient::new(
+        &s.env,
+        &s.env
+            .register(VerifierRegistry, (Address::generate(&s.env),)),
+    );
+    s.env.mock_all_auths();
+    registry.register(&symbol_short!("ed25519"), &s.verifier);
+    registry
+}
+
+#[test]
+fn test_initialize_from_registry_authenticates() {
+    let s = Setup::new();
+    let registry = ed25519_registry(&s);
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+    let key = SigningKey::from_bytes(&[31u8; 32]);
+
+    account.initialize_from_registry(
+        &registry.address,
+        &symbol_short!("ed25519"),
+        &Bytes::from_slice(&s.env, &key.verifying_key().to_bytes()),
+        &s.counter,
+        &String::from_str(&s.env, "phantom-signer"),
+    );
+
+    let signer = phantom_signer(&s.env, &s.verifier, &key);
+    assert_eq!(
+        account
+            .get_context_rule(&rule_for(&account, &s.counter))
+            .signers,
+        Vec::from_array(&s.env, [signer.clone()])
+    );
+
+    let signatures = Signatures(Map::from_array(
+        &s.env,
+        [(signer, phantom_sig_data(&s.env, &key, &[1u8; 32]))],
+    ));
+    let increment = Context::Contract(ContractContext {
+        contract: s.counter.clone(),
+        fn_name: symbol_short!("increment"),
+        args: (account.address.clone(),).into_val(&s.env),
+    });
+    assert_eq!(
+        s.env.try_invoke_contract_check_auth::<PhantomAccountError>(
+            &account.address,
+            &BytesN::from_array(&s.env, &[1u8; 32]),
+            signatures.into_val(&s.env),
+            &Vec::from_array(&s.env, [increment]),
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_initialize_from_registry_rejects_unknown_key_type() {
+    let s = Setup::new();
+    let registry = ed25519_registry(&s);
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+
+    assert!(account
+        .try_initialize_from_registry(
+            &registry.address,
+            &symbol_short!("p256"),
+            &Bytes::from_array(&s.env, &[4u8; 65]),
+            &s.counter,
+            &String::from_str(&s.env, "p256-signer"),
+        )
+        .is_err());
+    assert_eq!(account.get_context_rules_count(), 0);
+}
+
+/// Events of the last invocation whose first topic is `name`.
+fn count_events(s: &Setup, name: &str) -> u32 {
+    let name = Symbol::new(&s.env, name);
+    let mut count = 0;
+    for (_, topics, _) in s.env.events().all().iter() {
+        let topic = topics
+            .get(0)
+            .map(|topic| Symbol::try_from_val(&s.env, &topic));
+        if matches!(topic, Some(Ok(topic)) if topic == name) {
+            count += 1;
+        }
+    }
+    count
+}
+
+#[test]
+fn test_replace_verifier_moves_matching_signers() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let (laptop_key, laptop) = add_laptop_signer(&s);
+    let elsewhere = Signer::External(
+        Address::generate(&s.env),
+        Bytes::from_array(&s.env, &[5u8; 32]),
+    );
+    s.account.add_signer(&rule_id, &elsewhere);
+    let until = s.env.ledger().sequence() + 1_000;
+    s.account
+        .label_signer(&rule_id, &laptop, &String::from_str(&s.env, "laptop"));
+    s.account.set_signer_expiry(&rule_id, &laptop, &Some(until));
+    s.account.update_threshold(&rule_id, &2);
+
+    let verifier_v2 = s.env.register(
+        Ed25519Verifier,
+        (
+            None::<Address>,
+            None::<Bytes>,
+            true,
+            None::<Bytes>,
+            None::<u32>,
+        ),
+    );
+    s.account
+        .replace_verifier(&rule_id, &s.verifier, &verifier_v2);
+    assert_eq!(count_events(&s, "verifier_replaced"), 2);
+
+    let phantom_v2 = phantom_signer(&s.env, &verifier_v2, &s.key);
+    let laptop_v2 = phantom_signer(&s.env, &verifier_v2, &laptop_key);
+    let signers = s.account.get_signers_detailed(&rule_id);
+    assert_eq!(signers.len(), 3);
+    for expected in [
+        SignerInfo {
+            signer: phantom_v2.clone(),
+            label: None,
+            valid_until: None,
+            weight: 1,
+            keys: None,
+        },
+        SignerInfo {
+            signer: laptop_v2.clone(),
+            label: Some(String::from_str(&s.env, "laptop")),
+            valid_until: Some(until),
+            weight: 1,
+            keys: None,
+        },
+        SignerInfo {
+            signer: elsewhere,
+            label: None,
+            valid_until: None,
+            weight: 1,
+            keys: None,
+        },
+    ] {
+        assert!(signers.contains(&expected), "{expected:?}");
+    }
+    assert_eq!(s.account.get_threshold(&rule_id), 2);
+
+    let signatures = Signatures(Map::from_array(
+        &s.env,
+        [
+            (phantom_v2, phantom_sig_data(&s.env, &s.key, &[1u8; 32])),
+            (laptop_v2, phantom_sig_data(&s.env, &laptop_key, &[1u8; 32])),
+        ],
+    ));
+    assert_eq!(
+        s.check_auth_signed(signatures, &[s.increment_context()]),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_replace_verifier_without_matching_signer_is_noop() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let before = s.account.get_context_rule(&rule_id);
+
+    s.account.replace_verifier(
+        &rule_id,
+        &Address::generate(&s.env),
+        &s.env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        ),
+    );
+    assert_eq!(count_events(&s, "verifier_replaced"), 0);
+    assert_eq!(s.account.get_context_rule(&rule_id), before);
+    assert_eq!(s.check_auth(&[s.increment_context()]), Ok(()));
+}
+
+#[test]
+fn test_policy_cannot_reenter_account_during_install() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let before = s.account.get_context_rule(&rule_id);
+    let policy = s.env.register(ReentrantPolicy, ());
+
+    let install_param: Val = ().into_val(&s.env);
+    assert!(s
+        .account
+        .try_add_policy(&rule_id, &policy, &install_param)
+        .is_err());
+    assert_eq!(s.account.get_context_rule(&rule_id), before);
+}
+
+#[test]
+fn test_reading_policy_installs() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let policy = s.env.register(ReadingPolicy, ());
+
+    s.account
+        .add_policy(&rule_id, &policy, &s.counter.clone().into_val(&s.env));
+    assert_eq!(
+        s.account.get_context_rule(&rule_id).policies,
+        Vec::from_array(&s.env, [policy])
+    );
+}
+
+#[test]
+fn test_busy_flag_cleared_after_install() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let policy = s.env.register(ReadingPolicy, ());
+
+    s.account
+        .add_policy(&rule_id, &policy, &s.counter.clone().into_val(&s.env));
+    let busy = s
+        .env
+        .as_contract(&s.account.address, || storage::is_busy(&s.env));
+    assert!(!busy);
+    s.account.remove_policy(&rule_id, &policy);
+}
+
+#[test]
+fn test_management_calls_refused_while_busy() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.env
+        .as_contract(&s.account.address, || storage::set_busy(&s.env, true));
+
+    let signer = Signer::Delegated(Address::generate(&s.env));
+    assert_eq!(
+        s.account.try_add_signer(&s.counter_rule(), &signer),
+        Err(Ok(PhantomAccountError::Reentrancy.into()))
+    );
+    assert_eq!(
+        s.account.try_set_max_rules(&5),
+        Err(Ok(PhantomAccountError::Reentrancy.into()))
+    );
+    let args: Vec<Val> = (s.account.address.clone(),).into_val(&s.env);
+    assert_eq!(
+        s.account
+            .try_execute(&s.counter, &symbol_short!("increment"), &args, &0),
+        Err(Ok(PhantomAccountError::Reentrancy.into()))
+    );
+
+    // Reads stay open
+    assert_eq!(s.account.get_context_rules_count(), 2);
+    assert_eq!(s.account.get_nonce(), 0);
+}
+
+/// A day-long session rule on the counter, with an `AllowPolicy`.
+fn session_template(s: &Setup) -> RuleTemplate {
+    let mut policies = Map::new(&s.env);
+    policies.set(s.env.register(AllowPolicy, ()), ().into_val(&s.env));
+    RuleTemplate {
+        context_type: ContextRuleType::CallContract(s.counter.clone()),
+        valid_for: Some(17_280),
+        policies,
+    }
+}
+
+#[test]
+fn test_instantiate_template_applies_overrides() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let template = session_template(&s);
+    s.account
+        .save_template(&symbol_short!("session"), &template);
+
+    let session_key = phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[21u8; 32]));
+    let rule = s.account.instantiate_template(
+        &symbol_short!("session"),
+        &TemplateOverrides {
+            name: String::from_str(&s.env, "session-1"),
+            signers: Vec::from_array(&s.env, [session_key.clone()]),
+        },
+    );
+
+    assert_eq!(rule, s.account.get_context_rule(&rule.id));
+    assert_eq!(rule.context_type, template.context_type);
+    assert_eq!(rule.name, String::from_str(&s.env, "session-1"));
+    assert_eq!(rule.valid_until, Some(s.env.ledger().sequence() + 17_280));
+    assert_eq!(rule.signers, Vec::from_array(&s.env, [session_key]));
+    assert_eq!(rule.policies, template.policies.keys());
+    assert!(s.account.is_rule_active(&rule.id));
+}
+
+#[test]
+fn test_save_template_overwrites_and_delete_removes() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let name = symbol_short!("session");
+    s.account.save_template(&name, &session_template(&s));
+    let replacement = RuleTemplate {
+        valid_for: None,
+        ..session_template(&s)
+    };
+    s.account.save_template(&name, &replacement);
+    assert_eq!(s.account.get_template(&name), Some(replacement));
+
+    s.account.delete_template(&name);
+    assert_eq!(s.account.get_template(&name), None);
+    assert_eq!(
+        s.account.try_delete_template(&name),
+        Err(Ok(PhantomAccountError::TemplateNotFound.into()))
+    );
+    assert_eq!(
+        s.account.try_instantiate_template(
+            &name,
+            &TemplateOverrides {
+                name: String::from_str(&s.env, "session-1"),
+                signers: Vec::from_array(&s.env, [s.signer()]),
+            },
+        ),
+        Err(Ok(PhantomAccountError::TemplateNotFound.into()))
+    );
+}
+
+#[test]
+fn test_save_template_rejects_above_cap() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let template = session_template(&s);
+    for i in 0..MAX_TEMPLATES {
+        let name = Symbol::new(&s.env, &std::format!("t{i}"));
+        s.account.save_template(&name, &template);
+    }
+
+    assert_eq!(
+        s.account
+            .try_save_template(&symbol_short!("onemore"), &template),
+        Err(Ok(PhantomAccountError::TooManyTemplates.into()))
+    );
+    // Overwriting an existing template is still fine.
+    s.account
+        .save_template(&Symbol::new(&s.env, "t0"), &template);
+}
+
+/// The data of every `("latch", name)` event so far, including those of
+/// failed calls, which the SDK's `events()` leaves out.
+fn latch_events(s: &Setup, name: &str) -> std::vec::Vec<Map<Symbol, Val>> {
+    let topics: Vec<Val> = (symbol_short!("latch"), Symbol::new(&s.env, name)).into_val(&s.env);
+    let mut events = std::vec::Vec::new();
+    for event in s.env.host().get_events().unwrap().0 {
+        let ContractEventBody::V0(body) = event.event.body;
+        let mut event_topics = Vec::<Val>::new(&s.env);
+        for topic in body.topics.iter() {
+            event_topics.push_back(Val::try_from_val(&s.env, topic).unwrap());
+        }
+        if event_topics != topics {
+            continue;
+        }
+        let data = Val::try_from_val(&s.env, &body.data).unwrap();
+        events.push(Map::<Symbol, Val>::try_from_val(&s.env, &data).unwrap());
+    }
+    events
+}
+
+/// The rule diagnoses of every `AuthFailed` event so far.
+fn auth_failures(s: &Setup) -> std::vec::Vec<Vec<RuleDiagnosis>> {
+    latch_events(s, "auth_failed")
+        .iter()
+        .map(|data| {
+            let rules = data.get(Symbol::new(&s.env, "rules")).unwrap();
+            Vec::try_from_val(&s.env, &rules).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_debug_events_explain_expired_rule() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_debug_events(&true);
+    let until = expire_counter_rule(&s);
+    s.env.ledger().set_sequence_number(until + 1);
+
+    assert_eq!(
+        s.check_auth(&[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+    assert_eq!(
+        auth_failures(&s),
+        [Vec::from_array(
+            &s.env,
+            [RuleDiagnosis {
+                context_rule_id: s.counter_rule(),
+                failure: RuleFailure::Expired,
+            }]
+        )]
+    );
+}
+
+#[test]
+fn test_debug_events_name_rejecting_verifier() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    s.account.set_debug_events(&true);
+    let (rule_id, signer) = add_rejected_signer(&s);
+
+    assert_eq!(
+        s.check_auth_signed(sign_as(&s, &signer), &[unscoped_context(&s)]),
+        Err(Ok(PhantomAccountError::ExternalVerificationFailed))
+    );
+    assert_eq!(
+        auth_failures(&s),
+        [Vec::from_array(
+            &s.env,
+            [RuleDiagnosis {
+                context_rule_id: rule_id,
+                failure: RuleFailure::VerifierFalse,
+            }]
+        )]
+    );
+}
+
+#[test]
+fn test_no_debug_events_by_default() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    assert!(!s.account.debug_events_enabled());
+    let until = expire_counter_rule(&s);
+    s.env.ledger().set_sequence_number(until + 1);
+
+    assert_eq!(
+        s.check_auth(&[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+    assert!(auth_failures(&s).is_empty());
+}
+
+#[test]
+fn test_garbage_signature_does_not_block_other_signer() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let (laptop_key, laptop) = add_laptop_signer(&s);
+    s.account.update_threshold(&rule_id, &1);
+
+    let signatures = Signatures(Map::from_array(
+        &s.env,
+        [
+            (s.signer(), Bytes::from_slice(&s.env, b"garbage")),
+            (laptop, phantom_sig_data(&s.env, &laptop_key, &[1u8; 32])),
+        ],
+    ));
+    assert_eq!(
+        s.check_auth_signed(signatures, &[s.increment_context()]),
+        Ok(())
+    );
+}
+
+/// sig_data for `secp256k1-verifier`: `key` over sha256 of the prefixed
+/// `payload`.
+fn secp256k1_sig_data(env: &Env, key: &k256::ecdsa::SigningKey, payload: &[u8; 32]) -> Bytes {
+    let mut message = AUTH_PREFIX.to_vec();
+    message.extend_from_slice(&bytes_to_hex(payload));
+    let prefixed_message = Bytes::from_slice(env, &message);
+    let digest = env.crypto().sha256(&prefixed_message).to_array();
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&digest).unwrap();
+    let signature: [u8; 64] = signature.to_bytes().into();
+
+    Secp256k1SigData {
+        prefixed_message,
+        signature: BytesN::from_array(env, &signature),
+        recovery_id: recovery_id.to_byte() as u32,
+    }
+    .to_xdr(env)
+}
+
+#[test]
+fn test_secp256k1_signer_authorizes_next_to_phantom_key() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let secp_verifier = s.env.register(Secp256k1Verifier, ());
+    let secp_key = k256::ecdsa::SigningKey::from_slice(&[9u8; 32]).unwrap();
+    let point = secp_key.verifying_key().as_affine().to_encoded_point(true);
+    let secp_signer = Signer::External(secp_verifier, Bytes::from_slice(&s.env, point.as_bytes()));
+    s.account.add_signer(&rule_id, &secp_signer);
+    s.account.update_threshold(&rule_id, &2);
+
+    let secp_sig = secp256k1_sig_data(&s.env, &secp_key, &[1u8; 32]);
+    let signatures = Signatures(Map::from_array(
+        &s.env,
+        [
+            (s.signer(), phantom_sig_data(&s.env, &s.key, &[1u8; 32])),
+            (secp_signer.clone(), secp_sig.clone()),
+        ],
+    ));
+    assert_eq!(
+        s.check_auth_signed(signatures, &[s.increment_context()]),
+        Ok(())
+    );
+
+    // The secp256k1 signature alone is below the threshold
+    let signatures = Signatures(Map::from_array(&s.env, [(secp_signer, secp_sig)]));
+    assert_eq!(
+        s.check_auth_signed(signatures, &[s.increment_context()]),
+        Err(Ok(PhantomAccountError::UnvalidatedContext))
+    );
+}
+
+/// A secp256k1 signer holding `key` uncompressed, 65 bytes of key_data.
+fn uncompressed_secp256k1_signer(env: &Env, verifier: &Address, key: &[u8; 32]) -> Signer {
+    let key = k256::ecdsa::SigningKey::from_slice(key).unwrap();
+    let point = key.verifying_key().as_affine().to_encoded_point(false);
+    Signer::External(verifier.clone(), Bytes::from_slice(env, point.as_bytes()))
+}
+
+#[test]
+fn test_rule_with_long_secp256k1_keys_lists_and_rotates() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    let secp_verifier = s.env.register(Secp256k1Verifier, ());
+    let first = uncompressed_secp256k1_signer(&s.env, &secp_verifier, &[9u8; 32]);
+    let second = uncompressed_secp256k1_signer(&s.env, &secp_verifier, &[10u8; 32]);
+    s.account.add_signer(&rule_id, &first);
+    s.account.add_signer(&rule_id, &second);
+
+    // Neither 65-byte key_data is read as a list of keys
+    let signers = s.account.get_signers_detailed(&rule_id);
+    assert_eq!(signers.len(), 3);
+    for info in signers.iter() {
+        assert_eq!(info.keys, None);
+    }
+
+    let third = uncompressed_secp256k1_signer(&s.env, &secp_verifier, &[11u8; 32]);
+    s.account.rotate_key(&rule_id, &first, &third);
+    let signers = s.account.get_context_rule(&rule_id).signers;
+    assert!(!signers.contains(&first));
+    assert!(signers.contains(&second));
+    assert!(signers.contains(&third));
+}
@@ -0,0 +1,258 @@
+#![cfg(test)]
+// Access control: every entry point that changes the account's rules or
+// settings must be authorized by the account itself. The calls here carry
+// no auth at all and must fail in `require_auth`, before touching state.
+use crate::{
+    test::{phantom_signer, AllowPolicy, Setup},
+    CallSpec, PhantomAccountError, PhantomSmartAccount, PhantomSmartAccountClient,
+};
+use ed25519_dalek::SigningKey;
+use soroban_sdk::{
+    symbol_short,
+    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation},
+    xdr::{ScErrorCode, ScErrorType},
+    Address, BytesN, Error, IntoVal, InvokeError, Map, String, Symbol, Val, Vec,
+};
+use stellar_accounts::smart_account::{ContextRuleType, Signer};
+
+extern crate std;
+
+fn assert_unauthorized<T, C>(name: &str, result: Result<Result<T, C>, Result<Error, InvokeError>>) {
+    let auth_error = Error::from_type_and_code(ScErrorType::Auth, ScErrorCode::InvalidAction);
+    assert_eq!(
+        result.err(),
+        Some(Ok(auth_error)),
+        "{name} ran without the account's auth"
+    );
+}
+
+fn other_signer(s: &Setup) -> Signer {
+    phantom_signer(&s.env, &s.verifier, &SigningKey::from_bytes(&[9u8; 32]))
+}
+
+#[test]
+fn test_rule_management_requires_account_auth() {
+    let s = Setup::new();
+    let rule = s.counter_rule();
+    let signer = other_signer(&s);
+    let policy = s.env.register(AllowPolicy, ());
+    let install_param: Val = ().into_val(&s.env);
+    let before = s.account.get_config();
+
+    assert_unauthorized(
+        "add_context_rule",
+        s.account.try_add_context_rule(
+            &ContextRuleType::Default,
+            &String::from_str(&s.env, "takeover"),
+            &None,
+            &Vec::from_array(&s.env, [signer.clone()]),
+            &Map::new(&s.env),
+        ),
+    );
+    assert_unauthorized(
+        "update_context_rule_name",
+        s.account
+            .try_update_context_rule_name(&rule, &String::from_str(&s.env, "renamed")),
+    );
+    assert_unauthorized(
+        "update_context_rule_valid_until",
+        s.account
+            .try_update_context_rule_valid_until(&rule, &Some(1)),
+    );
+    assert_unauthorized(
+        "remove_context_rule",
+        s.account.try_remove_context_rule(&rule),
+    );
+    assert_unauthorized(
+        "remove_context_rule_with_force",
+        s.account.try_remove_context_rule_with_force(&rule, &true),
+    );
+    assert_unauthorized("add_signer", s.account.try_add_signer(&rule, &signer));
+    assert_unauthorized(
+        "remove_signer",
+        s.account.try_remove_signer(&rule, &s.signer()),
+    );
+    assert_unauthorized(
+        "remove_signer_with_force",
+        s.account
+            .try_remove_signer_with_force(&rule, &s.signer(), &true),
+    );
+    assert_unauthorized(
+        "add_policy",
+        s.account.try_add_policy(&rule, &policy, &install_param),
+    );
+    assert_unauthorized("remove_policy", s.account.try_remove_policy(&rule, &policy));
+    assert_unauthorized(
+        "add_delegated_signer",
+        s.account
+            .try_add_delegated_signer(&rule, &Address::generate(&s.env)),
+    );
+
+    assert_eq!(s.account.get_config(), before);
+}
+
+#[test]
+fn test_rule_settings_require_account_auth() {
+    let s = Setup::new();
+    let rule = s.counter_rule();
+
+    assert_unauthorized("set_max_rules", s.account.try_set_max_rules(&1));
+    assert_unauthorized(
+        "set_rule_active",
+        s.account.try_set_rule_active(&rule, &false),
+    );
+    assert_unauthorized(
+        "set_rule_rate_limit",
+        s.account.try_set_rule_rate_limit(&rule, &1, &10),
+    );
+    assert_unauthorized(
+        "remove_rule_rate_limit",
+        s.account.try_remove_rule_rate_limit(&rule),
+    );
+    assert_unauthorized(
+        "update_threshold",
+        s.account.try_update_threshold(&rule, &1),
+    );
+    assert_unauthorized(
+        "set_call_amount_cap",
+        s.account.try_set_call_amount_cap(&rule, &1, &0),
+    );
+    assert_unauthorized(
+        "remove_call_amount_cap",
+        s.account.try_remove_call_amount_cap(&rule),
+    );
+    assert_unauthorized(
+        "set_allowed_functions",
+        s.account
+            .try_set_allowed_functions(&rule, &Vec::from_array(&s.env, [symbol_short!("get")])),
+    );
+    assert_unauthorized("set_auth_events", s.account.try_set_auth_events(&false));
+    assert_unauthorized("set_debug_events", s.account.try_set_debug_events(&true));
+    assert_unauthorized("set_expiry_grace", s.account.try_set_expiry_grace(&1));
+    assert_unauthorized(
+        "set_rule_priority",
+        s.account.try_set_rule_priority(&rule, &1),
+    );
+
+    assert!(s.account.is_rule_active(&rule));
+    assert_eq!(s.account.get_rule_rate_limit(&rule), None);
+    assert!(s.account.auth_events_enabled());
+}
+
+#[test]
+fn test_signer_settings_require_account_auth() {
+    let s = Setup::new();
+    let rule = s.counter_rule();
+    let signer = s.signer();
+
+    assert_unauthorized(
+        "set_signer_expiry",
+        s.account.try_set_signer_expiry(&rule, &signer, &Some(1)),
+    );
+    assert_unauthorized(
+        "label_signer",
+        s.account
+            .try_label_signer(&rule, &signer, &String::from_str(&s.env, "mine")),
+    );
+    assert_unauthorized(
+        "add_fallback_verifier",
+        s.account
+            .try_add_fallback_verifier(&rule, &signer, &Address::generate(&s.env)),
+    );
+    assert_unauthorized(
+        "remove_fallback_verifier",
+        s.account.try_remove_fallback_verifier(&rule, &signer),
+    );
+    assert_unauthorized(
+        "rotate_key",
+        s.account.try_rotate_key(&rule, &signer, &other_signer(&s)),
+    );
+    assert_unauthorized(
+        "rotate_key_with_grace",
+        s.account
+            .try_rotate_key_with_grace(&rule, &signer, &other_signer(&s), &10),
+    );
+
+    assert_eq!(s.account.get_key_rotation(&rule), None);
+    assert_eq!(
+        s.account.get_context_rule(&rule).signers,
+        Vec::from_array(&s.env, [signer])
+    );
+}
+
+#[test]
+fn test_execution_and_lifecycle_require_account_auth() {
+    let s = Setup::new();
+    let call = CallSpec {
+        target: s.counter.clone(),
+        func: symbol_short!("increment"),
+        args: (s.account.address.clone(),).into_val(&s.env),
+    };
+
+    assert_unauthorized(
+        "execute",
+        s.account
+            .try_execute(&call.target, &call.func, &call.args, &0),
+    );
+    assert_unauthorized(
+        "execute_batch",
+        s.account
+            .try_execute_batch(&Vec::from_array(&s.env, [call]), &0),
+    );
+    assert_unauthorized(
+        "wind_down",
+        s.account
+            .try_wind_down(&Address::generate(&s.env), &Vec::new(&s.env)),
+    );
+
+    let account_id = s.env.register(PhantomSmartAccount, ());
+    let account = PhantomSmartAccountClient::new(&s.env, &account_id);
+    let public_key = BytesN::from_array(&s.env, &s.key.verifying_key().to_bytes());
+    assert_unauthorized(
+        "initialize_and_ping",
+        account.try_initialize_and_ping(&s.verifier, &public_key, &s.counter),
+    );
+
+    assert_eq!(s.account.get_nonce(), 0);
+    assert!(!s.account.is_closed());
+    assert_eq!(account.get_context_rules_count(), 0);
+}
+
+#[test]
+fn test_initialize_rejects_initialized_account() {
+    let s = Setup::new();
+    let before = s.account.get_config();
+    let attacker = SigningKey::from_bytes(&[66u8; 32]);
+    let public_key = BytesN::from_array(&s.env, &attacker.verifying_key().to_bytes());
+
+    assert_eq!(
+        s.account
+            .try_initialize(&s.verifier, &public_key, &s.counter),
+        Err(Ok(PhantomAccountError::AlreadyInitialized.into()))
+    );
+    assert_eq!(s.account.get_config(), before);
+}
+
+#[test]
+fn test_add_signer_requires_only_account_auth() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule = s.counter_rule();
+    let signer = other_signer(&s);
+
+    s.account.add_signer(&rule, &signer);
+    assert_eq!(
+        s.env.auths(),
+        std::vec![(
+            s.account.address.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    s.account.address.clone(),
+                    Symbol::new(&s.env, "add_signer"),
+                    (rule, signer).into_val(&s.env),
+                )),
+                sub_invocations: std::vec![],
+            }
+        )]
+    );
+}
@@ -0,0 +1,58 @@
+#![cfg(test)]
+// Budget regression test for the auth path: a realistic account setup,
+// checked through the real Ed25519Verifier.
+use crate::test::{phantom_signer, AllowPolicy, Setup};
+use ed25519_dalek::SigningKey;
+use soroban_sdk::{IntoVal, Map, String, Val, Vec};
+use stellar_accounts::smart_account::ContextRuleType;
+
+extern crate std;
+
+/// CPU instructions one `__check_auth` may use. The network allows 100M
+/// per transaction; staying at a fifth leaves the target call most of it.
+const CHECK_AUTH_CPU_CEILING: u64 = 20_000_000;
+
+/// Memory bytes one `__check_auth` may use, against the network's 40 MiB
+/// per transaction.
+const CHECK_AUTH_MEM_CEILING: u64 = 8 * 1024 * 1024;
+
+#[test]
+fn test_check_auth_stays_within_budget() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+
+    // Three rules: counter (two signers), self-admin, and a default rule
+    // with a policy.
+    let laptop_key = SigningKey::from_bytes(&[9u8; 32]);
+    s.account.add_signer(
+        &s.counter_rule(),
+        &phantom_signer(&s.env, &s.verifier, &laptop_key),
+    );
+    let policy = s.env.register(AllowPolicy, ());
+    let install_param: Val = ().into_val(&s.env);
+    s.account.add_context_rule(
+        &ContextRuleType::Default,
+        &String::from_str(&s.env, "policy"),
+        &None,
+        &Vec::from_array(&s.env, [s.signer()]),
+        &Map::from_array(&s.env, [(policy, install_param)]),
+    );
+    assert_eq!(s.account.get_context_rules_count(), 3);
+
+    let budget = s.env.cost_estimate().budget();
+    budget.reset_default();
+    s.check_auth_with(&[&s.key, &laptop_key], &[s.increment_context()])
+        .unwrap();
+    let cpu = budget.cpu_instruction_cost();
+    let mem = budget.memory_bytes_cost();
+
+    std::println!("__check_auth budget: cpu {cpu} insns, mem {mem} bytes");
+    assert!(
+        cpu <= CHECK_AUTH_CPU_CEILING,
+        "__check_auth used {cpu} CPU instructions, ceiling is {CHECK_AUTH_CPU_CEILING}"
+    );
+    assert!(
+        mem <= CHECK_AUTH_MEM_CEILING,
+        "__check_auth used {mem} memory bytes, ceiling is {CHECK_AUTH_MEM_CEILING}"
+    );
+}
@@ -0,0 +1,92 @@
+#![cfg(test)]
+// End-to-end: a top-level `counter.increment(account)` authorized only by a
+// real auth entry, so the call goes account `__check_auth` → Ed25519Verifier
+// → Counter with nothing mocked.
+use crate::test::{phantom_sig_data, phantom_signer, Setup};
+use counter::CounterClient;
+use ed25519_dalek::SigningKey;
+use ed25519_verifier::Ed25519SigData;
+use soroban_sdk::{
+    symbol_short,
+    testutils::{AuthorizedFunction, AuthorizedInvocation},
+    xdr::{FromXdr, ToXdr},
+    BytesN, IntoVal, Map,
+};
+use stellar_accounts::smart_account::Signatures;
+
+extern crate std;
+
+#[test]
+fn test_increment_with_real_phantom_signature() {
+    let s = Setup::new();
+    let counter = CounterClient::new(&s.env, &s.counter);
+
+    s.authorize(
+        &s.counter,
+        "increment",
+        (s.account.address.clone(),).into_val(&s.env),
+    );
+    assert_eq!(counter.increment(&s.account.address), 1);
+    assert_eq!(counter.get(), 1);
+    assert_eq!(
+        s.env.auths(),
+        std::vec![(
+            s.account.address.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    s.counter.clone(),
+                    symbol_short!("increment"),
+                    (s.account.address.clone(),).into_val(&s.env),
+                )),
+                sub_invocations: std::vec![],
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_increment_with_corrupted_signature_fails() {
+    let s = Setup::new();
+    let counter = CounterClient::new(&s.env, &s.counter);
+
+    s.authorize_with(
+        &s.counter,
+        "increment",
+        (s.account.address.clone(),).into_val(&s.env),
+        |payload| {
+            let sig_data = phantom_sig_data(&s.env, &s.key, payload);
+            let mut sig = Ed25519SigData::from_xdr(&s.env, &sig_data).unwrap();
+            let mut bytes = sig.signature.to_array();
+            bytes[0] ^= 0xff;
+            sig.signature = BytesN::from_array(&s.env, &bytes);
+
+            Signatures(Map::from_array(
+                &s.env,
+                [(
+                    phantom_signer(&s.env, &s.verifier, &s.key),
+                    sig.to_xdr(&s.env),
+                )],
+            ))
+        },
+    );
+    assert!(counter.try_increment(&s.account.address).is_err());
+    assert_eq!(counter.get(), 0);
+}
+
+#[test]
+fn test_increment_for_other_account_not_covered() {
+    let s = Setup::new();
+    let counter = CounterClient::new(&s.env, &s.counter);
+    let other = s
+        .new_account(&SigningKey::from_bytes(&[9u8; 32]), &s.counter)
+        .address;
+
+    // The entry signs for `s.account`, not for `other`.
+    s.authorize(
+        &s.counter,
+        "increment",
+        (s.account.address.clone(),).into_val(&s.env),
+    );
+    assert!(counter.try_increment(&other).is_err());
+    assert_eq!(counter.get(), 0);
+}
@@ -0,0 +1,53 @@
+#![cfg(test)]
+// Storage layout snapshot: upgrading the account wasm must not silently
+// change the keys or value shapes the account keeps next to its context
+// rules, or upgraded accounts would lose their settings.
+use crate::{storage::PhantomStorageKey, test::Setup, testutils::storage_entries};
+use soroban_sdk::TryFromVal;
+
+extern crate std;
+use std::string::String;
+
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/test_fixtures/storage_layout.json"
+);
+
+/// Set to rewrite the fixture after an intended layout change.
+const BLESS_VAR: &str = "LATCH_BLESS_LAYOUT";
+
+#[test]
+fn test_storage_layout_matches_fixture() {
+    let s = Setup::new();
+    s.env.mock_all_auths();
+    let rule_id = s.counter_rule();
+    s.account.set_rule_rate_limit(&rule_id, &2, &100);
+    s.account.set_rule_priority(&rule_id, &5);
+    s.account.set_max_rules(&10);
+    s.account.set_auth_events(&false);
+
+    let names = [
+        (s.account.address.clone(), "account"),
+        (s.verifier.clone(), "verifier"),
+        (s.counter.clone(), "counter"),
+    ];
+    // Only the account's own keys; the context rules themselves are laid
+    // out by `stellar_accounts`.
+    let layout = storage_entries(&s.env, &s.account.address, &names, |key| {
+        PhantomStorageKey::try_from_val(&s.env, key).is_ok()
+    });
+    let actual = serde_json::to_string_pretty(&layout).unwrap() + "\n";
+
+    if std::env::var_os(BLESS_VAR).is_some() {
+        std::fs::write(FIXTURE, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(FIXTURE).unwrap_or_else(|_| String::new());
+    assert!(
+        actual == expected,
+        "the account's storage layout no longer matches {FIXTURE}.\n\
+         If the change is intended (and `migrate` handles existing accounts), \
+         rerun with {BLESS_VAR}=1 to bless the new layout and commit the fixture.\n\n\
+         expected:\n{expected}\nactual:\n{actual}"
+    );
+}
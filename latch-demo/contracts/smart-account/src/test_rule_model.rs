@@ -0,0 +1,376 @@
+#![cfg(test)]
+// Model-based property test for context rule CRUD: random operation
+// sequences are applied to the account and to a plain `std` model of its
+// rules, and the two are compared after every step. On failure proptest
+// shrinks the sequence down to the operations that matter.
+use crate::{
+    test::{phantom_signer, AllowPolicy, Setup},
+    DEFAULT_MAX_RULES,
+};
+use ed25519_dalek::SigningKey;
+use proptest::{collection::vec, prelude::*};
+use soroban_sdk::{Address, Env, IntoVal, Map, String, Val, Vec};
+use stellar_accounts::smart_account::{ContextRuleType, Signer};
+
+extern crate std;
+use std::{
+    collections::{HashMap, HashSet},
+    format,
+    vec::Vec as StdVec,
+};
+
+const SIGNER_POOL: usize = 4;
+const POLICY_POOL: usize = 2;
+
+#[derive(Clone, Debug)]
+enum Op {
+    /// Signers and policies are bitmasks over the pools.
+    AddRule {
+        signers: u8,
+        policies: u8,
+    },
+    AddSigner {
+        rule: usize,
+        signer: usize,
+    },
+    RemoveSigner {
+        rule: usize,
+        signer: usize,
+    },
+    AddPolicy {
+        rule: usize,
+        policy: usize,
+    },
+    RemovePolicy {
+        rule: usize,
+        policy: usize,
+    },
+    Rename {
+        rule: usize,
+    },
+    Expire {
+        rule: usize,
+        ledgers: u32,
+    },
+    RemoveRule {
+        rule: usize,
+    },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    // `rule` is resolved against the ids issued so far, so it also hits
+    // removed rules and the next, never issued, id.
+    let rule = 0..64usize;
+    prop_oneof![
+        (0..1u8 << SIGNER_POOL, 0..1u8 << POLICY_POOL)
+            .prop_map(|(signers, policies)| Op::AddRule { signers, policies }),
+        (rule.clone(), 0..SIGNER_POOL).prop_map(|(rule, signer)| Op::AddSigner { rule, signer }),
+        (rule.clone(), 0..SIGNER_POOL).prop_map(|(rule, signer)| Op::RemoveSigner { rule, signer }),
+        (rule.clone(), 0..POLICY_POOL).prop_map(|(rule, policy)| Op::AddPolicy { rule, policy }),
+        (rule.clone(), 0..POLICY_POOL).prop_map(|(rule, policy)| Op::RemovePolicy { rule, policy }),
+        rule.clone().prop_map(|rule| Op::Rename { rule }),
+        (rule.clone(), 1..1_000u32).prop_map(|(rule, ledgers)| Op::Expire { rule, ledgers }),
+        rule.prop_map(|rule| Op::RemoveRule { rule }),
+    ]
+}
+
+/// A rule as the model sees it; signers and policies are pool indices.
+#[derive(Clone, Debug)]
+struct ModelRule {
+    name: String,
+    signers: StdVec<usize>,
+    policies: StdVec<usize>,
+    valid_until: Option<u32>,
+}
+
+struct Harness {
+    s: Setup,
+    signers: StdVec<Signer>,
+    policies: StdVec<Address>,
+    rules: HashMap<u32, ModelRule>,
+    removed: HashSet<u32>,
+    next_id: u32,
+    next_name: u32,
+}
+
+impl Harness {
+    /// An initialized account, with the model seeded from its two rules.
+    fn new() -> Self {
+        let s = Setup::new();
+        s.env.mock_all_auths();
+
+        let mut signers = StdVec::from([s.signer()]);
+        for seed in 1..SIGNER_POOL as u8 {
+            let key = SigningKey::from_bytes(&[seed; 32]);
+            signers.push(phantom_signer(&s.env, &s.verifier, &key));
+        }
+        let policies = (0..POLICY_POOL)
+            .map(|_| s.env.register(AllowPolicy, ()))
+            .collect();
+
+        let mut rules = HashMap::new();
+        for summary in s.account.get_config().rules.iter() {
+            let rule = s.account.get_context_rule(&summary.id);
+            let model = ModelRule {
+                name: rule.name.clone(),
+                signers: rule
+                    .signers
+                    .iter()
+                    .map(|signer| signers.iter().position(|known| *known == signer).unwrap())
+                    .collect(),
+                policies: StdVec::new(),
+                valid_until: rule.valid_until,
+            };
+            rules.insert(rule.id, model);
+        }
+        let next_id = rules.keys().max().unwrap() + 1;
+
+        Harness {
+            s,
+            signers,
+            policies,
+            rules,
+            removed: HashSet::new(),
+            next_id,
+            next_name: 0,
+        }
+    }
+
+    fn env(&self) -> &Env {
+        &self.s.env
+    }
+
+    fn rule_id(&self, pick: usize) -> u32 {
+        (pick % (self.next_id as usize + 1)) as u32
+    }
+
+    fn fresh_name(&mut self) -> String {
+        self.next_name += 1;
+        String::from_str(&self.s.env, &format!("rule-{}", self.next_name))
+    }
+
+    /// Applies `op` to the account, checks it succeeded exactly when the
+    /// model says it should, and updates the model to match.
+    fn apply(&mut self, op: &Op) {
+        match *op {
+            Op::AddRule { signers, policies } => {
+                let signer_ids = pool_indices(signers, SIGNER_POOL);
+                let policy_ids = pool_indices(policies, POLICY_POOL);
+                let expected = !(signer_ids.is_empty() && policy_ids.is_empty())
+                    && (self.rules.len() as u32) < DEFAULT_MAX_RULES;
+
+                let name = self.fresh_name();
+                let mut rule_signers = Vec::new(self.env());
+                for &i in &signer_ids {
+                    rule_signers.push_back(self.signers[i].clone());
+                }
+                let mut rule_policies: Map<Address, Val> = Map::new(self.env());
+                for &i in &policy_ids {
+                    rule_policies.set(self.policies[i].clone(), ().into_val(self.env()));
+                }
+
+                let result = self.s.account.try_add_context_rule(
+                    &ContextRuleType::CallContract(self.s.counter.clone()),
+                    &name,
+                    &None,
+                    &rule_signers,
+                    &rule_policies,
+                );
+                assert_eq!(result.is_ok(), expected, "unexpected outcome of {op:?}");
+                if let Ok(Ok(rule)) = result {
+                    assert_eq!(rule.id, self.next_id, "rule ids must never be reused");
+                    self.next_id += 1;
+                    let model = ModelRule {
+                        name,
+                        signers: signer_ids,
+                        policies: policy_ids,
+                        valid_until: None,
+                    };
+                    self.rules.insert(rule.id, model);
+                }
+            }
+            Op::AddSigner { rule, signer } => {
+                let id = self.rule_id(rule);
+                let expected = self
+                    .rules
+                    .get(&id)
+                    .is_some_and(|rule| !rule.signers.contains(&signer));
+                let ok = succeeded(self.s.account.try_add_signer(&id, &self.signers[signer]));
+                assert_eq!(ok, expected, "unexpected outcome of {op:?}");
+                if ok {
+                    self.rules.get_mut(&id).unwrap().signers.push(signer);
+                }
+            }
+            Op::RemoveSigner { rule, signer } => {
+                let id = self.rule_id(rule);
+                // Removing the only signer of a rule without policies is
+                // refused with `WouldBrickAccount`.
+                let expected = self.rules.get(&id).is_some_and(|rule| {
+                    rule.signers.contains(&signer)
+                        && !(rule.policies.is_empty() && rule.signers.len() == 1)
+                });
+                let ok = succeeded(self.s.account.try_remove_signer(&id, &self.signers[signer]));
+                assert_eq!(ok, expected, "unexpected outcome of {op:?}");
+                if ok {
+                    self.rules
+                        .get_mut(&id)
+                        .unwrap()
+                        .signers
+                        .retain(|&i| i != signer);
+                }
+            }
+            Op::AddPolicy { rule, policy } => {
+                let id = self.rule_id(rule);
+                let expected = self
+                    .rules
+                    .get(&id)
+                    .is_some_and(|rule| !rule.policies.contains(&policy));
+                let install_param: Val = ().into_val(self.env());
+                let ok = succeeded(self.s.account.try_add_policy(
+                    &id,
+                    &self.policies[policy],
+                    &install_param,
+                ));
+                assert_eq!(ok, expected, "unexpected outcome of {op:?}");
+                if ok {
+                    self.rules.get_mut(&id).unwrap().policies.push(policy);
+                }
+            }
+            Op::RemovePolicy { rule, policy } => {
+                let id = self.rule_id(rule);
+                // `stellar_accounts` keeps no rule with neither signers nor
+                // policies.
+                let expected = self.rules.get(&id).is_some_and(|rule| {
+                    rule.policies.contains(&policy)
+                        && !(rule.signers.is_empty() && rule.policies.len() == 1)
+                });
+                let ok = succeeded(
+                    self.s
+                        .account
+                        .try_remove_policy(&id, &self.policies[policy]),
+                );
+                assert_eq!(ok, expected, "unexpected outcome of {op:?}");
+                if ok {
+                    self.rules
+                        .get_mut(&id)
+                        .unwrap()
+                        .policies
+                        .retain(|&i| i != policy);
+                }
+            }
+            Op::Rename { rule } => {
+                let id = self.rule_id(rule);
+                let expected = self.rules.contains_key(&id);
+                let name = self.fresh_name();
+                let ok = succeeded(self.s.account.try_update_context_rule_name(&id, &name));
+                assert_eq!(ok, expected, "unexpected outcome of {op:?}");
+                if ok {
+                    self.rules.get_mut(&id).unwrap().name = name;
+                }
+            }
+            Op::Expire { rule, ledgers } => {
+                let id = self.rule_id(rule);
+                let expected = self.rules.contains_key(&id);
+                let valid_until = Some(self.env().ledger().sequence() + ledgers);
+                let ok = succeeded(
+                    self.s
+                        .account
+                        .try_update_context_rule_valid_until(&id, &valid_until),
+                );
+                assert_eq!(ok, expected, "unexpected outcome of {op:?}");
+                if ok {
+                    self.rules.get_mut(&id).unwrap().valid_until = valid_until;
+                }
+            }
+            Op::RemoveRule { rule } => {
+                let id = self.rule_id(rule);
+                // The last rule is guarded by `WouldBrickAccount`.
+                let expected = self.rules.contains_key(&id) && self.rules.len() > 1;
+                let ok = succeeded(self.s.account.try_remove_context_rule(&id));
+                assert_eq!(ok, expected, "unexpected outcome of {op:?}");
+                if ok {
+                    self.rules.remove(&id);
+                    self.removed.insert(id);
+                }
+            }
+        }
+    }
+
+    /// Compares the account's rules with the model.
+    fn check_invariants(&self) {
+        let account = &self.s.account;
+        assert_eq!(account.get_context_rules_count(), self.rules.len() as u32);
+
+        let config = account.get_config();
+        assert_eq!(config.rule_count, self.rules.len() as u32);
+        let mut enumerated: StdVec<u32> = config.rules.iter().map(|summary| summary.id).collect();
+        let mut modelled: StdVec<u32> = self.rules.keys().copied().collect();
+        enumerated.sort();
+        modelled.sort();
+        assert_eq!(enumerated, modelled, "enumerated rule ids");
+
+        for summary in config.rules.iter() {
+            let expected = &self.rules[&summary.id];
+            let rule = account.get_context_rule(&summary.id);
+            assert_eq!(summary.name, expected.name);
+            assert_eq!(rule.name, expected.name);
+            assert_eq!(rule.valid_until, expected.valid_until);
+
+            assert_eq!(rule.signers.len() as usize, expected.signers.len());
+            for (i, signer) in rule.signers.iter().enumerate() {
+                assert_eq!(
+                    rule.signers.first_index_of(&signer),
+                    Some(i as u32),
+                    "duplicate signer on rule {}",
+                    rule.id
+                );
+                assert!(expected.signers.iter().any(|&j| self.signers[j] == signer));
+            }
+            assert_eq!(rule.policies.len() as usize, expected.policies.len());
+            for policy in rule.policies.iter() {
+                assert!(expected
+                    .policies
+                    .iter()
+                    .any(|&j| self.policies[j] == policy));
+            }
+        }
+
+        let names: StdVec<String> = config.rules.iter().map(|summary| summary.name).collect();
+        for (i, name) in names.iter().enumerate() {
+            assert!(
+                !names[i + 1..].contains(name),
+                "duplicate rule name {name:?}"
+            );
+        }
+
+        for id in &self.removed {
+            assert!(
+                account.try_get_context_rule(id).is_err(),
+                "removed rule {id} is fetchable again"
+            );
+        }
+    }
+}
+
+/// The pool indices set in `mask`.
+fn pool_indices(mask: u8, pool: usize) -> StdVec<usize> {
+    (0..pool).filter(|i| mask & (1 << i) != 0).collect()
+}
+
+fn succeeded<T, E, F>(result: Result<Result<T, E>, F>) -> bool {
+    matches!(result, Ok(Ok(_)))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn test_rule_crud_matches_model(ops in vec(op(), 1..40)) {
+        let mut harness = Harness::new();
+        harness.check_invariants();
+        for op in &ops {
+            harness.apply(op);
+            harness.check_invariants();
+        }
+    }
+}
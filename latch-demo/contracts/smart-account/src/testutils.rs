@@ -0,0 +1,125 @@
+#![cfg(test)]
+//! Stable JSON rendering of a contract's storage for layout snapshot tests.
+//!
+//! Values are rendered from their plain contents (numbers, symbols, bytes as
+//! hex, ...) rather than the SDK's XDR formatting, so the output only
+//! changes when the stored data does.
+use serde_json::{json, Value};
+use soroban_sdk::{
+    xdr::{ContractDataDurability, LedgerEntryData, ScVal},
+    Address, Env, TryFromVal, Val,
+};
+
+extern crate std;
+use std::{format, string::String, vec::Vec};
+
+/// The storage entries of `contract` whose key passes `keep`, instance
+/// storage included, as a JSON array of `{storage, key, val}` objects sorted
+/// by storage and key. Addresses listed in `names` render as that name.
+pub fn storage_entries(
+    env: &Env,
+    contract: &Address,
+    names: &[(Address, &str)],
+    keep: impl Fn(&Val) -> bool,
+) -> Value {
+    let renderer = Renderer { env, names };
+    let mut entries: Vec<(&str, ScVal, ScVal)> = Vec::new();
+
+    for (_, (entry, _)) in env.to_ledger_snapshot().ledger_entries.iter() {
+        let LedgerEntryData::ContractData(data) = &entry.data else {
+            continue;
+        };
+        if renderer
+            .address(&ScVal::Address(data.contract.clone()))
+            .as_ref()
+            != Some(contract)
+        {
+            continue;
+        }
+
+        match (&data.key, &data.val) {
+            (ScVal::LedgerKeyContractInstance, ScVal::ContractInstance(instance)) => {
+                for item in instance.storage.iter().flat_map(|map| map.iter()) {
+                    entries.push(("instance", item.key.clone(), item.val.clone()));
+                }
+            }
+            (key, val) => {
+                let storage = match data.durability {
+                    ContractDataDurability::Persistent => "persistent",
+                    ContractDataDurability::Temporary => "temporary",
+                };
+                entries.push((storage, key.clone(), val.clone()));
+            }
+        }
+    }
+
+    let mut rendered: Vec<(String, Value)> = entries
+        .into_iter()
+        .filter(|(_, key, _)| keep(&Val::try_from_val(env, key).unwrap()))
+        .map(|(storage, key, val)| {
+            let key = renderer.render(&key);
+            let sort_key = format!("{storage} {key}");
+            let entry = json!({ "storage": storage, "key": key, "val": renderer.render(&val) });
+            (sort_key, entry)
+        })
+        .collect();
+    rendered.sort_by(|a, b| a.0.cmp(&b.0));
+    Value::Array(rendered.into_iter().map(|(_, entry)| entry).collect())
+}
+
+struct Renderer<'a> {
+    env: &'a Env,
+    names: &'a [(Address, &'a str)],
+}
+
+impl Renderer<'_> {
+    fn address(&self, val: &ScVal) -> Option<Address> {
+        let val = Val::try_from_val(self.env, val).ok()?;
+        Address::try_from_val(self.env, &val).ok()
+    }
+
+    fn render(&self, val: &ScVal) -> Value {
+        match val {
+            ScVal::Void => Value::Null,
+            ScVal::Bool(b) => json!(b),
+            ScVal::U32(n) => json!(n),
+            ScVal::I32(n) => json!(n),
+            ScVal::U64(n) => json!(format!("{n}u64")),
+            ScVal::I64(n) => json!(format!("{n}i64")),
+            ScVal::U128(parts) => {
+                let n = (u128::from(parts.hi) << 64) | u128::from(parts.lo);
+                json!(format!("{n}u128"))
+            }
+            ScVal::I128(parts) => {
+                let n = (i128::from(parts.hi) << 64) | i128::from(parts.lo);
+                json!(format!("{n}i128"))
+            }
+            ScVal::Symbol(s) => json!(s.to_utf8_string_lossy()),
+            ScVal::String(s) => json!({ "string": s.to_utf8_string_lossy() }),
+            ScVal::Bytes(b) => {
+                let hex: String = b.iter().map(|byte| format!("{byte:02x}")).collect();
+                json!({ "bytes": hex })
+            }
+            ScVal::Vec(v) => Value::Array(
+                v.iter()
+                    .flat_map(|v| v.iter())
+                    .map(|item| self.render(item))
+                    .collect(),
+            ),
+            ScVal::Map(m) => Value::Array(
+                m.iter()
+                    .flat_map(|m| m.iter())
+                    .map(|entry| json!([self.render(&entry.key), self.render(&entry.val)]))
+                    .collect(),
+            ),
+            ScVal::Address(_) => {
+                let address = self.address(val).unwrap();
+                match self.names.iter().find(|(known, _)| *known == address) {
+                    Some((_, name)) => json!({ "address": name }),
+                    None => json!({ "address": format!("{address:?}") }),
+                }
+            }
+            other => json!(format!("{other:?}")),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+#![no_std]
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, xdr::FromXdr, Address, Bytes, Env,
+    IntoVal, Symbol, Vec,
+};
+use stellar_accounts::verifiers::Verifier;
+
+/// Entries in one key. Each present signature costs a cross-contract call,
+/// so the set stays small.
+const MAX_ENTRIES: u32 = 8;
+
+/// Wraps other verifiers so that one external signer can stand for "2 of
+/// {Phantom key, passkey, Ledger}", each device checked by its own verifier.
+#[contract]
+pub struct ThresholdVerifier;
+
+/// key_data, as XDR.
+#[contracttype]
+pub struct ThresholdKeyData {
+    /// Each device's verifier contract and its key_data for that verifier.
+    pub entries: Vec<(Address, Bytes)>,
+    /// How many entries must verify.
+    pub threshold: u32,
+}
+
+/// The check `verify_checked` failed at. Codes 1-2 match the Ed25519
+/// verifier's, and 24 on the multisig verifier's.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum VerifierError {
+    /// sig_data is XDR of something other than a `Vec<Option<Bytes>>`, or
+    /// has a different number of items than the key has entries.
+    MalformedSigData = 1,
+    /// key_data is XDR of something other than `ThresholdKeyData`.
+    BadKeyLength = 2,
+    /// The threshold is 0 or more than the number of entries.
+    BadThreshold = 24,
+    /// The key has no entries or more than `MAX_ENTRIES`.
+    TooManyKeys = 25,
+    /// An entry appears twice, which would let one device count twice.
+    DuplicateKey = 27,
+    /// Fewer entries verified than the threshold.
+    BelowThreshold = 28,
+}
+
+#[contractimpl]
+impl Verifier for ThresholdVerifier {
+    type KeyData = Bytes;
+    type SigData = Bytes;
+
+    /// Verifies that at least `threshold` entries' verifiers accept their
+    /// item of sig_data, the item at the entry's index. `None` items are
+    /// not checked.
+    ///
+    /// An inner verifier that traps counts as one that returned `false`, so
+    /// one device's bad signature does not sink the others. Bytes that are
+    /// not XDR at all still trap, in the host's deserializer.
+    fn verify(
+        e: &Env,
+        signature_payload: Bytes,
+        key_data: Self::KeyData,
+        sig_data: Self::SigData,
+    ) -> bool {
+        check(e, signature_payload, key_data, sig_data).is_ok()
+    }
+}
+
+#[contractimpl]
+impl ThresholdVerifier {
+    /// `verify` that tells which check failed, for integrators and
+    /// simulation.
+    pub fn verify_checked(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> Result<(), VerifierError> {
+        check(&e, signature_payload, key_data, sig_data)
+    }
+}
+
+fn check(
+    e: &Env,
+    signature_payload: Bytes,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    let key = ThresholdKeyData::from_xdr(e, &key_data).map_err(|_| VerifierError::BadKeyLength)?;
+    let signatures = Vec::<Option<Bytes>>::from_xdr(e, &sig_data)
+        .map_err(|_| VerifierError::MalformedSigData)?;
+
+    let entries = key.entries;
+    if entries.is_empty() || entries.len() > MAX_ENTRIES {
+        return Err(VerifierError::TooManyKeys);
+    }
+    if key.threshold == 0 || key.threshold > entries.len() {
+        return Err(VerifierError::BadThreshold);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        if entries.iter().skip(i + 1).any(|other| other == entry) {
+            return Err(VerifierError::DuplicateKey);
+        }
+    }
+    if signatures.len() != entries.len() {
+        return Err(VerifierError::MalformedSigData);
+    }
+
+    let verify = Symbol::new(e, "verify");
+    let mut verified: u32 = 0;
+    for ((verifier, inner_key), signature) in entries.iter().zip(signatures.iter()) {
+        let Some(inner_sig) = signature else {
+            continue;
+        };
+        let result = e.try_invoke_contract::<bool, soroban_sdk::Error>(
+            &verifier,
+            &verify,
+            (signature_payload.clone(), inner_key, inner_sig).into_val(e),
+        );
+        if matches!(result, Ok(Ok(true))) {
+            verified += 1;
+            // The rest need not be paid for
+            if verified == key.threshold {
+                return Ok(());
+            }
+        }
+    }
+
+    Err(VerifierError::BelowThreshold)
+}
+
+#[cfg(test)]
+mod test;
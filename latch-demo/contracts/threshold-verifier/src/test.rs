@@ -0,0 +1,248 @@
+#![cfg(test)]
+use crate::{ThresholdKeyData, ThresholdVerifier, ThresholdVerifierClient, VerifierError};
+use ed25519_dalek::{Signer, SigningKey};
+use ed25519_verifier::{Ed25519SigData, Ed25519Verifier};
+use soroban_sdk::{contract, contractimpl, xdr::ToXdr, Address, Bytes, BytesN, Env, Vec};
+
+extern crate std;
+
+const AUTH_PREFIX: &[u8] = b"Stellar Smart Account Auth:\n";
+
+const PAYLOAD: [u8; 32] = [1u8; 32];
+
+/// Accepts every signature.
+#[contract]
+pub(crate) struct AlwaysTrue;
+
+#[contractimpl]
+impl AlwaysTrue {
+    pub fn verify(_e: Env, _payload: Bytes, _key_data: Bytes, _sig_data: Bytes) -> bool {
+        true
+    }
+}
+
+/// Refuses every signature.
+#[contract]
+pub(crate) struct AlwaysFalse;
+
+#[contractimpl]
+impl AlwaysFalse {
+    pub fn verify(_e: Env, _payload: Bytes, _key_data: Bytes, _sig_data: Bytes) -> bool {
+        false
+    }
+}
+
+/// Convert bytes to lowercase hex string (off-chain helper for tests)
+fn bytes_to_hex(bytes: &[u8]) -> std::vec::Vec<u8> {
+    const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+    let mut result = std::vec::Vec::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        result.push(HEX_CHARS[(byte >> 4) as usize]);
+        result.push(HEX_CHARS[(byte & 0x0f) as usize]);
+    }
+    result
+}
+
+struct Setup {
+    env: Env,
+    client: ThresholdVerifierClient<'static>,
+    ed25519: Address,
+    always_true: Address,
+    always_false: Address,
+    device: SigningKey,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let env = Env::default();
+        let client = ThresholdVerifierClient::new(&env, &env.register(ThresholdVerifier, ()));
+        let ed25519 = env.register(
+            Ed25519Verifier,
+            (
+                None::<Address>,
+                None::<Bytes>,
+                true,
+                None::<Bytes>,
+                None::<u32>,
+            ),
+        );
+        let always_true = env.register(AlwaysTrue, ());
+        let always_false = env.register(AlwaysFalse, ());
+        Setup {
+            env,
+            client,
+            ed25519,
+            always_true,
+            always_false,
+            device: SigningKey::from_bytes(&[7u8; 32]),
+        }
+    }
+
+    /// The Ed25519 device's entry.
+    fn device_entry(&self) -> (Address, Bytes) {
+        (
+            self.ed25519.clone(),
+            Bytes::from_slice(&self.env, &self.device.verifying_key().to_bytes()),
+        )
+    }
+
+    /// An entry for one of the mock verifiers, whose key_data is ignored.
+    fn mock_entry(&self, verifier: &Address, tag: u8) -> (Address, Bytes) {
+        (verifier.clone(), Bytes::from_array(&self.env, &[tag]))
+    }
+
+    fn key_data(
+        &self,
+        entries: impl IntoIterator<Item = (Address, Bytes)>,
+        threshold: u32,
+    ) -> Bytes {
+        let mut all = Vec::new(&self.env);
+        for entry in entries {
+            all.push_back(entry);
+        }
+        ThresholdKeyData {
+            entries: all,
+            threshold,
+        }
+        .to_xdr(&self.env)
+    }
+
+    fn sig_data(&self, sigs: impl IntoIterator<Item = Option<Bytes>>) -> Bytes {
+        let mut all = Vec::new(&self.env);
+        for sig in sigs {
+            all.push_back(sig);
+        }
+        all.to_xdr(&self.env)
+    }
+
+    /// `key`'s Ed25519 sig_data over the message for `payload`.
+    fn ed25519_sig(&self, key: &SigningKey, payload: &[u8; 32]) -> Option<Bytes> {
+        let mut message = AUTH_PREFIX.to_vec();
+        message.extend_from_slice(&bytes_to_hex(payload));
+        let sig_data = Ed25519SigData {
+            prefixed_message: Bytes::from_slice(&self.env, &message),
+            signature: BytesN::from_array(&self.env, &key.sign(&message).to_bytes()),
+            expires_at: None,
+        };
+        Some(sig_data.to_xdr(&self.env))
+    }
+
+    /// sig_data for a mock verifier, which ignores it.
+    fn mock_sig(&self) -> Option<Bytes> {
+        Some(Bytes::new(&self.env))
+    }
+
+    fn payload(&self) -> Bytes {
+        Bytes::from_slice(&self.env, &PAYLOAD)
+    }
+}
+
+#[test]
+fn test_threshold_met() {
+    let s = Setup::new();
+    let key_data = s.key_data(
+        [
+            s.device_entry(),
+            s.mock_entry(&s.always_true, 0),
+            s.mock_entry(&s.always_false, 1),
+        ],
+        2,
+    );
+    let sig_data = s.sig_data([
+        s.ed25519_sig(&s.device, &PAYLOAD),
+        s.mock_sig(),
+        s.mock_sig(),
+    ]);
+
+    assert!(s.client.verify(&s.payload(), &key_data, &sig_data));
+}
+
+#[test]
+fn test_threshold_not_met() {
+    let s = Setup::new();
+    let key_data = s.key_data(
+        [
+            s.device_entry(),
+            s.mock_entry(&s.always_true, 0),
+            s.mock_entry(&s.always_false, 1),
+        ],
+        2,
+    );
+
+    // The device signed for another payload, and the failing mock refuses
+    let sig_data = s.sig_data([
+        s.ed25519_sig(&s.device, &[2u8; 32]),
+        s.mock_sig(),
+        s.mock_sig(),
+    ]);
+    assert!(!s.client.verify(&s.payload(), &key_data, &sig_data));
+    assert_eq!(
+        s.client
+            .try_verify_checked(&s.payload(), &key_data, &sig_data),
+        Err(Ok(VerifierError::BelowThreshold))
+    );
+
+    // An absent signature is not checked at all
+    let sig_data = s.sig_data([s.ed25519_sig(&s.device, &PAYLOAD), None, s.mock_sig()]);
+    assert!(!s.client.verify(&s.payload(), &key_data, &sig_data));
+}
+
+#[test]
+fn test_inner_trap_counts_as_false() {
+    let s = Setup::new();
+    let stranger = SigningKey::from_bytes(&[8u8; 32]);
+    // The Ed25519 verifier traps on a signature from another key
+    let sig_data = s.sig_data([s.ed25519_sig(&stranger, &PAYLOAD), s.mock_sig()]);
+
+    let one_of_two = s.key_data([s.device_entry(), s.mock_entry(&s.always_true, 0)], 1);
+    assert!(s.client.verify(&s.payload(), &one_of_two, &sig_data));
+
+    let two_of_two = s.key_data([s.device_entry(), s.mock_entry(&s.always_true, 0)], 2);
+    assert_eq!(
+        s.client
+            .try_verify_checked(&s.payload(), &two_of_two, &sig_data),
+        Err(Ok(VerifierError::BelowThreshold))
+    );
+}
+
+#[test]
+fn test_malformed_keys() {
+    let s = Setup::new();
+    let payload = s.payload();
+    let entries = [s.device_entry(), s.mock_entry(&s.always_true, 0)];
+    let sig_data = s.sig_data([s.ed25519_sig(&s.device, &PAYLOAD), s.mock_sig()]);
+
+    for threshold in [0, 3] {
+        assert_eq!(
+            s.client.try_verify_checked(
+                &payload,
+                &s.key_data(entries.clone(), threshold),
+                &sig_data
+            ),
+            Err(Ok(VerifierError::BadThreshold))
+        );
+    }
+    assert_eq!(
+        s.client
+            .try_verify_checked(&payload, &s.key_data([], 1), &sig_data),
+        Err(Ok(VerifierError::TooManyKeys))
+    );
+    assert_eq!(
+        s.client.try_verify_checked(
+            &payload,
+            &s.key_data([s.device_entry(), s.device_entry()], 2),
+            &sig_data
+        ),
+        Err(Ok(VerifierError::DuplicateKey))
+    );
+
+    // One item of sig_data per entry
+    assert_eq!(
+        s.client.try_verify_checked(
+            &payload,
+            &s.key_data(entries, 1),
+            &s.sig_data([s.mock_sig()])
+        ),
+        Err(Ok(VerifierError::MalformedSigData))
+    );
+}
@@ -0,0 +1,16 @@
+#![no_std]
+//! The code upgrade tests swap a deployed contract to. The Ed25519
+//! verifier's build script builds its wasm for the verifier's tests, which
+//! upload it.
+use soroban_sdk::{contract, contractimpl, Env};
+
+#[contract]
+pub struct UpgradeTarget;
+
+#[contractimpl]
+impl UpgradeTarget {
+    /// Tells the upgraded contract from the one it replaced.
+    pub fn version(_e: Env) -> u32 {
+        2
+    }
+}
@@ -0,0 +1,91 @@
+#![no_std]
+use soroban_sdk::{
+    contract, contracterror, contractevent, contractimpl, contracttype, Address, Env, Map, Symbol,
+};
+
+/// Maps key types (`ed25519`, `secp256k1`, ...) to the verifier contract
+/// that checks them, so frontends and accounts look verifiers up instead of
+/// pinning their addresses.
+#[contract]
+pub struct VerifierRegistry;
+
+#[contracttype]
+#[derive(Clone)]
+enum DataKey {
+    Admin,
+    Verifiers,
+}
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum RegistryError {
+    /// No verifier is registered for the key type.
+    UnknownKeyType = 1,
+}
+
+/// Emitted when `key_type` is registered or moved to another verifier.
+#[contractevent(topics = ["verifier_registered"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifierRegistered {
+    #[topic]
+    pub key_type: Symbol,
+    /// The verifier `key_type` resolved to before, if any.
+    pub old_verifier: Option<Address>,
+    pub verifier: Address,
+}
+
+#[contractimpl]
+impl VerifierRegistry {
+    /// Sets the admin allowed to register verifiers.
+    pub fn __constructor(e: Env, admin: Address) {
+        e.storage().instance().set(&DataKey::Admin, &admin);
+    }
+
+    /// Resolves `key_type` to `verifier` from now on, replacing any verifier
+    /// registered for it before. Admin only.
+    ///
+    /// Accounts keep the verifier they resolved at initialization; moving a
+    /// key type does not move their signers.
+    pub fn register(e: Env, key_type: Symbol, verifier: Address) {
+        Self::admin(e.clone()).require_auth();
+
+        let mut verifiers = verifiers(&e);
+        let old_verifier = verifiers.get(key_type.clone());
+        verifiers.set(key_type.clone(), verifier.clone());
+        e.storage().instance().set(&DataKey::Verifiers, &verifiers);
+
+        VerifierRegistered {
+            key_type,
+            old_verifier,
+            verifier,
+        }
+        .publish(&e);
+    }
+
+    /// The verifier registered for `key_type`.
+    pub fn resolve(e: Env, key_type: Symbol) -> Result<Address, RegistryError> {
+        verifiers(&e)
+            .get(key_type)
+            .ok_or(RegistryError::UnknownKeyType)
+    }
+
+    /// Every registered key type and its verifier.
+    pub fn list(e: Env) -> Map<Symbol, Address> {
+        verifiers(&e)
+    }
+
+    pub fn admin(e: Env) -> Address {
+        e.storage().instance().get(&DataKey::Admin).unwrap()
+    }
+}
+
+fn verifiers(e: &Env) -> Map<Symbol, Address> {
+    e.storage()
+        .instance()
+        .get(&DataKey::Verifiers)
+        .unwrap_or_else(|| Map::new(e))
+}
+
+#[cfg(test)]
+mod test;
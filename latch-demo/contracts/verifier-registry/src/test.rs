@@ -0,0 +1,127 @@
+#![cfg(test)]
+use crate::{RegistryError, VerifierRegistry, VerifierRegistryClient};
+use soroban_sdk::{
+    symbol_short,
+    testutils::{Address as _, MockAuth, MockAuthInvoke},
+    Address, Env, IntoVal, Map, Symbol, Val,
+};
+
+fn setup(env: &Env) -> (VerifierRegistryClient<'_>, Address) {
+    let admin = Address::generate(env);
+    let contract_id = env.register(VerifierRegistry, (admin.clone(),));
+    (VerifierRegistryClient::new(env, &contract_id), admin)
+}
+
+#[test]
+fn test_register_and_resolve() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+    let ed25519 = Address::generate(&env);
+    let secp256k1 = Address::generate(&env);
+
+    client.register(&symbol_short!("ed25519"), &ed25519);
+    client.register(&symbol_short!("secp256k1"), &secp256k1);
+
+    assert_eq!(client.admin(), admin);
+    assert_eq!(client.resolve(&symbol_short!("ed25519")), ed25519);
+    assert_eq!(client.resolve(&symbol_short!("secp256k1")), secp256k1);
+    assert_eq!(
+        client.list(),
+        Map::from_array(
+            &env,
+            [
+                (symbol_short!("ed25519"), ed25519),
+                (symbol_short!("secp256k1"), secp256k1),
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_register_replaces_and_emits_events() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup(&env);
+    let old = Address::generate(&env);
+    let new = Address::generate(&env);
+
+    client.register(&symbol_short!("ed25519"), &old);
+    client.register(&symbol_short!("ed25519"), &new);
+
+    assert_eq!(client.resolve(&symbol_short!("ed25519")), new);
+    assert_eq!(client.list().len(), 1);
+    assert_eq!(
+        env.events().all(),
+        soroban_sdk::vec![
+            &env,
+            (
+                client.address.clone(),
+                (
+                    Symbol::new(&env, "verifier_registered"),
+                    symbol_short!("ed25519")
+                )
+                    .into_val(&env),
+                Map::<Symbol, Val>::from_array(
+                    &env,
+                    [
+                        (
+                            Symbol::new(&env, "old_verifier"),
+                            Some(old.clone()).into_val(&env)
+                        ),
+                        (Symbol::new(&env, "verifier"), new.clone().into_val(&env)),
+                    ]
+                )
+                .into_val(&env),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_register_requires_admin() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let verifier = Address::generate(&env);
+    let args = (symbol_short!("ed25519"), verifier.clone()).into_val(&env);
+
+    // Signed by someone else
+    let intruder = Address::generate(&env);
+    assert!(client
+        .mock_auths(&[MockAuth {
+            address: &intruder,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "register",
+                args: args.clone(),
+                sub_invokes: &[],
+            },
+        }])
+        .try_register(&symbol_short!("ed25519"), &verifier)
+        .is_err());
+    assert!(client.list().is_empty());
+
+    client
+        .mock_auths(&[MockAuth {
+            address: &admin,
+            invoke: &MockAuthInvoke {
+                contract: &client.address,
+                fn_name: "register",
+                args,
+                sub_invokes: &[],
+            },
+        }])
+        .register(&symbol_short!("ed25519"), &verifier);
+    assert_eq!(client.resolve(&symbol_short!("ed25519")), verifier);
+}
+
+#[test]
+fn test_resolve_unknown_key_type() {
+    let env = Env::default();
+    let (client, _) = setup(&env);
+
+    assert_eq!(
+        client.try_resolve(&symbol_short!("p256")),
+        Err(Ok(RegistryError::UnknownKeyType))
+    );
+}
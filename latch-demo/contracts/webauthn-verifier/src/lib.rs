@@ -0,0 +1,218 @@
+#![no_std]
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, xdr::FromXdr, Bytes, BytesN, Env,
+};
+use stellar_accounts::verifiers::Verifier;
+
+const PAYLOAD_LEN: usize = 32;
+/// rpIdHash (32) + flags (1) + signCount (4).
+const MIN_AUTH_DATA_LEN: u32 = 37;
+const FLAGS_INDEX: u32 = 32;
+/// The user-present bit of the authenticatorData flags.
+const FLAG_UP: u8 = 0x01;
+/// Browsers emit well under this; anything longer is not worth parsing.
+const MAX_CLIENT_DATA_LEN: usize = 1024;
+
+const TYPE_KEY: &[u8] = b"\"type\":\"";
+const CHALLENGE_KEY: &[u8] = b"\"challenge\":\"";
+const ASSERTION_TYPE: &[u8] = b"webauthn.get";
+
+/// The P-256 group order and half of it, big-endian.
+const ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xbc, 0xe6, 0xfa, 0xad, 0xa7, 0x17, 0x9e, 0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63, 0x25, 0x51,
+];
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0x80, 0x00, 0x00, 0x00, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xde, 0x73, 0x7d, 0x56, 0xd3, 0x8b, 0xcf, 0x42, 0x79, 0xdc, 0xe5, 0x61, 0x7e, 0x31, 0x92, 0xa8,
+];
+
+#[contract]
+pub struct WebAuthnVerifier;
+
+/// A WebAuthn assertion as the browser returns it.
+#[contracttype]
+pub struct WebAuthnSigData {
+    pub authenticator_data: Bytes,
+    pub client_data_json: Bytes,
+    /// Raw `r || s` (not DER) over `authenticator_data || sha256(client_data_json)`.
+    pub signature: BytesN<64>,
+}
+
+/// The check `verify_checked` failed at. Codes match the other verifiers';
+/// 4 (prefix) and 7 (high-s) have no counterpart here.
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum VerifierError {
+    /// sig_data is XDR of something other than `WebAuthnSigData`.
+    MalformedSigData = 1,
+    /// key_data is not a 65-byte uncompressed SEC1 public key.
+    BadKeyLength = 2,
+    /// The payload is not 32 bytes, authenticatorData is too short to carry
+    /// flags, or clientDataJSON is over `MAX_CLIENT_DATA_LEN`.
+    WrongMessageLength = 3,
+    /// The challenge is missing, not base64url, or not the payload.
+    PayloadMismatch = 5,
+    /// Never returned by this verifier: `secp256r1_verify` traps on a bad
+    /// signature. Kept so the codes line up across verifiers.
+    BadSignature = 6,
+    /// clientDataJSON's type is not "webauthn.get".
+    WrongType = 8,
+    /// The authenticator did not set the user-present flag.
+    UserNotPresent = 9,
+}
+
+#[contractimpl]
+impl Verifier for WebAuthnVerifier {
+    type KeyData = Bytes;
+    type SigData = Bytes;
+
+    /// Verifies a passkey assertion whose challenge is the base64url of the
+    /// signature payload.
+    ///
+    /// Assertions for another payload, of another type or without user
+    /// presence return `false`. High-s signatures, which authenticators
+    /// produce about half the time, are normalized rather than rejected.
+    /// Bytes that are not XDR at all and a signature that does not verify
+    /// still trap in the host.
+    fn verify(
+        e: &Env,
+        signature_payload: Bytes,
+        key_data: Self::KeyData,
+        sig_data: Self::SigData,
+    ) -> bool {
+        check(e, signature_payload, key_data, sig_data).is_ok()
+    }
+}
+
+#[contractimpl]
+impl WebAuthnVerifier {
+    /// `verify` that tells which check failed, for integrators and
+    /// simulation.
+    pub fn verify_checked(
+        e: Env,
+        signature_payload: Bytes,
+        key_data: Bytes,
+        sig_data: Bytes,
+    ) -> Result<(), VerifierError> {
+        check(&e, signature_payload, key_data, sig_data)
+    }
+}
+
+fn check(
+    e: &Env,
+    signature_payload: Bytes,
+    key_data: Bytes,
+    sig_data: Bytes,
+) -> Result<(), VerifierError> {
+    let sig_struct =
+        WebAuthnSigData::from_xdr(e, &sig_data).map_err(|_| VerifierError::MalformedSigData)?;
+
+    let public_key = BytesN::<65>::try_from(key_data).map_err(|_| VerifierError::BadKeyLength)?;
+
+    if signature_payload.len() != PAYLOAD_LEN as u32 {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let payload = signature_payload.to_buffer::<PAYLOAD_LEN>();
+
+    let auth_data = &sig_struct.authenticator_data;
+    if auth_data.len() < MIN_AUTH_DATA_LEN {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    if auth_data.get_unchecked(FLAGS_INDEX) & FLAG_UP == 0 {
+        return Err(VerifierError::UserNotPresent);
+    }
+
+    let json_len = sig_struct.client_data_json.len() as usize;
+    if json_len > MAX_CLIENT_DATA_LEN {
+        return Err(VerifierError::WrongMessageLength);
+    }
+    let mut json_buf = [0u8; MAX_CLIENT_DATA_LEN];
+    let json = &mut json_buf[..json_len];
+    sig_struct.client_data_json.copy_into_slice(json);
+
+    if json_string(json, TYPE_KEY) != Some(ASSERTION_TYPE) {
+        return Err(VerifierError::WrongType);
+    }
+
+    let challenge = json_string(json, CHALLENGE_KEY).ok_or(VerifierError::PayloadMismatch)?;
+    let mut decoded = [0u8; PAYLOAD_LEN];
+    if !base64url_decode(challenge, &mut decoded) || decoded[..] != *payload.as_slice() {
+        return Err(VerifierError::PayloadMismatch);
+    }
+
+    // The authenticator signs authenticatorData || sha256(clientDataJSON)
+    let client_data_hash = e.crypto().sha256(&sig_struct.client_data_json);
+    let mut message = sig_struct.authenticator_data.clone();
+    message.append(&client_data_hash.to_bytes().into());
+    let digest = e.crypto().sha256(&message);
+
+    let mut signature = sig_struct.signature.to_array();
+    normalize_s(&mut signature);
+    e.crypto()
+        .secp256r1_verify(&public_key, &digest, &BytesN::from_array(e, &signature));
+
+    Ok(())
+}
+
+/// The string value of the first `"key":"` in `json`, up to the next quote.
+///
+/// Not a JSON parser: it relies on browsers serializing clientDataJSON
+/// compactly with `type` and `challenge` first, and on neither value ever
+/// holding an escaped quote (the type is fixed, the challenge is base64url).
+fn json_string<'a>(json: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let start = json.windows(key.len()).position(|w| w == key)? + key.len();
+    let len = json[start..].iter().position(|&b| b == b'"')?;
+    Some(&json[start..start + len])
+}
+
+/// Decodes unpadded base64url `src` into exactly `dst.len()` bytes.
+fn base64url_decode(src: &[u8], dst: &mut [u8]) -> bool {
+    if src.len() != (dst.len() * 4).div_ceil(3) {
+        return false;
+    }
+
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut di = 0;
+    for &c in src {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => return false,
+        };
+        acc = ((acc << 6) | v as u32) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            dst[di] = (acc >> bits) as u8;
+            di += 1;
+        }
+    }
+
+    // Trailing bits must be zero, so each payload has one encoding
+    acc & ((1 << bits) - 1) == 0
+}
+
+/// Replaces a high `s` with `n - s`, the equivalent low-s signature the host
+/// accepts.
+fn normalize_s(signature: &mut [u8; 64]) {
+    if signature[32..] <= HALF_ORDER[..] {
+        return;
+    }
+
+    let mut borrow = false;
+    for i in (0..32).rev() {
+        let (d, b1) = ORDER[i].overflowing_sub(signature[32 + i]);
+        let (d, b2) = d.overflowing_sub(borrow as u8);
+        signature[32 + i] = d;
+        borrow = b1 || b2;
+    }
+}
+
+#[cfg(test)]
+mod test;
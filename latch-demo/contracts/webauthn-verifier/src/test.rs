@@ -0,0 +1,262 @@
+#![cfg(test)]
+// Assertions are put together the way Chrome returns them from
+// `navigator.credentials.get`: compact clientDataJSON with type, challenge,
+// origin and crossOrigin in that order, and authenticatorData carrying the
+// rpIdHash of "localhost", the UP|UV flags and a sign count.
+use crate::{VerifierError, WebAuthnSigData, WebAuthnVerifier, WebAuthnVerifierClient};
+use p256::{
+    ecdsa::{signature::Signer as _, Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use soroban_sdk::{xdr::ToXdr, Bytes, BytesN, Env};
+
+extern crate std;
+use std::{format, string::String, vec::Vec};
+
+const PAYLOAD: [u8; 32] = [1u8; 32];
+const ORIGIN: &str = "http://localhost:5173";
+
+/// Unpadded base64url (off-chain helper for tests)
+fn base64url(bytes: &[u8]) -> String {
+    const CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..=chunk.len() {
+            out.push(CHARS[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+fn client_data_json(kind: &str, payload: &[u8; 32]) -> Vec<u8> {
+    format!(
+        r#"{{"type":"{kind}","challenge":"{}","origin":"{ORIGIN}","crossOrigin":false}}"#,
+        base64url(payload)
+    )
+    .into_bytes()
+}
+
+fn authenticator_data(env: &Env, flags: u8) -> Vec<u8> {
+    let rp_id_hash = env
+        .crypto()
+        .sha256(&Bytes::from_slice(env, b"localhost"))
+        .to_array();
+    let mut data = rp_id_hash.to_vec();
+    data.push(flags);
+    data.extend_from_slice(&[0, 0, 0, 1]);
+    data
+}
+
+fn signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_slice(&[seed; 32]).unwrap()
+}
+
+/// Uncompressed SEC1 encoding of `key`'s public key.
+fn key_data(env: &Env, key: &SigningKey) -> Bytes {
+    let point = key.verifying_key().as_affine().to_encoded_point(false);
+    Bytes::from_slice(env, point.as_bytes())
+}
+
+/// What the authenticator does: sign `auth_data || sha256(client_data)`.
+/// Returns the low-s signature.
+fn sign(env: &Env, key: &SigningKey, auth_data: &[u8], client_data: &[u8]) -> Signature {
+    let mut message = auth_data.to_vec();
+    let client_data_hash = env
+        .crypto()
+        .sha256(&Bytes::from_slice(env, client_data))
+        .to_array();
+    message.extend_from_slice(&client_data_hash);
+    let signature: Signature = key.sign(&message);
+    signature.normalize_s().unwrap_or(signature)
+}
+
+fn sig_data(env: &Env, auth_data: &[u8], client_data: &[u8], signature: &Signature) -> Bytes {
+    let signature: [u8; 64] = signature.to_bytes().into();
+    WebAuthnSigData {
+        authenticator_data: Bytes::from_slice(env, auth_data),
+        client_data_json: Bytes::from_slice(env, client_data),
+        signature: BytesN::from_array(env, &signature),
+    }
+    .to_xdr(env)
+}
+
+/// A well-formed assertion over `PAYLOAD` by `key`.
+fn assertion(env: &Env, key: &SigningKey) -> Bytes {
+    let auth_data = authenticator_data(env, 0x05);
+    let client_data = client_data_json("webauthn.get", &PAYLOAD);
+    let signature = sign(env, key, &auth_data, &client_data);
+    sig_data(env, &auth_data, &client_data, &signature)
+}
+
+fn setup(env: &Env) -> WebAuthnVerifierClient<'_> {
+    let contract_id = env.register(WebAuthnVerifier, ());
+    WebAuthnVerifierClient::new(env, &contract_id)
+}
+
+#[test]
+fn test_verify_valid_assertion() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &key),
+        &assertion(&env, &key),
+    ));
+}
+
+#[test]
+fn test_verify_tolerates_extra_client_data_keys() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let auth_data = authenticator_data(&env, 0x05);
+    // Chrome sometimes appends this to discourage template matching
+    let client_data = format!(
+        r#"{{"type":"webauthn.get","challenge":"{}","origin":"{ORIGIN}","crossOrigin":false,"other_keys_can_be_added_here":"do not compare clientDataJSON against a template. See https://goo.gl/yabPex"}}"#,
+        base64url(&PAYLOAD)
+    )
+    .into_bytes();
+    let signature = sign(&env, &key, &auth_data, &client_data);
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &key),
+        &sig_data(&env, &auth_data, &client_data, &signature),
+    ));
+}
+
+#[test]
+fn test_verify_normalizes_high_s() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let auth_data = authenticator_data(&env, 0x05);
+    let client_data = client_data_json("webauthn.get", &PAYLOAD);
+    let signature = sign(&env, &key, &auth_data, &client_data);
+
+    let (r, s) = signature.split_scalars();
+    let high = Signature::from_scalars(r, -s).unwrap();
+    assert!(high.normalize_s().is_some());
+
+    assert!(client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &key),
+        &sig_data(&env, &auth_data, &client_data, &high),
+    ));
+}
+
+#[test]
+fn test_verify_wrong_challenge() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let auth_data = authenticator_data(&env, 0x05);
+    // Genuinely signed, but for another payload
+    let client_data = client_data_json("webauthn.get", &[2u8; 32]);
+    let signature = sign(&env, &key, &auth_data, &client_data);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let sig_data = sig_data(&env, &auth_data, &client_data, &signature);
+
+    assert!(!client.verify(&payload, &key_data(&env, &key), &sig_data));
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data(&env, &key), &sig_data),
+        Err(Ok(VerifierError::PayloadMismatch))
+    );
+}
+
+#[test]
+fn test_verify_rejects_registration_type() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let auth_data = authenticator_data(&env, 0x05);
+    // A registration ceremony's client data, replayed as an assertion
+    let client_data = client_data_json("webauthn.create", &PAYLOAD);
+    let signature = sign(&env, &key, &auth_data, &client_data);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let sig_data = sig_data(&env, &auth_data, &client_data, &signature);
+
+    assert!(!client.verify(&payload, &key_data(&env, &key), &sig_data));
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data(&env, &key), &sig_data),
+        Err(Ok(VerifierError::WrongType))
+    );
+}
+
+#[test]
+fn test_verify_requires_user_presence() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    // UV without UP
+    let auth_data = authenticator_data(&env, 0x04);
+    let client_data = client_data_json("webauthn.get", &PAYLOAD);
+    let signature = sign(&env, &key, &auth_data, &client_data);
+
+    assert_eq!(
+        client.try_verify_checked(
+            &Bytes::from_slice(&env, &PAYLOAD),
+            &key_data(&env, &key),
+            &sig_data(&env, &auth_data, &client_data, &signature),
+        ),
+        Err(Ok(VerifierError::UserNotPresent))
+    );
+}
+
+#[test]
+fn test_verify_checked_reports_length_errors() {
+    let env = Env::default();
+    let client = setup(&env);
+    let key = signing_key(7);
+    let payload = Bytes::from_slice(&env, &PAYLOAD);
+    let client_data = client_data_json("webauthn.get", &PAYLOAD);
+
+    assert_eq!(
+        client.try_verify_checked(
+            &payload,
+            &Bytes::from_array(&env, &[2u8; 33]),
+            &assertion(&env, &key)
+        ),
+        Err(Ok(VerifierError::BadKeyLength))
+    );
+
+    let short = sig_data(
+        &env,
+        &[0x05; 36],
+        &client_data,
+        &Signature::from_slice(&[1; 64]).unwrap(),
+    );
+    assert_eq!(
+        client.try_verify_checked(&payload, &key_data(&env, &key), &short),
+        Err(Ok(VerifierError::WrongMessageLength))
+    );
+
+    assert_eq!(
+        client.try_verify_checked(
+            &payload,
+            &key_data(&env, &key),
+            &payload.clone().to_xdr(&env)
+        ),
+        Err(Ok(VerifierError::MalformedSigData))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_verify_wrong_key() {
+    let env = Env::default();
+    let client = setup(&env);
+
+    // Host traps on a signature that does not verify
+    client.verify(
+        &Bytes::from_slice(&env, &PAYLOAD),
+        &key_data(&env, &signing_key(8)),
+        &assertion(&env, &signing_key(7)),
+    );
+}
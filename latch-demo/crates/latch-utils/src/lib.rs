@@ -0,0 +1,77 @@
+#![no_std]
+//! Helpers shared by the Latch contracts and their tests.
+
+const HEX_LOWER: &[u8; 16] = b"0123456789abcdef";
+const HEX_UPPER: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Why `hex_decode` refused its input.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HexError {
+    /// The hex has an odd number of characters.
+    OddLength,
+    /// The hex does not decode to exactly `dst.len()` bytes.
+    WrongLength,
+    /// The byte at `index` of the hex is not 0-9, a-f or A-F.
+    InvalidByte { index: usize },
+}
+
+/// Characters in the hex of `bytes` bytes.
+pub const fn hex_len(bytes: usize) -> usize {
+    bytes * 2
+}
+
+/// Bytes `hex_len` hex characters decode to, rounding an odd length down.
+pub const fn decoded_len(hex_len: usize) -> usize {
+    hex_len / 2
+}
+
+/// Fast hex encoding using direct array indexing.
+/// Each input byte becomes two hex characters (0-9, a-f) in `dst`, which
+/// must be at least `hex_len(src.len())` bytes.
+pub fn hex_encode(dst: &mut [u8], src: &[u8]) {
+    encode_with(dst, src, HEX_LOWER)
+}
+
+/// `hex_encode` with A-F.
+pub fn hex_encode_upper(dst: &mut [u8], src: &[u8]) {
+    encode_with(dst, src, HEX_UPPER)
+}
+
+fn encode_with(dst: &mut [u8], src: &[u8], chars: &[u8; 16]) {
+    let mut di: usize = 0;
+    for &byte in src {
+        dst[di] = chars[(byte >> 4) as usize];
+        dst[di + 1] = chars[(byte & 0x0f) as usize];
+        di += 2;
+    }
+}
+
+/// Decodes hex `src`, in either case, into exactly `dst.len()` bytes.
+/// `dst` is left partly written on error.
+pub fn hex_decode(dst: &mut [u8], src: &[u8]) -> Result<(), HexError> {
+    if !src.len().is_multiple_of(2) {
+        return Err(HexError::OddLength);
+    }
+    if decoded_len(src.len()) != dst.len() {
+        return Err(HexError::WrongLength);
+    }
+
+    for (i, pair) in src.chunks_exact(2).enumerate() {
+        let high = nibble(pair[0]).ok_or(HexError::InvalidByte { index: 2 * i })?;
+        let low = nibble(pair[1]).ok_or(HexError::InvalidByte { index: 2 * i + 1 })?;
+        dst[i] = (high << 4) | low;
+    }
+    Ok(())
+}
+
+fn nibble(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test;
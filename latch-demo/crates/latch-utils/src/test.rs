@@ -0,0 +1,88 @@
+#![cfg(test)]
+use crate::{decoded_len, hex_decode, hex_encode, hex_encode_upper, hex_len, HexError};
+use proptest::prelude::*;
+
+extern crate std;
+use std::{format, vec, vec::Vec};
+
+#[test]
+fn test_every_byte_round_trips() {
+    for byte in 0..=u8::MAX {
+        let mut lower = [0u8; 2];
+        hex_encode(&mut lower, &[byte]);
+        assert_eq!(lower, *format!("{byte:02x}").as_bytes());
+
+        let mut upper = [0u8; 2];
+        hex_encode_upper(&mut upper, &[byte]);
+        assert_eq!(upper, *format!("{byte:02X}").as_bytes());
+
+        for hex in [lower, upper] {
+            let mut decoded = [0u8; 1];
+            hex_decode(&mut decoded, &hex).unwrap();
+            assert_eq!(decoded, [byte]);
+        }
+    }
+}
+
+#[test]
+fn test_decode_accepts_mixed_case() {
+    let mut decoded = [0u8; 2];
+    hex_decode(&mut decoded, b"aBcD").unwrap();
+    assert_eq!(decoded, [0xab, 0xcd]);
+}
+
+#[test]
+fn test_decode_rejects_odd_length() {
+    let mut decoded = [0u8; 1];
+    assert_eq!(hex_decode(&mut decoded, b"abc"), Err(HexError::OddLength));
+}
+
+#[test]
+fn test_decode_rejects_wrong_length() {
+    let mut decoded = [0u8; 2];
+    assert_eq!(hex_decode(&mut decoded, b"ab"), Err(HexError::WrongLength));
+    assert_eq!(
+        hex_decode(&mut decoded, b"abcdef"),
+        Err(HexError::WrongLength)
+    );
+}
+
+#[test]
+fn test_decode_rejects_non_hex() {
+    let mut decoded = [0u8; 2];
+    for (hex, index) in [
+        (b"g0ab", 0),
+        (b"0gab", 1),
+        (b"ab 0", 2),
+        (b"ab0x", 3),
+    ] {
+        assert_eq!(
+            hex_decode(&mut decoded, hex),
+            Err(HexError::InvalidByte { index })
+        );
+    }
+}
+
+#[test]
+fn test_lengths() {
+    const PAYLOAD_HEX: usize = hex_len(32);
+    assert_eq!(PAYLOAD_HEX, 64);
+    assert_eq!(decoded_len(PAYLOAD_HEX), 32);
+    assert_eq!(decoded_len(65), 32);
+}
+
+proptest! {
+    #[test]
+    fn prop_decode_inverts_encode(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+        let mut hex = vec![0u8; hex_len(bytes.len())];
+        hex_encode(&mut hex, &bytes);
+        let mut decoded = vec![0u8; decoded_len(hex.len())];
+        hex_decode(&mut decoded, &hex).unwrap();
+        prop_assert_eq!(&decoded, &bytes);
+
+        hex_encode_upper(&mut hex, &bytes);
+        let mut decoded: Vec<u8> = vec![0u8; bytes.len()];
+        hex_decode(&mut decoded, &hex).unwrap();
+        prop_assert_eq!(decoded, bytes);
+    }
+}
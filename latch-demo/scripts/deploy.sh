@@ -3,6 +3,7 @@ set -e
 
 NETWORK="testnet"
 SOURCE="franky"
+ADMIN=$(stellar keys address $SOURCE)
 
 echo "=== Building contracts ==="
 cd "$(dirname "$0")/.."
@@ -10,10 +11,16 @@ cd "$(dirname "$0")/.."
 
 echo ""
 echo "=== Deploying Ed25519 Verifier ==="
+# The frontend signs the short message format, so it must be accepted.
+# --prefix, --siws_domain and --expected_key_len are left out, which
+# keeps the Phantom prefix, no SIWS domain and any key_data length.
 VERIFIER=$(stellar contract deploy \
   --wasm target/wasm32-unknown-unknown/release/ed25519_verifier.wasm \
   --source $SOURCE \
-  --network $NETWORK)
+  --network $NETWORK \
+  -- \
+  --admin $ADMIN \
+  --accept_short_format)
 echo "Verifier: $VERIFIER"
 
 echo ""
@@ -21,7 +28,9 @@ echo "=== Deploying Counter ==="
 COUNTER=$(stellar contract deploy \
   --wasm target/wasm32-unknown-unknown/release/counter.wasm \
   --source $SOURCE \
-  --network $NETWORK)
+  --network $NETWORK \
+  -- \
+  --admin $ADMIN)
 echo "Counter: $COUNTER"
 
 echo ""